@@ -0,0 +1,199 @@
+use std::collections::VecDeque;
+use crate::{BinaryImage, Color, ColorImage};
+
+/// How the background of a frame sequence is estimated
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BackgroundMethod {
+    /// Exponential running average; `alpha` in (0, 1] is the weight of the newest frame
+    RunningAverage { alpha: f64 },
+    /// Per-pixel median over the last `history` frames
+    Median { history: usize },
+}
+
+impl Default for BackgroundMethod {
+    fn default() -> Self {
+        Self::RunningAverage { alpha: 0.05 }
+    }
+}
+
+/// Models the static background of a stream of frames, so that moving objects can be isolated
+/// as a foreground `BinaryImage` and then fed to the usual clustering and tracing pipeline.
+pub struct BackgroundModel {
+    method: BackgroundMethod,
+    /// a pixel is foreground if the sum of absolute RGB difference to the background exceeds this
+    threshold: i32,
+    width: usize,
+    height: usize,
+    /// RGB per pixel, used by `RunningAverage`
+    average: Vec<f64>,
+    /// recent frames, used by `Median`
+    frames: VecDeque<ColorImage>,
+}
+
+impl BackgroundModel {
+    pub fn new(method: BackgroundMethod, threshold: i32) -> Self {
+        if let BackgroundMethod::RunningAverage { alpha } = method {
+            assert!(0.0 < alpha && alpha <= 1.0);
+        }
+        if let BackgroundMethod::Median { history } = method {
+            assert!(history > 0);
+        }
+        Self {
+            method,
+            threshold,
+            width: 0,
+            height: 0,
+            average: Vec::new(),
+            frames: VecDeque::new(),
+        }
+    }
+
+    /// Returns true if no frame has been seen yet
+    pub fn is_empty(&self) -> bool {
+        self.average.is_empty() && self.frames.is_empty()
+    }
+
+    /// Classifies each pixel of `frame` against the current background, then updates the model with `frame`.
+    ///
+    /// The first frame initializes the model and yields an empty foreground.
+    pub fn apply(&mut self, frame: &ColorImage) -> BinaryImage {
+        let mut foreground = BinaryImage::new_w_h(frame.width, frame.height);
+        if !self.is_empty() {
+            assert_eq!(self.width, frame.width);
+            assert_eq!(self.height, frame.height);
+            let background = self.background();
+            for i in 0..frame.width * frame.height {
+                let a = frame.get_pixel_at(i);
+                let b = background.get_pixel_at(i);
+                let diff = (a.r as i32 - b.r as i32).abs() +
+                    (a.g as i32 - b.g as i32).abs() +
+                    (a.b as i32 - b.b as i32).abs();
+                if diff > self.threshold {
+                    foreground.set_pixel_index(i, true);
+                }
+            }
+        }
+        self.update(frame);
+        foreground
+    }
+
+    /// Updates the model with `frame` without classifying it
+    pub fn update(&mut self, frame: &ColorImage) {
+        if self.is_empty() {
+            self.width = frame.width;
+            self.height = frame.height;
+        }
+        match self.method {
+            BackgroundMethod::RunningAverage { alpha } => {
+                if self.average.is_empty() {
+                    self.average = frame
+                        .iter()
+                        .flat_map(|c| [c.r as f64, c.g as f64, c.b as f64])
+                        .collect();
+                    return;
+                }
+                for (i, c) in frame.iter().enumerate() {
+                    let avg = &mut self.average[i * 3..i * 3 + 3];
+                    avg[0] += alpha * (c.r as f64 - avg[0]);
+                    avg[1] += alpha * (c.g as f64 - avg[1]);
+                    avg[2] += alpha * (c.b as f64 - avg[2]);
+                }
+            },
+            BackgroundMethod::Median { history } => {
+                if self.frames.len() == history {
+                    self.frames.pop_front();
+                }
+                self.frames.push_back(frame.clone());
+            },
+        }
+    }
+
+    /// Returns the current estimate of the background
+    pub fn background(&self) -> ColorImage {
+        let mut image = ColorImage::new_w_h(self.width, self.height);
+        match self.method {
+            BackgroundMethod::RunningAverage { .. } => {
+                for i in 0..self.width * self.height {
+                    let avg = &self.average[i * 3..i * 3 + 3];
+                    image.set_pixel_at(i, &Color::new(
+                        avg[0].round() as u8,
+                        avg[1].round() as u8,
+                        avg[2].round() as u8,
+                    ));
+                }
+            },
+            BackgroundMethod::Median { .. } => {
+                let mut r = Vec::with_capacity(self.frames.len());
+                let mut g = Vec::with_capacity(self.frames.len());
+                let mut b = Vec::with_capacity(self.frames.len());
+                for i in 0..self.width * self.height {
+                    r.clear();
+                    g.clear();
+                    b.clear();
+                    for frame in self.frames.iter() {
+                        let c = frame.get_pixel_at(i);
+                        r.push(c.r);
+                        g.push(c.g);
+                        b.push(c.b);
+                    }
+                    image.set_pixel_at(i, &Color::new(median(&mut r), median(&mut g), median(&mut b)));
+                }
+            },
+        }
+        image
+    }
+}
+
+fn median(values: &mut [u8]) -> u8 {
+    values.sort_unstable();
+    values[values.len() / 2]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame_with_dot(x: usize, y: usize) -> ColorImage {
+        let mut image = ColorImage::new_w_h(4, 4);
+        for i in 0..16 {
+            image.set_pixel_at(i, &Color::new(10, 10, 10));
+        }
+        image.set_pixel(x, y, &Color::new(250, 250, 250));
+        image
+    }
+
+    #[test]
+    fn running_average_foreground() {
+        let mut model = BackgroundModel::new(BackgroundMethod::RunningAverage { alpha: 0.1 }, 30);
+        assert_eq!(model.apply(&frame_with_dot(0, 0)).area(), 0);
+        let foreground = model.apply(&frame_with_dot(2, 1));
+        assert!(foreground.get_pixel(2, 1));
+        // the previous position is foreground too: the background still has the first dot there
+        assert!(foreground.get_pixel(0, 0));
+        assert_eq!(foreground.area(), 2);
+
+        // a model started by `update` takes the size of the frame
+        let mut model = BackgroundModel::new(BackgroundMethod::RunningAverage { alpha: 0.1 }, 30);
+        model.update(&frame_with_dot(0, 0));
+        assert!(!model.is_empty());
+        let background = model.background();
+        assert_eq!((background.width, background.height), (4, 4));
+        assert_eq!(model.apply(&frame_with_dot(0, 0)).area(), 0);
+    }
+
+    #[test]
+    fn median_foreground() {
+        let mut model = BackgroundModel::new(BackgroundMethod::Median { history: 3 }, 30);
+        model.apply(&frame_with_dot(0, 0));
+        model.apply(&frame_with_dot(1, 0));
+        model.apply(&frame_with_dot(2, 0));
+        let foreground = model.apply(&frame_with_dot(3, 3));
+        assert_eq!(foreground.to_string(),
+            "----\n".to_owned() +
+            "----\n" +
+            "----\n" +
+            "---*\n"
+        );
+        assert_eq!(model.background().get_pixel(3, 3), Color::new(10, 10, 10));
+    }
+}
@@ -0,0 +1,157 @@
+use crate::{BorderMode, BoundingRectF64, Kernel, PlanarImage, PointF64, nms_boxes};
+
+/// How the scale space of `detect_blobs` is computed
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum BlobMethod {
+    /// Laplacian of Gaussian, exact at each scale
+    #[default]
+    LoG,
+    /// difference of Gaussians of adjacent scales, approximating the LoG at less cost
+    DoG,
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BlobConfig {
+    pub method: BlobMethod,
+    /// smallest and largest standard deviation of the Gaussian searched; a blob of radius r is found at r / sqrt(2)
+    pub min_sigma: f64,
+    pub max_sigma: f64,
+    /// number of scales between `min_sigma` and `max_sigma` (geometrically spaced), at least 3
+    pub num_scales: usize,
+    /// minimum scale normalized response, in units of the image values
+    pub threshold: f32,
+    /// of two blobs whose bounding squares overlap by an IoU over this, the weaker is suppressed; 1 keeps all
+    pub overlap: f64,
+}
+
+impl Default for BlobConfig {
+    fn default() -> Self {
+        Self {
+            method: BlobMethod::default(),
+            min_sigma: 1.0,
+            max_sigma: 16.0,
+            num_scales: 9,
+            threshold: 16.0,
+            overlap: 0.5,
+        }
+    }
+}
+
+/// A blob found by `detect_blobs`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Blob {
+    pub center: PointF64,
+    pub radius: f64,
+    /// scale normalized response; larger is a stronger blob
+    pub response: f32,
+    /// brighter than its surroundings, rather than darker
+    pub bright: bool,
+}
+
+impl PlanarImage<f32, 1> {
+    /// Multi-scale blob detection: extrema of the scale normalized Laplacian over space and scale,
+    /// e.g. for dot markers, or splatter artifacts to remove before vectorization.
+    /// Blobs are sorted by decreasing response, with overlapping weaker blobs suppressed (see `nms_boxes`).
+    pub fn detect_blobs(&self, config: &BlobConfig) -> Vec<Blob> {
+        let n = config.num_scales.max(3);
+        let ratio = (config.max_sigma / config.min_sigma).powf(1.0 / (n - 1) as f64);
+        let sigmas: Vec<f64> = (0..n).map(|i| config.min_sigma * ratio.powi(i as i32)).collect();
+        let laplacian = Kernel::laplacian();
+
+        // responses, positive for bright blobs
+        let responses: Vec<PlanarImage<f32, 1>> = match config.method {
+            BlobMethod::LoG => sigmas.iter()
+                .map(|&sigma| {
+                    let log = self.gaussian_blur(sigma, BorderMode::Replicate).convolve(&laplacian, BorderMode::Replicate);
+                    log.map(|[v]| [-v * (sigma * sigma) as f32])
+                })
+                .collect(),
+            BlobMethod::DoG => {
+                let mut blurred: Vec<_> = sigmas.iter().map(|&sigma| self.gaussian_blur(sigma, BorderMode::Replicate)).collect();
+                blurred.push(self.gaussian_blur(sigmas[n - 1] * ratio, BorderMode::Replicate));
+                // G(k sigma) - G(sigma) ~ (k - 1) sigma^2 LoG
+                let scale = (ratio - 1.0) as f32;
+                blurred.windows(2)
+                    .map(|pair| {
+                        let mut diff = pair[0].clone();
+                        for (d, &b) in diff.channel_mut(0).iter_mut().zip(pair[1].channel(0)) {
+                            *d = (*d - b) / scale;
+                        }
+                        diff
+                    })
+                    .collect()
+            },
+        };
+
+        let (width, height) = (self.width() as i64, self.height() as i64);
+        let mut blobs = Vec::new();
+        for (s, response) in responses.iter().enumerate() {
+            for y in 0..height {
+                for x in 0..width {
+                    let v = response.get(x as usize, y as usize, 0);
+                    if v.abs() < config.threshold {
+                        continue;
+                    }
+                    // an extremum among its 26 neighbours in space and scale;
+                    // of equal neighbours (e.g. about a center between pixels) only the first counts
+                    let sign = v.signum();
+                    let extremum = (s.saturating_sub(1)..(s + 2).min(n)).all(|t| {
+                        (-1..=1).all(|dy| (-1..=1).all(|dx| {
+                            let before = (t, dy, dx) < (s, 0, 0);
+                            (t == s && dx == 0 && dy == 0) ||
+                            BorderMode::Constant.pixel(&responses[t], x + dx, y + dy)
+                                .is_none_or(|[u]| if before { u * sign < v * sign } else { u * sign <= v * sign })
+                        }))
+                    });
+                    if extremum {
+                        blobs.push(Blob {
+                            center: PointF64::new(x as f64 + 0.5, y as f64 + 0.5),
+                            radius: sigmas[s] * std::f64::consts::SQRT_2,
+                            response: v.abs(),
+                            bright: v > 0.0,
+                        });
+                    }
+                }
+            }
+        }
+        nms_boxes(&blobs, |blob| {
+            let r = blob.radius;
+            let rect = BoundingRectF64::new_x_y_w_h(blob.center.x - r, blob.center.y - r, 2.0 * r, 2.0 * r).to_rect();
+            (rect, blob.response as f64)
+        }, config.overlap, usize::MAX)
+            .into_iter()
+            .map(|i| blobs[i])
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detect_discs() {
+        // a bright disc of radius 6 and a dark one of radius 3 on gray
+        let mut image = PlanarImage::<f32, 1>::new_w_h_with_value(64, 40, 128.0);
+        for y in 0..40 {
+            for x in 0..64 {
+                let (px, py) = (x as f64 + 0.5, y as f64 + 0.5);
+                if (px - 20.0).hypot(py - 20.0) <= 6.0 {
+                    image.set(x, y, 0, 255.0);
+                } else if (px - 48.0).hypot(py - 16.0) <= 3.0 {
+                    image.set(x, y, 0, 0.0);
+                }
+            }
+        }
+        for method in [BlobMethod::LoG, BlobMethod::DoG] {
+            let config = BlobConfig { method, min_sigma: 1.0, max_sigma: 8.0, num_scales: 10, threshold: 20.0, ..Default::default() };
+            let blobs = image.detect_blobs(&config);
+            let bright = blobs.iter().find(|b| b.bright).unwrap();
+            assert!((bright.center - PointF64::new(20.0, 20.0)).norm() <= 1.0, "{:?}", bright);
+            assert!((bright.radius - 6.0).abs() <= 2.0, "{:?}", bright);
+            let dark = blobs.iter().find(|b| !b.bright).unwrap();
+            assert!((dark.center - PointF64::new(48.0, 16.0)).norm() <= 1.0, "{:?}", dark);
+            assert!((dark.radius - 3.0).abs() <= 1.5, "{:?}", dark);
+        }
+    }
+}
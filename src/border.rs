@@ -0,0 +1,118 @@
+use crate::{Color, ColorImage, Image, ImageMut, PlanarImage};
+
+/// How pixels beyond the edges of an image are extended, by filters and morphological operations,
+/// and by `ColorImage::pad`
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum BorderMode {
+    /// the default pixel (transparent black, or zero)
+    Constant,
+    /// the nearest edge pixel: `aaa|abcd|ddd`
+    #[default]
+    Replicate,
+    /// mirrored, the edge pixel repeated: `cba|abcd|dcb`
+    Reflect,
+    /// tiled, from the opposite edge: `bcd|abcd|abc`
+    Wrap,
+}
+
+impl BorderMode {
+    /// The index within `0..len` that index `i` maps to, `None` for `Constant` (or if `len` is zero)
+    pub fn resolve(self, i: i64, len: usize) -> Option<usize> {
+        let n = len as i64;
+        if (0..n).contains(&i) {
+            return Some(i as usize);
+        }
+        if n == 0 {
+            return None;
+        }
+        match self {
+            Self::Constant => None,
+            Self::Replicate => Some(i.clamp(0, n - 1) as usize),
+            Self::Reflect => {
+                let period = i.rem_euclid(2 * n);
+                Some(if period < n { period } else { 2 * n - 1 - period } as usize)
+            },
+            Self::Wrap => Some(i.rem_euclid(n) as usize),
+        }
+    }
+
+    /// The pixel of `image` at (x, y), which may be outside the image; `None` where `Constant`
+    pub fn pixel<I: Image>(self, image: &I, x: i64, y: i64) -> Option<I::Pixel> {
+        Some(image.pixel(self.resolve(x, image.width())?, self.resolve(y, image.height())?))
+    }
+}
+
+impl<const C: usize> PlanarImage<f32, C> {
+    /// Bilinear interpolation at (`x`, `y`), with pixel centers at integer coordinates;
+    /// pixels beyond the edges are extended by `border` (zero where `Constant`)
+    pub fn sample_bilinear(&self, x: f64, y: f64, border: BorderMode) -> [f32; C] {
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = ((x - x0) as f32, (y - y0) as f32);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+        let at = |x: i64, y: i64| border.pixel(self, x, y).unwrap_or([0.0; C]);
+        let (a, b, c, d) = (at(x0, y0), at(x0 + 1, y0), at(x0, y0 + 1), at(x0 + 1, y0 + 1));
+        std::array::from_fn(|k| {
+            let top = a[k] * (1.0 - fx) + b[k] * fx;
+            let bottom = c[k] * (1.0 - fx) + d[k] * fx;
+            top * (1.0 - fy) + bottom * fy
+        })
+    }
+}
+
+/// A copy of `image` with `border` pixels added on each side, extended by `mode`, or else `fill`
+fn pad<I: ImageMut>(image: &I, border: usize, mode: BorderMode, fill: I::Pixel) -> I {
+    let (width, height) = (image.width() + 2 * border, image.height() + 2 * border);
+    let mut padded = I::new_image(width, height);
+    let b = border as i64;
+    for y in 0..height {
+        for x in 0..width {
+            padded.put_pixel(x, y, mode.pixel(image, x as i64 - b, y as i64 - b).unwrap_or(fill));
+        }
+    }
+    padded
+}
+
+impl ColorImage {
+    /// A copy with `border` pixels added on each side, extended by `mode`
+    pub fn pad(&self, border: usize, mode: BorderMode) -> Self {
+        pad(self, border, mode, Color::default())
+    }
+}
+
+impl<T, const C: usize> PlanarImage<T, C> where T: Copy + Default {
+    /// A copy with `border` pixels added on each side, extended by `mode`
+    pub fn pad(&self, border: usize, mode: BorderMode) -> Self {
+        pad(self, border, mode, [T::default(); C])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn border_modes() {
+        let row = |mode: BorderMode| (-3..7).map(|i| mode.resolve(i, 4)).collect::<Vec<_>>();
+        assert_eq!(row(BorderMode::Replicate), [0, 0, 0, 0, 1, 2, 3, 3, 3, 3].map(Some));
+        assert_eq!(row(BorderMode::Reflect), [2, 1, 0, 0, 1, 2, 3, 3, 2, 1].map(Some));
+        assert_eq!(row(BorderMode::Wrap), [1, 2, 3, 0, 1, 2, 3, 0, 1, 2].map(Some));
+        assert_eq!(row(BorderMode::Constant)[2..4], [None, Some(0)]);
+        assert_eq!(BorderMode::Reflect.resolve(-9, 4), Some(0));
+
+        let mut image = ColorImage::new_w_h(2, 1);
+        image.set_pixel(0, 0, &Color::new(10, 0, 0));
+        image.set_pixel(1, 0, &Color::new(20, 0, 0));
+        let padded = image.pad(1, BorderMode::Wrap);
+        assert_eq!((padded.width, padded.height), (4, 3));
+        assert_eq!(padded.get_pixel(0, 0), Color::new(20, 0, 0));
+        assert_eq!(padded.get_pixel(3, 2), Color::new(10, 0, 0));
+        assert_eq!(image.pad(1, BorderMode::Constant).get_pixel(0, 1), Color::default());
+
+        let mut ramp = PlanarImage::<f32, 1>::new_w_h(2, 1);
+        ramp.set(1, 0, 0, 10.0);
+        assert_eq!(ramp.sample_bilinear(0.25, 0.0, BorderMode::Replicate), [2.5]);
+        assert_eq!(ramp.sample_bilinear(1.5, 0.0, BorderMode::Replicate), [10.0]);
+        assert_eq!(ramp.sample_bilinear(1.5, 0.0, BorderMode::Constant), [5.0]);
+        assert_eq!(ramp.sample_bilinear(1.0, 0.5, BorderMode::Constant), [5.0]);
+    }
+}
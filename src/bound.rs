@@ -1,7 +1,7 @@
 //! Functions to compute and manipulate bounding rectangles
 
 use std::cmp::min;
-use crate::{PointI32, PointF64, disjoint_sets};
+use crate::{BinaryImage, PointI32, PointF64, disjoint_sets};
 
 /// Any object that has a bounding rect
 pub trait Bound {
@@ -211,6 +211,15 @@ impl BoundingRect {
         }
     }
 
+    /// Area of the intersection over area of the union, 0 if both are empty
+    pub fn iou(self, other: Self) -> f64 {
+        let w = (self.right.min(other.right) - self.left.max(other.left)).max(0) as i64;
+        let h = (self.bottom.min(other.bottom) - self.top.max(other.top)).max(0) as i64;
+        let area = |r: Self| r.width().max(0) as i64 * r.height().max(0) as i64;
+        let union = area(self) + area(other) - w * h;
+        if union > 0 { (w * h) as f64 / union as f64 } else { 0.0 }
+    }
+
     pub fn squared(self) -> Self {
         let size = std::cmp::max(self.width(), self.height());
         Self::new_x_y_w_h(
@@ -228,6 +237,32 @@ impl BoundingRect {
         self.bottom += p.y;
     }
 
+    /// Rows `(y, left, right)` of the rect, `right` exclusive, top to bottom
+    pub fn scanlines(self) -> impl Iterator<Item = (i32, i32, i32)> {
+        (self.top..self.bottom).map(move |y| (y, self.left, self.right))
+    }
+
+    /// Runs `(y, left, right)` of the pixels set in `mask` within the rect, `right` exclusive,
+    /// top to bottom and left to right. `mask` is in the coordinates of the rect, and unset outside itself.
+    pub fn masked_scanlines(self, mask: &BinaryImage) -> impl Iterator<Item = (i32, i32, i32)> + '_ {
+        self.scanlines().flat_map(move |(y, left, right)| {
+            let mut runs = Vec::new();
+            let mut start = None;
+            for x in left..=right {
+                let set = x < right && mask.get_pixel_safe(x, y);
+                match start {
+                    None if set => start = Some(x),
+                    Some(s) if !set => {
+                        runs.push((y, s, x));
+                        start = None;
+                    },
+                    _ => {},
+                }
+            }
+            runs
+        })
+    }
+
     /// Tolerance means:
     ///     1. Extend each boundary on both sides by `tolerance` units along its direction.
     ///     2. `true` is returned iff `p` lies on either one of the extended boundaries.
@@ -485,6 +520,14 @@ pub fn expand(b: BoundingRect, expand_x: i32, expand_y: i32) -> BoundingRect {
 mod tests {
     use super::*;
 
+    #[test]
+    fn masked_scanlines() {
+        let mask = BinaryImage::from_string("-**-*\n*---*\n");
+        let rect = BoundingRect::new_x_y_w_h(1, 0, 5, 3);
+        assert_eq!(rect.scanlines().count(), 3);
+        assert_eq!(rect.masked_scanlines(&mask).collect::<Vec<_>>(), [(0, 1, 3), (0, 4, 5), (1, 4, 5)]);
+    }
+
     #[test]
     fn bounding_rect_1x1() {
         let mut rect = BoundingRect::default();
@@ -0,0 +1,115 @@
+use crate::{ColorImage, LensDistortion, Matrix, PerspectiveTransform, PointF64};
+
+/// The intrinsic parameters of a pinhole camera, in pixels: focal lengths `fx`, `fy`,
+/// principal point (`cx`, `cy`) and `skew`. Normalized coordinates are on the plane one unit in front
+/// of the camera (z = 1), with x to the right and y down as in the image.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CameraIntrinsics {
+    pub fx: f64,
+    pub fy: f64,
+    pub cx: f64,
+    pub cy: f64,
+    pub skew: f64,
+}
+
+impl CameraIntrinsics {
+    pub fn new(fx: f64, fy: f64, cx: f64, cy: f64) -> Self {
+        Self { fx, fy, cx, cy, skew: 0.0 }
+    }
+
+    /// A camera of square pixels with a horizontal field of view of `fov` (radians) over an image of
+    /// `width` x `height`, centered; a guess where the camera is not calibrated
+    pub fn from_field_of_view(width: usize, height: usize, fov: f64) -> Self {
+        let f = width as f64 / 2.0 / (fov / 2.0).tan();
+        Self::new(f, f, width as f64 / 2.0, height as f64 / 2.0)
+    }
+
+    /// The camera matrix K
+    pub fn matrix(&self) -> Matrix<3, 3> {
+        Matrix::new([[self.fx, self.skew, self.cx], [0.0, self.fy, self.cy], [0.0, 0.0, 1.0]])
+    }
+
+    pub fn to_normalized(&self, pixel: PointF64) -> PointF64 {
+        let y = (pixel.y - self.cy) / self.fy;
+        PointF64::new((pixel.x - self.cx - self.skew * y) / self.fx, y)
+    }
+
+    pub fn to_pixel(&self, normalized: PointF64) -> PointF64 {
+        PointF64::new(
+            self.fx * normalized.x + self.skew * normalized.y + self.cx,
+            self.fy * normalized.y + self.cy,
+        )
+    }
+
+    /// The pixel at which a point in camera coordinates is seen; None if not in front of the camera
+    pub fn project(&self, point: [f64; 3]) -> Option<PointF64> {
+        if point[2] <= 0.0 {
+            return None;
+        }
+        Some(self.to_pixel(PointF64::new(point[0] / point[2], point[1] / point[2])))
+    }
+
+    /// The point in camera coordinates seen at `pixel`, `depth` in front of the camera
+    pub fn unproject(&self, pixel: PointF64, depth: f64) -> [f64; 3] {
+        let n = self.to_normalized(pixel);
+        [n.x * depth, n.y * depth, depth]
+    }
+
+    /// `LensDistortion::undistort` of points in pixels of this camera
+    pub fn undistort_points(&self, distortion: &LensDistortion, points: &[PointF64]) -> Vec<PointF64> {
+        points.iter().map(|&p| self.to_pixel(distortion.undistort(self.to_normalized(p)))).collect()
+    }
+
+    /// `image`, taken by this camera through `distortion`, as without the distortion; see `LensDistortion::undistort_image`
+    pub fn undistort_image(&self, distortion: &LensDistortion, image: &ColorImage) -> ColorImage {
+        let mut undistorted = ColorImage::new_w_h(image.width, image.height);
+        for y in 0..image.height {
+            for x in 0..image.width {
+                let source = self.to_pixel(distortion.distort(self.to_normalized(PointF64::new(x as f64, y as f64))));
+                undistorted.set_pixel(x, y, &image.sample_pixel_at_safe(source.to_point_f32()).unwrap_or_default());
+            }
+        }
+        undistorted
+    }
+
+    /// A homography (as fitted by `PerspectiveTransform`, e.g. from a planar marker to its corners in the image)
+    /// in normalized coordinates of this camera, K⁻¹ H; None if the camera matrix is singular
+    pub fn normalize_homography(&self, homography: &PerspectiveTransform) -> Option<Matrix<3, 3>> {
+        Some(self.matrix().inv()?.dot_mm_small(&homography.to_matrix()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn project_and_unproject() {
+        let mut camera = CameraIntrinsics::new(500.0, 480.0, 320.0, 240.0);
+        camera.skew = 2.0;
+        let point = [0.3, -0.2, 2.0];
+        let pixel = camera.project(point).unwrap();
+        assert!((pixel - PointF64::new(394.8, 192.0)).norm() < 1e-9);
+        let back = camera.unproject(pixel, 2.0);
+        assert!(back.iter().zip(point).all(|(a, b)| (a - b).abs() < 1e-12));
+        assert_eq!(camera.project([0.0, 0.0, -1.0]), None);
+
+        let k = camera.matrix();
+        assert!((k.dot_mv(&[0.15, -0.1, 1.0])[0] - pixel.x).abs() < 1e-9);
+        let square = CameraIntrinsics::from_field_of_view(640, 480, std::f64::consts::FRAC_PI_2);
+        assert!((square.fx - 320.0).abs() < 1e-9);
+        assert!((square.to_normalized(PointF64::new(640.0, 240.0)).x - 1.0).abs() < 1e-9);
+
+        let lens = LensDistortion::radial(-0.1, 0.0);
+        let corner = PointF64::new(600.0, 400.0);
+        let undistorted = camera.undistort_points(&lens, &[corner])[0];
+        assert!((camera.to_pixel(lens.distort(camera.to_normalized(undistorted))) - corner).norm() < 1e-6);
+
+        // a unit square seen as a quadrilateral; its corners in normalized coordinates, by the homography
+        let unit = [PointF64::new(0.0, 0.0), PointF64::new(1.0, 0.0), PointF64::new(1.0, 1.0), PointF64::new(0.0, 1.0)];
+        let seen = [PointF64::new(300.0, 200.0), PointF64::new(420.0, 210.0), PointF64::new(410.0, 330.0), PointF64::new(290.0, 320.0)];
+        let h = camera.normalize_homography(&PerspectiveTransform::from_point_f64(&unit, &seen)).unwrap();
+        let [x, y, w] = h.dot_mv(&[1.0, 1.0, 1.0]);
+        assert!((PointF64::new(x / w, y / w) - camera.to_normalized(seen[2])).norm() < 1e-6);
+    }
+}
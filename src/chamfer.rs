@@ -0,0 +1,146 @@
+use crate::{BinaryImage, BoundingRect, CompoundPath, PointF64, PointI32, distance_to_mask, nms_boxes};
+use crate::sdf::flatten;
+
+/// Parameters of `chamfer_match`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ChamferConfig {
+    /// distances to the nearest edge are truncated at this, so missing edges cost a bounded amount
+    pub max_distance: f64,
+    /// positions with a higher score (mean distance, in pixels) are not matches
+    pub max_score: f64,
+    /// matches closer than this (in pixels, along either axis) to a better match are suppressed
+    pub min_separation: i32,
+    pub max_matches: usize,
+}
+
+impl Default for ChamferConfig {
+    fn default() -> Self {
+        Self {
+            max_distance: 10.0,
+            max_score: 2.0,
+            min_separation: 4,
+            max_matches: 10,
+        }
+    }
+}
+
+/// A position of the template in the image
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ChamferMatch {
+    /// translation of the template (in its own coordinates) into the image
+    pub offset: PointI32,
+    /// mean distance from the template to the nearest edge, lower is better
+    pub score: f64,
+}
+
+/// Sample points of `template`, one per pixel of length along its outlines
+pub fn chamfer_template(template: &CompoundPath) -> Vec<PointF64> {
+    let mut points = Vec::new();
+    for polygon in flatten(template) {
+        for segment in polygon.windows(2) {
+            let (a, b) = (segment[0], segment[1]);
+            let steps = a.distance_to(b).ceil().max(1.0) as usize;
+            for i in 0..steps {
+                points.push(a + (b - a) * (i as f64 / steps as f64));
+            }
+        }
+    }
+    points
+}
+
+/// Slides `template` over every position where its bounding box lies inside `edges`,
+/// scoring each by the mean (truncated) distance from the template to the nearest edge pixel.
+///
+/// A template point `p` is scored at pixel `floor(p + offset)`.
+/// Returns the best matches by increasing score, with worse matches near a better one suppressed.
+pub fn chamfer_match(template: &CompoundPath, edges: &BinaryImage, config: &ChamferConfig) -> Vec<ChamferMatch> {
+    let points = chamfer_template(template);
+    if points.is_empty() {
+        return vec![];
+    }
+    let mut bound = BoundingRect::default();
+    let cells: Vec<PointI32> = points.iter().map(|p| {
+        let cell = PointI32::new(p.x.floor() as i32, p.y.floor() as i32);
+        bound.add_x_y(cell.x, cell.y);
+        cell
+    }).collect();
+    let distance = distance_to_mask(edges, config.max_distance);
+    let (width, height) = (edges.width as i32, edges.height as i32);
+
+    let mut candidates = Vec::new();
+    for y in -bound.top..=height - bound.bottom {
+        for x in -bound.left..=width - bound.right {
+            let sum: f64 = cells.iter().map(|c| {
+                distance.get((c.x + x) as usize, (c.y + y) as usize, 0) as f64
+            }).sum();
+            let score = sum / cells.len() as f64;
+            if score <= config.max_score {
+                candidates.push(ChamferMatch { offset: PointI32::new(x, y), score });
+            }
+        }
+    }
+    // matches are near if within `min_separation` along both axes, i.e. if such squares at them overlap
+    let separation = config.min_separation;
+    nms_boxes(&candidates, |m| {
+        (BoundingRect::new_x_y_w_h(m.offset.x, m.offset.y, separation, separation), -m.score)
+    }, 0.0, config.max_matches)
+        .into_iter()
+        .map(|i| candidates[i])
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PathI32;
+
+    #[test]
+    fn chamfer_finds_square() {
+        // the outline of a 4x4 square at (10, 5), and some clutter
+        let mut edges = BinaryImage::new_w_h(24, 16);
+        for i in 0..4 {
+            edges.set_pixel(10 + i, 5, true);
+            edges.set_pixel(10 + i, 8, true);
+            edges.set_pixel(10, 5 + i, true);
+            edges.set_pixel(13, 5 + i, true);
+        }
+        for x in 0..24 {
+            edges.set_pixel(x, 14, true);
+        }
+        let mut path = PathI32::new();
+        for &(x, y) in [(0, 0), (3, 0), (3, 3), (0, 3), (0, 0)].iter() {
+            path.add(PointI32::new(x, y));
+        }
+        let mut template = CompoundPath::new();
+        template.add_path_i32(path);
+        assert_eq!(chamfer_template(&template).len(), 12);
+
+        let matches = chamfer_match(&template, &edges, &ChamferConfig::default());
+        assert_eq!(matches[0], ChamferMatch { offset: PointI32::new(10, 5), score: 0.0 });
+        assert!(matches[1..].iter().all(|m| m.score > 0.5));
+
+        let strict = ChamferConfig { max_score: 0.1, ..ChamferConfig::default() };
+        assert_eq!(chamfer_match(&template, &edges, &strict).len(), 1);
+    }
+
+    #[test]
+    fn chamfer_finds_square_in_corner() {
+        // the outline of a 4x4 square flush with the bottom right corner
+        let mut edges = BinaryImage::new_w_h(12, 10);
+        for i in 0..4 {
+            edges.set_pixel(8 + i, 6, true);
+            edges.set_pixel(8 + i, 9, true);
+            edges.set_pixel(8, 6 + i, true);
+            edges.set_pixel(11, 6 + i, true);
+        }
+        let mut path = PathI32::new();
+        for &(x, y) in [(0, 0), (3, 0), (3, 3), (0, 3), (0, 0)].iter() {
+            path.add(PointI32::new(x, y));
+        }
+        let mut template = CompoundPath::new();
+        template.add_path_i32(path);
+
+        let matches = chamfer_match(&template, &edges, &ChamferConfig::default());
+        assert_eq!(matches[0], ChamferMatch { offset: PointI32::new(8, 6), score: 0.0 });
+    }
+}
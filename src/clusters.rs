@@ -44,6 +44,15 @@ impl Cluster {
         image
     }
 
+    /// Horizontal runs `(y, left, right)` of the points, `right` exclusive, top to bottom and left to right
+    pub fn scan_runs(&self) -> Vec<(i32, i32, i32)> {
+        let image = self.to_binary_image();
+        let local = BoundingRect::new_x_y_w_h(0, 0, self.rect.width(), self.rect.height());
+        local.masked_scanlines(&image)
+            .map(|(y, left, right)| (y + self.rect.top, left + self.rect.left, right + self.rect.left))
+            .collect()
+    }
+
     pub fn boundary(&self) -> Vec<PointI32> {
         Shape::image_boundary_list(&self.to_binary_image())
     }
@@ -88,7 +97,8 @@ impl Cluster {
         splice_threshold: f64
     ) -> CompoundPath {
         match mode {
-            PathSimplifyMode::None | PathSimplifyMode::Polygon => {
+            PathSimplifyMode::None | PathSimplifyMode::Polygon |
+            PathSimplifyMode::Orthogonal | PathSimplifyMode::Octilinear => {
                 let paths = Self::image_to_paths(image, mode);
                 let mut group = CompoundPath::new();
                 for mut path in paths.into_iter() {
@@ -376,6 +386,13 @@ mod tests {
         assert_eq!(bin.get_pixel(0, 0), true);
     }
 
+    #[test]
+    fn cluster_scan_runs() {
+        let image = BinaryImage::from_string("-----\n--***\n-**--\n");
+        let clusters = image.to_clusters(false);
+        assert_eq!(clusters.clusters[0].scan_runs(), [(1, 2, 5), (2, 1, 3)]);
+    }
+
     #[test]
     fn clusters_3x3_diagonal() {
         let size = 3;
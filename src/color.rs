@@ -36,14 +36,20 @@ pub struct ColorF64 {
     pub b: f64,
 }
 
-/// RGBA; each channel is 32 bit unsigned
+/// Sum of up to 2^24 colors (any more may overflow); 2^56 with the `large-images` feature
+#[cfg(not(feature = "large-images"))]
+pub type ColorSumElem = u32;
+#[cfg(feature = "large-images")]
+pub type ColorSumElem = u64;
+
+/// RGBA; each channel is 32 bit unsigned (64 bit with the `large-images` feature)
 #[derive(Copy, Clone, Default, PartialEq, Eq)]
 pub struct ColorSum {
-    pub r: u32,
-    pub g: u32,
-    pub b: u32,
-    pub a: u32,
-    pub counter: u32,
+    pub r: ColorSumElem,
+    pub g: ColorSumElem,
+    pub b: ColorSumElem,
+    pub a: ColorSumElem,
+    pub counter: ColorSumElem,
 }
 
 /// HSV; each channel is 64 bit float
@@ -160,6 +166,27 @@ impl Color {
     }
 }
 
+/// Converts an 8 bit sRGB channel to linear light in [0, 1]
+pub fn srgb_to_linear(v: u8) -> f64 {
+    let v = v as f64 / 255.0;
+    if v <= 0.04045 {
+        v / 12.92
+    } else {
+        crate::fmath::powf((v + 0.055) / 1.055, 2.4)
+    }
+}
+
+/// Converts a linear light value in [0, 1] to an 8 bit sRGB channel
+pub fn linear_to_srgb(v: f64) -> u8 {
+    let v = v.clamp(0.0, 1.0);
+    let v = if v <= 0.0031308 {
+        v * 12.92
+    } else {
+        1.055 * crate::fmath::powf(v, 1.0 / 2.4) - 0.055
+    };
+    (v * 255.0).round() as u8
+}
+
 impl ColorType for Color {
     type ValueType = u8;
 
@@ -237,10 +264,10 @@ impl ColorSum {
     }
 
     pub fn add(&mut self, color: &Color) {
-        self.r += color.r as u32;
-        self.g += color.g as u32;
-        self.b += color.b as u32;
-        self.a += color.a as u32;
+        self.r += color.r as ColorSumElem;
+        self.g += color.g as ColorSumElem;
+        self.b += color.b as ColorSumElem;
+        self.a += color.a as ColorSumElem;
         self.counter += 1;
     }
 
@@ -0,0 +1,138 @@
+use crate::{Color, ColorImage, ciede2000, linear_to_srgb, srgb_to_linear};
+
+/// A complete color vision deficiency (dichromacy)
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum ColorBlindness {
+    /// no long wavelength (red) cones
+    Protanopia,
+    /// no medium wavelength (green) cones
+    Deuteranopia,
+    /// no short wavelength (blue) cones
+    Tritanopia,
+}
+
+impl ColorBlindness {
+    pub const ALL: [ColorBlindness; 3] = [Self::Protanopia, Self::Deuteranopia, Self::Tritanopia];
+
+    /// Simulation matrix in linear RGB, from Machado, Oliveira and Fernandes (2009) at full severity
+    fn matrix(&self) -> [[f64; 3]; 3] {
+        match self {
+            Self::Protanopia => [
+                [0.152286, 1.052583, -0.204868],
+                [0.114503, 0.786281, 0.099216],
+                [-0.003882, -0.048116, 1.051998],
+            ],
+            Self::Deuteranopia => [
+                [0.367322, 0.860646, -0.227968],
+                [0.280085, 0.672501, 0.047413],
+                [-0.011820, 0.042940, 0.968881],
+            ],
+            Self::Tritanopia => [
+                [1.255528, -0.076749, -0.178779],
+                [-0.078411, 0.930809, 0.147602],
+                [0.004733, 0.691367, 0.303900],
+            ],
+        }
+    }
+}
+
+impl Color {
+    /// How this color appears with `blindness`; alpha is kept
+    pub fn simulate_color_blindness(&self, blindness: ColorBlindness) -> Color {
+        let rgb = [srgb_to_linear(self.r), srgb_to_linear(self.g), srgb_to_linear(self.b)];
+        let m = blindness.matrix();
+        let channel = |row: [f64; 3]| linear_to_srgb(row[0] * rgb[0] + row[1] * rgb[1] + row[2] * rgb[2]);
+        Color::new_rgba(channel(m[0]), channel(m[1]), channel(m[2]), self.a)
+    }
+
+    /// Relative luminance as defined by WCAG, in [0, 1]
+    pub fn relative_luminance(&self) -> f64 {
+        0.2126 * srgb_to_linear(self.r) + 0.7152 * srgb_to_linear(self.g) + 0.0722 * srgb_to_linear(self.b)
+    }
+}
+
+impl ColorImage {
+    /// See `Color::simulate_color_blindness`
+    pub fn simulate_color_blindness(&self, blindness: ColorBlindness) -> ColorImage {
+        let mut image = ColorImage::new_w_h(self.width, self.height);
+        for (i, color) in self.iter().enumerate() {
+            image.set_pixel_at(i, &color.simulate_color_blindness(blindness));
+        }
+        image
+    }
+}
+
+/// WCAG contrast ratio of two colors, from 1 (same luminance) to 21 (black on white)
+pub fn contrast_ratio(a: Color, b: Color) -> f64 {
+    let (la, lb) = (a.relative_luminance(), b.relative_luminance());
+    (la.max(lb) + 0.05) / (la.min(lb) + 0.05)
+}
+
+/// Two palette colors which are hard to tell apart
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ConfusablePair {
+    /// indices into the palette, `a < b`
+    pub a: usize,
+    pub b: usize,
+    /// `None` if confusable even with normal vision
+    pub blindness: Option<ColorBlindness>,
+    /// CIEDE2000 difference as seen with `blindness`
+    pub delta_e: f64,
+}
+
+/// Pairs of `palette` colors which differ by less than `min_delta_e` (CIEDE2000),
+/// with normal vision or once simulated for any `ColorBlindness`.
+/// A pair confusable with normal vision is reported only once, with `blindness` `None`.
+pub fn confusable_colors(palette: &[Color], min_delta_e: f64) -> Vec<ConfusablePair> {
+    let normal: Vec<_> = palette.iter().map(|c| c.to_lab()).collect();
+    let simulated: Vec<Vec<_>> = ColorBlindness::ALL
+        .iter()
+        .map(|&blindness| palette.iter().map(|c| c.simulate_color_blindness(blindness).to_lab()).collect())
+        .collect();
+
+    let mut pairs = Vec::new();
+    for a in 0..palette.len() {
+        for b in a + 1..palette.len() {
+            let delta_e = ciede2000(normal[a], normal[b]);
+            if delta_e < min_delta_e {
+                pairs.push(ConfusablePair { a, b, blindness: None, delta_e });
+                continue;
+            }
+            for (labs, &blindness) in simulated.iter().zip(ColorBlindness::ALL.iter()) {
+                let delta_e = ciede2000(labs[a], labs[b]);
+                if delta_e < min_delta_e {
+                    pairs.push(ConfusablePair { a, b, blindness: Some(blindness), delta_e });
+                }
+            }
+        }
+    }
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn red_green_confusion() {
+        let (white, black) = (Color::new(255, 255, 255), Color::new(0, 0, 0));
+        for &blindness in ColorBlindness::ALL.iter() {
+            assert_eq!(white.simulate_color_blindness(blindness), white);
+            assert_eq!(black.simulate_color_blindness(blindness), black);
+        }
+        assert!((contrast_ratio(white, black) - 21.0).abs() < 1e-9);
+        assert_eq!(contrast_ratio(white, white), 1.0);
+
+        // a red and a green of similar lightness, and a blue
+        let palette = [Color::new(200, 60, 40), Color::new(110, 130, 40), Color::new(30, 40, 200)];
+        let pairs = confusable_colors(&palette, 10.0);
+        assert!(pairs.iter().any(|p| (p.a, p.b) == (0, 1) && p.blindness == Some(ColorBlindness::Deuteranopia)));
+        assert!(pairs.iter().all(|p| p.blindness.is_some() && p.b != 2));
+
+        let mut image = ColorImage::new_w_h(2, 1);
+        image.set_pixel(0, 0, &palette[0]);
+        image.set_pixel(1, 0, &palette[1]);
+        let simulated = image.simulate_color_blindness(ColorBlindness::Protanopia);
+        assert_eq!(simulated.get_pixel(1, 0), palette[1].simulate_color_blindness(ColorBlindness::Protanopia));
+    }
+}
@@ -0,0 +1,114 @@
+use std::{cell::Cell, rc::Rc, time::Duration};
+use crate::ColorImage;
+use crate::pipeline::Clock;
+use super::{ClusterEvent, Clusters, Runner, RunnerConfig, ZERO, downscale::scale_area_config, preview::decimate};
+
+/// Images are sampled down to about this many pixels for `RunnerConfig::analyze`
+const ANALYSIS_PIXELS: usize = 128 * 128;
+
+/// What a run of a config on an image is expected to produce, see `RunnerConfig::analyze`
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct ConfigAnalysis {
+    /// the image was sampled every `sample_rate`th pixel in each direction
+    pub sample_rate: usize,
+    /// estimated number of stage 1 patches, each of which is merged in stage 2
+    pub patches: usize,
+    /// estimated number of output clusters
+    pub clusters: usize,
+    /// estimated depth of the cluster hierarchy, 1 if no cluster is nested in another
+    pub depth: usize,
+    /// estimated time of the full run; zero where the platform has no clock (e.g. wasm)
+    pub runtime: Duration,
+}
+
+impl RunnerConfig {
+    /// Estimates the outcome of clustering `image` with this config, by clustering a sample of it,
+    /// so that a UI can warn before launching a pathological run (e.g. millions of patches on a noisy photo).
+    ///
+    /// Patches and runtime scale with the number of pixels; clusters and depth are as sampled,
+    /// since shapes above `good_min_area` persist at full resolution. Always analyzes `Segmentation::Hierarchical`.
+    pub fn analyze(&self, image: &ColorImage) -> ConfigAnalysis {
+        let area = image.width * image.height;
+        if area == 0 {
+            return ConfigAnalysis { sample_rate: 1, ..Default::default() };
+        }
+        let rate = ((area as f64 / ANALYSIS_PIXELS as f64).sqrt().ceil() as usize).max(1);
+        let mut config = self.clone();
+        scale_area_config(&mut config, rate * rate);
+        let sample = if rate > 1 { decimate(image, rate) } else { image.clone() };
+        let sampled = sample.width * sample.height;
+
+        let patches = Rc::new(Cell::new(0));
+        let counter = patches.clone();
+        let clock = Clock::start();
+        let clusters = Runner::new(config, sample)
+            .builder()
+            .on_cluster_event(move |event| if let ClusterEvent::Created { .. } = event {
+                counter.set(counter.get() + 1);
+            })
+            .run();
+        let elapsed = clock.elapsed();
+
+        let scale = area as f64 / sampled as f64;
+        ConfigAnalysis {
+            sample_rate: rate,
+            patches: (patches.get() as f64 * scale).round() as usize,
+            clusters: clusters.output_len(),
+            depth: hierarchy_depth(&clusters),
+            runtime: elapsed.mul_f64(scale),
+        }
+    }
+}
+
+/// Levels of nesting of the output clusters; each is nested in the smallest larger one containing its first pixel
+fn hierarchy_depth(clusters: &Clusters) -> usize {
+    let mut outputs = clusters.clusters_output.clone();
+    outputs.sort_by_key(|&i| std::cmp::Reverse(clusters.clusters[i.0 as usize].area()));
+    let mut innermost = vec![ZERO; (clusters.width * clusters.height) as usize];
+    let mut depths = vec![0; clusters.clusters.len()];
+    let mut depth = 0;
+    for &index in outputs.iter() {
+        let cluster = &clusters.clusters[index.0 as usize];
+        let Some(&first) = cluster.indices.first() else { continue };
+        let parent = innermost[first as usize];
+        let level = if parent == ZERO { 1 } else { depths[parent.0 as usize] + 1 };
+        depths[index.0 as usize] = level;
+        depth = depth.max(level);
+        for &pixel in cluster.indices.iter() {
+            innermost[pixel as usize] = index;
+        }
+    }
+    depth
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Color;
+    use crate::synth::{add_noise, checkerboard};
+
+    #[test]
+    fn analyze_flat_and_noisy() {
+        // nested squares: white, black, red
+        let mut image = ColorImage::new_w_h(512, 512);
+        for y in 0..512 {
+            for x in 0..512 {
+                let (dx, dy) = ((x as i32 - 256).abs(), (y as i32 - 256).abs());
+                image.set_pixel(x, y, &match dx.max(dy) {
+                    0..=63 => Color::new(255, 0, 0),
+                    64..=95 => Color::new(0, 0, 0),
+                    _ => Color::new(255, 255, 255),
+                });
+            }
+        }
+        let analysis = RunnerConfig::default().analyze(&image);
+        assert_eq!(analysis.sample_rate, 4);
+        assert_eq!(analysis.clusters, 3);
+        assert_eq!(analysis.depth, 3);
+        assert!(analysis.patches < 100);
+
+        let mut noisy = checkerboard(512, 512, 2, Color::new(0, 0, 0), Color::new(255, 255, 255));
+        add_noise(&mut noisy, 40, 1);
+        assert!(RunnerConfig::default().analyze(&noisy).patches > 100 * analysis.patches);
+    }
+}
@@ -0,0 +1,115 @@
+use std::collections::HashMap;
+use crate::{Color, ColorSum, ColorSumElem};
+use super::{ClusterIndex, ClusterIndexElem, Clusters, ZERO};
+
+/// How the output color of a cluster is computed from its pixels
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub enum AverageColor {
+    /// Mean of all pixels
+    #[default]
+    Mean,
+    /// Mean of each channel, after discarding the `trim` fraction (up to 0.5) of both the lowest and highest values
+    TrimmedMean { trim: f64 },
+    /// Mean of the most frequent color, with channels quantized to `bits` bits (1 to 8)
+    Mode { bits: u32 },
+}
+
+impl AverageColor {
+    /// The representative color of `colors`, which must not be empty
+    pub fn average(&self, colors: &[Color]) -> Color {
+        assert!(!colors.is_empty());
+        match *self {
+            AverageColor::Mean => {
+                let mut sum = ColorSum::new();
+                colors.iter().for_each(|c| sum.add(c));
+                sum.average()
+            },
+            AverageColor::TrimmedMean { trim } => {
+                let trim = ((colors.len() as f64 * trim.clamp(0.0, 0.5)) as usize).min((colors.len() - 1) / 2);
+                let channel = |f: fn(&Color) -> u8| {
+                    let mut values: Vec<u8> = colors.iter().map(f).collect();
+                    values.sort_unstable();
+                    let kept = &values[trim..values.len() - trim];
+                    let sum: u64 = kept.iter().map(|&v| v as u64).sum();
+                    ((sum as f64 / kept.len() as f64).round()) as u8
+                };
+                Color::new_rgba(channel(|c| c.r), channel(|c| c.g), channel(|c| c.b), channel(|c| c.a))
+            },
+            AverageColor::Mode { bits } => {
+                let shift = 8 - bits.clamp(1, 8);
+                let mut bins: HashMap<[u8; 4], ColorSum> = HashMap::new();
+                for c in colors.iter() {
+                    bins.entry([c.r >> shift, c.g >> shift, c.b >> shift, c.a >> shift])
+                        .or_default()
+                        .add(c);
+                }
+                // ties go to the lower bin, so the result does not depend on the hash order
+                let (_, sum) = bins
+                    .into_iter()
+                    .max_by_key(|(key, sum)| (sum.counter, std::cmp::Reverse(*key)))
+                    .unwrap();
+                sum.average()
+            },
+        }
+    }
+}
+
+impl Clusters {
+    /// Recomputes the residue color of each output cluster with `average`, from the pixels
+    /// it is drawn on (see `label_map`). `AverageColor::Mean` leaves the clusters as they are.
+    pub fn set_average_color(&mut self, average: AverageColor) {
+        if average == AverageColor::Mean {
+            return;
+        }
+        let mut members: Vec<Vec<Color>> = vec![Vec::new(); self.clusters.len()];
+        for (&label, p) in self.label_map().iter().zip(self.pixels.chunks_exact(4)) {
+            if label != ZERO.0 {
+                members[label as usize].push(Color::new_rgba(p[0], p[1], p[2], p[3]));
+            }
+        }
+        for (index, colors) in members.iter().enumerate() {
+            if !colors.is_empty() {
+                self.set_residue_color(ClusterIndex(index as ClusterIndexElem), average.average(colors));
+            }
+        }
+    }
+
+    /// Sets the residue color of a cluster, as drawn by `to_color_image`, keeping its pixel count
+    pub fn set_residue_color(&mut self, index: ClusterIndex, color: Color) {
+        let cluster = &mut self.clusters[index.0 as usize];
+        let counter = cluster.residue_sum.counter.max(1);
+        cluster.residue_sum = ColorSum {
+            r: color.r as ColorSumElem * counter,
+            g: color.g as ColorSumElem * counter,
+            b: color.b as ColorSumElem * counter,
+            a: color.a as ColorSumElem * counter,
+            counter,
+        };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ColorImage;
+
+    #[test]
+    fn average_rejects_outliers() {
+        // a flat red region with two anti-aliased pixels
+        let mut image = ColorImage::new_w_h(10, 1);
+        for x in 0..10 {
+            let color = if x < 8 { Color::new(200, 0, 0) } else { Color::new(100, 100, 100) };
+            image.set_pixel(x, 0, &color);
+        }
+        let colors: Vec<_> = image.iter().collect();
+        assert_eq!(AverageColor::Mean.average(&colors), Color::new(180, 20, 20));
+        assert_eq!(AverageColor::TrimmedMean { trim: 0.2 }.average(&colors), Color::new(200, 0, 0));
+        assert_eq!(AverageColor::Mode { bits: 4 }.average(&colors), Color::new(200, 0, 0));
+
+        let mut clusters = Clusters::from_label_map(10, 1, &[1u32; 10], image);
+        let index = clusters.view().clusters_output[0];
+        assert_eq!(clusters.view().get_cluster(index).residue_color(), Color::new(180, 20, 20));
+        clusters.set_average_color(AverageColor::Mode { bits: 4 });
+        assert_eq!(clusters.view().get_cluster(index).residue_color(), Color::new(200, 0, 0));
+    }
+}
@@ -0,0 +1,81 @@
+use crate::{Color, ColorImage};
+
+/// Block size of JPEG (and most DCT based codecs)
+pub const JPEG_BLOCK_SIZE: usize = 8;
+
+/// Luma steps larger than this are taken for real edges, not blocking artifacts
+const MAX_ARTIFACT_STEP: f64 = 32.0;
+
+impl ColorImage {
+    /// Estimates the strength of blocking artifacts on a grid of `block_size`: how much larger (in 8 bit luma units)
+    /// the step between neighbouring pixels is across block boundaries than within blocks.
+    /// 0 for images without visible blocking.
+    pub fn block_artifacts(&self, block_size: usize) -> f64 {
+        assert!(block_size > 1);
+        let luma: Vec<f64> = self.iter().map(|c: Color| 0.299 * c.r as f64 + 0.587 * c.g as f64 + 0.114 * c.b as f64).collect();
+        let (width, height) = (self.width, self.height);
+        // (sum, count) of steps across boundaries and within blocks
+        let (mut across, mut within) = ((0.0, 0), (0.0, 0));
+        let mut add = |step: f64, boundary: bool| {
+            let step = step.min(MAX_ARTIFACT_STEP);
+            let total = if boundary { &mut across } else { &mut within };
+            total.0 += step;
+            total.1 += 1;
+        };
+        for y in 0..height {
+            for x in 0..width {
+                let v = luma[y * width + x];
+                if x > 0 {
+                    add((v - luma[y * width + x - 1]).abs(), x % block_size == 0);
+                }
+                if y > 0 {
+                    add((v - luma[(y - 1) * width + x]).abs(), y % block_size == 0);
+                }
+            }
+        }
+        if across.1 == 0 || within.1 == 0 {
+            return 0.0;
+        }
+        (across.0 / across.1 as f64 - within.0 / within.1 as f64).max(0.0)
+    }
+}
+
+/// Whether pixels at `a` and `b` lie in different blocks of `block_size`
+pub(crate) fn across_blocks(a: (i32, i32), b: (i32, i32), block_size: i32) -> bool {
+    a.0.div_euclid(block_size) != b.0.div_euclid(block_size) ||
+        a.1.div_euclid(block_size) != b.1.div_euclid(block_size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color_clusters::{Runner, RunnerConfig};
+
+    #[test]
+    fn blocking_is_tolerated() {
+        // a smooth horizontal ramp, quantized to flat 8x8 blocks as a heavily compressed JPEG would be
+        let (width, height) = (32, 16);
+        let mut image = ColorImage::new_w_h(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let v = 100 + (x / JPEG_BLOCK_SIZE) as u8 * 12;
+                image.set_pixel(x, y, &Color::new(v, v, v));
+            }
+        }
+        assert!(image.block_artifacts(JPEG_BLOCK_SIZE) > 3.0);
+        let mut flat = ColorImage::new_w_h(width, height);
+        flat.pixels.iter_mut().for_each(|v| *v = 200);
+        assert_eq!(flat.block_artifacts(JPEG_BLOCK_SIZE), 0.0);
+
+        let config = RunnerConfig { good_min_area: 0, is_same_color_a: 2, ..RunnerConfig::default() };
+        // whether the pixels either side of each block boundary are in the same patch after stage 1
+        let joined = |jpeg_tolerance: bool| {
+            let config = RunnerConfig { hierarchical: 0, jpeg_tolerance, ..config.clone() };
+            let labels = Runner::new(config, image.clone()).run().label_map();
+            (1..4).map(|b| labels[5 * width + b * 8 - 1] == labels[5 * width + b * 8]).collect::<Vec<_>>()
+        };
+        assert_eq!(joined(false), [false; 3]);
+        assert_eq!(joined(true), [true; 3]);
+        assert!(across_blocks((7, 0), (8, 0), 8) && !across_blocks((8, 0), (15, 7), 8));
+    }
+}
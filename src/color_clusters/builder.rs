@@ -1,15 +1,28 @@
-use std::collections::HashMap;
-use crate::{Color, ColorImage};
-use super::{Cluster, Clusters, ClustersView, container::ClusterIndex, container::ClusterIndexElem};
+use std::collections::{HashMap, HashSet};
+use crate::{BinaryImage, BoundingRect, Color, ColorImage, ColorImageF32, ColorRgba};
+use super::{AverageColor, ClusterTrace, JPEG_BLOCK_SIZE, MergeRecord, MergeRule, OutputReason, blocking::across_blocks, priority::priority_order, salient_tile_priority, Cluster, Clusters, ClustersView, container::ClusterIndex, container::ClusterIndexElem, container::PixelIndex, container::check_capacity};
 
 // Describes what to do with pixels that match the key color
-#[derive(Default, Clone, Copy)]
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
 pub enum KeyingAction {
     #[default]
     Keep,
     Discard,
 }
 
+/// The order in which pixels are visited when forming the initial patches
+#[derive(Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ScanOrder {
+    #[default]
+    RowMajor,
+    /// Along a Hilbert curve, for better memory locality and more compact patches.
+    /// Patches are the connected components of similar colors, so results differ slightly from `RowMajor`.
+    Hilbert,
+    /// Tiles of `tile_size` pixels square, from the highest priority down (see `Builder::priority`),
+    /// so that the previews of an incremental build show the important content first
+    Priority { tile_size: u32 },
+}
+
 #[derive(Clone)]
 pub struct BuilderConfig {
     pub(crate) diagonal: bool,
@@ -17,6 +30,20 @@ pub struct BuilderConfig {
     pub(crate) batch_size: u32,
     pub(crate) key: Color,
     pub(crate) keying_action: KeyingAction,
+    /// Caps the memory (in bytes) held by the builder. Once reached, clusters are no longer deepened
+    /// (copied into their parent) but merged, so the output degrades to a flatter hierarchy instead of failing.
+    /// The cap is approximate: it bounds the memory held after stage 1 (`memory_usage`) plus the pixel indices
+    /// copied by deepening, but not the slack of vectors grown by merging, nor what `result` adds to the output.
+    /// Only deepening is capped: the buffers of stage 1 are allocated whatever the cap, so a cap below
+    /// their size flattens the whole hierarchy but does not prevent them from being allocated.
+    pub(crate) memory_cap: usize,
+    pub(crate) scan_order: ScanOrder,
+    /// how the output color of each cluster is computed, see `Clusters::set_average_color`
+    pub(crate) average_color: AverageColor,
+    /// size of the compression blocks `block_same` applies across, see `Builder::block_same`
+    pub(crate) block_size: u32,
+    /// records why each cluster was merged and output, see `Clusters::trace`
+    pub(crate) trace: bool,
 }
 
 impl Default for BuilderConfig {
@@ -27,32 +54,76 @@ impl Default for BuilderConfig {
             batch_size: 10000,
             key: Color::default(),
             keying_action: KeyingAction::default(),
+            memory_cap: usize::MAX,
+            scan_order: ScanOrder::default(),
+            average_color: AverageColor::default(),
+            block_size: JPEG_BLOCK_SIZE as u32,
+            trace: false,
         }
     }
 }
 
+#[non_exhaustive]
 pub struct NeighbourInfo {
     pub index: ClusterIndex,
     pub diff: i32,
+    /// length (in pixel edges) of the boundary shared with the neighbour
+    pub boundary: usize,
+    /// `boundary` as a fraction of the whole boundary of the cluster, so that merge heuristics
+    /// can prefer the neighbours which surround it
+    pub boundary_fraction: f64,
 }
 
 type Cmp = Box<dyn Fn(Color, Color) -> bool>;
 type Diff = Box<dyn Fn(Color, Color) -> i32>;
+type DeepDiff = Box<dyn Fn(ColorRgba<f32>, ColorRgba<f32>) -> i32>;
 type Deepen = Box<dyn Fn(&BuilderImpl, &Cluster, &[NeighbourInfo]) -> bool>;
 type Hollow = Box<dyn Fn(&BuilderImpl, &Cluster, &[NeighbourInfo]) -> bool>;
+type Priority = Box<dyn Fn(&ColorImage, &BoundingRect) -> f64>;
+type Observer = Box<dyn FnMut(ClusterEvent)>;
+/// neighbours with the length of the shared boundary, and the whole perimeter, see `neighbour_boundaries_internal`
+type Boundaries = (Vec<(ClusterIndex, usize)>, usize);
+
+/// A step of the build, reported to the observer set by `Builder::on_cluster_event`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ClusterEvent {
+    /// A patch of same colored pixels formed in stage 1, before any merging
+    Created { index: ClusterIndex, area: usize },
+    /// `from` was merged into `to`, its neighbour of the least `diff`; if `deepened`,
+    /// `from` is kept as an output cluster nested in `to` (and a hole of it, if `hollow`)
+    Merged { from: ClusterIndex, to: ClusterIndex, area: usize, diff: i32, deepened: bool, hollow: bool },
+    /// `index` became an output cluster without being merged, e.g. above `hierarchical` or the final background
+    Output { index: ClusterIndex },
+    /// The build is complete
+    Finished,
+}
 
 /// the 0th cluster is reserved for internal use
 pub const ZERO: ClusterIndex = ClusterIndex(0);
 pub const HIERARCHICAL_MAX: u32 = std::u32::MAX;
 
+/// pixels to scan in a pass of stage 2 before it is split across threads
+#[cfg(feature = "parallel")]
+const PARALLEL_MIN_PIXELS: usize = 1 << 14;
+
+/// typical number of pixels per cluster after stage 1, for `Builder::memory_estimate`
+const ESTIMATED_PIXELS_PER_CLUSTER: usize = 16;
+/// typical number of times a pixel is replicated in the hierarchy, for `Builder::memory_estimate`
+const ESTIMATED_HIERARCHY_DEPTH: usize = 4;
+
 #[derive(Default)]
 pub struct Builder {
     pub(crate) conf: BuilderConfig,
     pub(crate) same: Option<Cmp>,
+    pub(crate) block_same: Option<Cmp>,
     pub(crate) diff: Option<Diff>,
     pub(crate) deepen: Option<Deepen>,
     pub(crate) hollow: Option<Hollow>,
+    pub(crate) priority: Option<Priority>,
+    pub(crate) observer: Option<Observer>,
     pub(crate) image: Option<ColorImage>,
+    pub(crate) mask: Option<BinaryImage>,
+    pub(crate) deep: Option<(ColorImageF32, DeepDiff)>,
 }
 
 pub struct IncrementalBuilder {
@@ -87,6 +158,25 @@ impl Builder {
         self
     }
 
+    /// Restricts clustering to the pixels set in `mask`; other pixels are left out as if keyed and discarded
+    pub fn mask(mut self, mask: BinaryImage) -> Self {
+        self.mask = Some(mask);
+        self
+    }
+
+    /// Compares clusters for merging by their average colors in `image`, a deeper copy of the image of the same size,
+    /// with `diff` in place of the `diff` of 8 bit colors; `same` still compares the 8 bit pixels
+    pub fn deep(mut self, image: ColorImageF32, diff: impl Fn(ColorRgba<f32>, ColorRgba<f32>) -> i32 + 'static) -> Self {
+        self.deep = Some((image, Box::new(diff)));
+        self
+    }
+
+    /// Calls `observer` on each step of the build, e.g. to animate or log the clustering process
+    pub fn on_cluster_event(mut self, observer: impl FnMut(ClusterEvent) + 'static) -> Self {
+        self.observer = Some(Box::new(observer));
+        self
+    }
+
     pub fn run(self) -> Clusters {
         let mut bimpl = BuilderImpl::from(self);
         while !bimpl.tick() {}
@@ -97,16 +187,45 @@ impl Builder {
         IncrementalBuilder::new(BuilderImpl::from(self))
     }
 
+    /// Rough estimate of the peak memory (in bytes) used in building clusters of the image,
+    /// assuming typical cluster sizes and hierarchy depth. Noisy images can use much more.
+    pub fn memory_estimate(&self) -> usize {
+        let len = match &self.image {
+            Some(image) => image.width * image.height,
+            None => return 0,
+        };
+        let per_pixel = 4 + // pixels
+            std::mem::size_of::<ClusterIndex>() + // cluster_indices
+            std::mem::size_of::<PixelIndex>(); // indices of stage 1 clusters
+        let hierarchy = if self.conf.hierarchical == HIERARCHICAL_MAX {
+            std::mem::size_of::<PixelIndex>() * ESTIMATED_HIERARCHY_DEPTH
+        } else {
+            0
+        };
+        len * (per_pixel + hierarchy) +
+            len / ESTIMATED_PIXELS_PER_CLUSTER * std::mem::size_of::<Cluster>()
+    }
+
     config_setter!(diagonal, bool);
     config_setter!(hierarchical, u32);
     config_setter!(batch_size, u32);
     config_setter!(key, Color);
     config_setter!(keying_action, KeyingAction);
+    config_setter!(memory_cap, usize);
+    config_setter!(scan_order, ScanOrder);
+    config_setter!(average_color, AverageColor);
+    config_setter!(block_size, u32);
+    config_setter!(trace, bool);
 
     closure_setter!(same, Fn(Color, Color) -> bool);
+    // replaces `same` for neighbours in different blocks of `block_size`, typically with a higher tolerance,
+    // so that the blocking artifacts of lossy compression are not traced as boundaries
+    closure_setter!(block_same, Fn(Color, Color) -> bool);
     closure_setter!(diff, Fn(Color, Color) -> i32);
     closure_setter!(deepen, Fn(&BuilderImpl, &Cluster, &[NeighbourInfo]) -> bool);
     closure_setter!(hollow, Fn(&BuilderImpl, &Cluster, &[NeighbourInfo]) -> bool);
+    // priority of a tile for `ScanOrder::Priority`; `salient_tile_priority` if not set
+    closure_setter!(priority, Fn(&ColorImage, &BoundingRect) -> f64);
 }
 
 impl IncrementalBuilder {
@@ -128,6 +247,11 @@ impl IncrementalBuilder {
         self.builder_impl.take().unwrap().result()
     }
 
+    /// Current memory (in bytes) held by the builder
+    pub fn memory_usage(&self) -> usize {
+        self.builder_impl.as_ref().map_or(0, |builder| builder.memory_usage())
+    }
+
     pub fn progress(&self) -> u32 {
         match &self.builder_impl {
             None => {
@@ -151,19 +275,38 @@ pub struct BuilderImpl {
     batch_size: u32,
     key: Color,
     keying_action: KeyingAction,
+    memory_cap: usize,
+    average_color: AverageColor,
+    /// memory used by the hierarchy so far
+    memory_hierarchy: usize,
+    /// memory used before stage 2
+    memory_base: usize,
+    /// pixel indices in the order of visit, if not row major
+    scan: Option<Vec<PixelIndex>>,
+    /// whether each pixel has been visited, if not row major
+    visited: Vec<bool>,
+    /// pixels to be clustered, if not all
+    mask: Option<BinaryImage>,
     same: Cmp,
+    block_same: Option<Cmp>,
+    block_size: i32,
     diff: Diff,
+    /// the deeper image, its diff and the sum of its colors in each cluster, see `Builder::deep`
+    deep: Option<(ColorImageF32, DeepDiff, Vec<[f64; 3]>)>,
     deepen: Deepen,
     hollow: Hollow,
-    pub(crate) width: u32,
-    pub(crate) height: u32,
+    observer: Option<Observer>,
+    /// history of each cluster, if traced
+    trace: Option<Vec<ClusterTrace>>,
+    pub(crate) width: PixelIndex,
+    pub(crate) height: PixelIndex,
     pixels: Vec<u8>,           // raw bytes from getImageData; 4 bytes as a pixel
     clusters: Vec<Cluster>,    // array of clusters
     pub(crate) cluster_indices: Vec<ClusterIndex>, // the cluster index each pixel belongs to
     cluster_areas: Vec<Area>,  // uniquely sorted array of cluster sizes
     clusters_output: Vec<ClusterIndex>, // indices of good clusters
     stage: u32,
-    iteration: u32,
+    iteration: PixelIndex,
     next_index: ClusterIndex,
 }
 
@@ -172,6 +315,15 @@ impl From<Builder> for BuilderImpl {
     fn from(mut b: Builder) -> Self {
         let im = b.image.unwrap();
         let len = im.pixels.len();
+        if let Some(mask) = &b.mask {
+            assert_eq!(mask.width, im.width);
+            assert_eq!(mask.height, im.height);
+        }
+        if let Some((deep, _)) = &b.deep {
+            assert_eq!(deep.width, im.width);
+            assert_eq!(deep.height, im.height);
+        }
+        check_capacity(im.width, im.height);
 
         Self {
             diagonal: b.conf.diagonal,
@@ -179,12 +331,34 @@ impl From<Builder> for BuilderImpl {
             batch_size: b.conf.batch_size,
             key: b.conf.key,
             keying_action: b.conf.keying_action,
+            memory_cap: b.conf.memory_cap,
+            average_color: b.conf.average_color,
+            memory_hierarchy: 0,
+            memory_base: 0,
+            scan: match b.conf.scan_order {
+                ScanOrder::RowMajor => None,
+                ScanOrder::Hilbert => Some(hilbert_order(im.width as PixelIndex, im.height as PixelIndex)),
+                ScanOrder::Priority { tile_size } => Some(match &b.priority {
+                    Some(priority) => priority_order(&im, tile_size, priority),
+                    None => priority_order(&im, tile_size, &salient_tile_priority),
+                }),
+            },
+            visited: match b.conf.scan_order {
+                ScanOrder::RowMajor => Vec::new(),
+                _ => vec![false; len / 4],
+            },
+            mask: b.mask.take(),
             same: b.same.take().unwrap(),
+            block_same: b.block_same.take(),
+            block_size: b.conf.block_size as i32,
             diff: b.diff.take().unwrap(),
+            deep: b.deep.take().map(|(image, diff)| (image, diff, Vec::new())),
             deepen: b.deepen.take().unwrap(),
             hollow: b.hollow.take().unwrap(),
-            width: im.width as u32,
-            height: im.height as u32,
+            observer: b.observer.take(),
+            trace: if b.conf.trace { Some(Vec::new()) } else { None },
+            width: im.width as PixelIndex,
+            height: im.height as PixelIndex,
             pixels: im.pixels,
             clusters: vec![Cluster::new()],
             cluster_indices: vec![Default::default(); len / 4],
@@ -202,6 +376,14 @@ impl BuilderImpl {
         match self.stage {
             1 => {
                 if self.stage_1() {
+                    if self.observer.is_some() {
+                        for i in 1..self.clusters.len() {
+                            let area = self.clusters[i].area();
+                            if area > 0 {
+                                self.emit(ClusterEvent::Created { index: ClusterIndex(i as ClusterIndexElem), area });
+                            }
+                        }
+                    }
                     if self.hierarchical != 0 {
                         self.stage += 1;
                         self.iteration = 0;
@@ -222,10 +404,21 @@ impl BuilderImpl {
                 }
                 false
             },
+            3 => {
+                self.emit(ClusterEvent::Finished);
+                self.stage += 1;
+                true
+            },
             _ => true,
         }
     }
 
+    fn emit(&mut self, event: ClusterEvent) {
+        if let Some(observer) = self.observer.as_mut() {
+            observer(event);
+        }
+    }
+
     pub fn get_cluster(&self, index: ClusterIndex) -> &Cluster {
         &self.clusters[index.0 as usize]
     }
@@ -235,14 +428,20 @@ impl BuilderImpl {
     }
 
     pub fn result(self) -> Clusters {
-        Clusters {
+        let mut clusters = Clusters {
             width: self.width,
             height: self.height,
             pixels: self.pixels,
             clusters: self.clusters,
             cluster_indices: self.cluster_indices,
             clusters_output: self.clusters_output,
+            trace: self.trace.unwrap_or_default(),
+        };
+        if !clusters.trace.is_empty() {
+            clusters.trace.resize(clusters.clusters.len(), ClusterTrace::default());
         }
+        clusters.set_average_color(self.average_color);
+        clusters
     }
 
     pub fn view(&self) -> ClustersView {
@@ -256,13 +455,28 @@ impl BuilderImpl {
         }
     }
 
+    /// Memory (in bytes) currently held by the builder
+    pub fn memory_usage(&self) -> usize {
+        let clusters: usize = self.clusters.iter()
+            .map(|c| (c.indices.capacity() + c.holes.capacity()) * std::mem::size_of::<PixelIndex>())
+            .sum();
+        self.pixels.capacity() +
+            self.cluster_indices.capacity() * std::mem::size_of::<ClusterIndex>() +
+            self.clusters.capacity() * std::mem::size_of::<Cluster>() +
+            self.cluster_areas.capacity() * std::mem::size_of::<Area>() +
+            self.clusters_output.capacity() * std::mem::size_of::<ClusterIndex>() +
+            clusters
+    }
+
+    // in u64, as `usize` is 32 bits on wasm; `PixelIndex` is already u64 with `large-images`
+    #[allow(clippy::useless_conversion)]
     pub fn progress(&self) -> u32 {
         match self.stage {
             1 => {
-                50 * self.iteration / self.cluster_indices.len() as u32
+                (50 * u64::from(self.iteration) / self.cluster_indices.len() as u64) as u32
             },
             2 => {
-                50 + 50 * self.iteration / self.cluster_areas.len() as u32
+                50 + (50 * u64::from(self.iteration) / self.cluster_areas.len() as u64) as u32
             },
             _ => {
                 100
@@ -271,6 +485,9 @@ impl BuilderImpl {
     }
 
     fn stage_1(&mut self) -> bool {
+        if self.scan.is_some() {
+            return self.stage_1_ordered();
+        }
         let diagonal = self.diagonal;
         let batch_size = self.batch_size;
         let key = self.key;
@@ -278,7 +495,7 @@ impl BuilderImpl {
         let has_key = key != Color::default();
         let len = self.cluster_indices.len();
 
-        for i in (self.iteration..(self.iteration + batch_size)).take_while(|&i| (i as usize) < len)
+        for i in (self.iteration..(self.iteration + batch_size as PixelIndex)).take_while(|&i| (i as usize) < len)
         {
             let x = (i % self.width) as i32;
             let y = (i / self.width) as i32;
@@ -289,29 +506,30 @@ impl BuilderImpl {
             let upleft = self.pixel_at(x - 1, y - 1);
 
             let mut cluster_up = if y > 0 {
-                self.cluster_indices[(self.width as i32 * (y - 1) + x) as usize]
+                self.cluster_indices[self.width as usize * (y - 1) as usize + x as usize]
             } else {
                 ZERO
             };
             let mut cluster_left = if x > 0 {
-                self.cluster_indices[(self.width as i32 * y + (x - 1)) as usize]
+                self.cluster_indices[self.width as usize * y as usize + (x - 1) as usize]
             } else {
                 ZERO
             };
             let cluster_upleft = if x > 0 && y > 0 {
-                self.cluster_indices[(self.width as i32 * (y - 1) + (x - 1)) as usize]
+                self.cluster_indices[self.width as usize * (y - 1) as usize + (x - 1) as usize]
             } else {
                 ZERO
             };
 
+            let (x_up, y_up, x_left, y_left) = (x, y - 1, x - 1, y);
             if cluster_left != cluster_up
-                && self.is_same(left, up)
+                && self.is_same_at(left, (x_left, y_left), up, (x_up, y_up))
                 && (diagonal || // if not diagonal, self color must be same as up & left
-                self.is_same(color, left) &&
-                self.is_same(color, up))
+                self.is_same_at(color, (x, y), left, (x_left, y_left)) &&
+                self.is_same_at(color, (x, y), up, (x_up, y_up)))
             {
                 if self.get_cluster(cluster_left).area() <= self.get_cluster(cluster_up).area() {
-                    self.combine_clusters(cluster_left, cluster_up);
+                    self.combine_same(cluster_left, cluster_up);
                     if cluster_left.0 == self.next_index.0 - 1
                         && self.next_index.0 as usize == self.clusters.len()
                     {
@@ -320,25 +538,28 @@ impl BuilderImpl {
                     }
                     cluster_left = cluster_up;
                 } else {
-                    self.combine_clusters(cluster_up, cluster_left);
+                    self.combine_same(cluster_up, cluster_left);
                     cluster_up = cluster_left;
                 }
             }
 
-            let c = color.unwrap();
+            let c = match color {
+                Some(c) => c,
+                None => continue, // masked out
+            };
 
             if has_key && c == key {
                 match keying_action {
                     KeyingAction::Keep => self.get_cluster_mut(ZERO).add(i, &c, x, y),
                     KeyingAction::Discard => {},
                 }
-            } else if self.is_same(color, up) && self.is_same(color, upleft) {
+            } else if self.is_same_at(color, (x, y), up, (x_up, y_up)) && self.is_same_at(color, (x, y), upleft, (x - 1, y - 1)) {
                 self.cluster_indices[i as usize] = cluster_up;
                 self.get_cluster_mut(cluster_up).add(i, &c, x, y);
-            } else if self.is_same(color, left) && self.is_same(color, upleft) {
+            } else if self.is_same_at(color, (x, y), left, (x_left, y_left)) && self.is_same_at(color, (x, y), upleft, (x - 1, y - 1)) {
                 self.cluster_indices[i as usize] = cluster_left;
                 self.get_cluster_mut(cluster_left).add(i, &c, x, y);
-            } else if diagonal && self.is_same(color, upleft) {
+            } else if diagonal && self.is_same_at(color, (x, y), upleft, (x - 1, y - 1)) {
                 self.cluster_indices[i as usize] = cluster_upleft;
                 self.get_cluster_mut(cluster_upleft).add(i, &c, x, y);
             } else {
@@ -354,7 +575,7 @@ impl BuilderImpl {
             }
         }
 
-        self.iteration += batch_size;
+        self.iteration += batch_size as PixelIndex;
         if self.iteration as usize >= self.cluster_indices.len() {
             self.prepare_stage_2();
             true
@@ -363,6 +584,81 @@ impl BuilderImpl {
         }
     }
 
+    /// Stage 1 in the order of `scan`. As neighbours may be visited in any order,
+    /// a pixel joins every visited neighbour of the same color, uniting their clusters.
+    fn stage_1_ordered(&mut self) -> bool {
+        let scan = self.scan.take().unwrap();
+        let has_key = self.key != Color::default();
+        let len = scan.len();
+        let width = self.width as i32;
+        let height = self.height as i32;
+        let neighbours: &[(i32, i32)] = if self.diagonal {
+            &[(-1, 0), (1, 0), (0, -1), (0, 1), (-1, -1), (1, -1), (-1, 1), (1, 1)]
+        } else {
+            &[(-1, 0), (1, 0), (0, -1), (0, 1)]
+        };
+
+        for &i in scan.iter().skip(self.iteration as usize).take(self.batch_size as usize) {
+            let x = (i % self.width) as i32;
+            let y = (i / self.width) as i32;
+            self.visited[i as usize] = true;
+            let c = match self.get_pixel(i) {
+                Some(c) => c,
+                None => continue, // masked out
+            };
+
+            if has_key && c == self.key {
+                if let KeyingAction::Keep = self.keying_action {
+                    self.get_cluster_mut(ZERO).add(i, &c, x, y);
+                }
+                continue;
+            }
+
+            let mut mine = ZERO;
+            for &(dx, dy) in neighbours.iter() {
+                let (nx, ny) = (x + dx, y + dy);
+                if nx < 0 || ny < 0 || nx >= width || ny >= height {
+                    continue;
+                }
+                let n = ny as PixelIndex * self.width + nx as PixelIndex;
+                let other = self.cluster_indices[n as usize];
+                if !self.visited[n as usize] || other == ZERO || other == mine ||
+                    !self.is_same_at(Some(c), (x, y), self.get_pixel(n), (nx, ny)) {
+                    continue;
+                }
+                if mine == ZERO {
+                    mine = other;
+                    self.cluster_indices[i as usize] = mine;
+                    self.get_cluster_mut(mine).add(i, &c, x, y);
+                } else if self.get_cluster(mine).area() <= self.get_cluster(other).area() {
+                    self.combine_same(mine, other);
+                    mine = other;
+                } else {
+                    self.combine_same(other, mine);
+                }
+            }
+
+            if mine == ZERO {
+                let mut new_cluster = Cluster::new();
+                new_cluster.add(i, &c, x, y);
+                self.clusters.push(new_cluster);
+                self.cluster_indices[i as usize] = self.next_index;
+                self.next_index.0 += 1;
+            }
+        }
+
+        self.iteration += self.batch_size as PixelIndex;
+        self.scan = Some(scan);
+        if self.iteration as usize >= len {
+            self.scan = None;
+            self.visited = Vec::new();
+            self.prepare_stage_2();
+            true
+        } else {
+            false
+        }
+    }
+
     fn stage_1_output(&mut self) {
         let mut output = Vec::new();
         for index in 0..self.clusters.len() {
@@ -372,14 +668,26 @@ impl BuilderImpl {
                 output.push((index, area));
             }
         }
-        output.sort_by_key(|c| c.1 as u64 * 65535 + c.0.0 as u64);
-        output.iter().for_each(|c| self.clusters_output.push(c.0));
+        output.sort_by_key(|c| (c.1, c.0));
+        for &(index, _) in output.iter() {
+            self.clusters_output.push(index);
+            self.emit(ClusterEvent::Output { index });
+            self.trace_output(index, OutputReason::Unmerged);
+        }
     }
 
     fn prepare_stage_2(&mut self) {
         for c in self.clusters.iter_mut() {
             c.residue_sum = c.sum;
         }
+        if let Some((image, _, sums)) = &mut self.deep {
+            *sums = self.clusters.iter().map(|c| {
+                c.iter().fold([0.0; 3], |sum, &i| {
+                    let p = image.get_pixel_at(i as usize);
+                    [sum[0] + p.r as f64, sum[1] + p.g as f64, sum[2] + p.b as f64]
+                })
+            }).collect();
+        }
 
         let mut counts = HashMap::new();
 
@@ -400,9 +708,14 @@ impl BuilderImpl {
         areas.sort_by_key(|a| a.area);
 
         self.cluster_areas = areas;
+        self.memory_base = self.memory_usage();
     }
 
     fn stage_2(&mut self) -> bool {
+        // no clusters at all, e.g. of an empty image
+        if self.iteration as usize >= self.cluster_areas.len() {
+            return true;
+        }
         if self.cluster_areas[self.iteration as usize].count == 0 {
             self.iteration += 1;
             if self.iteration as usize == self.cluster_areas.len() {
@@ -414,9 +727,20 @@ impl BuilderImpl {
         let cur_area = self.cluster_areas[self.iteration as usize].area;
         let can_discard_pixels = matches!(self.keying_action, KeyingAction::Discard) && self.key != Color::default();
 
-        for index in 0..self.clusters.len() {
+        // merging only grows clusters, so no other cluster can come down to `cur_area` in this pass
+        let candidates: Vec<_> = (0..self.clusters.len())
+            .map(|index| ClusterIndex(index as ClusterIndexElem))
+            .filter(|&index| self.get_cluster(index).area() == cur_area)
+            .collect();
+        #[cfg(feature = "parallel")]
+        let mut scans = self.scan_neighbours(&candidates, cur_area);
+        #[cfg(not(feature = "parallel"))]
+        let mut scans: Vec<Option<Boundaries>> = vec![None; candidates.len()];
+        // clusters merged away in this pass, which invalidate the scans of their neighbours
+        let mut merged = HashSet::new();
+
+        for (k, &index) in candidates.iter().enumerate() {
 
-            let index = ClusterIndex(index as ClusterIndexElem);
             let mycluster = self.get_cluster(index);
 
             if mycluster.area() != cur_area {
@@ -425,23 +749,33 @@ impl BuilderImpl {
 
             if cur_area > self.hierarchical as usize {
                 self.clusters_output.push(index);
+                self.emit(ClusterEvent::Output { index });
+                self.trace_output(index, OutputReason::Unmerged);
                 continue;
             }
 
             let mycolor = mycluster.color();
-            let mut infos: Vec<_> = mycluster
-                .neighbours_internal(self)
+            let (neighbours, perimeter) = match scans[k].take() {
+                Some(scan) if !scan.0.iter().any(|(other, _)| merged.contains(other)) => scan,
+                _ => mycluster.neighbour_boundaries_internal(self),
+            };
+            let mut infos: Vec<_> = neighbours
                 .iter()
-                .map(|other| NeighbourInfo {
-                    index: *other,
-                    diff: (self.diff)(mycolor, self.get_cluster(*other).color()),
+                .map(|&(other, boundary)| NeighbourInfo {
+                    index: other,
+                    diff: self.cluster_diff(index, mycolor, other),
+                    boundary,
+                    boundary_fraction: boundary as f64 / perimeter as f64,
                 })
                 .collect();
 
             if infos.is_empty() {
-                if self.iteration == self.cluster_areas.len() as ClusterIndexElem - 1  || can_discard_pixels {
+                let last = self.iteration == self.cluster_areas.len() as ClusterIndexElem - 1;
+                if last || can_discard_pixels {
                     // this is either the final background, or an isolated cluster surrounded by keyed, discarded pixels
                     self.clusters_output.push(index);
+                    self.emit(ClusterEvent::Output { index });
+                    self.trace_output(index, if last { OutputReason::Background } else { OutputReason::Keyed });
                 }
                 continue;
             }
@@ -456,6 +790,12 @@ impl BuilderImpl {
                 false
             };
             let hollow = (self.hollow)(self, self.get_cluster(index), &infos);
+            // deepening keeps a copy of the indices, and another in holes if hollow
+            let cost = cur_area * std::mem::size_of::<PixelIndex>() * if hollow { 2 } else { 1 };
+            let deepen = deepen && self.memory_base + self.memory_hierarchy + cost <= self.memory_cap;
+            if deepen {
+                self.memory_hierarchy += cost;
+            }
 
             if deepen {
                 self.clusters_output.push(index);
@@ -467,7 +807,22 @@ impl BuilderImpl {
                 .unwrap();
 
             self.cluster_areas[target_in_areas].count -= 1;
+            let diff = infos[0].diff;
+            if self.trace.is_some() {
+                let rule = match (deepen, hollow) {
+                    (false, _) => MergeRule::Flattened,
+                    (true, false) => MergeRule::Deepen,
+                    (true, true) => MergeRule::Hollow,
+                };
+                let record = MergeRecord { from: index, rule, area: cur_area, diff, neighbours: infos.len() };
+                self.trace_merge(index, target, record, !deepen);
+                if deepen {
+                    self.trace_output(index, OutputReason::Deepened { parent: target });
+                }
+            }
             self.merge_cluster_into(index, target, deepen, hollow);
+            merged.insert(index);
+            self.emit(ClusterEvent::Merged { from: index, to: target, area: cur_area, diff, deepened: deepen, hollow });
             let updated_area = self.clusters[target.0 as usize].area();
 
             match self
@@ -489,7 +844,38 @@ impl BuilderImpl {
         self.iteration as usize == self.cluster_areas.len()
     }
 
+    /// `neighbour_boundaries_internal` of each of `candidates`, scanned concurrently on the current state;
+    /// `None` if too few pixels to be worth the threads, for the merge loop to scan on the way.
+    /// One scoped thread per core, each taking a contiguous chunk: the scans are of similar cost, so there is
+    /// little for a work stealing pool such as rayon to balance, and the feature stays free of dependencies.
+    #[cfg(feature = "parallel")]
+    fn scan_neighbours(&self, candidates: &[ClusterIndex], cur_area: usize) -> Vec<Option<Boundaries>> {
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        if threads < 2 || cur_area > self.hierarchical as usize || candidates.len() * cur_area < PARALLEL_MIN_PIXELS {
+            return vec![None; candidates.len()];
+        }
+        let (width, height) = (self.width, self.height);
+        let (clusters, cluster_indices) = (&self.clusters, &self.cluster_indices);
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = candidates
+                .chunks(candidates.len().div_ceil(threads))
+                .map(|chunk| scope.spawn(move || {
+                    chunk.iter()
+                        .map(|index| Some(clusters[index.0 as usize].neighbour_boundaries_in(width, height, cluster_indices)))
+                        .collect::<Vec<_>>()
+                }))
+                .collect();
+            handles.into_iter().flat_map(|handle| handle.join().unwrap()).collect()
+        })
+    }
+
     pub fn merge_cluster_into(&mut self, from: ClusterIndex, to: ClusterIndex, deepen: bool, hollow: bool) {
+        if let Some((_, _, sums)) = &mut self.deep {
+            let from_sum = sums[from.0 as usize];
+            for (s, f) in sums[to.0 as usize].iter_mut().zip(from_sum) {
+                *s += f;
+            }
+        }
         if !deepen {
             let residue_sum = self.clusters[from.0 as usize].residue_sum;
             self.clusters[to.0 as usize].residue_sum.merge(&residue_sum);
@@ -520,6 +906,43 @@ impl BuilderImpl {
         self.clusters[from.0 as usize].indices = indices;
     }
 
+    /// `combine_clusters` of two patches of the same color in stage 1
+    fn combine_same(&mut self, from: ClusterIndex, to: ClusterIndex) {
+        if self.trace.is_some() {
+            let record = MergeRecord { from, rule: MergeRule::SameColor, area: self.get_cluster(from).area(), diff: 0, neighbours: 0 };
+            self.trace_merge(from, to, record, true);
+        }
+        self.combine_clusters(from, to);
+    }
+
+    fn trace_entry(&mut self, index: ClusterIndex) -> Option<&mut ClusterTrace> {
+        let trace = self.trace.as_mut()?;
+        let i = index.0 as usize;
+        if trace.len() <= i {
+            trace.resize(i + 1, ClusterTrace::default());
+        }
+        Some(&mut trace[i])
+    }
+
+    /// Records `record` on `to`; with the history of `from` too, if `from` is gone into `to`
+    fn trace_merge(&mut self, from: ClusterIndex, to: ClusterIndex, record: MergeRecord, gone: bool) {
+        let mut history = match self.trace_entry(from) {
+            Some(entry) if gone => std::mem::take(&mut entry.merges),
+            Some(_) => Vec::new(),
+            None => return,
+        };
+        if let Some(entry) = self.trace_entry(to) {
+            entry.merges.append(&mut history);
+            entry.merges.push(record);
+        }
+    }
+
+    fn trace_output(&mut self, index: ClusterIndex, reason: OutputReason) {
+        if let Some(entry) = self.trace_entry(index) {
+            entry.reason = Some(reason);
+        }
+    }
+
     fn combine_clusters(&mut self, from: ClusterIndex, to: ClusterIndex) {
         for &i in self.clusters[from.0 as usize].indices.iter() {
             self.cluster_indices[i as usize] = to;
@@ -535,6 +958,20 @@ impl BuilderImpl {
         self.clusters[from.0 as usize].rect.clear();
     }
 
+    /// `diff` of cluster `a` (of color `a_color`) and cluster `b`, or the deep diff if set
+    fn cluster_diff(&self, a: ClusterIndex, a_color: Color, b: ClusterIndex) -> i32 {
+        match &self.deep {
+            Some((_, diff, sums)) => {
+                let average = |index: ClusterIndex| {
+                    let (sum, area) = (sums[index.0 as usize], self.get_cluster(index).area() as f64);
+                    ColorRgba::new_rgba((sum[0] / area) as f32, (sum[1] / area) as f32, (sum[2] / area) as f32, 1.0)
+                };
+                diff(average(a), average(b))
+            },
+            None => (self.diff)(a_color, self.get_cluster(b).color()),
+        }
+    }
+
     fn is_same(&self, left: Option<Color>, right: Option<Color>) -> bool {
         if let (Some(l), Some(r)) = (left, right) {
             (self.same)(l, r)
@@ -543,15 +980,29 @@ impl BuilderImpl {
         }
     }
 
+    /// `is_same` of the pixels at `a` and `b`, with `block_same` if they are in different blocks
+    fn is_same_at(&self, left: Option<Color>, a: (i32, i32), right: Option<Color>, b: (i32, i32)) -> bool {
+        match (&self.block_same, left, right) {
+            (Some(block_same), Some(l), Some(r)) if across_blocks(a, b, self.block_size) => block_same(l, r),
+            _ => self.is_same(left, right),
+        }
+    }
+
     fn pixel_at(&self, x: i32, y: i32) -> Option<Color> {
         if x < 0 || y < 0 {
             return None;
         }
 
-        self.get_pixel(y as u32 * self.width + x as u32)
+        self.get_pixel(y as PixelIndex * self.width + x as PixelIndex)
     }
 
-    fn get_pixel(&self, i: u32) -> Option<Color> {
+    /// `None` if out of bounds or masked out
+    fn get_pixel(&self, i: PixelIndex) -> Option<Color> {
+        if let Some(mask) = &self.mask {
+            if (i as usize) < mask.pixels.len() && !mask.pixels[i as usize] {
+                return None;
+            }
+        }
         let i = i as usize * 4;
         if i < self.pixels.len() {
             Some(Color::new_rgba(
@@ -565,3 +1016,137 @@ impl BuilderImpl {
         }
     }
 }
+
+/// Pixel indices of a `width` x `height` image along a Hilbert curve.
+/// The curve fills the smallest power of two square over the image; blocks of it outside the image
+/// are skipped whole, so the cost is linear in the number of pixels.
+fn hilbert_order(width: PixelIndex, height: PixelIndex) -> Vec<PixelIndex> {
+    let mut order = Vec::with_capacity(width as usize * height as usize);
+    let n = std::cmp::max(width, height).next_power_of_two() as i64;
+    hilbert_block(&mut order, (width as i64, height as i64), (0, 0), [1, 0, 0, 1], n);
+    order
+}
+
+/// Appends the pixels of the Hilbert curve of a `size` square block, whose point (u, v) is at
+/// `origin + axes * (u, v)` in the image, `axes` a row major 2x2 matrix of a rotation or reflection
+fn hilbert_block(order: &mut Vec<PixelIndex>, (width, height): (i64, i64), origin: (i64, i64), axes: [i64; 4], size: i64) {
+    // extent of the block along x and y
+    let extent = |o: i64, a: i64, b: i64| if a + b > 0 { (o, o + size - 1) } else { (o - size + 1, o) };
+    let (left, right) = extent(origin.0, axes[0], axes[1]);
+    let (top, bottom) = extent(origin.1, axes[2], axes[3]);
+    if right < 0 || bottom < 0 || left >= width || top >= height {
+        return;
+    }
+    if size == 1 {
+        order.push((origin.1 * width + origin.0) as PixelIndex);
+        return;
+    }
+    let half = size / 2;
+    let at = |u: i64, v: i64| (origin.0 + axes[0] * u + axes[1] * v, origin.1 + axes[2] * u + axes[3] * v);
+    let swapped = [axes[1], axes[0], axes[3], axes[2]];
+    // the quadrants in the order of the curve; the first is transposed, the last transposed and flipped
+    hilbert_block(order, (width, height), at(0, 0), swapped, half);
+    hilbert_block(order, (width, height), at(0, half), axes, half);
+    hilbert_block(order, (width, height), at(half, half), axes, half);
+    hilbert_block(order, (width, height), at(size - 1, half - 1), swapped.map(|a| -a), half);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{cell::RefCell, rc::Rc};
+    use crate::color_clusters::{Runner, RunnerConfig, black_square_on_white};
+
+    #[test]
+    fn cluster_events() {
+        let image = black_square_on_white();
+        let events = Rc::new(RefCell::new(Vec::new()));
+        let log = events.clone();
+        let clusters = Runner::new(RunnerConfig::default(), image)
+            .builder()
+            .on_cluster_event(move |event| log.borrow_mut().push(event))
+            .run();
+        let events = events.borrow();
+        assert_eq!(events.last(), Some(&ClusterEvent::Finished));
+        let created = events.iter().filter(|e| matches!(e, ClusterEvent::Created { .. })).count();
+        let merged = events.iter().filter(|e| matches!(e, ClusterEvent::Merged { .. })).count();
+        let output = events.iter().filter(|e| matches!(e, ClusterEvent::Output { .. })).count();
+        // each patch but the background is merged once
+        assert_eq!(merged + 1, created);
+        assert_eq!(output, 1);
+        let deepened = events.iter().filter(|e| matches!(e, ClusterEvent::Merged { deepened: true, .. })).count();
+        assert_eq!(deepened + output, clusters.output_len());
+        assert!(events.iter().any(|e| matches!(e, ClusterEvent::Merged { area: 36, diff, deepened: true, .. } if *diff > 64)));
+    }
+
+    #[test]
+    fn hilbert_scan() {
+        let order = hilbert_order(5, 3);
+        let mut sorted = order.clone();
+        sorted.sort_unstable();
+        assert_eq!(sorted, (0..15).collect::<Vec<_>>());
+        // consecutive pixels of a full square are adjacent
+        let square = hilbert_order(8, 8);
+        assert_eq!(&square[..4], &[0, 8, 9, 1]);
+        assert!(square.windows(2).all(|w| {
+            let (a, b) = (w[0] as i64, w[1] as i64);
+            ((a % 8 - b % 8).abs() + (a / 8 - b / 8).abs()) == 1
+        }));
+        assert_eq!(hilbert_order(1, 1 << 16).len(), 1 << 16);
+    }
+
+    #[test]
+    fn memory_cap_bounds_hierarchy() {
+        // nested square bands, each deepened into the one around it
+        let mut image = ColorImage::new_w_h(32, 32);
+        for y in 0..32i32 {
+            for x in 0..32i32 {
+                let band = (x - 16).abs().max((y - 16).abs()) as u8 / 4;
+                image.set_pixel(x as usize, y as usize, &Color::new(if band & 1 == 0 { 250 } else { 0 }, band * 30, 0));
+            }
+        }
+        let run = |memory_cap: usize| {
+            let config = RunnerConfig { memory_cap, hierarchical: HIERARCHICAL_MAX, ..RunnerConfig::default() };
+            let mut bimpl = BuilderImpl::from(Runner::new(config, image.clone()).builder());
+            while !bimpl.tick() {}
+            (bimpl.memory_base, bimpl.memory_hierarchy, bimpl.result().output_len())
+        };
+        let (base, hierarchy, outputs) = run(usize::MAX);
+        assert!(hierarchy > 0);
+        let cap = base + hierarchy / 2;
+        let (capped_base, capped_hierarchy, capped_outputs) = run(cap);
+        assert_eq!(capped_base, base);
+        assert!(capped_base + capped_hierarchy <= cap);
+        assert!(capped_outputs < outputs);
+    }
+
+    #[cfg(feature = "parallel")]
+    #[test]
+    fn parallel_scans() {
+        // speckles of 2x2 pixels, so that a pass of stage 2 is large enough to be split
+        let mut image = ColorImage::new_w_h(256, 256);
+        for y in 0..256 {
+            for x in 0..256 {
+                let v = ((x / 2 * 7 + y / 2 * 13) % 5 * 60) as u8;
+                image.set_pixel(x, y, &Color::new(v, v, v));
+            }
+        }
+        let mut bimpl = BuilderImpl::from(Runner::new(RunnerConfig::default(), image).builder());
+        while bimpl.stage == 1 {
+            bimpl.tick();
+        }
+        let candidates: Vec<_> = (1..bimpl.clusters.len())
+            .map(|index| ClusterIndex(index as ClusterIndexElem))
+            .filter(|&index| bimpl.get_cluster(index).area() == 4)
+            .collect();
+        let scans = bimpl.scan_neighbours(&candidates, 4);
+        assert_eq!(scans.len(), candidates.len());
+        let threads = std::thread::available_parallelism().map_or(1, |n| n.get());
+        assert!(threads < 2 || scans.iter().all(Option::is_some));
+        for (scan, &index) in scans.into_iter().zip(candidates.iter()) {
+            if let Some(scan) = scan {
+                assert_eq!(scan, bimpl.get_cluster(index).neighbour_boundaries_internal(&bimpl));
+            }
+        }
+    }
+}
@@ -1,13 +1,13 @@
-use std::collections::HashSet;
-use crate::{BinaryImage, BoundingRect, Color, ColorImage, ColorSum, CompoundPath, PointI32, PathSimplifyMode, Shape};
+use std::collections::{HashMap, HashSet};
+use crate::{BinaryImage, BoundingRect, Color, ColorImage, ColorSum, CompoundPath, Perimeter, PointF64, PointI32, PathSimplifyMode, Shape, sdf_from_mask};
 use crate::clusters::Cluster as BinaryCluster;
-use super::container::{ClusterIndex, ClustersView};
+use super::container::{ClusterIndex, ClustersView, PixelIndex};
 use super::builder::{BuilderImpl, ZERO};
 
 #[derive(Clone, Default)]
 pub struct Cluster {
-    pub indices: Vec<u32>,
-    pub holes: Vec<u32>,
+    pub indices: Vec<PixelIndex>,
+    pub holes: Vec<PixelIndex>,
     pub num_holes: u32,
     pub depth: u32,
     pub sum: ColorSum,
@@ -21,7 +21,7 @@ impl Cluster {
         Self::default()
     }
 
-    pub fn add(&mut self, i: u32, color: &Color, x: i32, y: i32) {
+    pub fn add(&mut self, i: PixelIndex, color: &Color, x: i32, y: i32) {
         self.indices.push(i);
         self.sum.add(color);
         self.rect.add_x_y(x, y);
@@ -31,7 +31,7 @@ impl Cluster {
         self.indices.len()
     }
 
-    pub fn iter(&self) -> impl Iterator<Item = &u32> {
+    pub fn iter(&self) -> impl Iterator<Item = &PixelIndex> {
         self.indices.iter()
     }
 
@@ -43,9 +43,45 @@ impl Cluster {
         self.residue_sum.average()
     }
     
+    /// Number of pixels on the boundary, i.e. `Perimeter::Inner4`; see `perimeter_by` for other definitions
     pub fn perimeter(&self, parent: &ClustersView) -> u32 {
         Shape::image_boundary_list(&self.to_image(parent)).len() as u32
     }
+
+    /// Perimeter by any of the definitions of `Perimeter`; `Perimeter::Inner4` is the same as `perimeter`
+    pub fn perimeter_by(&self, parent: &ClustersView, kind: Perimeter) -> f64 {
+        self.to_image(parent).perimeter(kind)
+    }
+    /// The most interior point of the cluster and its distance to the boundary, i.e. the center and radius
+    /// of the maximum inscribed circle, accurate to the pixel. Holes count as outside.
+    /// Useful for placing labels inside shapes.
+    pub fn pole_of_inaccessibility(&self, parent: &ClustersView) -> (PointF64, f64) {
+        let image = self.to_image(parent);
+        // padded, so that the bounding box edge counts as outside
+        let mut padded = BinaryImage::new_w_h(image.width + 2, image.height + 2);
+        for y in 0..image.height {
+            for x in 0..image.width {
+                padded.set_pixel(x + 1, y + 1, image.get_pixel(x, y));
+            }
+        }
+        let sdf = sdf_from_mask(&padded, f64::MAX);
+        let (mut best, mut best_distance) = (0, 0.0);
+        for (i, &d) in sdf.channel(0).iter().enumerate() {
+            if d < best_distance {
+                best = i;
+                best_distance = d;
+            }
+        }
+        let (x, y) = (best % padded.width, best / padded.width);
+        (
+            PointF64::new(
+                (self.rect.left + x as i32 - 1) as f64 + 0.5,
+                (self.rect.top + y as i32 - 1) as f64 + 0.5,
+            ),
+            -best_distance as f64,
+        )
+    }
+
     pub(crate) fn perimeter_internal(&self, internal: &BuilderImpl) -> u32 {
         Shape::image_boundary_list(&self.to_image_internal(internal)).len() as u32
     }
@@ -57,21 +93,21 @@ impl Cluster {
         self.to_image_with_hole(internal.width, true)
     }
 
-    pub fn to_image_with_hole(&self, parent_width: u32, hole: bool) -> BinaryImage {
+    pub fn to_image_with_hole(&self, parent_width: PixelIndex, hole: bool) -> BinaryImage {
         let width = self.rect.width() as usize;
         let height = self.rect.height() as usize;
         let mut image = BinaryImage::new_w_h(width, height);
 
         for &i in self.iter() {
-            let x = (i as i32 % parent_width as i32) - self.rect.left;
-            let y = (i as i32 / parent_width as i32) - self.rect.top;
+            let x = (i % parent_width) as i32 - self.rect.left;
+            let y = (i / parent_width) as i32 - self.rect.top;
             image.set_pixel(x as usize, y as usize, true);
         }
 
         if hole {
             for &i in self.holes.iter() {
-                let x = (i as i32 % parent_width as i32) - self.rect.left;
-                let y = (i as i32 / parent_width as i32) - self.rect.top;
+                let x = (i % parent_width) as i32 - self.rect.left;
+                let y = (i / parent_width) as i32 - self.rect.top;
                 image.set_pixel(x as usize, y as usize, false);
             }
         }
@@ -100,6 +136,47 @@ impl Cluster {
         }
     }
 
+    /// Horizontal runs `(y, left, right)` of the pixels of the cluster, `right` exclusive,
+    /// sorted top to bottom and left to right
+    pub fn spans(&self, parent: &ClustersView) -> Vec<(usize, usize, usize)> {
+        let mut indices = self.indices.clone();
+        indices.sort_unstable();
+        let width = parent.width as usize;
+        let mut spans: Vec<(usize, usize, usize)> = Vec::new();
+        for i in indices.into_iter().map(|i| i as usize) {
+            let (x, y) = (i % width, i / width);
+            match spans.last_mut() {
+                Some(span) if span.0 == y && span.2 == x => span.2 += 1,
+                _ => spans.push((y, x, x + 1)),
+            }
+        }
+        spans
+    }
+
+    /// Fills the pixels of the cluster into `buffer`, a row major framebuffer with rows `stride` pixels apart,
+    /// scanline by scanline. `color` is written as is, so it must be packed in the format of the buffer.
+    pub fn fill_into(&self, parent: &ClustersView, buffer: &mut [u32], stride: usize, color: u32) {
+        assert!(stride >= parent.width as usize);
+        for (y, left, right) in self.spans(parent) {
+            buffer[y * stride + left..y * stride + right].fill(color);
+        }
+    }
+
+    /// Pastes the pixels of the cluster (from the image of `parent`) into `target`, moved by `offset`,
+    /// blended in by `ColorImage::seamless_clone`
+    pub fn seamless_clone_into(&self, parent: &ClustersView, target: &mut ColorImage, offset: PointI32) {
+        let mask = self.to_image(parent);
+        let mut source = ColorImage::new_w_h(mask.width, mask.height);
+        for y in 0..mask.height {
+            for x in 0..mask.width {
+                if let Some(color) = parent.get_pixel(self.rect.left + x as i32, self.rect.top + y as i32) {
+                    source.set_pixel(x, y, &color);
+                }
+            }
+        }
+        target.seamless_clone(&source, &mask, PointI32::new(self.rect.left + offset.x, self.rect.top + offset.y));
+    }
+
     pub fn to_shape(&self, parent: &ClustersView) -> Shape {
         self.to_image(parent).into()
     }
@@ -155,31 +232,58 @@ impl Cluster {
     }
 
     /// Equivalent to [`neighbours()`] but operates on `BuilderImpl` directly, 
-    /// removing the overhead of constructing a `ClustersView`
-    pub(crate) fn neighbours_internal(&self, internal: &BuilderImpl) -> Vec<ClusterIndex> {
-        let myself = internal.cluster_indices[*self.indices.first().unwrap() as usize];
-        let mut neighbours = HashSet::new();
+    /// removing the overhead of constructing a `ClustersView`.
+    /// Each neighbour comes with the length of the boundary shared with this cluster, followed by
+    /// the length of the whole boundary of this cluster (including the image border), in pixel edges.
+    pub(crate) fn neighbour_boundaries_internal(&self, internal: &BuilderImpl) -> (Vec<(ClusterIndex, usize)>, usize) {
+        self.neighbour_boundaries_in(internal.width, internal.height, &internal.cluster_indices)
+    }
+
+    /// `neighbour_boundaries_internal` given only the cluster indices of a `width` x `height` image
+    pub(crate) fn neighbour_boundaries_in(&self, width: PixelIndex, height: PixelIndex, cluster_indices: &[ClusterIndex]) -> (Vec<(ClusterIndex, usize)>, usize) {
+        let myself = cluster_indices[*self.indices.first().unwrap() as usize];
+        let mut neighbours = HashMap::new();
+        let mut perimeter = 0;
 
         for &i in self.iter() {
-            let x = i % internal.width;
-            let y = i / internal.width;
+            let x = i % width;
+            let y = i / width;
 
             for k in 0..4 {
                 let index = match k {
-                    0 => if y > 0 { internal.cluster_indices[(internal.width * (y - 1) + x) as usize] } else { ZERO },
-                    1 => if y < internal.height - 1 { internal.cluster_indices[(internal.width * (y + 1) + x) as usize] } else { ZERO },
-                    2 => if x > 0 { internal.cluster_indices[(internal.width * y + (x - 1)) as usize] } else { ZERO },
-                    3 => if x < internal.width - 1 { internal.cluster_indices[(internal.width * y + (x + 1)) as usize] } else { ZERO },
+                    0 => if y > 0 { cluster_indices[(width * (y - 1) + x) as usize] } else { ZERO },
+                    1 => if y < height - 1 { cluster_indices[(width * (y + 1) + x) as usize] } else { ZERO },
+                    2 => if x > 0 { cluster_indices[(width * y + (x - 1)) as usize] } else { ZERO },
+                    3 => if x < width - 1 { cluster_indices[(width * y + (x + 1)) as usize] } else { ZERO },
                     _ => unreachable!(),
                 };
-                if index != ZERO && index != myself {
-                    neighbours.insert(index);
+                if index != myself {
+                    perimeter += 1;
+                    if index != ZERO {
+                        *neighbours.entry(index).or_insert(0) += 1;
+                    }
                 }
             }
         }
 
-        let mut list: Vec<ClusterIndex> = neighbours.into_iter().collect();
+        let mut list: Vec<(ClusterIndex, usize)> = neighbours.into_iter().collect();
         list.sort();
-        list
+        (list, perimeter)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::color_clusters::{ClusterIndex, Runner, RunnerConfig, black_square_on_white};
+
+    #[test]
+    fn pole_of_square() {
+        let image = black_square_on_white();
+        let clusters = Runner::new(RunnerConfig::default(), image).run();
+        let view = clusters.view();
+        let inner = ClusterIndex(clusters.label_map()[4 * 12 + 4]);
+        let (center, radius) = view.get_cluster(inner).pole_of_inaccessibility(&view);
+        assert_eq!(radius, 2.5);
+        assert!((5.5..=6.5).contains(&center.x) && (5.5..=6.5).contains(&center.y));
     }
 }
@@ -1,19 +1,47 @@
-use crate::{Color, ColorImage, PointI32};
-use super::Cluster;
+use std::collections::{HashMap, HashSet};
+use std::hash::Hash;
+use crate::{BinaryImage, Color, ColorImage, PointI32};
+use super::{Cluster, ClusterTrace, Runner, RunnerConfig, ZERO};
 
 pub struct Clusters {
-    pub width: u32,
-    pub height: u32,
+    pub width: PixelIndex,
+    pub height: PixelIndex,
     pub(crate) pixels: Vec<u8>,
     pub(crate) clusters: Vec<Cluster>,
     pub(crate) cluster_indices: Vec<ClusterIndex>,
     pub(crate) clusters_output: Vec<ClusterIndex>, // valid outputs. Valid outputs are clusters with at least one pixel.
+    /// history of each cluster, empty if not traced
+    pub(crate) trace: Vec<ClusterTrace>,
 }
 
-#[derive(Copy, Clone, Default, Eq, Ord, Hash, PartialEq, PartialOrd)]
+#[derive(Copy, Clone, Debug, Default, Eq, Ord, Hash, PartialEq, PartialOrd)]
 pub struct ClusterIndex(pub ClusterIndexElem);
 
+#[cfg(not(feature = "large-images"))]
 pub type ClusterIndexElem = u32;
+#[cfg(feature = "large-images")]
+pub type ClusterIndexElem = u64;
+
+/// Index of a pixel in row major order; `u64` with the `large-images` feature,
+/// for images of more than 2^32 pixels
+#[cfg(not(feature = "large-images"))]
+pub type PixelIndex = u32;
+#[cfg(feature = "large-images")]
+pub type PixelIndex = u64;
+
+/// Panics if a `width` x `height` image has too many pixels to be indexed by `PixelIndex`,
+/// instead of letting the indices silently wrap around
+pub(crate) fn check_capacity(width: usize, height: usize) {
+    let pixels = width.checked_mul(height).and_then(|n| PixelIndex::try_from(n).ok());
+    assert!(
+        pixels.is_some_and(|n| n < PixelIndex::MAX),
+        "{}x{} image is too large to be clustered; enable the `large-images` feature", width, height
+    );
+    #[cfg(not(feature = "large-images"))]
+    if pixels.unwrap() > 1 << 24 {
+        log::warn!("color sums of a {}x{} image may overflow; enable the `large-images` feature", width, height);
+    }
+}
 
 impl Clusters {
     pub fn output_len(&self) -> usize {
@@ -31,6 +59,76 @@ impl Clusters {
         }
     }
 
+    /// Constructs clusters from a segmentation produced elsewhere. `labels` (row major) assigns each pixel
+    /// of `image` to a segment; each distinct label becomes one cluster, whether connected or not.
+    /// The result is flat: there is no hierarchy among the clusters.
+    pub fn from_label_map<L: Copy + Eq + Hash>(width: PixelIndex, height: PixelIndex, labels: &[L], image: ColorImage) -> Self {
+        assert_eq!(labels.len(), width as usize * height as usize);
+        check_capacity(width as usize, height as usize);
+        assert_eq!(image.width, width as usize);
+        assert_eq!(image.height, height as usize);
+
+        let mut index_of_label = HashMap::new();
+        let mut clusters = vec![Cluster::new()]; // ZERO is reserved
+        let mut cluster_indices = Vec::with_capacity(labels.len());
+        for (i, &label) in labels.iter().enumerate() {
+            let index = *index_of_label.entry(label).or_insert_with(|| {
+                clusters.push(Cluster::new());
+                ClusterIndex((clusters.len() - 1) as ClusterIndexElem)
+            });
+            let (x, y) = (i % width as usize, i / width as usize);
+            clusters[index.0 as usize].add(i as PixelIndex, &image.get_pixel_at(i), x as i32, y as i32);
+            cluster_indices.push(index);
+        }
+        for cluster in clusters.iter_mut() {
+            cluster.residue_sum = cluster.sum;
+        }
+
+        // small to large, same as the builder
+        let mut clusters_output: Vec<_> = (1..clusters.len())
+            .map(|i| ClusterIndex(i as ClusterIndexElem))
+            .collect();
+        clusters_output.sort_by_key(|&i| (clusters[i.0 as usize].area(), i));
+
+        Self {
+            width: width as PixelIndex,
+            height: height as PixelIndex,
+            pixels: image.pixels,
+            clusters,
+            cluster_indices,
+            clusters_output,
+            trace: Vec::new(),
+        }
+    }
+
+    /// See `ClustersView::label_map`
+    pub fn label_map(&self) -> Vec<ClusterIndexElem> {
+        self.view().label_map()
+    }
+
+    /// See `ClustersView::label_map_at_depth`
+    pub fn label_map_at_depth(&self, depth: u32) -> Vec<ClusterIndexElem> {
+        self.view().label_map_at_depth(depth)
+    }
+
+    /// Clusters the pixels of the cluster at `index` anew with `config`, e.g. with finer thresholds for more detail
+    /// on one object. The result is cropped to the bounding rect of the cluster, as in `ClustersView::extract_subtree`;
+    /// pixels outside the cluster are masked out.
+    pub fn recluster(&self, index: ClusterIndex, config: RunnerConfig) -> Clusters {
+        let cluster = &self.clusters[index.0 as usize];
+        let rect = cluster.rect;
+        let (width, height) = (rect.width() as usize, rect.height() as usize);
+        let mut image = ColorImage::new_w_h(width, height);
+        let mut mask = BinaryImage::new_w_h(width, height);
+        for &i in cluster.indices.iter() {
+            let (x, y) = ((i % self.width) as usize - rect.left as usize, (i / self.width) as usize - rect.top as usize);
+            let p = i as usize * 4;
+            image.set_pixel(x, y, &Color::new_rgba(self.pixels[p], self.pixels[p + 1], self.pixels[p + 2], self.pixels[p + 3]));
+            mask.set_pixel(x, y, true);
+        }
+        Runner::new(config, image).with_mask(mask).run()
+    }
+
     pub fn take_image(self) -> ColorImage {
         ColorImage {
             pixels: self.pixels,
@@ -41,8 +139,8 @@ impl Clusters {
 }
 
 pub struct ClustersView<'a> {
-    pub width: u32,
-    pub height: u32,
+    pub width: PixelIndex,
+    pub height: PixelIndex,
     pub pixels: &'a [u8],
     pub clusters: &'a [Cluster],
     pub cluster_indices: &'a [ClusterIndex],
@@ -69,11 +167,11 @@ impl ClustersView<'_> {
     }
 
     pub fn get_cluster_at_point(&self, point: PointI32) -> ClusterIndex {
-        let index = (point.y * self.width as i32 + point.x) as u32;
+        let index = point.y as PixelIndex * self.width + point.x as PixelIndex;
         self.get_cluster_at(index)
     }
 
-    pub fn get_cluster_at(&self, index: u32) -> ClusterIndex {
+    pub fn get_cluster_at(&self, index: PixelIndex) -> ClusterIndex {
         self.cluster_indices[index as usize]
     }
 
@@ -81,14 +179,14 @@ impl ClustersView<'_> {
         if x < 0 || y < 0 {
             return None;
         }
-        if x as u32 >= self.width {
+        if x as PixelIndex >= self.width {
             return None;
         }
-        let index = (y * self.width as i32 + x) as u32;
+        let index = y as PixelIndex * self.width + x as PixelIndex;
         self.get_pixel_at_index(index)
     }
 
-    pub fn get_pixel_at_index(&self, index: u32) -> Option<Color> {
+    pub fn get_pixel_at_index(&self, index: PixelIndex) -> Option<Color> {
         let index = index as usize * 4;
         if index >= self.pixels.len() {
             return None;
@@ -101,6 +199,100 @@ impl ClustersView<'_> {
         Some(Color::new_rgba(r, g, b, a))
     }
 
+    /// A standalone `Clusters` of the cluster at `index` and the output clusters nested inside it,
+    /// cropped to its bounding rect (so coordinates are relative to its top left).
+    /// Pixels of the crop outside the cluster keep their color, but belong to no cluster.
+    pub fn extract_subtree(&self, index: ClusterIndex) -> Clusters {
+        let root = self.get_cluster(index);
+        let rect = root.rect;
+        let (width, height) = (rect.width() as PixelIndex, rect.height() as PixelIndex);
+        let mut inside = vec![false; self.cluster_indices.len()];
+        root.indices.iter().for_each(|&i| inside[i as usize] = true);
+        let rebase = |i: PixelIndex| {
+            let (x, y) = (i % self.width, i / self.width);
+            (y - rect.top as PixelIndex) * width + (x - rect.left as PixelIndex)
+        };
+
+        // smaller clusters come first in the output, so the root is last
+        let subtree: Vec<ClusterIndex> = self.clusters_output
+            .iter()
+            .copied()
+            .filter(|&i| i == index || {
+                let cluster = self.get_cluster(i);
+                cluster.area() < root.area() && cluster.indices.iter().all(|&p| inside[p as usize])
+            })
+            .collect();
+        let mut clusters = vec![Cluster::new()]; // ZERO is reserved
+        for &i in subtree.iter() {
+            let cluster = self.get_cluster(i);
+            let mut rebased = Cluster {
+                indices: cluster.indices.iter().map(|&p| rebase(p)).collect(),
+                holes: cluster.holes.iter().map(|&p| rebase(p)).collect(),
+                merged_into: ZERO,
+                ..cluster.clone()
+            };
+            rebased.rect.translate(PointI32::new(-rect.left, -rect.top));
+            clusters.push(rebased);
+        }
+        let clusters_output: Vec<_> = (1..clusters.len())
+            .map(|i| ClusterIndex(i as ClusterIndexElem))
+            .collect();
+
+        // as in the builder, pixels map to the cluster they ended up in, which is the root if outside the subtree
+        let new_index: HashMap<ClusterIndex, ClusterIndex> = subtree.iter().copied().zip(clusters_output.iter().copied()).collect();
+        let new_root = *clusters_output.last().unwrap();
+        let mut cluster_indices = vec![ZERO; (width * height) as usize];
+        for &p in root.indices.iter() {
+            let old = self.cluster_indices[p as usize];
+            cluster_indices[rebase(p) as usize] = *new_index.get(&old).unwrap_or(&new_root);
+        }
+        let mut pixels = Vec::with_capacity(cluster_indices.len() * 4);
+        for y in rect.top..rect.bottom {
+            let start = (y as usize * self.width as usize + rect.left as usize) * 4;
+            pixels.extend_from_slice(&self.pixels[start..start + width as usize * 4]);
+        }
+
+        Clusters {
+            width,
+            height,
+            pixels,
+            clusters,
+            cluster_indices,
+            clusters_output,
+            trace: Vec::new(),
+        }
+    }
+
+    /// Length (in pixel edges) of the boundary between the pixels of clusters `a` and `b`
+    pub fn shared_boundary_length(&self, a: ClusterIndex, b: ClusterIndex) -> usize {
+        let others: HashSet<PixelIndex> = self.get_cluster(b).indices.iter().copied().collect();
+        let (width, height) = (self.width, self.height);
+        self.get_cluster(a).indices.iter().map(|&i| {
+            let (x, y) = (i % width, i / width);
+            [
+                (y > 0).then(|| i - width),
+                (y + 1 < height).then(|| i + width),
+                (x > 0).then(|| i - 1),
+                (x + 1 < width).then(|| i + 1),
+            ].iter().flatten().filter(|n| others.contains(n)).count()
+        }).sum()
+    }
+
+    /// See `Cluster::fill_into`
+    pub fn fill_cluster_into(&self, index: ClusterIndex, buffer: &mut [u32], stride: usize, color: u32) {
+        self.get_cluster(index).fill_into(self, buffer, stride, color);
+    }
+
+    /// The image with the pixels of the cluster at `index` removed and filled from their surroundings,
+    /// see `ColorImage::inpaint`; e.g. to cluster again without the object
+    pub fn inpaint_cluster(&self, index: ClusterIndex, radius: usize) -> ColorImage {
+        let (width, height) = (self.width as usize, self.height as usize);
+        let image = ColorImage { pixels: self.pixels.to_vec(), width, height };
+        let mut mask = BinaryImage::new_w_h(width, height);
+        self.get_cluster(index).render_to_binary_image(self, &mut mask);
+        image.inpaint(&mask, radius)
+    }
+
     pub fn to_color_image(&self) -> ColorImage {
         let mut image = ColorImage::new_w_h(self.width as usize, self.height as usize);
 
@@ -114,6 +306,35 @@ impl ClustersView<'_> {
 
         image
     }
+
+    /// Maps each pixel (row major) to the index of the output cluster drawn on top of it,
+    /// i.e. the same cluster as in `to_color_image`; 0 if the pixel belongs to no output cluster.
+    pub fn label_map(&self) -> Vec<ClusterIndexElem> {
+        self.label_map_at_depth(u32::MAX)
+    }
+
+    /// Like `label_map`, but descends the hierarchy at most `depth` levels;
+    /// depth 0 labels each pixel with its outermost cluster.
+    pub fn label_map_at_depth(&self, depth: u32) -> Vec<ClusterIndexElem> {
+        let mut labels = vec![ZERO.0; self.width as usize * self.height as usize];
+        let mut levels = vec![0; self.clusters.len()];
+
+        // from largest to smallest, so that a cluster finds its parent already labelled
+        for &index in self.clusters_output.iter().rev() {
+            let cluster = self.get_cluster(index);
+            let parent = cluster.indices.first().map_or(ZERO.0, |&i| labels[i as usize]);
+            let level = if parent == ZERO.0 { 0 } else { levels[parent as usize] + 1 };
+            levels[index.0 as usize] = level;
+            if level > depth {
+                continue;
+            }
+            for &i in cluster.indices.iter() {
+                labels[i as usize] = index.0;
+            }
+        }
+
+        labels
+    }
 }
 
 impl<'a> Iterator for ClustersOutputIterator<'a> {
@@ -129,4 +350,173 @@ impl<'a> Iterator for ClustersOutputIterator<'a> {
             None
         }
     }
-}
\ No newline at end of file
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BoundingRect;
+    use crate::color_clusters::{BuilderImpl, NeighbourInfo, Runner, RunnerConfig, black_square_on_white};
+
+    #[test]
+    fn label_map_nested() {
+        let image = black_square_on_white();
+        let clusters = Runner::new(RunnerConfig::default(), image).run();
+        let labels = clusters.label_map();
+        assert_eq!(labels.len(), 12 * 12);
+        let outer = labels[0];
+        let inner = labels[4 * 12 + 4];
+        assert!(outer != ZERO.0 && inner != ZERO.0 && outer != inner);
+        assert_eq!(labels.iter().filter(|&&l| l == inner).count(), 36);
+
+        let view = clusters.view();
+        assert_eq!(view.get_cluster(ClusterIndex(inner)).color(), Color::new(0, 0, 0));
+        assert!(clusters.label_map_at_depth(0).iter().all(|&l| l == outer));
+        assert_eq!(clusters.label_map_at_depth(1), labels);
+    }
+
+    #[test]
+    fn from_label_map_round_trip() {
+        let mut image = ColorImage::new_w_h(3, 2);
+        image.set_pixel(2, 0, &Color::new(0, 0, 255));
+        image.set_pixel(2, 1, &Color::new(0, 0, 255));
+        let labels = [7, 7, 3, 7, 7, 3];
+        let clusters = Clusters::from_label_map(3, 2, &labels, image);
+        assert_eq!(clusters.output_len(), 2);
+        let view = clusters.view();
+        let blue = view.get_cluster_at(2);
+        assert_eq!(view.get_cluster(blue).color(), Color::new(0, 0, 255));
+        assert_eq!(view.get_cluster(blue).rect, BoundingRect::new_x_y_w_h(2, 0, 1, 2));
+        assert_eq!(view.iter().next().unwrap().area(), 2);
+
+        let relabelled = clusters.label_map();
+        for i in 0..labels.len() {
+            for j in 0..labels.len() {
+                assert_eq!(labels[i] == labels[j], relabelled[i] == relabelled[j]);
+            }
+        }
+    }
+
+    #[test]
+    fn fill_cluster_into_framebuffer() {
+        // a 2x2 red square at (1, 1), filled into a framebuffer with padded rows
+        let mut image = ColorImage::new_w_h(4, 3);
+        image.set_pixel(1, 1, &Color::new(255, 0, 0));
+        image.set_pixel(2, 1, &Color::new(255, 0, 0));
+        image.set_pixel(1, 2, &Color::new(255, 0, 0));
+        image.set_pixel(2, 2, &Color::new(255, 0, 0));
+        let labels = [0, 0, 0, 0, 0, 1, 1, 0, 0, 1, 1, 0];
+        let clusters = Clusters::from_label_map(4, 3, &labels, image);
+        let view = clusters.view();
+        let red = view.get_cluster_at(5);
+        assert_eq!(view.get_cluster(red).spans(&view), [(1, 1, 3), (2, 1, 3)]);
+
+        let mut buffer = vec![0u32; 6 * 3];
+        view.fill_cluster_into(red, &mut buffer, 6, 0xff0000ff);
+        let filled: Vec<usize> = (0..buffer.len()).filter(|&i| buffer[i] != 0).collect();
+        assert_eq!(filled, [7, 8, 13, 14]);
+    }
+
+    #[test]
+    fn extract_nested_square() {
+        // a black square inside a red square, on white
+        let mut image = ColorImage::new_w_h(16, 16);
+        for y in 0..16 {
+            for x in 0..16 {
+                let color = if (6..10).contains(&x) && (6..10).contains(&y) {
+                    Color::new(0, 0, 0)
+                } else if (2..14).contains(&x) && (3..13).contains(&y) {
+                    Color::new(255, 0, 0)
+                } else {
+                    Color::new(255, 255, 255)
+                };
+                image.set_pixel(x, y, &color);
+            }
+        }
+        let config = RunnerConfig { good_min_area: 4, ..RunnerConfig::default() };
+        let clusters = Runner::new(config, image).run();
+        let view = clusters.view();
+        let labels = clusters.label_map();
+        let red = ClusterIndex(labels[4 * 16 + 4]);
+        assert_eq!(view.get_cluster(red).rect, BoundingRect::new_x_y_w_h(2, 3, 12, 10));
+
+        let subtree = view.extract_subtree(red);
+        assert_eq!((subtree.width, subtree.height), (12, 10));
+        assert_eq!(subtree.output_len(), 2);
+        let sub = subtree.view();
+        let labels = subtree.label_map();
+        let black = ClusterIndex(labels[4 * 12 + 5]);
+        assert_eq!(sub.get_cluster(black).rect, BoundingRect::new_x_y_w_h(4, 3, 4, 4));
+        assert_eq!(sub.get_cluster(black).residue_color(), Color::new(0, 0, 0));
+        assert_eq!(sub.get_cluster(ClusterIndex(labels[0])).residue_color(), Color::new(255, 0, 0));
+        let rendered = sub.to_color_image();
+        assert_eq!(rendered.get_pixel(4, 3), Color::new(0, 0, 0));
+        assert_eq!(rendered.get_pixel(11, 9), Color::new(255, 0, 0));
+
+        let leaf = sub.extract_subtree(black);
+        assert_eq!((leaf.width, leaf.height, leaf.output_len()), (4, 4, 1));
+    }
+
+    #[test]
+    fn recluster_with_finer_threshold() {
+        // two close shades of grey side by side, on a white background
+        let mut image = ColorImage::new_w_h(12, 8);
+        for y in 0..8 {
+            for x in 0..12 {
+                let color = match ((2..10).contains(&x) && (2..6).contains(&y), x < 6) {
+                    (true, true) => Color::new(100, 100, 100),
+                    (true, false) => Color::new(110, 110, 110),
+                    _ => Color::new(255, 255, 255),
+                };
+                image.set_pixel(x, y, &color);
+            }
+        }
+        let coarse = RunnerConfig { is_same_color_a: 5, good_min_area: 4, ..RunnerConfig::default() };
+        let clusters = Runner::new(coarse, image).run();
+        let grey = ClusterIndex(clusters.label_map()[3 * 12 + 3]);
+        assert_eq!(clusters.view().get_cluster(grey).area(), 32);
+
+        let fine = RunnerConfig { is_same_color_a: 0, is_same_color_b: 2, hierarchical: 0, ..RunnerConfig::default() };
+        let detail = clusters.recluster(grey, fine);
+        assert_eq!((detail.width, detail.height), (8, 4));
+        let labels = detail.label_map();
+        assert_ne!(labels[0], labels[7]);
+        let view = detail.view();
+        assert_eq!(view.get_cluster(ClusterIndex(labels[7])).residue_color(), Color::new(110, 110, 110));
+        assert_eq!(view.iter().map(|c| c.area()).sum::<usize>(), 32);
+    }
+
+    #[test]
+    fn shared_boundary() {
+        // a 2x2 square in the top left corner of a 4x3 image
+        let labels = [1, 1, 2, 2, 1, 1, 2, 2, 2, 2, 2, 2];
+        let clusters = Clusters::from_label_map(4, 3, &labels, ColorImage::new_w_h(4, 3));
+        let view = clusters.view();
+        let (square, rest) = (view.get_cluster_at(0), view.get_cluster_at(2));
+        assert_eq!(view.shared_boundary_length(square, rest), 4);
+        assert_eq!(view.shared_boundary_length(rest, square), 4);
+
+        // the square as seen by the merge heuristics: half of its boundary is shared, the rest is the image border
+        let mut image = ColorImage::new_w_h(4, 3);
+        for (i, &label) in labels.iter().enumerate() {
+            image.set_pixel_at(i, &if label == 1 { Color::new(0, 0, 0) } else { Color::new(255, 255, 255) });
+        }
+        let fractions = std::rc::Rc::new(std::cell::RefCell::new(Vec::new()));
+        let recorded = fractions.clone();
+        Runner::new(RunnerConfig::default(), image)
+            .builder()
+            .deepen(move |_: &BuilderImpl, patch: &Cluster, neighbours: &[NeighbourInfo]| {
+                recorded.borrow_mut().extend(neighbours.iter().map(|n| (patch.area(), n.boundary, n.boundary_fraction)));
+                false
+            })
+            .run();
+        assert!(fractions.borrow().contains(&(4, 4, 0.5)), "{:?}", fractions.borrow());
+    }
+
+    #[test]
+    fn capacity_is_checked() {
+        check_capacity(1 << 15, 1 << 15);
+        assert!(std::panic::catch_unwind(|| check_capacity(usize::MAX, 2)).is_err());
+        let wraps = std::panic::catch_unwind(|| check_capacity(1 << 16, 1 << 16)).is_err();
+        assert_eq!(wraps, cfg!(not(feature = "large-images")));
+    }
+}
@@ -0,0 +1,199 @@
+use crate::{BinaryImage, BoundingRect, ColorImage, ColorSum, ColorSumElem};
+use super::{Clusters, Runner, RunnerConfig, ZERO, color_diff, container::PixelIndex};
+
+const MASKED_OUT: PixelIndex = PixelIndex::MAX;
+
+impl Runner {
+    /// Clusters a copy of the image downscaled by `factor` (averaged in linear light),
+    /// then maps the clusters back onto the full resolution image.
+    ///
+    /// Area related config (`good_min_area`, `good_max_area`, `hierarchical`) is interpreted at full resolution.
+    /// Along cluster boundaries, each full resolution pixel snaps to the neighbouring cluster closest in color,
+    /// so edges are not as blocky as a plain nearest neighbour upsampling.
+    pub fn run_downscaled(mut self, factor: usize) -> Clusters {
+        if factor <= 1 {
+            return self.run();
+        }
+        let image = std::mem::take(&mut self.image);
+        scale_area_config(&mut self.config, factor * factor);
+        self.image = image.downscale_linear(factor);
+        let masks = self.mask.take().map(|mask| {
+            // a block is clustered if any of its pixels is
+            let mut small = BinaryImage::new_w_h(self.image.width, self.image.height);
+            for y in 0..mask.height {
+                for x in 0..mask.width {
+                    if mask.get_pixel(x, y) {
+                        small.set_pixel(x / factor, y / factor, true);
+                    }
+                }
+            }
+            self.mask = Some(small.clone());
+            (mask, small)
+        });
+        let small = self.run();
+        upsample_clusters(small, image, masks.as_ref(), factor)
+    }
+}
+
+/// Divides the area related config by `area_factor`, for clustering an image that much smaller
+pub(super) fn scale_area_config(config: &mut RunnerConfig, area_factor: usize) {
+    config.good_min_area /= area_factor;
+    config.good_max_area /= area_factor;
+    if config.hierarchical != super::HIERARCHICAL_MAX {
+        config.hierarchical /= area_factor as u32;
+    }
+}
+
+/// Maps clusters computed on an image downscaled by `factor` back to the full resolution `image`;
+/// given the full and low resolution `masks`, pixels not in the mask are left out
+fn upsample_clusters(
+    small: Clusters,
+    image: ColorImage,
+    masks: Option<&(BinaryImage, BinaryImage)>,
+    factor: usize,
+) -> Clusters {
+    let (sw, sh) = (small.width as usize, small.height as usize);
+    let (width, height) = (image.width, image.height);
+    let view = small.view();
+
+    // the low resolution pixel each full resolution pixel is assigned to
+    let mut source = vec![MASKED_OUT; width * height];
+    for y in 0..height {
+        for x in 0..width {
+            if masks.is_some_and(|(mask, _)| !mask.get_pixel(x, y)) {
+                continue;
+            }
+            let (cx, cy) = (x / factor, y / factor);
+            let own = cy * sw + cx;
+            let own_label = small.cluster_indices[own];
+            let color = image.get_pixel(x, y);
+            let mut best = own;
+            let mut best_diff = i32::MAX;
+            for ny in cy.saturating_sub(1)..std::cmp::min(cy + 2, sh) {
+                for nx in cx.saturating_sub(1)..std::cmp::min(cx + 2, sw) {
+                    let n = ny * sw + nx;
+                    let label = small.cluster_indices[n];
+                    if n != own && (label == own_label || masks.is_some_and(|(_, small)| !small.get_pixel(nx, ny))) {
+                        continue;
+                    }
+                    let reference = if label == ZERO {
+                        view.get_pixel_at_index(n as PixelIndex).unwrap()
+                    } else {
+                        view.get_cluster(label).color()
+                    };
+                    let diff = color_diff(color, reference);
+                    if diff < best_diff {
+                        best = n;
+                        best_diff = diff;
+                    }
+                }
+            }
+            source[y * width + x] = best as PixelIndex;
+        }
+    }
+
+    // full resolution pixels belonging to each low resolution pixel
+    let mut starts = vec![0usize; sw * sh + 1];
+    for &s in source.iter().filter(|&&s| s != MASKED_OUT) {
+        starts[s as usize + 1] += 1;
+    }
+    for i in 0..sw * sh {
+        starts[i + 1] += starts[i];
+    }
+    let mut members: Vec<PixelIndex> = vec![0; starts[sw * sh]];
+    let mut fill = starts.clone();
+    for (i, &s) in source.iter().enumerate().filter(|&(_, &s)| s != MASKED_OUT) {
+        members[fill[s as usize]] = i as PixelIndex;
+        fill[s as usize] += 1;
+    }
+    let expand = |indices: &[PixelIndex]| -> Vec<PixelIndex> {
+        indices
+            .iter()
+            .flat_map(|&i| members[starts[i as usize]..starts[i as usize + 1]].iter().copied())
+            .collect()
+    };
+
+    let mut clusters = small.clusters.clone();
+    for cluster in clusters.iter_mut() {
+        let old_area = cluster.area();
+        let indices = expand(&cluster.indices);
+        cluster.holes = expand(&cluster.holes);
+        cluster.indices.clear();
+        cluster.sum = ColorSum::new();
+        cluster.rect = BoundingRect::default();
+        for i in indices {
+            let (x, y) = (i as usize % width, i as usize / width);
+            cluster.add(i, &image.get_pixel(x, y), x as i32, y as i32);
+        }
+        if old_area > 0 {
+            // residue is not recomputed, only scaled to the new area
+            let scale = |v: ColorSumElem| (v as u128 * cluster.indices.len() as u128 / old_area as u128) as ColorSumElem;
+            let residue = cluster.residue_sum;
+            cluster.residue_sum = ColorSum {
+                r: scale(residue.r),
+                g: scale(residue.g),
+                b: scale(residue.b),
+                a: scale(residue.a),
+                counter: scale(residue.counter),
+            };
+        }
+    }
+
+    let cluster_indices = source
+        .iter()
+        .map(|&s| if s == MASKED_OUT { ZERO } else { small.cluster_indices[s as usize] })
+        .collect();
+
+    // clusters along an edge may have lost all their pixels to neighbours
+    let clusters_output = small
+        .clusters_output
+        .iter()
+        .copied()
+        .filter(|&i| clusters[i.0 as usize].area() > 0)
+        .collect();
+
+    Clusters {
+        width: width as PixelIndex,
+        height: height as PixelIndex,
+        pixels: image.pixels,
+        clusters,
+        cluster_indices,
+        clusters_output,
+        trace: small.trace.clone(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Color, PointI32};
+
+    #[test]
+    fn run_downscaled_two_halves() {
+        let mut image = ColorImage::new_w_h(16, 16);
+        for y in 0..16 {
+            for x in 0..16 {
+                // the edge at x = 7 does not align with the 4x4 blocks
+                let color = if x < 7 { Color::new(255, 0, 0) } else { Color::new(0, 0, 255) };
+                image.set_pixel(x, y, &color);
+            }
+        }
+        let config = RunnerConfig {
+            hierarchical: 0,
+            good_min_area: 0,
+            ..RunnerConfig::default()
+        };
+        let clusters = Runner::new(config, image).run_downscaled(4);
+        assert_eq!(clusters.width, 16);
+        let view = clusters.view();
+        for y in 0..16 {
+            for x in 0..16 {
+                // the mixed column of blocks is snapped back to pure colors
+                let cluster = view.get_cluster(view.get_cluster_at_point(PointI32::new(x, y)));
+                assert_eq!(cluster.color(), view.get_pixel(x, y).unwrap());
+            }
+        }
+        assert_eq!(view.iter().map(|c| c.area()).sum::<usize>(), 16 * 16);
+        assert!(view.iter().all(|c| c.color() == Color::new(255, 0, 0) || c.color() == Color::new(0, 0, 255)));
+    }
+}
@@ -0,0 +1,121 @@
+use crate::ColorImage;
+use super::{ColorSpace, runner::to_space};
+
+/// Efficient graph based segmentation (Felzenszwalb & Huttenlocher, 2004).
+///
+/// Pixels are nodes of a grid graph (8-connected) weighted by the color distance between neighbours.
+/// Edges are visited from light to heavy, and two segments are merged if the edge between them is no heavier
+/// than the internal variation of both, relaxed by `k / size`; larger `k` gives larger segments.
+/// Segments smaller than `min_size` are then merged into a neighbour.
+///
+/// Returns a label map (row major) which can be passed to `Clusters::from_label_map`.
+pub fn felzenszwalb(image: &ColorImage, color_space: ColorSpace, k: f64, min_size: usize) -> Vec<u32> {
+    let (width, height) = (image.width, image.height);
+    let colors: Vec<[f64; 3]> = image.iter().map(|c| to_space(c, color_space)).collect();
+    let mut edges = Vec::with_capacity(width * height * 4);
+    for y in 0..height {
+        for x in 0..width {
+            let i = y * width + x;
+            let mut add = |j: usize| {
+                let (a, b) = (colors[i], colors[j]);
+                let weight = ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)).sqrt();
+                edges.push((weight, i as u32, j as u32));
+            };
+            if x + 1 < width {
+                add(i + 1);
+            }
+            if y + 1 < height {
+                add(i + width);
+                if x + 1 < width {
+                    add(i + width + 1);
+                }
+                if x > 0 {
+                    add(i + width - 1);
+                }
+            }
+        }
+    }
+    edges.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let mut forest = Forest::new(width * height);
+    let mut threshold = vec![k; width * height];
+    for &(weight, a, b) in edges.iter() {
+        let (a, b) = (forest.find(a as usize), forest.find(b as usize));
+        if a != b && weight <= threshold[a] && weight <= threshold[b] {
+            let root = forest.union(a, b);
+            threshold[root] = weight + k / forest.size[root] as f64;
+        }
+    }
+    for &(_, a, b) in edges.iter() {
+        let (a, b) = (forest.find(a as usize), forest.find(b as usize));
+        if a != b && (forest.size[a] < min_size || forest.size[b] < min_size) {
+            forest.union(a, b);
+        }
+    }
+
+    (0..width * height).map(|i| forest.find(i) as u32).collect()
+}
+
+/// Union find with union by size
+struct Forest {
+    parent: Vec<usize>,
+    size: Vec<usize>,
+}
+
+impl Forest {
+    fn new(len: usize) -> Self {
+        Self {
+            parent: (0..len).collect(),
+            size: vec![1; len],
+        }
+    }
+
+    fn find(&mut self, mut i: usize) -> usize {
+        while self.parent[i] != i {
+            self.parent[i] = self.parent[self.parent[i]];
+            i = self.parent[i];
+        }
+        i
+    }
+
+    /// Joins two roots, returning the new root
+    fn union(&mut self, a: usize, b: usize) -> usize {
+        let (big, small) = if self.size[a] >= self.size[b] { (a, b) } else { (b, a) };
+        self.parent[small] = big;
+        self.size[big] += self.size[small];
+        big
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Color;
+    use crate::color_clusters::{Runner, RunnerConfig, Segmentation};
+
+    #[test]
+    fn felzenszwalb_noisy_halves() {
+        let mut image = ColorImage::new_w_h(12, 6);
+        for y in 0..6 {
+            for x in 0..12 {
+                // textured: a checker of two close shades on each half
+                let noise = ((x + y) % 2 * 12) as u8;
+                let color = if x < 6 { Color::new(40 + noise, 40, 40) } else { Color::new(200 - noise, 220, 200) };
+                image.set_pixel(x, y, &color);
+            }
+        }
+        let labels = felzenszwalb(&image, ColorSpace::RGB, 300.0, 4);
+        for y in 0..6 {
+            for x in 0..12 {
+                assert_eq!(labels[y * 12 + x] == labels[0], x < 6);
+            }
+        }
+        assert!(labels.iter().all(|&l| l == labels[0] || l == labels[11]));
+
+        let config = RunnerConfig {
+            segmentation: Segmentation::Felzenszwalb { k: 300.0, min_size: 4 },
+            ..RunnerConfig::default()
+        };
+        assert_eq!(Runner::new(config, image).run().output_len(), 2);
+    }
+}
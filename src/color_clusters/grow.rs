@@ -0,0 +1,160 @@
+use crate::{BinaryImage, Color, ColorImage, CompoundPath, PathSimplifyMode, PointI32};
+use crate::clusters::Cluster as BinaryCluster;
+use super::{Cluster, ColorSpace, color_diff, color_same, oklab_color_diff, container::PixelIndex};
+
+/// Parameters of `GrowConfig::grow`, in the same terms as `RunnerConfig`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct GrowConfig {
+    /// grow to 8-connected neighbours, not only 4-connected
+    pub diagonal: bool,
+    /// a neighbour must be `color_same` to the adjacent region pixel
+    pub is_same_color_a: i32,
+    pub is_same_color_b: i32,
+    /// and differ from the average color of the region by at most this
+    pub max_diff: i32,
+    pub color_space: ColorSpace,
+}
+
+impl Default for GrowConfig {
+    fn default() -> Self {
+        Self {
+            diagonal: false,
+            is_same_color_a: 4,
+            is_same_color_b: 1,
+            max_diff: 64,
+            color_space: ColorSpace::default(),
+        }
+    }
+}
+
+/// A single region grown from seeds
+pub struct GrownRegion {
+    pub cluster: Cluster,
+    /// width of the image grown on
+    pub width: PixelIndex,
+}
+
+impl GrowConfig {
+    /// Grows from `seeds` with the builder's notion of same color (to the neighbour)
+    /// and color difference (to the region average)
+    pub fn grow(&self, image: &ColorImage, seeds: &[PointI32]) -> GrownRegion {
+        let (a, b, max_diff) = (self.is_same_color_a, self.is_same_color_b, self.max_diff);
+        let diff = match self.color_space {
+            ColorSpace::RGB => color_diff,
+            ColorSpace::Oklab => oklab_color_diff,
+        };
+        grow_region(image, seeds, self.diagonal, |candidate, from, region| {
+            color_same(candidate, from, a, b) && diff(candidate, region.color()) <= max_diff
+        })
+    }
+}
+
+/// Grows one region from `seeds` by flood fill: a neighbour of the region is added if
+/// `accept(neighbour color, color of the adjacent region pixel, region so far)`.
+/// Seeds outside the image are ignored.
+pub fn grow_region<F>(image: &ColorImage, seeds: &[PointI32], diagonal: bool, accept: F) -> GrownRegion
+where
+    F: Fn(Color, Color, &Cluster) -> bool,
+{
+    let (width, height) = (image.width as i32, image.height as i32);
+    let mut visited = BinaryImage::new_w_h(image.width, image.height);
+    let mut cluster = Cluster::new();
+    let mut stack = Vec::new();
+    for &seed in seeds.iter() {
+        if seed.x < 0 || seed.y < 0 || seed.x >= width || seed.y >= height ||
+            visited.get_pixel(seed.x as usize, seed.y as usize) {
+            continue;
+        }
+        visited.set_pixel(seed.x as usize, seed.y as usize, true);
+        let color = image.get_pixel(seed.x as usize, seed.y as usize);
+        cluster.add(seed.y as PixelIndex * image.width as PixelIndex + seed.x as PixelIndex, &color, seed.x, seed.y);
+        stack.push(seed);
+    }
+
+    let neighbours: &[(i32, i32)] = if diagonal {
+        &[(-1, 0), (1, 0), (0, -1), (0, 1), (-1, -1), (1, -1), (-1, 1), (1, 1)]
+    } else {
+        &[(-1, 0), (1, 0), (0, -1), (0, 1)]
+    };
+    while let Some(p) = stack.pop() {
+        let from = image.get_pixel(p.x as usize, p.y as usize);
+        for &(dx, dy) in neighbours.iter() {
+            let (x, y) = (p.x + dx, p.y + dy);
+            if x < 0 || y < 0 || x >= width || y >= height || visited.get_pixel(x as usize, y as usize) {
+                continue;
+            }
+            let color = image.get_pixel(x as usize, y as usize);
+            if accept(color, from, &cluster) {
+                visited.set_pixel(x as usize, y as usize, true);
+                cluster.add(y as PixelIndex * image.width as PixelIndex + x as PixelIndex, &color, x, y);
+                stack.push(PointI32::new(x, y));
+            }
+        }
+    }
+    GrownRegion {
+        cluster,
+        width: image.width as PixelIndex,
+    }
+}
+
+impl GrownRegion {
+    /// The region, cropped to `cluster.rect`
+    pub fn mask(&self) -> BinaryImage {
+        self.cluster.to_image_with_hole(self.width, false)
+    }
+
+    /// The boundary of the region in image coordinates, see `Cluster::to_compound_path`
+    pub fn to_compound_path(&self,
+        mode: PathSimplifyMode,
+        corner_threshold: f64,
+        length_threshold: f64,
+        max_iterations: usize,
+        splice_threshold: f64
+    ) -> CompoundPath {
+        let mut paths = CompoundPath::new();
+        for cluster in self.mask().to_clusters(false).iter() {
+            paths.append(
+                BinaryCluster::image_to_compound_path(&PointI32 {
+                    x: self.cluster.rect.left + cluster.rect.left,
+                    y: self.cluster.rect.top + cluster.rect.top,
+                }, &cluster.to_binary_image(), mode,
+                corner_threshold, length_threshold, max_iterations, splice_threshold)
+            );
+        }
+        paths
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grow_from_seed() {
+        // a gradient square on a contrasting background
+        let mut image = ColorImage::new_w_h(8, 8);
+        for y in 0..8 {
+            for x in 0..8 {
+                let inside = (2..6).contains(&x) && (2..6).contains(&y);
+                let color = if inside {
+                    Color::new(100 + (x * 4) as u8, 0, 0)
+                } else {
+                    Color::new(0, 200, 0)
+                };
+                image.set_pixel(x, y, &color);
+            }
+        }
+        let region = GrowConfig::default().grow(&image, &[PointI32::new(3, 3), PointI32::new(100, 0)]);
+        assert_eq!(region.cluster.area(), 16);
+        assert_eq!(region.cluster.rect, crate::BoundingRect::new_x_y_w_h(2, 2, 4, 4));
+        assert_eq!(region.mask().area(), 16);
+        let path = region.to_compound_path(PathSimplifyMode::None, 0.0, 0.0, 0, 0.0);
+        let (svg, offset) = path.to_svg_string(true, PointI32::default(), None);
+        assert_eq!(svg, "M0,0 L4,0 L4,4 L0,4 Z ");
+        assert_eq!(offset, PointI32::new(2, 2));
+
+        // a predicate which never accepts leaves only the seed
+        let single = grow_region(&image, &[PointI32::new(0, 0)], true, |_, _, _| false);
+        assert_eq!(single.cluster.area(), 1);
+    }
+}
@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+use crate::{Color, ColorLab, ciede2000};
+use super::{ClusterIndex, Clusters};
+
+/// A palette shared by several clusterings, see `harmonize_palettes`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SharedPalette {
+    pub palette: Vec<Color>,
+    /// for each clustering, the palette entry of each of its output clusters
+    pub remaps: Vec<HashMap<ClusterIndex, usize>>,
+}
+
+impl SharedPalette {
+    /// Recolors the output clusters of `clusters`, the `image`th of those harmonized, with their palette entries
+    pub fn apply(&self, image: usize, clusters: &mut Clusters) {
+        for (&index, &entry) in self.remaps[image].iter() {
+            clusters.set_residue_color(index, self.palette[entry]);
+        }
+    }
+}
+
+/// Computes a palette shared by the output clusters of all `clusterings`, so that a batch of images
+/// is traced with consistent colors. From the largest cluster (by area, over all images) down,
+/// each color joins the first entry within `max_delta_e` (CIEDE2000), or starts a new one.
+/// Entries are the area weighted mean of their members.
+pub fn harmonize_palettes(clusterings: &[&Clusters], max_delta_e: f64) -> SharedPalette {
+    let mut members: Vec<(usize, ClusterIndex, Color, usize)> = Vec::new();
+    for (image, clusters) in clusterings.iter().enumerate() {
+        let view = clusters.view();
+        for &index in view.clusters_output.iter() {
+            let cluster = view.get_cluster(index);
+            members.push((image, index, cluster.residue_color(), cluster.area()));
+        }
+    }
+    // stable, so that ties keep the order of the images
+    members.sort_by_key(|m| std::cmp::Reverse(m.3));
+
+    struct Entry {
+        sum: [f64; 4],
+        area: f64,
+        color: Color,
+        lab: ColorLab,
+    }
+    let mut entries: Vec<Entry> = Vec::new();
+    let mut remaps = vec![HashMap::new(); clusterings.len()];
+    for &(image, index, color, area) in members.iter() {
+        let lab = color.to_lab();
+        let entry = match entries.iter().position(|e| ciede2000(e.lab, lab) <= max_delta_e) {
+            Some(entry) => entry,
+            None => {
+                entries.push(Entry { sum: [0.0; 4], area: 0.0, color, lab });
+                entries.len() - 1
+            },
+        };
+        let e = &mut entries[entry];
+        let weight = area.max(1) as f64;
+        for (s, c) in e.sum.iter_mut().zip([color.r, color.g, color.b, color.a]) {
+            *s += c as f64 * weight;
+        }
+        e.area += weight;
+        let mean = |s: f64| (s / e.area).round() as u8;
+        e.color = Color::new_rgba(mean(e.sum[0]), mean(e.sum[1]), mean(e.sum[2]), mean(e.sum[3]));
+        e.lab = e.color.to_lab();
+        remaps[image].insert(index, entry);
+    }
+    SharedPalette {
+        palette: entries.into_iter().map(|e| e.color).collect(),
+        remaps,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ColorImage;
+
+    #[test]
+    fn shared_palette() {
+        // two images of slightly different reds on white
+        let make = |red: Color| {
+            let mut image = ColorImage::new_w_h(4, 1);
+            image.set_pixel(0, 0, &red);
+            (1..4).for_each(|x| image.set_pixel(x, 0, &Color::new(255, 255, 255)));
+            Clusters::from_label_map(4, 1, &[1, 2, 2, 2], image)
+        };
+        let mut a = make(Color::new(200, 0, 0));
+        let mut b = make(Color::new(204, 4, 0));
+        let shared = harmonize_palettes(&[&a, &b], 5.0);
+        assert_eq!(shared.palette, [Color::new(255, 255, 255), Color::new(202, 2, 0)]);
+        assert_eq!(shared.remaps.len(), 2);
+        assert!(shared.remaps.iter().all(|remap| remap.len() == 2));
+
+        shared.apply(0, &mut a);
+        shared.apply(1, &mut b);
+        assert_eq!(a.view().to_color_image().pixels, b.view().to_color_image().pixels);
+        assert_eq!(a.view().to_color_image().get_pixel(0, 0), Color::new(202, 2, 0));
+
+        assert_eq!(harmonize_palettes(&[&a, &b], 0.0).palette.len(), 2);
+    }
+}
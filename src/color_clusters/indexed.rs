@@ -0,0 +1,97 @@
+use std::collections::HashMap;
+use crate::Color;
+use super::{Clusters, ClustersView, ZERO};
+
+/// A palette image of the clustered colors, as needed for writing indexed PNG or GIF
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct IndexedImage {
+    pub width: usize,
+    pub height: usize,
+    /// distinct colors in order of first appearance (row major)
+    pub palette: Vec<Color>,
+    /// index into `palette` of each pixel, row major
+    pub indices: Vec<u32>,
+}
+
+impl IndexedImage {
+    /// The indices as bytes, if the palette has no more than 256 colors
+    pub fn indices_u8(&self) -> Option<Vec<u8>> {
+        if self.palette.len() > 256 {
+            return None;
+        }
+        Some(self.indices.iter().map(|&i| i as u8).collect())
+    }
+
+    /// Run length encoding of each row, as `(index, length)` pairs; runs do not cross rows
+    pub fn runs(&self) -> Vec<Vec<(u32, usize)>> {
+        self.indices.chunks(self.width.max(1)).map(|row| {
+            let mut runs: Vec<(u32, usize)> = Vec::new();
+            for &index in row.iter() {
+                match runs.last_mut() {
+                    Some(run) if run.0 == index => run.1 += 1,
+                    _ => runs.push((index, 1)),
+                }
+            }
+            runs
+        }).collect()
+    }
+}
+
+impl ClustersView<'_> {
+    /// The image drawn by `to_color_image`, as a palette of the cluster colors (clusters of the same color
+    /// share an entry) and an index per pixel. Pixels of no output cluster are transparent black.
+    pub fn to_indexed(&self) -> IndexedImage {
+        let labels = self.label_map();
+        let mut palette = Vec::new();
+        let mut entries: HashMap<[u8; 4], u32> = HashMap::new();
+        let indices = labels.iter().map(|&label| {
+            let color = if label == ZERO.0 {
+                Color::new_rgba(0, 0, 0, 0)
+            } else {
+                self.clusters[label as usize].residue_color()
+            };
+            *entries.entry([color.r, color.g, color.b, color.a]).or_insert_with(|| {
+                palette.push(color);
+                palette.len() as u32 - 1
+            })
+        }).collect();
+        IndexedImage {
+            width: self.width as usize,
+            height: self.height as usize,
+            palette,
+            indices,
+        }
+    }
+}
+
+impl Clusters {
+    /// See `ClustersView::to_indexed`
+    pub fn to_indexed(&self) -> IndexedImage {
+        self.view().to_indexed()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ColorImage;
+
+    #[test]
+    fn indexed_matches_color_image() {
+        // two red clusters and a blue one
+        let (red, blue) = (Color::new(255, 0, 0), Color::new(0, 0, 255));
+        let mut image = ColorImage::new_w_h(4, 2);
+        for (i, color) in [red, red, blue, red, red, red, blue, red].iter().enumerate() {
+            image.set_pixel_at(i, color);
+        }
+        let labels = [1, 1, 2, 3, 1, 1, 2, 3];
+        let clusters = Clusters::from_label_map(4, 2, &labels, image);
+        let indexed = clusters.to_indexed();
+        assert_eq!(indexed.palette, [red, blue]);
+        assert_eq!(indexed.indices, [0, 0, 1, 0, 0, 0, 1, 0]);
+        let colors: Vec<Color> = indexed.indices.iter().map(|&i| indexed.palette[i as usize]).collect();
+        assert_eq!(colors, clusters.view().to_color_image().iter().collect::<Vec<_>>());
+        assert_eq!(indexed.indices_u8().unwrap(), [0, 0, 1, 0, 0, 0, 1, 0]);
+        assert_eq!(indexed.runs(), [vec![(0, 2), (1, 1), (0, 1)], vec![(0, 2), (1, 1), (0, 1)]]);
+    }
+}
@@ -0,0 +1,144 @@
+use crate::{BorderMode, Color, ColorImage};
+use super::{ColorSpace, runner::to_space};
+
+/// Maximum number of mean shift steps per pixel
+const MAX_ITERATIONS: usize = 8;
+/// A pixel has converged once its mode moves less than this (in pixels, and in color units)
+const CONVERGENCE: f64 = 0.5;
+
+/// Mean shift filtering (Comaniciu & Meer): each pixel climbs to the mode of the joint spatial-range density,
+/// with flat kernels of radius `spatial` pixels and `range` color units (in `color_space`, roughly 8 bit units),
+/// and takes the average color at the mode. Edges are preserved while regions are smoothed.
+pub fn mean_shift_filter(image: &ColorImage, color_space: ColorSpace, spatial: usize, range: f64) -> ColorImage {
+    let (width, height) = (image.width, image.height);
+    let colors: Vec<[f64; 3]> = image.iter().map(|c| to_space(c, color_space)).collect();
+    let radius = spatial as f64;
+    let mut result = ColorImage::new_w_h(width, height);
+    for y0 in 0..height {
+        for x0 in 0..width {
+            let (mut x, mut y) = (x0 as f64, y0 as f64);
+            let mut c = colors[y0 * width + x0];
+            let mut average = image.get_pixel(x0, y0);
+            for _ in 0..MAX_ITERATIONS {
+                let (mut sx, mut sy, mut sc) = (0.0, 0.0, [0.0; 3]);
+                let mut sum_rgb = [0.0; 4];
+                let mut count = 0.0;
+                let (cx, cy) = (x.round() as i64, y.round() as i64);
+                let s = spatial as i64;
+                // the window is cut off at the borders
+                let border = BorderMode::Constant;
+                for ny in (cy - s..=cy + s).filter_map(|ny| border.resolve(ny, height)) {
+                    for nx in (cx - s..=cx + s).filter_map(|nx| border.resolve(nx, width)) {
+                        let (dx, dy) = (nx as f64 - x, ny as f64 - y);
+                        if dx * dx + dy * dy > radius * radius {
+                            continue;
+                        }
+                        let i = ny * width + nx;
+                        let n = colors[i];
+                        let d = (n[0] - c[0]).powi(2) + (n[1] - c[1]).powi(2) + (n[2] - c[2]).powi(2);
+                        if d > range * range {
+                            continue;
+                        }
+                        sx += nx as f64;
+                        sy += ny as f64;
+                        for k in 0..3 {
+                            sc[k] += n[k];
+                        }
+                        let p = image.get_pixel_at(i);
+                        for (k, v) in [p.r, p.g, p.b, p.a].into_iter().enumerate() {
+                            sum_rgb[k] += v as f64;
+                        }
+                        count += 1.0;
+                    }
+                }
+                if count == 0.0 {
+                    break;
+                }
+                let (mx, my) = (sx / count, sy / count);
+                let mc = [sc[0] / count, sc[1] / count, sc[2] / count];
+                let shift = (mx - x).powi(2) + (my - y).powi(2) +
+                    (mc[0] - c[0]).powi(2) + (mc[1] - c[1]).powi(2) + (mc[2] - c[2]).powi(2);
+                x = mx;
+                y = my;
+                c = mc;
+                let v = sum_rgb.map(|v| (v / count).round() as u8);
+                average = Color::new_rgba(v[0], v[1], v[2], v[3]);
+                if shift < CONVERGENCE * CONVERGENCE {
+                    break;
+                }
+            }
+            result.set_pixel(x0, y0, &average);
+        }
+    }
+    result
+}
+
+/// Mean shift segmentation: `mean_shift_filter`, then joining 4-connected neighbours
+/// whose filtered colors are within half of `range`.
+///
+/// Returns a label map (row major) which can be passed to `Clusters::from_label_map`.
+pub fn mean_shift(image: &ColorImage, color_space: ColorSpace, spatial: usize, range: f64) -> Vec<u32> {
+    let (width, height) = (image.width, image.height);
+    let filtered = mean_shift_filter(image, color_space, spatial, range);
+    let colors: Vec<[f64; 3]> = filtered.iter().map(|c| to_space(c, color_space)).collect();
+    let close = |a: usize, b: usize| {
+        let (a, b) = (colors[a], colors[b]);
+        (a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2) <= range * range / 4.0
+    };
+    let mut labels = vec![u32::MAX; width * height];
+    let mut next = 0;
+    let mut stack = Vec::new();
+    for start in 0..width * height {
+        if labels[start] != u32::MAX {
+            continue;
+        }
+        labels[start] = next;
+        stack.push(start);
+        while let Some(i) = stack.pop() {
+            let (x, y) = (i % width, i / width);
+            let neighbours = [
+                (x > 0).then(|| i - 1),
+                (x + 1 < width).then(|| i + 1),
+                (y > 0).then(|| i - width),
+                (y + 1 < height).then(|| i + width),
+            ];
+            for n in neighbours.into_iter().flatten() {
+                if labels[n] == u32::MAX && close(i, n) {
+                    labels[n] = next;
+                    stack.push(n);
+                }
+            }
+        }
+        next += 1;
+    }
+    labels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color_clusters::{Runner, RunnerConfig, Segmentation};
+
+    #[test]
+    fn mean_shift_smooths_noisy_halves() {
+        let mut image = ColorImage::new_w_h(12, 6);
+        for y in 0..6 {
+            for x in 0..12 {
+                let noise = ((x * 7 + y * 3) % 5 * 4) as u8;
+                let color = if x < 6 { Color::new(40 + noise, 40, 40) } else { Color::new(200 - noise, 220, 200) };
+                image.set_pixel(x, y, &color);
+            }
+        }
+        let filtered = mean_shift_filter(&image, ColorSpace::RGB, 3, 40.0);
+        // noise is averaged out, the edge is kept
+        assert!((filtered.get_pixel(1, 1).r as i32 - filtered.get_pixel(2, 1).r as i32).abs() <= 2);
+        assert!(filtered.get_pixel(5, 3).g < 50);
+        assert!(filtered.get_pixel(6, 3).g > 210);
+
+        let config = RunnerConfig {
+            segmentation: Segmentation::MeanShift { spatial: 3, range: 40.0 },
+            ..RunnerConfig::default()
+        };
+        assert_eq!(Runner::new(config, image).run().output_len(), 2);
+    }
+}
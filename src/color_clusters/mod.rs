@@ -9,12 +9,58 @@
 //! + tick() -> bool: computation. returning false to continue, returning true when finish
 //! + result() -> T: cleanup & collect results
 
+mod analyze;
+mod average;
+mod blocking;
 mod builder;
 mod cluster;
 mod container;
+mod downscale;
+mod felzenszwalb;
+mod grow;
+mod harmonize;
+mod indexed;
+mod mean_shift;
+mod preview;
+mod priority;
+mod progressive;
+mod quantize;
+mod refine;
 mod runner;
+#[cfg(feature = "async")]
+mod run_async;
+mod spatial;
+mod trace;
+mod validate;
+mod watershed;
 
+pub use analyze::*;
+pub use average::*;
+pub use blocking::*;
 pub use builder::*;
 pub use cluster::*;
 pub use container::*;
-pub use runner::*;
\ No newline at end of file
+pub use felzenszwalb::*;
+pub use grow::*;
+pub use harmonize::*;
+pub use indexed::*;
+pub use mean_shift::*;
+pub use preview::*;
+pub use priority::*;
+pub use progressive::*;
+pub use refine::*;
+pub use runner::*;
+pub use spatial::*;
+pub use trace::*;
+pub use validate::*;
+pub use watershed::*;
+
+/// A 6x6 black square in the middle of a 12x12 white image, the fixture of the tests of clustering
+#[cfg(test)]
+fn black_square_on_white() -> crate::ColorImage {
+    use crate::{BoundingRect, Color, ColorImage};
+    let mut image = ColorImage::new_w_h(12, 12);
+    image.fill_rect(&BoundingRect::new_x_y_w_h(0, 0, 12, 12), &Color::new(255, 255, 255));
+    image.fill_rect(&BoundingRect::new_x_y_w_h(3, 3, 6, 6), &Color::new(0, 0, 0));
+    image
+}
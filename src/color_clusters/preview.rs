@@ -0,0 +1,97 @@
+use crate::{BoundingRect, Color, ImageMut, Sampler};
+use super::{ClusterIndex, Clusters, Runner, downscale::scale_area_config};
+
+/// A cluster of a `Preview`, in full resolution coordinates
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ApproximateCluster {
+    /// index into `Preview::clusters`
+    pub index: ClusterIndex,
+    /// bounds, scaled up and clipped to the image
+    pub rect: BoundingRect,
+    /// estimated number of pixels
+    pub area: usize,
+    pub color: Color,
+}
+
+/// Clusters of a decimated image, see `Runner::preview`
+pub struct Preview {
+    /// the clusters of the decimated image, in its coordinates
+    pub clusters: Clusters,
+    pub sample_rate: usize,
+    /// the output clusters of `clusters`, in the same order, scaled up to the full image
+    pub approximations: Vec<ApproximateCluster>,
+}
+
+impl Runner {
+    /// Clusters a decimated copy of the image, every `sample_rate`th pixel in each direction
+    /// (nearest neighbour sampling by `Sampler`), for a quick preview of the result,
+    /// e.g. while a slider is dragged, with the full `run` in the background.
+    /// Area related config is interpreted at full resolution, as in `run_downscaled`.
+    pub fn preview(mut self, sample_rate: usize) -> Preview {
+        let rate = sample_rate.max(1);
+        let (width, height) = (self.image.width, self.image.height);
+        self.image = decimate(&self.image, rate);
+        self.mask = self.mask.take().map(|mask| decimate(&mask, rate));
+        scale_area_config(&mut self.config, rate * rate);
+        let clusters = self.run();
+
+        let view = clusters.view();
+        let full = BoundingRect::new_x_y_w_h(0, 0, width as i32, height as i32);
+        let approximations = view.clusters_output
+            .iter()
+            .map(|&index| {
+                let cluster = view.get_cluster(index);
+                let r = cluster.rect;
+                let scale = rate as i32;
+                let mut rect = BoundingRect {
+                    left: r.left * scale,
+                    top: r.top * scale,
+                    right: r.right * scale,
+                    bottom: r.bottom * scale,
+                };
+                rect.clip(full);
+                ApproximateCluster { index, rect, area: cluster.area() * rate * rate, color: cluster.residue_color() }
+            })
+            .collect();
+        Preview { clusters, sample_rate: rate, approximations }
+    }
+}
+
+/// Every `rate`th pixel of `image` in each direction, starting at the top left
+pub(super) fn decimate<I: ImageMut>(image: &I, rate: usize) -> I where I::Pixel: Default {
+    let (w, h) = (image.width().div_ceil(rate), image.height().div_ceil(rate));
+    let mut decimated = I::new_image(w, h);
+    Sampler::resample_image_with_crop_to_image(
+        image,
+        BoundingRect::new_x_y_w_h(0, 0, (w * rate) as i32, (h * rate) as i32),
+        &mut decimated,
+        BoundingRect::new_x_y_w_h(0, 0, w as i32, h as i32),
+    );
+    decimated
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ColorImage;
+    use crate::color_clusters::RunnerConfig;
+
+    #[test]
+    fn preview_of_square() {
+        // a red square on white
+        let mut image = ColorImage::new_w_h(62, 40);
+        for y in 0..40 {
+            for x in 0..62 {
+                let inside = (16..40).contains(&x) && (8..32).contains(&y);
+                image.set_pixel(x, y, &if inside { Color::new(255, 0, 0) } else { Color::new(255, 255, 255) });
+            }
+        }
+        let preview = Runner::new(RunnerConfig::default(), image).preview(4);
+        assert_eq!((preview.clusters.width, preview.clusters.height), (16, 10));
+        assert_eq!(preview.approximations.len(), preview.clusters.output_len());
+        let red = preview.approximations.iter().find(|c| c.color == Color::new(255, 0, 0)).unwrap();
+        assert_eq!(red.rect, BoundingRect::new_x_y_w_h(16, 8, 24, 24));
+        assert_eq!(red.area, 24 * 24);
+        assert!(preview.approximations.iter().all(|c| c.rect.right <= 62 && c.rect.bottom <= 40));
+    }
+}
@@ -0,0 +1,99 @@
+use crate::{BoundingRect, Color, ColorImage};
+use super::container::PixelIndex;
+
+/// Default priority of `ScanOrder::Priority`: the contrast (standard deviation of luma) of the tile,
+/// weighted down to half towards the corners of the image, so that detailed and central content comes first
+pub fn salient_tile_priority(image: &ColorImage, tile: &BoundingRect) -> f64 {
+    let (mut sum, mut sum_sq, mut count) = (0.0, 0.0, 0.0);
+    for y in tile.top..tile.bottom {
+        for x in tile.left..tile.right {
+            let c: Color = image.get_pixel(x as usize, y as usize);
+            let luma = 0.299 * c.r as f64 + 0.587 * c.g as f64 + 0.114 * c.b as f64;
+            sum += luma;
+            sum_sq += luma * luma;
+            count += 1.0;
+        }
+    }
+    if count == 0.0 {
+        return 0.0;
+    }
+    let mean = sum / count;
+    let deviation = (sum_sq / count - mean * mean).max(0.0).sqrt();
+    let (cx, cy) = ((tile.left + tile.right) as f64 / 2.0, (tile.top + tile.bottom) as f64 / 2.0);
+    let (hw, hh) = (image.width as f64 / 2.0, image.height as f64 / 2.0);
+    let distance = ((((cx - hw) / hw).powi(2) + ((cy - hh) / hh).powi(2)) / 2.0).sqrt();
+    deviation * (1.0 - 0.5 * distance)
+}
+
+/// Pixel indices tile by tile, from the highest `priority` down (ties in row major order of the tiles),
+/// row major within each tile
+pub(crate) fn priority_order(
+    image: &ColorImage,
+    tile_size: u32,
+    priority: &dyn Fn(&ColorImage, &BoundingRect) -> f64,
+) -> Vec<PixelIndex> {
+    assert!(tile_size > 0);
+    let (width, height, size) = (image.width as i32, image.height as i32, tile_size as i32);
+    let mut tiles = Vec::new();
+    for top in (0..height).step_by(size as usize) {
+        for left in (0..width).step_by(size as usize) {
+            let tile = BoundingRect::new_x_y_w_h(left, top, size.min(width - left), size.min(height - top));
+            tiles.push((priority(image, &tile), tile));
+        }
+    }
+    tiles.sort_by(|a, b| b.0.total_cmp(&a.0));
+    let mut order = Vec::with_capacity(image.width * image.height);
+    for (_, tile) in tiles.iter() {
+        for y in tile.top..tile.bottom {
+            for x in tile.left..tile.right {
+                order.push((y as usize * image.width + x as usize) as PixelIndex);
+            }
+        }
+    }
+    order
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color_clusters::{Runner, RunnerConfig, ScanOrder};
+
+    #[test]
+    fn salient_tiles_first() {
+        // flat white, except for a checkerboard in the bottom right tile
+        let mut image = ColorImage::new_w_h(16, 16);
+        for y in 0..16 {
+            for x in 0..16 {
+                let dark = x >= 8 && y >= 8 && (x + y) % 2 == 0;
+                image.set_pixel(x, y, &if dark { Color::new(0, 0, 0) } else { Color::new(255, 255, 255) });
+            }
+        }
+        let order = priority_order(&image, 8, &salient_tile_priority);
+        assert_eq!(order.len(), 16 * 16);
+        assert_eq!(order[0], 8 * 16 + 8);
+        // the remaining flat tiles keep their row major order
+        assert_eq!(&order[64..66], &[0, 1]);
+
+        let config = RunnerConfig {
+            scan_order: ScanOrder::Priority { tile_size: 8 },
+            batch_size: 64,
+            ..RunnerConfig::default()
+        };
+        let mut builder = Runner::new(config, image.clone()).start();
+        builder.tick();
+        // the preview after one batch covers the salient tile
+        let view = builder.view();
+        assert_eq!(view.clusters.iter().map(|c| c.area()).sum::<usize>(), 64);
+        assert!((8..16).all(|x| view.get_cluster_at(15 * 16 + x).0 != 0));
+        while !builder.tick() {}
+        let row_major = Runner::new(RunnerConfig::default(), image).run();
+        assert_eq!(builder.result().output_len(), row_major.output_len());
+    }
+
+    #[test]
+    #[should_panic(expected = "tile_size")]
+    fn zero_tile_size_panics() {
+        let config = RunnerConfig { scan_order: ScanOrder::Priority { tile_size: 0 }, ..RunnerConfig::default() };
+        Runner::new(config, ColorImage::new_w_h(4, 4)).builder();
+    }
+}
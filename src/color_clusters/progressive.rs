@@ -0,0 +1,228 @@
+use crate::{Color, ColorImage, CompoundPath, PathSimplifyMode};
+use super::{Clusters, IncrementalBuilder, Runner, RunnerConfig};
+
+/// Parameters of `ProgressiveVectorizer`; the curve fitting parameters are as in `Cluster::to_compound_path`
+#[derive(Clone, Copy, Debug)]
+pub struct ProgressiveConfig {
+    /// the coarse result is clustered on the image downscaled by this factor
+    pub coarse_factor: usize,
+    /// path fitting of the coarse and clustered stages, which should be fast
+    pub coarse_mode: PathSimplifyMode,
+    /// path fitting of the refined stage
+    pub mode: PathSimplifyMode,
+    pub corner_threshold: f64,
+    pub length_threshold: f64,
+    pub max_iterations: usize,
+    pub splice_threshold: f64,
+    /// number of clusters traced per `tick` in the refined stage
+    pub paths_per_tick: usize,
+}
+
+impl Default for ProgressiveConfig {
+    fn default() -> Self {
+        Self {
+            coarse_factor: 4,
+            coarse_mode: PathSimplifyMode::Polygon,
+            mode: PathSimplifyMode::Spline,
+            corner_threshold: 60.0_f64.to_radians(),
+            length_threshold: 4.0,
+            max_iterations: 10,
+            splice_threshold: 45.0_f64.to_radians(),
+            paths_per_tick: 64,
+        }
+    }
+}
+
+/// The results of `ProgressiveVectorizer`, from quickest to best
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProgressStage {
+    /// clusters of the downscaled image, in `coarse_mode`
+    Coarse,
+    /// clusters of the full image, in `coarse_mode`
+    Clustered,
+    /// clusters of the full image, in `mode`
+    Refined,
+}
+
+/// The outline of a cluster and its color, in paint order
+#[derive(Clone, Debug, Default)]
+pub struct TracedPath {
+    pub path: CompoundPath,
+    pub color: Color,
+}
+
+type StageCallback = Box<dyn FnMut(ProgressStage, &[TracedPath])>;
+
+enum State {
+    Coarse,
+    Clustering(IncrementalBuilder),
+    Refining(Clusters, usize),
+    Done,
+}
+
+/// Vectorizes an image in stages, so that a responsive UI can show a coarse result quickly
+/// and replace it as better results complete. Follows the state machine model of this module:
+/// call `tick` until it returns true; `on_stage` is called with the paths after each stage.
+pub struct ProgressiveVectorizer {
+    runner_config: RunnerConfig,
+    config: ProgressiveConfig,
+    image: ColorImage,
+    state: State,
+    stage: Option<ProgressStage>,
+    paths: Vec<TracedPath>,
+    refined: Vec<TracedPath>,
+    callback: Option<StageCallback>,
+}
+
+impl ProgressiveVectorizer {
+    /// `runner_config` is used for both the coarse and the full clustering
+    pub fn new(runner_config: RunnerConfig, config: ProgressiveConfig, image: ColorImage) -> Self {
+        Self {
+            runner_config,
+            config,
+            image,
+            state: State::Coarse,
+            stage: None,
+            paths: Vec::new(),
+            refined: Vec::new(),
+            callback: None,
+        }
+    }
+
+    pub fn on_stage(mut self, callback: impl FnMut(ProgressStage, &[TracedPath]) + 'static) -> Self {
+        self.callback = Some(Box::new(callback));
+        self
+    }
+
+    /// Returns true when the refined stage is complete
+    pub fn tick(&mut self) -> bool {
+        match std::mem::replace(&mut self.state, State::Done) {
+            State::Coarse => {
+                let coarse = Runner::new(self.runner_config.clone(), self.image.clone())
+                    .run_downscaled(self.config.coarse_factor);
+                self.paths = self.trace(&coarse, 0, usize::MAX, self.config.coarse_mode);
+                self.complete(ProgressStage::Coarse);
+                let runner = Runner::new(self.runner_config.clone(), std::mem::take(&mut self.image));
+                self.state = State::Clustering(runner.start());
+            },
+            State::Clustering(mut builder) => {
+                if builder.tick() {
+                    let clusters = builder.result();
+                    self.paths = self.trace(&clusters, 0, usize::MAX, self.config.coarse_mode);
+                    self.complete(ProgressStage::Clustered);
+                    self.state = State::Refining(clusters, 0);
+                } else {
+                    self.state = State::Clustering(builder);
+                }
+            },
+            State::Refining(clusters, start) => {
+                let count = std::cmp::max(self.config.paths_per_tick, 1);
+                let mut traced = self.trace(&clusters, start, count, self.config.mode);
+                self.refined.append(&mut traced);
+                if start + count >= clusters.output_len() {
+                    self.paths = std::mem::take(&mut self.refined);
+                    self.complete(ProgressStage::Refined);
+                } else {
+                    self.state = State::Refining(clusters, start + count);
+                }
+            },
+            State::Done => {},
+        }
+        matches!(self.state, State::Done)
+    }
+
+    /// The latest stage completed, if any
+    pub fn stage(&self) -> Option<ProgressStage> {
+        self.stage
+    }
+
+    /// The paths of the latest stage completed
+    pub fn paths(&self) -> &[TracedPath] {
+        &self.paths
+    }
+
+    /// Overall progress in [0, 100]
+    pub fn progress(&self) -> u32 {
+        match &self.state {
+            State::Coarse => 0,
+            State::Clustering(builder) => 10 + builder.progress() * 6 / 10,
+            State::Refining(clusters, start) => {
+                70 + (*start * 30 / std::cmp::max(clusters.output_len(), 1)) as u32
+            },
+            State::Done => 100,
+        }
+    }
+
+    /// Ticks to completion and returns the refined paths
+    pub fn run(mut self) -> Vec<TracedPath> {
+        while !self.tick() {}
+        self.result()
+    }
+
+    pub fn result(self) -> Vec<TracedPath> {
+        self.paths
+    }
+
+    fn complete(&mut self, stage: ProgressStage) {
+        self.stage = Some(stage);
+        if let Some(callback) = self.callback.as_mut() {
+            callback(stage, &self.paths);
+        }
+    }
+
+    /// Traces `count` output clusters starting from `start`, in paint order
+    fn trace(&self, clusters: &Clusters, start: usize, count: usize, mode: PathSimplifyMode) -> Vec<TracedPath> {
+        let view = clusters.view();
+        let config = &self.config;
+        view.clusters_output.iter().rev().skip(start).take(count).map(|&index| {
+            let cluster = view.get_cluster(index);
+            TracedPath {
+                path: cluster.to_compound_path(
+                    &view, false, mode, config.corner_threshold, config.length_threshold,
+                    config.max_iterations, config.splice_threshold,
+                ),
+                color: cluster.residue_color(),
+            }
+        }).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{cell::RefCell, rc::Rc};
+
+    #[test]
+    fn progressive_stages() {
+        // a dark square on white
+        let mut image = ColorImage::new_w_h(32, 32);
+        for y in 0..32 {
+            for x in 0..32 {
+                let inside = (8..24).contains(&x) && (8..24).contains(&y);
+                image.set_pixel(x, y, &if inside { Color::new(20, 20, 20) } else { Color::new(250, 250, 250) });
+            }
+        }
+        let stages = Rc::new(RefCell::new(Vec::new()));
+        let recorded = stages.clone();
+        let config = ProgressiveConfig { paths_per_tick: 1, ..ProgressiveConfig::default() };
+        let mut vectorizer = ProgressiveVectorizer::new(RunnerConfig::default(), config, image)
+            .on_stage(move |stage, paths| recorded.borrow_mut().push((stage, paths.len())));
+
+        assert!(!vectorizer.tick());
+        assert_eq!(vectorizer.stage(), Some(ProgressStage::Coarse));
+        assert!(!vectorizer.paths().is_empty());
+        let mut ticks = 0;
+        while !vectorizer.tick() {
+            ticks += 1;
+            assert!(vectorizer.progress() <= 100);
+        }
+        assert!(ticks >= 2);
+        assert_eq!(vectorizer.progress(), 100);
+        let stages = stages.borrow();
+        assert_eq!(stages.iter().map(|s| s.0).collect::<Vec<_>>(),
+            vec![ProgressStage::Coarse, ProgressStage::Clustered, ProgressStage::Refined]);
+        assert_eq!(stages[1].1, stages[2].1);
+        let paths = vectorizer.result();
+        assert!(paths.iter().any(|p| p.color == Color::new(20, 20, 20)));
+    }
+}
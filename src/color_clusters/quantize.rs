@@ -0,0 +1,175 @@
+use std::collections::HashMap;
+use crate::{Color, ColorLab, ciede2000};
+use super::{Clusters, ZERO};
+
+impl Clusters {
+    /// Reduces the colors of the output clusters to a palette of at most `max_colors`, then merges the pixels
+    /// of each palette color into one (flat) cluster, e.g. for flat art traced with a fixed number of inks.
+    ///
+    /// Starting from one entry per distinct color, the pair of entries cheapest to merge
+    /// (CIEDE2000 difference weighted by the smaller area) is merged, into its area weighted mean, until few enough remain.
+    /// Pixels of no output cluster keep a cluster of their own.
+    pub fn quantize(&self, max_colors: usize) -> Clusters {
+        let view = self.view();
+        let labels = view.label_map();
+
+        struct Entry {
+            sum: [f64; 4],
+            area: f64,
+            lab: ColorLab,
+        }
+        impl Entry {
+            fn color(&self) -> Color {
+                let mean = |s: f64| (s / self.area).round() as u8;
+                Color::new_rgba(mean(self.sum[0]), mean(self.sum[1]), mean(self.sum[2]), mean(self.sum[3]))
+            }
+        }
+        let mut entries: Vec<Entry> = Vec::new();
+        // the entry of each distinct color
+        let mut entry_of_color: HashMap<[u8; 4], usize> = HashMap::new();
+        // the entry of each cluster
+        let mut entry_of = vec![usize::MAX; self.clusters.len()];
+        for &index in view.clusters_output.iter() {
+            let cluster = view.get_cluster(index);
+            let color = cluster.residue_color();
+            let entry = *entry_of_color.entry([color.r, color.g, color.b, color.a]).or_insert_with(|| {
+                entries.push(Entry { sum: [0.0; 4], area: 0.0, lab: color.to_lab() });
+                entries.len() - 1
+            });
+            let area = cluster.area().max(1) as f64;
+            let e = &mut entries[entry];
+            for (s, c) in e.sum.iter_mut().zip([color.r, color.g, color.b, color.a]) {
+                *s += c as f64 * area;
+            }
+            e.area += area;
+            entry_of[index.0 as usize] = entry;
+        }
+
+        // agglomerative merging; `target[i]` is the entry that entry i was merged into
+        let len = entries.len();
+        let mut target: Vec<usize> = (0..len).collect();
+        let mut alive = vec![true; len];
+        let cost = |entries: &[Entry], i: usize, j: usize| {
+            let (i, j) = (i.min(j), i.max(j));
+            ciede2000(entries[i].lab, entries[j].lab) * entries[i].area.min(entries[j].area)
+        };
+        let nearest = |entries: &[Entry], alive: &[bool], i: usize| {
+            (0..len)
+                .filter(|&j| j != i && alive[j])
+                .map(|j| (cost(entries, i, j), j))
+                .fold((f64::MAX, usize::MAX), |best, c| if c.0 < best.0 { c } else { best })
+        };
+        // the cheapest merge of each entry, so that each step rescans only the entries it affects
+        let mut nearest_of: Vec<(f64, usize)> = (0..len).map(|i| nearest(&entries, &alive, i)).collect();
+        let mut remaining = len;
+        while remaining > max_colors.max(1) {
+            let i = (0..len)
+                .filter(|&i| alive[i])
+                .min_by(|&a, &b| nearest_of[a].0.total_cmp(&nearest_of[b].0))
+                .unwrap();
+            let j = nearest_of[i].1;
+            let (sum, area) = (entries[j].sum, entries[j].area);
+            let e = &mut entries[i];
+            e.sum.iter_mut().zip(sum).for_each(|(s, o)| *s += o);
+            e.area += area;
+            e.lab = e.color().to_lab();
+            target[j] = i;
+            alive[j] = false;
+            remaining -= 1;
+
+            nearest_of[i] = nearest(&entries, &alive, i);
+            for k in 0..len {
+                if !alive[k] || k == i {
+                    continue;
+                }
+                if nearest_of[k].1 == i || nearest_of[k].1 == j {
+                    nearest_of[k] = nearest(&entries, &alive, k);
+                } else {
+                    let c = cost(&entries, k, i);
+                    if c < nearest_of[k].0 {
+                        nearest_of[k] = (c, i);
+                    }
+                }
+            }
+        }
+        let resolve = |mut i: usize| {
+            while target[i] != i {
+                i = target[i];
+            }
+            i
+        };
+
+        // pixels of no output cluster are labelled past the entries
+        let merged: Vec<usize> = labels.iter()
+            .map(|&label| if label == ZERO.0 { usize::MAX } else { resolve(entry_of[label as usize]) })
+            .collect();
+        let image = view.to_color_image();
+        let mut quantized = Clusters::from_label_map(self.width, self.height, &merged, image);
+        let outputs = quantized.clusters_output.clone();
+        for index in outputs {
+            let entry = merged[quantized.clusters[index.0 as usize].indices[0] as usize];
+            if entry != usize::MAX {
+                quantized.set_residue_color(index, entries[entry].color());
+            }
+        }
+        quantized
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ColorImage;
+
+    #[test]
+    fn quantize_to_two_colors() {
+        // a large white, two close reds and a small blue
+        let mut image = ColorImage::new_w_h(10, 1);
+        for x in 0..10 {
+            image.set_pixel(x, 0, &match x {
+                0..=5 => Color::new(255, 255, 255),
+                6 => Color::new(200, 0, 0),
+                7 => Color::new(0, 0, 200),
+                _ => Color::new(204, 4, 0),
+            });
+        }
+        let labels = [1, 1, 1, 1, 1, 1, 2, 3, 4, 4];
+        let clusters = Clusters::from_label_map(10, 1, &labels, image);
+        let three = clusters.quantize(3);
+        assert_eq!(three.output_len(), 3);
+        let rendered = three.view().to_color_image();
+        assert_eq!(rendered.get_pixel(6, 0), rendered.get_pixel(8, 0));
+        assert_eq!(rendered.get_pixel(7, 0), Color::new(0, 0, 200));
+
+        let two = clusters.quantize(2);
+        assert_eq!(two.output_len(), 2);
+        assert_eq!(two.view().to_color_image().get_pixel(0, 0), Color::new(255, 255, 255));
+        assert_eq!(clusters.quantize(10).output_len(), 4);
+    }
+
+    #[test]
+    fn quantize_keeps_alpha() {
+        let mut image = ColorImage::new_w_h(4, 1);
+        for x in 0..4 {
+            image.set_pixel(x, 0, &Color::new_rgba(10 * x as u8, 0, 0, 128));
+        }
+        let clusters = Clusters::from_label_map(4, 1, &[1, 2, 3, 4], image);
+        let one = clusters.quantize(1);
+        assert_eq!(one.output_len(), 1);
+        assert_eq!(one.view().to_color_image().get_pixel(0, 0).a, 128);
+    }
+
+    #[test]
+    fn quantize_many_colors() {
+        // a thousand distinct colors
+        let mut image = ColorImage::new_w_h(1000, 1);
+        let mut labels = Vec::new();
+        for x in 0..1000 {
+            image.set_pixel(x, 0, &Color::new((x % 10 * 25) as u8, (x / 10 % 10 * 25) as u8, (x / 100 * 25) as u8));
+            labels.push(x + 1);
+        }
+        let clusters = Clusters::from_label_map(1000, 1, &labels, image);
+        assert_eq!(clusters.output_len(), 1000);
+        assert_eq!(clusters.quantize(8).output_len(), 8);
+    }
+}
@@ -0,0 +1,100 @@
+//! Async wrappers, which yield to the executor between ticks, so that many jobs
+//! can share the threads of a single runtime. They depend on no particular runtime.
+//! They are plain `std::future::Future`s, so `futures` (e.g. `futures::executor::block_on`, or `select!`
+//! against a cancellation signal) works on them as on any other future, without the crate depending on it.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use super::{Clusters, IncrementalBuilder, ProgressiveVectorizer, TracedPath};
+
+impl IncrementalBuilder {
+    /// Ticks to completion, yielding between batches
+    pub async fn run_async(mut self) -> Clusters {
+        while !self.tick() {
+            yield_now().await;
+        }
+        self.result()
+    }
+}
+
+impl ProgressiveVectorizer {
+    /// Ticks to completion, yielding between ticks; `on_stage` is still called after each stage
+    pub async fn run_async(mut self) -> Vec<TracedPath> {
+        while !self.tick() {
+            yield_now().await;
+        }
+        self.result()
+    }
+}
+
+/// Returns `Pending` once, after asking to be polled again
+fn yield_now() -> YieldNow {
+    YieldNow { yielded: false }
+}
+
+struct YieldNow {
+    yielded: bool,
+}
+
+impl Future for YieldNow {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        if self.yielded {
+            return Poll::Ready(());
+        }
+        self.yielded = true;
+        cx.waker().wake_by_ref();
+        Poll::Pending
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Color, ColorImage};
+    use crate::color_clusters::{ProgressiveConfig, Runner, RunnerConfig};
+    use std::sync::Arc;
+    use std::task::{Wake, Waker};
+
+    /// `Waker::noop` needs Rust 1.85
+    struct NoopWake;
+
+    impl Wake for NoopWake {
+        fn wake(self: Arc<Self>) {}
+    }
+
+    /// Polls to completion, counting the times the future yielded
+    fn block_on<F: Future>(future: F) -> (F::Output, usize) {
+        let mut future = Box::pin(future);
+        let waker = Waker::from(Arc::new(NoopWake));
+        let mut cx = Context::from_waker(&waker);
+        let mut yields = 0;
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return (output, yields);
+            }
+            yields += 1;
+        }
+    }
+
+    #[test]
+    fn run_async_yields() {
+        let mut image = ColorImage::new_w_h(16, 16);
+        for y in 0..16 {
+            for x in 0..16 {
+                image.set_pixel(x, y, &if x < 8 { Color::new(0, 0, 0) } else { Color::new(255, 255, 255) });
+            }
+        }
+        let config = RunnerConfig { batch_size: 16, ..RunnerConfig::default() };
+        let (clusters, yields) = block_on(Runner::new(config, image.clone()).start().run_async());
+        assert!(yields > 1);
+        assert_eq!(clusters.output_len(), Runner::new(RunnerConfig::default(), image.clone()).run().output_len());
+
+        let vectorizer = ProgressiveVectorizer::new(RunnerConfig::default(), ProgressiveConfig::default(), image);
+        let (paths, yields) = block_on(vectorizer.run_async());
+        assert!(yields >= 2);
+        assert_eq!(paths.len(), clusters.output_len());
+    }
+}
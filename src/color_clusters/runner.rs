@@ -1,4 +1,4 @@
-use crate::{Color, ColorImage, ColorI32};
+use crate::{BinaryImage, Color, ColorDepth, ColorImage, ColorImageF32, ColorI32, ColorRgba, DeepColorImage, MattingConfig, PlanarImage};
 use super::*;
 
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
@@ -13,11 +13,26 @@ impl Default for ColorSpace {
     }
 }
 
+/// The algorithm `Runner::run` clusters with
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub enum Segmentation {
+    /// Hierarchical region growing by `Builder`
+    #[default]
+    Hierarchical,
+    /// Flat graph based segmentation, see [`felzenszwalb`]
+    Felzenszwalb { k: f64, min_size: usize },
+    /// Flat segmentation by mean shift, see [`mean_shift`]
+    MeanShift { spatial: usize, range: f64 },
+}
+
 pub struct Runner {
-    config: RunnerConfig,
-    image: ColorImage,
+    pub(super) config: RunnerConfig,
+    pub(super) image: ColorImage,
+    pub(super) mask: Option<BinaryImage>,
+    pub(super) deep: Option<ColorImageF32>,
 }
 
+#[derive(Clone, Debug, PartialEq)]
 #[non_exhaustive]
 pub struct RunnerConfig {
     pub diagonal: bool,
@@ -32,6 +47,19 @@ pub struct RunnerConfig {
     pub key_color: Color,
     pub keying_action: KeyingAction,
     pub color_space: ColorSpace,
+    /// see `BuilderConfig::memory_cap`
+    pub memory_cap: usize,
+    pub scan_order: ScanOrder,
+    /// Other than `Hierarchical`, only `color_space` and `average_color` apply; the mask and key color are ignored.
+    /// `builder` and `start` are always `Hierarchical`.
+    pub segmentation: Segmentation,
+    /// how the output color of each cluster is computed from its pixels
+    pub average_color: AverageColor,
+    /// Raises the same color tolerance across 8x8 block boundaries by the estimated strength of JPEG blocking
+    /// (see `ColorImage::block_artifacts`), so that blocking edges are not traced as shape boundaries
+    pub jpeg_tolerance: bool,
+    /// see `BuilderConfig::trace` and `Clusters::trace`
+    pub trace: bool,
 }
 
 impl Default for RunnerConfig {
@@ -49,6 +77,12 @@ impl Default for RunnerConfig {
             key_color: Color::default(),
             keying_action: KeyingAction::default(),
             color_space: ColorSpace::default(),
+            memory_cap: usize::MAX,
+            scan_order: ScanOrder::default(),
+            segmentation: Segmentation::default(),
+            average_color: AverageColor::default(),
+            jpeg_tolerance: false,
+            trace: false,
         }
     }
 }
@@ -58,6 +92,8 @@ impl Default for Runner {
         Self {
             config: RunnerConfig::default(),
             image: ColorImage::new(),
+            mask: None,
+            deep: None,
         }
     }
 }
@@ -67,10 +103,29 @@ impl Runner {
     pub fn new(config: RunnerConfig, image: ColorImage) -> Self {
         Self {
             config,
-            image
+            image,
+            mask: None,
+            deep: None,
+        }
+    }
+
+    /// Clusters a 16 bit or float image: patches are formed of its 8 bit quantization,
+    /// but merged by the full depth colors (see `Builder::deep`). Only `Segmentation::Hierarchical` merges by depth.
+    pub fn new_deep<T: ColorDepth>(config: RunnerConfig, image: &DeepColorImage<T>) -> Self {
+        Self {
+            config,
+            image: image.to_color_image(),
+            mask: None,
+            deep: Some(image.convert()),
         }
     }
 
+    /// Only clusters the pixels set in `mask`, which must be of the same size as the image
+    pub fn with_mask(mut self, mask: BinaryImage) -> Self {
+        self.mask = Some(mask);
+        self
+    }
+
     pub fn init(&mut self, image: ColorImage) {
         self.image = image;
     }
@@ -89,22 +144,64 @@ impl Runner {
             key_color,
             keying_action,
             color_space,
+            memory_cap,
+            scan_order,
+            segmentation: _,
+            average_color,
+            jpeg_tolerance,
+            trace,
         } = self.config;
 
         assert!(is_same_color_a < 8);
+        if let ScanOrder::Priority { tile_size } = scan_order {
+            assert!(tile_size > 0, "tile_size of ScanOrder::Priority must be positive");
+        }
 
         let diff_fn = match color_space {
             ColorSpace::RGB => color_diff,
             ColorSpace::Oklab => oklab_color_diff,
         };
 
-        Builder::new()
-            .from(self.image)
+        // the tolerance is in units of the quantized channels
+        let block_extra = if jpeg_tolerance {
+            (self.image.block_artifacts(JPEG_BLOCK_SIZE) / (1 << is_same_color_a) as f64).ceil() as i32
+        } else {
+            0
+        };
+
+        let builder = Builder::new().from(self.image);
+        let builder = match self.mask {
+            Some(mask) => builder.mask(mask),
+            None => builder,
+        };
+        let builder = match self.deep {
+            Some(deep) => {
+                let deep_diff = match color_space {
+                    ColorSpace::RGB => color_diff_depth::<f32>,
+                    ColorSpace::Oklab => oklab_color_diff_depth::<f32>,
+                };
+                builder.deep(deep, move |a, b| deep_diff(a, b).round() as i32)
+            },
+            None => builder,
+        };
+        let builder = if block_extra > 0 {
+            builder.block_same(move |a: Color, b: Color| {
+                color_same(a, b, is_same_color_a, is_same_color_b + block_extra)
+            })
+        } else {
+            builder
+        };
+
+        builder
             .diagonal(diagonal)
             .hierarchical(hierarchical)
             .key(key_color)
             .keying_action(keying_action)
+            .memory_cap(memory_cap)
+            .scan_order(scan_order)
+            .average_color(average_color)
             .batch_size(batch_size as u32)
+            .trace(trace)
             .same(move |a: Color, b: Color| {
                 color_same(a, b, is_same_color_a, is_same_color_b)
             })
@@ -118,16 +215,46 @@ impl Runner {
             })
     }
 
+    /// Runs, and estimates a soft alpha along the key boundary (see `ColorImage::alpha_matte`),
+    /// so that the traced shapes can be composited without a hard edge
+    pub fn run_with_matte(self, config: &MattingConfig) -> (Clusters, PlanarImage<f32, 1>) {
+        let matte = self.image.alpha_matte(config);
+        (self.run(), matte)
+    }
+
     pub fn start(self) -> IncrementalBuilder {
         self.builder().start()
     }
 
     pub fn run(self) -> Clusters {
-        self.builder().run()
+        let (width, height) = (self.image.width as PixelIndex, self.image.height as PixelIndex);
+        let labels = match self.config.segmentation {
+            Segmentation::Hierarchical => return self.builder().run(),
+            Segmentation::Felzenszwalb { k, min_size } => {
+                felzenszwalb(&self.image, self.config.color_space, k, min_size)
+            },
+            Segmentation::MeanShift { spatial, range } => {
+                mean_shift(&self.image, self.config.color_space, spatial, range)
+            },
+        };
+        let mut clusters = Clusters::from_label_map(width, height, &labels, self.image);
+        clusters.set_average_color(self.config.average_color);
+        clusters
     }
 
 }
 
+/// Coordinates in which Euclidean distance is the color distance, roughly in 8 bit units
+pub(super) fn to_space(c: Color, color_space: ColorSpace) -> [f64; 3] {
+    match color_space {
+        ColorSpace::RGB => [c.r as f64, c.g as f64, c.b as f64],
+        ColorSpace::Oklab => {
+            let lab: oklab::Oklab = oklab::Rgb { r: c.r, g: c.g, b: c.b }.into();
+            [lab.l as f64 * 255.0, lab.a as f64 * 255.0, lab.b as f64 * 255.0]
+        },
+    }
+}
+
 pub fn color_diff(a: Color, b: Color) -> i32 {
     let a = ColorI32::new(&a);
     let b = ColorI32::new(&b);
@@ -167,6 +294,35 @@ pub fn color_same(a: Color, b: Color, shift: i32, thres: i32) -> bool {
     diff.r.abs() <= thres && diff.g.abs() <= thres && diff.b.abs() <= thres
 }
 
+/// Depth aware equivalent of [`color_diff`]. The result is in 8 bit units (so the same thresholds apply),
+/// but retains the precision of deeper channels. `Runner::new_deep` merges by it.
+pub fn color_diff_depth<T: ColorDepth>(a: ColorRgba<T>, b: ColorRgba<T>) -> f64 {
+    ((a.r.to_unit() - b.r.to_unit()).abs() +
+     (a.g.to_unit() - b.g.to_unit()).abs() +
+     (a.b.to_unit() - b.b.to_unit()).abs()) * 255.0
+}
+
+/// Depth aware equivalent of [`oklab_color_diff`]
+pub fn oklab_color_diff_depth<T: ColorDepth>(a: ColorRgba<T>, b: ColorRgba<T>) -> f64 {
+    let to_oklab = |c: ColorRgba<T>| oklab::srgb_f32_to_oklab(oklab::Rgb {
+        r: c.r.to_unit().min(1.0) as f32,
+        g: c.g.to_unit().min(1.0) as f32,
+        b: c.b.to_unit().min(1.0) as f32,
+    });
+    let (a, b) = (to_oklab(a), to_oklab(b));
+    let (dl, da, db) = (a.l - b.l, a.a - b.a, a.b - b.b);
+    ((dl * dl + da * da + db * db) * 255.0) as f64
+}
+
+/// Depth aware equivalent of [`color_same`]: channels are quantized to `bits` bits before comparison.
+/// `color_same(a, b, shift, thres)` corresponds to `color_same_depth(a, b, 8 - shift, thres)`.
+pub fn color_same_depth<T: ColorDepth>(a: ColorRgba<T>, b: ColorRgba<T>, bits: u32, thres: i32) -> bool {
+    let levels = (1u64 << bits) as f64;
+    let quantize = |v: T| (v.to_unit() * levels).floor().clamp(0.0, levels - 1.0) as i64;
+    let same = |u: T, v: T| (quantize(u) - quantize(v)).abs() <= thres as i64;
+    same(a.r, b.r) && same(a.g, b.g) && same(a.b, b.b)
+}
+
 fn patch_good(
     internal: &BuilderImpl,
     patch: &Cluster,
@@ -187,6 +343,7 @@ fn patch_good(
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::ColorImageU16;
 
     #[test]
     fn test_oklab_color_diff() {
@@ -209,4 +366,124 @@ mod tests {
         // (delta_sq * 255.0) as i32 = 0
         assert_eq!(diff, 0);
     }
+
+    #[test]
+    fn test_color_diff_depth() {
+        let a = Color::new(100, 150, 200);
+        let b = Color::new(110, 160, 190);
+        let diff = color_diff_depth(ColorRgba::<u16>::from_color(&a), ColorRgba::from_color(&b));
+        assert!((diff - color_diff(a, b) as f64).abs() < 1e-6);
+        // 16 bit channels differing by less than one 8 bit step are still distinguished
+        let c = ColorRgba::<u16>::new_rgba(1000, 1000, 1000, 65535);
+        let d = ColorRgba::<u16>::new_rgba(1100, 1000, 1000, 65535);
+        assert!(color_diff_depth(c, d) > 0.0);
+        assert_eq!(color_diff(c.to_color(), d.to_color()), 0);
+    }
+
+    #[test]
+    fn deep_runner_merges_by_depth() {
+        // in 8 bit, the square is 40 apart from the background in each channel and merges into it as
+        // `deepen_diff` is 120, but it is 40.8 apart at the full depth and so is kept as a cluster of its own
+        let (square, background) = (79 * 257 + 154, 120 * 257 + 103);
+        let mut image = ColorImageU16::new_w_h(16, 16);
+        for y in 0..16 {
+            for x in 0..16 {
+                let v = if (5..11).contains(&x) && (5..11).contains(&y) { square } else { background };
+                image.set_pixel(x, y, &ColorRgba::new_rgba(v, v, v, 65535));
+            }
+        }
+        assert_eq!(image.to_color_image().get_pixel(8, 8), Color::new(80, 80, 80));
+        assert_eq!(image.to_color_image().get_pixel(0, 0), Color::new(120, 120, 120));
+        let config = RunnerConfig { deepen_diff: 120, ..Default::default() };
+        assert_eq!(Runner::new(config.clone(), image.to_color_image()).run().output_len(), 1);
+        assert_eq!(Runner::new_deep(config, &image).run().output_len(), 2);
+    }
+
+    #[test]
+    fn test_color_same_depth() {
+        for (a, b) in [(16u8, 31u8), (16, 32), (0, 255), (100, 120)] {
+            let ca = Color::new(a, a, a);
+            let cb = Color::new(b, b, b);
+            assert_eq!(
+                color_same(ca, cb, 4, 0),
+                color_same_depth(ColorRgba::<u16>::from_color(&ca), ColorRgba::from_color(&cb), 4, 0)
+            );
+        }
+        let c = ColorRgba::<u16>::new_rgba(1000, 1000, 1000, 65535);
+        let d = ColorRgba::<u16>::new_rgba(1010, 1000, 1000, 65535);
+        assert!(!color_same_depth(c, d, 16, 0));
+        assert!(color_same_depth(c, d, 8, 0));
+    }
+
+    #[test]
+    fn memory_cap_flattens_hierarchy() {
+        let image = black_square_on_white();
+        let builder = Runner::new(RunnerConfig::default(), image.clone()).builder();
+        assert!(builder.memory_estimate() > 12 * 12 * 12);
+        let mut incremental = builder.start();
+        while !incremental.tick() {}
+        assert!(incremental.memory_usage() > 0);
+        assert_eq!(incremental.result().output_len(), 2);
+
+        let config = RunnerConfig {
+            memory_cap: 0,
+            ..RunnerConfig::default()
+        };
+        assert_eq!(Runner::new(config, image).run().output_len(), 1);
+    }
+
+    #[test]
+    fn hilbert_scan_order() {
+        // a ring, so that patches can only be joined when scanned in some orders
+        let mut image = ColorImage::new_w_h(13, 11);
+        for y in 0..11 {
+            for x in 0..13 {
+                let ring = (2..11).contains(&x) && (2..9).contains(&y) && !((4..9).contains(&x) && (4..7).contains(&y));
+                let color = if ring { Color::new(200, 0, 0) } else { Color::new(255, 255, 255) };
+                image.set_pixel(x, y, &color);
+            }
+        }
+        let config = RunnerConfig {
+            hierarchical: 0,
+            scan_order: ScanOrder::Hilbert,
+            ..RunnerConfig::default()
+        };
+        let clusters = Runner::new(config, image).run();
+        let view = clusters.view();
+        // exactly the connected components: the ring, the inside and the outside
+        assert_eq!(clusters.output_len(), 3);
+        let ring = view.get_cluster(view.get_cluster_at(2 * 13 + 2));
+        assert_eq!(ring.area(), 9 * 7 - 5 * 3);
+        assert_eq!(ring.color(), Color::new(200, 0, 0));
+        assert_eq!(view.iter().map(|c| c.area()).sum::<usize>(), 13 * 11);
+    }
+
+    #[test]
+    fn mask_restricts_clustering() {
+        let mut image = ColorImage::new_w_h(8, 8);
+        for x in 0..8 {
+            for y in 0..8 {
+                let color = if x < 4 { Color::new(255, 0, 0) } else { Color::new(0, 0, 255) };
+                image.set_pixel(x, y, &color);
+            }
+        }
+        let mut mask = BinaryImage::new_w_h(8, 8);
+        for x in 2..6 {
+            for y in 2..6 {
+                mask.set_pixel(x, y, true);
+            }
+        }
+        for factor in [1, 2] {
+            let config = RunnerConfig {
+                good_min_area: 0,
+                ..RunnerConfig::default()
+            };
+            let clusters = Runner::new(config, image.clone()).with_mask(mask.clone()).run_downscaled(factor);
+            let view = clusters.view();
+            assert!(view.iter().all(|c| c.iter().all(|&i| mask.get_pixel(i as usize % 8, i as usize / 8))));
+            assert_eq!(view.get_cluster_at(0), ZERO);
+            let covered: std::collections::HashSet<_> = view.iter().flat_map(|c| c.iter().copied()).collect();
+            assert_eq!(covered.len(), 16, "factor {}", factor);
+        }
+    }
 }
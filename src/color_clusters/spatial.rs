@@ -0,0 +1,138 @@
+use crate::{BoundingRect, PointI32};
+use super::{ClusterIndex, ClustersView, ZERO, container::PixelIndex};
+
+/// Side length of a grid cell, in pixels
+const CELL_SIZE: i32 = 32;
+
+/// Spatial index over the output clusters of a `ClustersView`, for fast hit-testing.
+///
+/// Point queries are exact and `O(depth)`, using a per-pixel map of the innermost cluster
+/// and the parent of each cluster. Rect queries use a uniform grid of cluster bounding boxes.
+pub struct ClustersIndex {
+    width: PixelIndex,
+    height: PixelIndex,
+    /// innermost output cluster of each pixel
+    labels: Vec<ClusterIndex>,
+    /// smallest output cluster enclosing each cluster
+    parents: Vec<ClusterIndex>,
+    rects: Vec<BoundingRect>,
+    /// position of each cluster in `clusters_output`
+    order: Vec<usize>,
+    cols: i32,
+    rows: i32,
+    cells: Vec<Vec<ClusterIndex>>,
+}
+
+impl ClustersView<'_> {
+    /// Builds a `ClustersIndex`; it has to be rebuilt if the clusters change.
+    pub fn spatial_index(&self) -> ClustersIndex {
+        let mut labels = vec![ZERO; self.width as usize * self.height as usize];
+        let mut parents = vec![ZERO; self.clusters.len()];
+        let mut rects = vec![BoundingRect::default(); self.clusters.len()];
+        let mut order = vec![usize::MAX; self.clusters.len()];
+        let cols = (self.width as i32 + CELL_SIZE - 1) / CELL_SIZE;
+        let rows = (self.height as i32 + CELL_SIZE - 1) / CELL_SIZE;
+        let mut cells = vec![Vec::new(); (cols * rows) as usize];
+
+        for (position, &index) in self.clusters_output.iter().enumerate() {
+            let rect = self.get_cluster(index).rect;
+            rects[index.0 as usize] = rect;
+            order[index.0 as usize] = position;
+            if rect.is_empty() {
+                continue;
+            }
+            for row in (rect.top / CELL_SIZE)..=((rect.bottom - 1) / CELL_SIZE) {
+                for col in (rect.left / CELL_SIZE)..=((rect.right - 1) / CELL_SIZE) {
+                    cells[(row * cols + col) as usize].push(index);
+                }
+            }
+        }
+
+        // from largest to smallest, so that a cluster finds its parent already painted
+        for &index in self.clusters_output.iter().rev() {
+            let cluster = self.get_cluster(index);
+            if let Some(&first) = cluster.indices.first() {
+                parents[index.0 as usize] = labels[first as usize];
+            }
+            for &i in cluster.indices.iter() {
+                labels[i as usize] = index;
+            }
+        }
+
+        ClustersIndex { width: self.width, height: self.height, labels, parents, rects, order, cols, rows, cells }
+    }
+}
+
+impl ClustersIndex {
+    /// Output clusters containing `point`, from the innermost to the outermost
+    pub fn query_point(&self, point: PointI32) -> Vec<ClusterIndex> {
+        let mut result = Vec::new();
+        if point.x < 0 || point.y < 0 || point.x >= self.width as i32 || point.y >= self.height as i32 {
+            return result;
+        }
+        let mut index = self.labels[point.y as usize * self.width as usize + point.x as usize];
+        while index != ZERO {
+            result.push(index);
+            index = self.parents[index.0 as usize];
+        }
+        result
+    }
+
+    /// Output clusters whose bounding box overlaps `rect`, in the order of `clusters_output`
+    pub fn query_rect(&self, rect: BoundingRect) -> Vec<ClusterIndex> {
+        let mut result = Vec::new();
+        if rect.is_empty() {
+            return result;
+        }
+        let col_range = std::cmp::max(rect.left / CELL_SIZE, 0)..=std::cmp::min((rect.right - 1) / CELL_SIZE, self.cols - 1);
+        let row_range = std::cmp::max(rect.top / CELL_SIZE, 0)..=std::cmp::min((rect.bottom - 1) / CELL_SIZE, self.rows - 1);
+        for row in row_range {
+            for col in col_range.clone() {
+                for &index in self.cells[(row * self.cols + col) as usize].iter() {
+                    let r = self.rects[index.0 as usize];
+                    if r.left < rect.right && rect.left < r.right && r.top < rect.bottom && rect.top < r.bottom {
+                        result.push(index);
+                    }
+                }
+            }
+        }
+        result.sort_by_key(|index| self.order[index.0 as usize]);
+        result.dedup();
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Color, ColorImage};
+    use crate::color_clusters::{Runner, RunnerConfig};
+
+    #[test]
+    fn spatial_index_queries() {
+        // two black squares on white
+        let mut image = ColorImage::new_w_h(80, 40);
+        for y in 0..40 {
+            for x in 0..80 {
+                let inside = (5..15).contains(&y) && ((5..15).contains(&x) || (60..70).contains(&x));
+                let color = if inside { Color::new(0, 0, 0) } else { Color::new(255, 255, 255) };
+                image.set_pixel(x, y, &color);
+            }
+        }
+        let clusters = Runner::new(RunnerConfig::default(), image).run();
+        let view = clusters.view();
+        let index = view.spatial_index();
+
+        let hit = index.query_point(PointI32::new(10, 10));
+        assert_eq!(hit.len(), 2);
+        assert_eq!(view.get_cluster(hit[0]).color(), Color::new(0, 0, 0));
+        assert_eq!(view.get_cluster(hit[0]).area(), 100);
+        assert_eq!(index.query_point(PointI32::new(30, 30)), vec![hit[1]]);
+        assert!(index.query_point(PointI32::new(80, 0)).is_empty());
+
+        let found = index.query_rect(BoundingRect::new_x_y_w_h(50, 0, 30, 20));
+        assert_eq!(found.len(), 2);
+        assert_eq!(view.get_cluster(found[0]).rect, BoundingRect::new_x_y_w_h(60, 5, 10, 10));
+        assert_eq!(found[1], hit[1]);
+    }
+}
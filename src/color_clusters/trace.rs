@@ -0,0 +1,88 @@
+use super::{ClusterIndex, Clusters};
+
+/// The rule by which a cluster was merged into another, see `MergeRecord`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MergeRule {
+    /// combined in stage 1, the colors being `same` along the scan
+    SameColor,
+    /// merged into its neighbour of the least diff, and gone from the output
+    Flattened,
+    /// kept as an output cluster nested in its neighbour of the least diff, `deepen` holding
+    Deepen,
+    /// as `Deepen`, and cut out of the neighbour as a hole, `hollow` holding too
+    Hollow,
+}
+
+/// A merge into a cluster, recorded with `Builder::trace`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct MergeRecord {
+    pub from: ClusterIndex,
+    pub rule: MergeRule,
+    /// area of `from` when merged
+    pub area: usize,
+    /// diff (by `Builder::diff`) to `from`; 0 for `SameColor`, where the diff is not measured
+    pub diff: i32,
+    /// number of neighbours `from` had; 0 for `SameColor`
+    pub neighbours: usize,
+}
+
+/// Why a cluster is an output cluster
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum OutputReason {
+    /// above `hierarchical` (or with no hierarchy), so never merged
+    Unmerged,
+    /// the last cluster left, i.e. the background
+    Background,
+    /// surrounded by key colored pixels which are discarded, so with no neighbour to merge into
+    Keyed,
+    /// nested in `parent` by a `Deepen` or `Hollow` merge
+    Deepened { parent: ClusterIndex },
+}
+
+/// The history of a cluster, recorded with `Builder::trace`; see `Clusters::trace`
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ClusterTrace {
+    /// None if not an output cluster
+    pub reason: Option<OutputReason>,
+    /// merges into this cluster, in order, including those into clusters flattened into it
+    pub merges: Vec<MergeRecord>,
+}
+
+impl Clusters {
+    /// The history of the cluster at `index`, if built with `Builder::trace` (or `RunnerConfig::trace`)
+    pub fn trace(&self, index: ClusterIndex) -> Option<&ClusterTrace> {
+        self.trace.get(index.0 as usize)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Color;
+    use crate::color_clusters::{Runner, RunnerConfig, black_square_on_white};
+
+    #[test]
+    fn trace_of_square() {
+        let image = black_square_on_white();
+        let untraced = Runner::new(RunnerConfig::default(), image.clone()).run();
+        assert!(untraced.trace(untraced.clusters_output[0]).is_none());
+
+        let clusters = Runner::new(RunnerConfig { trace: true, ..Default::default() }, image).run();
+        let view = clusters.view();
+        let square = clusters.clusters_output.iter().copied()
+            .find(|&i| view.get_cluster(i).residue_color() == Color::new(0, 0, 0)).unwrap();
+        let background = clusters.clusters_output.iter().copied()
+            .find(|&i| view.get_cluster(i).residue_color() == Color::new(255, 255, 255)).unwrap();
+
+        let trace = clusters.trace(square).unwrap();
+        assert_eq!(trace.reason, Some(OutputReason::Deepened { parent: background }));
+        assert!(trace.merges.iter().any(|m| m.rule == MergeRule::SameColor));
+
+        let trace = clusters.trace(background).unwrap();
+        assert_eq!(trace.reason, Some(OutputReason::Background));
+        let deepen = trace.merges.iter().find(|m| m.from == square).unwrap();
+        assert!(matches!(deepen.rule, MergeRule::Deepen | MergeRule::Hollow));
+        assert!(deepen.diff > 64);
+        assert_eq!(deepen.area, 36);
+    }
+}
@@ -0,0 +1,160 @@
+use super::{ClusterIndex, Clusters, PixelIndex, ZERO};
+
+/// A broken invariant of `Clusters`, see `Clusters::validate`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ClusterViolation {
+    /// the pixel or cluster index buffers do not match `width` x `height`
+    Size,
+    /// an index (in the pixel map or the outputs) to no cluster
+    InvalidIndex(ClusterIndex),
+    /// a pixel of a cluster outside the image or the cluster's rect
+    OutsideRect { cluster: ClusterIndex, pixel: PixelIndex },
+    /// a pixel in more than one top level (outermost) output cluster
+    Overlap { pixel: PixelIndex },
+    /// a pixel mapped to a cluster other than the top level cluster containing it (or to none)
+    Mislabelled { pixel: PixelIndex },
+    /// a nested output cluster not entirely inside the cluster it is nested in
+    Hierarchy { cluster: ClusterIndex, parent: ClusterIndex },
+    /// `b` is a neighbour of `a`, but `a` is not of `b`
+    Neighbours { a: ClusterIndex, b: ClusterIndex },
+}
+
+impl Clusters {
+    /// Checks the internal consistency of the clusters, e.g. after each operation in a property-based test:
+    /// each pixel is mapped to the one top level output cluster containing it (or to none, if masked out),
+    /// nested output clusters lie within their parent, and neighbours are mutual.
+    /// Returns all violations found, none if consistent.
+    pub fn validate(&self) -> Vec<ClusterViolation> {
+        let mut violations = Vec::new();
+        let (width, height) = (self.width as usize, self.height as usize);
+        let size = width * height;
+        if self.pixels.len() != size * 4 || self.cluster_indices.len() != size {
+            violations.push(ClusterViolation::Size);
+            return violations;
+        }
+        let valid = |i: ClusterIndex| (i.0 as usize) < self.clusters.len();
+        for &i in self.cluster_indices.iter().chain(self.clusters_output.iter()) {
+            if !valid(i) && !violations.contains(&ClusterViolation::InvalidIndex(i)) {
+                violations.push(ClusterViolation::InvalidIndex(i));
+            }
+        }
+        for &index in self.clusters_output.iter() {
+            if !valid(index) {
+                continue;
+            }
+            let cluster = &self.clusters[index.0 as usize];
+            for &pixel in cluster.indices.iter() {
+                // checked before dividing, as `width` is 0 for an empty image
+                if pixel as usize >= size {
+                    violations.push(ClusterViolation::OutsideRect { cluster: index, pixel });
+                    continue;
+                }
+                let (x, y) = ((pixel % self.width) as i32, (pixel / self.width) as i32);
+                let rect = cluster.rect;
+                if x < rect.left || x >= rect.right || y < rect.top || y >= rect.bottom {
+                    violations.push(ClusterViolation::OutsideRect { cluster: index, pixel });
+                }
+            }
+        }
+        if !violations.is_empty() {
+            return violations;
+        }
+
+        // from largest to smallest, each output cluster is nested in the smallest already seen containing its first pixel
+        let mut outputs = self.clusters_output.clone();
+        outputs.sort_by_key(|&i| std::cmp::Reverse(self.clusters[i.0 as usize].area()));
+        let mut innermost = vec![ZERO; size];
+        let mut top = vec![ZERO; size];
+        for &index in outputs.iter() {
+            let cluster = &self.clusters[index.0 as usize];
+            let parent = match cluster.indices.first() {
+                Some(&first) => innermost[first as usize],
+                None => continue,
+            };
+            for &pixel in cluster.indices.iter() {
+                let p = pixel as usize;
+                if parent == ZERO {
+                    if top[p] != ZERO {
+                        violations.push(ClusterViolation::Overlap { pixel });
+                    }
+                    top[p] = index;
+                } else if innermost[p] != parent {
+                    violations.push(ClusterViolation::Hierarchy { cluster: index, parent });
+                    break;
+                }
+            }
+            for &pixel in cluster.indices.iter() {
+                innermost[pixel as usize] = index;
+            }
+        }
+        for (pixel, (&label, &top)) in self.cluster_indices.iter().zip(top.iter()).enumerate() {
+            if label != top {
+                violations.push(ClusterViolation::Mislabelled { pixel: pixel as PixelIndex });
+            }
+        }
+
+        let view = self.view();
+        let tops: Vec<ClusterIndex> = outputs.iter().copied()
+            .filter(|&i| self.clusters[i.0 as usize].indices.first().is_some_and(|&p| top[p as usize] == i))
+            .collect();
+        for &a in tops.iter() {
+            for b in self.clusters[a.0 as usize].neighbours(&view) {
+                if !valid(b) || self.clusters[b.0 as usize].indices.is_empty() ||
+                    !self.clusters[b.0 as usize].neighbours(&view).contains(&a) {
+                    violations.push(ClusterViolation::Neighbours { a, b });
+                }
+            }
+        }
+        violations
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BinaryImage, Color, ColorImage};
+    use crate::color_clusters::{Runner, RunnerConfig};
+
+    #[test]
+    fn runner_output_is_valid() {
+        let mut image = ColorImage::new_w_h(16, 12);
+        for y in 0..12 {
+            for x in 0..16 {
+                let color = if (3..9).contains(&x) && (2..8).contains(&y) {
+                    Color::new(0, 0, 0)
+                } else if (5..7).contains(&x) && (9..11).contains(&y) || x > 12 {
+                    Color::new(200, 30, 30)
+                } else {
+                    Color::new(255, 255, 255)
+                };
+                image.set_pixel(x, y, &color);
+            }
+        }
+        let clusters = Runner::new(RunnerConfig::default(), image.clone()).run();
+        assert_eq!(clusters.validate(), []);
+
+        // masked out pixels belong to no cluster
+        let mut mask = BinaryImage::new_w_h(16, 12);
+        mask.pixels.set_all();
+        mask.set_pixel(0, 0, false);
+        let masked = Runner::new(RunnerConfig::default(), image.clone()).with_mask(mask).run();
+        assert_eq!(masked.validate(), []);
+
+        let mut broken = Clusters::from_label_map(16, 12, &vec![1; 16 * 12], image);
+        broken.cluster_indices[5] = ZERO;
+        assert_eq!(broken.validate(), [ClusterViolation::Mislabelled { pixel: 5 }]);
+        broken.cluster_indices.pop();
+        assert_eq!(broken.validate(), [ClusterViolation::Size]);
+    }
+
+    #[test]
+    fn pixels_of_empty_image_are_outside() {
+        let mut empty = Clusters::from_label_map(0, 0, &[0u8; 0], ColorImage::new_w_h(0, 0));
+        let index = ClusterIndex(empty.clusters.len() as _);
+        let mut cluster = crate::color_clusters::Cluster::new();
+        cluster.indices.push(3);
+        empty.clusters.push(cluster);
+        empty.clusters_output.push(index);
+        assert_eq!(empty.validate(), [ClusterViolation::OutsideRect { cluster: index, pixel: 3 }]);
+    }
+}
@@ -0,0 +1,110 @@
+use std::cmp::{Ordering, Reverse};
+use std::collections::BinaryHeap;
+use crate::{ColorImage, PlanarImage};
+use super::{Clusters, PixelIndex};
+
+/// Marker controlled watershed (Meyer's flooding): every pixel with a non zero label in `markers` is a seed,
+/// and labels spread to the remaining pixels in order of increasing `gradient`,
+/// so that regions meet along gradient ridges.
+///
+/// Returns a label map (row major) which can be passed to `Clusters::from_label_map`.
+/// Pixels not connected to any marker keep label 0.
+pub fn watershed(gradient: &PlanarImage<f32, 1>, markers: &[u32]) -> Vec<u32> {
+    let (width, height) = (gradient.width(), gradient.height());
+    assert_eq!(markers.len(), width * height);
+    let mut labels = markers.to_vec();
+    let mut queue = BinaryHeap::new();
+    // ties are flooded first in first out, so plateaus are split evenly
+    let mut sequence = 0u64;
+    let level = |i: usize| Level(gradient.channel(0)[i]);
+
+    let mut push_neighbours = |labels: &[u32], queue: &mut BinaryHeap<_>, i: usize| {
+        let (x, y) = (i % width, i / width);
+        let neighbours = [
+            (x > 0).then(|| i - 1),
+            (x + 1 < width).then(|| i + 1),
+            (y > 0).then(|| i - width),
+            (y + 1 < height).then(|| i + width),
+        ];
+        for n in neighbours.into_iter().flatten() {
+            if labels[n] == 0 {
+                queue.push(Reverse((level(n), sequence, n, labels[i])));
+                sequence += 1;
+            }
+        }
+    };
+
+    for i in 0..width * height {
+        if labels[i] != 0 {
+            push_neighbours(&labels, &mut queue, i);
+        }
+    }
+    while let Some(Reverse((_, _, i, label))) = queue.pop() {
+        if labels[i] != 0 {
+            continue;
+        }
+        labels[i] = label;
+        push_neighbours(&labels, &mut queue, i);
+    }
+    labels
+}
+
+impl Clusters {
+    /// Segments `image` by `watershed` of its gradient magnitude from `markers` (row major, 0 for no marker)
+    pub fn from_watershed(image: ColorImage, markers: &[u32]) -> Self {
+        let labels = watershed(&image.gradient_magnitude(), markers);
+        Self::from_label_map(image.width as PixelIndex, image.height as PixelIndex, &labels, image)
+    }
+}
+
+/// Totally ordered gradient value
+#[derive(Copy, Clone, PartialEq)]
+struct Level(f32);
+
+impl Eq for Level {}
+
+impl PartialOrd for Level {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Level {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Color;
+
+    #[test]
+    fn watershed_two_regions() {
+        // a dark left half and a light right half, seeded at the far ends
+        let mut image = ColorImage::new_w_h(8, 4);
+        for y in 0..4 {
+            for x in 0..8 {
+                let color = if x < 3 { Color::new(0, 0, 0) } else { Color::new(255, 255, 255) };
+                image.set_pixel(x, y, &color);
+            }
+        }
+        let mut markers = vec![0; 32];
+        markers[8] = 1;
+        markers[15] = 2;
+        let clusters = Clusters::from_watershed(image, &markers);
+        let labels = watershed(&ColorImage::new_w_h(8, 4).gradient_magnitude(), &[0; 32]);
+        assert!(labels.iter().all(|&l| l == 0));
+
+        let view = clusters.view();
+        assert_eq!(view.clusters_output.len(), 2);
+        for y in 0..4 {
+            for x in 0..8 {
+                let index = view.get_cluster_at(y * 8 + x);
+                let expected = if x < 3 { Color::new(0, 0, 0) } else { Color::new(255, 255, 255) };
+                assert_eq!(view.get_cluster(index).color(), expected);
+            }
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use std::fmt::Debug;
+use crate::{Color, ColorImage, Image, ImageMut};
+
+/// A color channel of some bit depth
+pub trait ColorDepth: Copy + Default + PartialEq + Debug {
+    /// The value of a fully saturated channel
+    const MAX: f64;
+
+    fn to_f64(self) -> f64;
+
+    /// Converts from `f64`, clamping to the representable range
+    fn from_f64(v: f64) -> Self;
+
+    /// Maps the channel to [0, 1] (may exceed 1 for HDR float values)
+    fn to_unit(self) -> f64 {
+        self.to_f64() / Self::MAX
+    }
+
+    fn from_unit(v: f64) -> Self {
+        Self::from_f64(v * Self::MAX)
+    }
+}
+
+impl ColorDepth for u8 {
+    const MAX: f64 = 255.0;
+
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    fn from_f64(v: f64) -> Self {
+        v.round().clamp(0.0, <Self as ColorDepth>::MAX) as u8
+    }
+}
+
+impl ColorDepth for u16 {
+    const MAX: f64 = 65535.0;
+
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    fn from_f64(v: f64) -> Self {
+        v.round().clamp(0.0, <Self as ColorDepth>::MAX) as u16
+    }
+}
+
+/// Floating point channels are nominally in [0, 1]; values above 1 are allowed for HDR content
+impl ColorDepth for f32 {
+    const MAX: f64 = 1.0;
+
+    fn to_f64(self) -> f64 {
+        self as f64
+    }
+
+    fn from_f64(v: f64) -> Self {
+        v.max(0.0) as f32
+    }
+}
+
+/// RGBA; each channel is of depth `T`
+#[derive(Copy, Clone, Default, PartialEq, Debug)]
+pub struct ColorRgba<T> {
+    pub r: T,
+    pub g: T,
+    pub b: T,
+    pub a: T,
+}
+
+/// Image with 4 channels of depth `T` per pixel
+#[derive(Clone, Default)]
+pub struct DeepColorImage<T> {
+    pub pixels: Vec<T>,
+    pub width: usize,
+    pub height: usize,
+}
+
+/// Image with 4 channels of 16 bit unsigned per pixel
+pub type ColorImageU16 = DeepColorImage<u16>;
+/// Image with 4 channels of 32 bit float per pixel
+pub type ColorImageF32 = DeepColorImage<f32>;
+
+impl<T: ColorDepth> ColorRgba<T> {
+    pub fn new_rgba(r: T, g: T, b: T, a: T) -> Self {
+        Self { r, g, b, a }
+    }
+
+    /// Converts to another depth, preserving the relative intensity of each channel
+    pub fn convert<U: ColorDepth>(&self) -> ColorRgba<U> {
+        ColorRgba {
+            r: U::from_unit(self.r.to_unit()),
+            g: U::from_unit(self.g.to_unit()),
+            b: U::from_unit(self.b.to_unit()),
+            a: U::from_unit(self.a.to_unit()),
+        }
+    }
+
+    /// Quantizes to 8 bit
+    pub fn to_color(&self) -> Color {
+        let c = self.convert::<u8>();
+        Color::new_rgba(c.r, c.g, c.b, c.a)
+    }
+
+    pub fn from_color(color: &Color) -> Self {
+        ColorRgba::new_rgba(color.r, color.g, color.b, color.a).convert()
+    }
+}
+
+impl<T: ColorDepth> DeepColorImage<T> {
+    pub fn new_w_h(width: usize, height: usize) -> Self {
+        Self {
+            pixels: vec![T::default(); width * height * 4],
+            width,
+            height,
+        }
+    }
+
+    pub fn get_pixel(&self, x: usize, y: usize) -> ColorRgba<T> {
+        self.get_pixel_at(y * self.width + x)
+    }
+
+    pub fn get_pixel_at(&self, index: usize) -> ColorRgba<T> {
+        let i = index * 4;
+        ColorRgba::new_rgba(self.pixels[i], self.pixels[i + 1], self.pixels[i + 2], self.pixels[i + 3])
+    }
+
+    pub fn set_pixel(&mut self, x: usize, y: usize, color: &ColorRgba<T>) {
+        self.set_pixel_at(y * self.width + x, color);
+    }
+
+    pub fn set_pixel_at(&mut self, index: usize, color: &ColorRgba<T>) {
+        let i = index * 4;
+        self.pixels[i] = color.r;
+        self.pixels[i + 1] = color.g;
+        self.pixels[i + 2] = color.b;
+        self.pixels[i + 3] = color.a;
+    }
+
+    /// Converts to another depth
+    pub fn convert<U: ColorDepth>(&self) -> DeepColorImage<U> {
+        DeepColorImage {
+            pixels: self.pixels.iter().map(|&v| U::from_unit(v.to_unit())).collect(),
+            width: self.width,
+            height: self.height,
+        }
+    }
+
+    pub fn from_color_image(image: &ColorImage) -> Self {
+        DeepColorImage {
+            pixels: image.pixels.clone(),
+            width: image.width,
+            height: image.height,
+        }.convert()
+    }
+
+    /// Quantizes to an 8 bit `ColorImage`
+    pub fn to_color_image(&self) -> ColorImage {
+        let image = self.convert::<u8>();
+        ColorImage {
+            pixels: image.pixels,
+            width: image.width,
+            height: image.height,
+        }
+    }
+}
+
+impl<T: ColorDepth> Image for DeepColorImage<T> {
+    type Pixel = ColorRgba<T>;
+
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn pixel(&self, x: usize, y: usize) -> ColorRgba<T> {
+        self.get_pixel(x, y)
+    }
+}
+
+impl<T: ColorDepth> ImageMut for DeepColorImage<T> {
+    fn new_image(width: usize, height: usize) -> Self {
+        Self::new_w_h(width, height)
+    }
+
+    fn put_pixel(&mut self, x: usize, y: usize, v: ColorRgba<T>) {
+        self.set_pixel(x, y, &v);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn depth_conversion() {
+        let c = ColorRgba::<u16>::from_color(&Color::new(255, 128, 0));
+        assert_eq!(c, ColorRgba::new_rgba(65535, 32896, 0, 65535));
+        assert_eq!(c.to_color(), Color::new(255, 128, 0));
+        let f = c.convert::<f32>();
+        assert_eq!(f.r, 1.0);
+        assert_eq!(f.b, 0.0);
+    }
+
+    #[test]
+    fn deep_image_round_trip() {
+        let mut image = ColorImage::new_w_h(2, 1);
+        image.set_pixel(1, 0, &Color::new(10, 20, 30));
+        let deep = ColorImageU16::from_color_image(&image);
+        assert_eq!(deep.get_pixel(1, 0).g, 20 * 257);
+        assert_eq!(deep.to_color_image().pixels, image.pixels);
+    }
+}
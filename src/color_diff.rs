@@ -0,0 +1,245 @@
+use crate::{Color, srgb_to_linear};
+use crate::fmath::{atan2, cos, exp, powf, sin};
+
+/// CIE L*a*b* under the D65 white point; `l` is in [0, 100]
+#[derive(Copy, Clone, Default, PartialEq, Debug)]
+pub struct ColorLab {
+    pub l: f64,
+    pub a: f64,
+    pub b: f64,
+}
+
+impl ColorLab {
+    pub fn new(l: f64, a: f64, b: f64) -> Self {
+        Self { l, a, b }
+    }
+
+    pub fn chroma(&self) -> f64 {
+        (self.a * self.a + self.b * self.b).sqrt()
+    }
+}
+
+impl Color {
+    /// Converts from sRGB to CIE L*a*b* (D65); alpha is ignored
+    pub fn to_lab(&self) -> ColorLab {
+        let (r, g, b) = (srgb_to_linear(self.r), srgb_to_linear(self.g), srgb_to_linear(self.b));
+        let x = (0.4124564 * r + 0.3575761 * g + 0.1804375 * b) / 0.95047;
+        let y = 0.2126729 * r + 0.7151522 * g + 0.0721750 * b;
+        let z = (0.0193339 * r + 0.1191920 * g + 0.9503041 * b) / 1.08883;
+        let f = |t: f64| {
+            if t > 216.0 / 24389.0 {
+                powf(t, 1.0 / 3.0)
+            } else {
+                (24389.0 / 27.0 * t + 16.0) / 116.0
+            }
+        };
+        let (fx, fy, fz) = (f(x), f(y), f(z));
+        ColorLab::new(116.0 * fy - 16.0, 500.0 * (fx - fy), 200.0 * (fy - fz))
+    }
+}
+
+/// A color difference formula.
+///
+/// `diff` is the integer interface of the clusterer (see `Builder::diff`): each formula is scaled
+/// so that black and white are about 765 apart, the same as `color_clusters::color_diff`,
+/// so the thresholds of `RunnerConfig` roughly carry over.
+pub trait ColorDifference {
+    /// Difference in the units of the formula, e.g. delta E for the CIE formulas
+    fn delta(&self, a: Color, b: Color) -> f64;
+
+    /// Multiplier from `delta` to `diff`
+    fn scale(&self) -> f64;
+
+    fn diff(&self, a: Color, b: Color) -> i32 {
+        (self.delta(a, b) * self.scale()).round() as i32
+    }
+}
+
+/// Euclidean distance in sRGB
+#[derive(Copy, Clone, Debug, Default)]
+pub struct EuclideanRgb;
+
+/// Euclidean distance in sRGB, with channels weighted by the mean red ("redmean")
+#[derive(Copy, Clone, Debug, Default)]
+pub struct WeightedRgb;
+
+/// CIE 1976: Euclidean distance in L*a*b*
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Cie76;
+
+/// CIE 1994, with the graphic arts constants. Not symmetric: the first color is the reference.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Cie94;
+
+/// CIEDE2000, with unit weighting factors
+#[derive(Copy, Clone, Debug, Default)]
+pub struct Ciede2000;
+
+/// Delta E of black to white is 100
+const DELTA_E_SCALE: f64 = 7.65;
+
+impl ColorDifference for EuclideanRgb {
+    fn delta(&self, a: Color, b: Color) -> f64 {
+        let (dr, dg, db) = (a.r as f64 - b.r as f64, a.g as f64 - b.g as f64, a.b as f64 - b.b as f64);
+        (dr * dr + dg * dg + db * db).sqrt()
+    }
+
+    fn scale(&self) -> f64 {
+        3f64.sqrt()
+    }
+}
+
+impl ColorDifference for WeightedRgb {
+    fn delta(&self, a: Color, b: Color) -> f64 {
+        let mean = (a.r as f64 + b.r as f64) / 2.0;
+        let (dr, dg, db) = (a.r as f64 - b.r as f64, a.g as f64 - b.g as f64, a.b as f64 - b.b as f64);
+        ((2.0 + mean / 256.0) * dr * dr + 4.0 * dg * dg + (2.0 + (255.0 - mean) / 256.0) * db * db).sqrt()
+    }
+
+    fn scale(&self) -> f64 {
+        1.0
+    }
+}
+
+impl ColorDifference for Cie76 {
+    fn delta(&self, a: Color, b: Color) -> f64 {
+        cie76(a.to_lab(), b.to_lab())
+    }
+
+    fn scale(&self) -> f64 {
+        DELTA_E_SCALE
+    }
+}
+
+impl ColorDifference for Cie94 {
+    fn delta(&self, a: Color, b: Color) -> f64 {
+        cie94(a.to_lab(), b.to_lab())
+    }
+
+    fn scale(&self) -> f64 {
+        DELTA_E_SCALE
+    }
+}
+
+impl ColorDifference for Ciede2000 {
+    fn delta(&self, a: Color, b: Color) -> f64 {
+        ciede2000(a.to_lab(), b.to_lab())
+    }
+
+    fn scale(&self) -> f64 {
+        DELTA_E_SCALE
+    }
+}
+
+/// Delta E (CIE 1976)
+pub fn cie76(x: ColorLab, y: ColorLab) -> f64 {
+    let (dl, da, db) = (x.l - y.l, x.a - y.a, x.b - y.b);
+    (dl * dl + da * da + db * db).sqrt()
+}
+
+/// Delta E (CIE 1994, graphic arts), with `reference` as the first color
+pub fn cie94(reference: ColorLab, sample: ColorLab) -> f64 {
+    let (dl, da, db) = (reference.l - sample.l, reference.a - sample.a, reference.b - sample.b);
+    let c = reference.chroma();
+    let dc = c - sample.chroma();
+    let dh_sq = (da * da + db * db - dc * dc).max(0.0);
+    let (sc, sh) = (1.0 + 0.045 * c, 1.0 + 0.015 * c);
+    (dl * dl + (dc / sc).powi(2) + dh_sq / (sh * sh)).sqrt()
+}
+
+/// Delta E (CIEDE2000), after Sharma, Wu and Dalal (2005)
+pub fn ciede2000(x: ColorLab, y: ColorLab) -> f64 {
+    let pow7 = 25f64.powi(7);
+    let c_mean = (x.chroma() + y.chroma()) / 2.0;
+    let g = 0.5 * (1.0 - (c_mean.powi(7) / (c_mean.powi(7) + pow7)).sqrt());
+    let prime = |lab: ColorLab| {
+        let a = (1.0 + g) * lab.a;
+        let c = (a * a + lab.b * lab.b).sqrt();
+        let h = if c == 0.0 { 0.0 } else { atan2(lab.b, a).to_degrees().rem_euclid(360.0) };
+        (c, h)
+    };
+    let ((c1, h1), (c2, h2)) = (prime(x), prime(y));
+
+    let dl = y.l - x.l;
+    let dc = c2 - c1;
+    let dh = if c1 * c2 == 0.0 {
+        0.0
+    } else if (h2 - h1).abs() <= 180.0 {
+        h2 - h1
+    } else if h2 - h1 > 180.0 {
+        h2 - h1 - 360.0
+    } else {
+        h2 - h1 + 360.0
+    };
+    let dh = 2.0 * (c1 * c2).sqrt() * sin((dh / 2.0).to_radians());
+
+    let l_mean = (x.l + y.l) / 2.0;
+    let c_mean = (c1 + c2) / 2.0;
+    let h_mean = if c1 * c2 == 0.0 {
+        h1 + h2
+    } else if (h1 - h2).abs() <= 180.0 {
+        (h1 + h2) / 2.0
+    } else if h1 + h2 < 360.0 {
+        (h1 + h2 + 360.0) / 2.0
+    } else {
+        (h1 + h2 - 360.0) / 2.0
+    };
+    let cos_deg = |d: f64| cos(d.to_radians());
+    let t = 1.0 - 0.17 * cos_deg(h_mean - 30.0) + 0.24 * cos_deg(2.0 * h_mean) +
+        0.32 * cos_deg(3.0 * h_mean + 6.0) - 0.20 * cos_deg(4.0 * h_mean - 63.0);
+    let dtheta = 30.0 * exp(-((h_mean - 275.0) / 25.0).powi(2));
+    let rc = 2.0 * (c_mean.powi(7) / (c_mean.powi(7) + pow7)).sqrt();
+    let l50 = (l_mean - 50.0).powi(2);
+    let sl = 1.0 + 0.015 * l50 / (20.0 + l50).sqrt();
+    let sc = 1.0 + 0.045 * c_mean;
+    let sh = 1.0 + 0.015 * c_mean * t;
+    let rt = -sin((2.0 * dtheta).to_radians()) * rc;
+
+    let (l, c, h) = (dl / sl, dc / sc, dh / sh);
+    (l * l + c * c + h * h + rt * c * h).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ciede2000_reference_pairs() {
+        // from the test data of Sharma, Wu and Dalal
+        let pairs = [
+            ((50.0, 2.6772, -79.7751), (50.0, 0.0, -82.7485), 2.0425),
+            ((50.0, 0.0, 0.0), (50.0, -1.0, 2.0), 2.3669),
+            ((50.0, 2.5, 0.0), (73.0, 25.0, -18.0), 27.1492),
+            ((50.0, 2.5, 0.0), (61.0, -5.0, 29.0), 22.8977),
+            ((50.0, 2.5, 0.0), (50.0, 3.2972, 0.0), 1.0),
+            ((60.2574, -34.0099, 36.2677), (60.4626, -34.1751, 39.4387), 1.2644),
+        ];
+        for &((l1, a1, b1), (l2, a2, b2), expected) in pairs.iter() {
+            let (x, y) = (ColorLab::new(l1, a1, b1), ColorLab::new(l2, a2, b2));
+            assert!((ciede2000(x, y) - expected).abs() < 1e-4, "{:?} {:?} {}", x, y, ciede2000(x, y));
+            assert!((ciede2000(y, x) - expected).abs() < 1e-4);
+        }
+    }
+
+    #[test]
+    fn color_difference_formulas() {
+        let red = Color::new(255, 0, 0).to_lab();
+        assert!((red.l - 53.2408).abs() < 1e-2 && (red.a - 80.0925).abs() < 1e-2 && (red.b - 67.2032).abs() < 1e-2);
+        let white = Color::new(255, 255, 255).to_lab();
+        assert!((white.l - 100.0).abs() < 1e-3 && white.a.abs() < 1e-2 && white.b.abs() < 1e-2);
+
+        let (grey, tinted) = (ColorLab::new(50.0, 0.0, 0.0), ColorLab::new(50.0, 10.0, 0.0));
+        assert_eq!(cie76(ColorLab::new(50.0, 0.0, 0.0), ColorLab::new(53.0, 4.0, 0.0)), 5.0);
+        assert_eq!(cie94(grey, ColorLab::new(60.0, 0.0, 0.0)), 10.0);
+        assert_eq!(cie94(grey, tinted), 10.0);
+        assert!((cie94(tinted, grey) - 10.0 / 1.45).abs() < 1e-9);
+
+        let (black, white) = (Color::new(0, 0, 0), Color::new(255, 255, 255));
+        let formulas: [&dyn ColorDifference; 5] = [&EuclideanRgb, &WeightedRgb, &Cie76, &Cie94, &Ciede2000];
+        for formula in formulas.iter() {
+            assert_eq!(formula.diff(black, black), 0);
+            assert!((760..=770).contains(&formula.diff(black, white)), "{}", formula.diff(black, white));
+        }
+        assert_eq!(EuclideanRgb.delta(black, Color::new(3, 4, 0)), 5.0);
+    }
+}
@@ -0,0 +1,76 @@
+use crate::{Color, ColorImage, PlanarImage};
+
+/// Mean and standard deviation of each Oklab channel of the (not fully transparent) pixels of an image
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct ColorMoments {
+    pub mean: [f64; 3],
+    pub std_dev: [f64; 3],
+}
+
+impl ColorMoments {
+    pub fn of(image: &ColorImage) -> Self {
+        let lab = PlanarImage::oklab_from_color_image(image);
+        let opaque: Vec<usize> = (0..image.width * image.height).filter(|&i| image.get_pixel_at(i).a > 0).collect();
+        let mut moments = Self::default();
+        if opaque.is_empty() {
+            return moments;
+        }
+        let n = opaque.len() as f64;
+        for c in 0..3 {
+            let plane = lab.channel(c);
+            let mean = opaque.iter().map(|&i| plane[i] as f64).sum::<f64>() / n;
+            let variance = opaque.iter().map(|&i| (plane[i] as f64 - mean).powi(2)).sum::<f64>() / n;
+            moments.mean[c] = mean;
+            moments.std_dev[c] = variance.sqrt();
+        }
+        moments
+    }
+}
+
+/// Color transfer (Reinhard et al.): `source` recolored to the color statistics of `target`,
+/// e.g. to tone-match a batch of assets to a reference before extracting a shared palette
+pub fn transfer_color(source: &ColorImage, target: &ColorImage) -> ColorImage {
+    match_color_moments(source, &ColorMoments::of(target))
+}
+
+/// `image` with each Oklab channel shifted and scaled to the mean and standard deviation of `moments`;
+/// alpha is kept. A channel without variation is only shifted.
+pub fn match_color_moments(image: &ColorImage, moments: &ColorMoments) -> ColorImage {
+    let from = ColorMoments::of(image);
+    let mut lab = PlanarImage::oklab_from_color_image(image);
+    for c in 0..3 {
+        let scale = if from.std_dev[c] > 1e-6 { moments.std_dev[c] / from.std_dev[c] } else { 1.0 };
+        for v in lab.channel_mut(c).iter_mut() {
+            *v = ((*v as f64 - from.mean[c]) * scale + moments.mean[c]) as f32;
+        }
+    }
+    // the lightness of Oklab is in [0, 1]
+    lab.channel_mut(0).iter_mut().for_each(|v| *v = v.clamp(0.0, 1.0));
+    let mut matched = lab.oklab_to_color_image();
+    for i in 0..image.width * image.height {
+        let c = matched.get_pixel_at(i);
+        matched.set_pixel_at(i, &Color::new_rgba(c.r, c.g, c.b, image.get_pixel_at(i).a));
+    }
+    matched
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::synth::gradient;
+
+    #[test]
+    fn transfer_moments() {
+        let source = gradient(32, 8, Color::new(20, 20, 60), Color::new(90, 90, 160), true);
+        let target = gradient(16, 16, Color::new(220, 120, 40), Color::new(250, 200, 120), false);
+        let transferred = transfer_color(&source, &target);
+        let (got, want) = (ColorMoments::of(&transferred), ColorMoments::of(&target));
+        for c in 0..3 {
+            assert!((got.mean[c] - want.mean[c]).abs() < 0.01, "{:?} {:?}", got, want);
+            assert!((got.std_dev[c] - want.std_dev[c]).abs() < 0.01, "{:?} {:?}", got, want);
+        }
+        // the ramp of the source is kept, lighter to the right
+        assert!(transferred.get_pixel(31, 0).r > transferred.get_pixel(0, 0).r);
+        assert_eq!(transfer_color(&target, &target).get_pixel(3, 3), target.get_pixel(3, 3));
+    }
+}
@@ -0,0 +1,227 @@
+use crate::{BinaryImage, PointI32};
+use crate::clusters::{Cluster, Clusters};
+
+/// The connected components of a `BinaryImage`, see `Components::filter`
+pub struct Components {
+    pub width: usize,
+    pub height: usize,
+    pub clusters: Clusters,
+}
+
+type ComponentPredicate<'a> = Box<dyn Fn(&Cluster) -> bool + 'a>;
+
+/// Chainable predicates over `Components`; a component is kept if it passes all of them
+pub struct ComponentFilter<'a> {
+    components: &'a Components,
+    predicates: Vec<ComponentPredicate<'a>>,
+}
+
+impl Components {
+    pub fn new(image: &BinaryImage, diagonal: bool) -> Self {
+        Self {
+            width: image.width,
+            height: image.height,
+            clusters: image.to_clusters(diagonal),
+        }
+    }
+
+    /// Starts a filter which keeps every component, e.g. to remove small blobs touching the border:
+    /// `components.filter().min_area(10).touching_border(false).to_binary_image()`
+    pub fn filter(&self) -> ComponentFilter<'_> {
+        ComponentFilter {
+            components: self,
+            predicates: Vec::new(),
+        }
+    }
+
+    /// Whether the component has a pixel on the edge of the image
+    pub fn touches_border(&self, cluster: &Cluster) -> bool {
+        let rect = cluster.rect;
+        rect.left <= 0 || rect.top <= 0 ||
+            rect.right >= self.width as i32 || rect.bottom >= self.height as i32
+    }
+}
+
+impl<'a> ComponentFilter<'a> {
+    pub fn min_area(self, area: usize) -> Self {
+        self.custom(move |c| c.size() >= area)
+    }
+
+    pub fn max_area(self, area: usize) -> Self {
+        self.custom(move |c| c.size() <= area)
+    }
+
+    /// Keeps components covering at least this fraction of their bounding rect
+    pub fn min_extent(self, extent: f64) -> Self {
+        self.custom(move |c| c.extent() >= extent)
+    }
+
+    pub fn max_extent(self, extent: f64) -> Self {
+        self.custom(move |c| c.extent() <= extent)
+    }
+
+    /// Keeps components whose bounding rect is at most this elongated (long side over short side)
+    pub fn max_aspect_ratio(self, ratio: f64) -> Self {
+        self.custom(move |c| c.aspect_ratio() <= ratio)
+    }
+
+    pub fn min_aspect_ratio(self, ratio: f64) -> Self {
+        self.custom(move |c| c.aspect_ratio() >= ratio)
+    }
+
+    /// Keeps components covering at least this fraction of their convex hull
+    pub fn min_solidity(self, solidity: f64) -> Self {
+        self.custom(move |c| c.solidity() >= solidity)
+    }
+
+    pub fn max_solidity(self, solidity: f64) -> Self {
+        self.custom(move |c| c.solidity() <= solidity)
+    }
+
+    /// Keeps only the components which do (`true`) or do not (`false`) touch the image border
+    pub fn touching_border(self, touching: bool) -> Self {
+        let components = self.components;
+        self.custom(move |c| components.touches_border(c) == touching)
+    }
+
+    /// Adds an arbitrary predicate
+    pub fn custom(mut self, predicate: impl Fn(&Cluster) -> bool + 'a) -> Self {
+        self.predicates.push(Box::new(predicate));
+        self
+    }
+
+    /// Indices into `Components::clusters` of the components kept
+    pub fn indices(&self) -> Vec<usize> {
+        self.components.clusters.iter()
+            .enumerate()
+            .filter(|(_, c)| self.predicates.iter().all(|p| p(c)))
+            .map(|(i, _)| i)
+            .collect()
+    }
+
+    /// An image, the size of the original, of the components kept
+    pub fn to_binary_image(&self) -> BinaryImage {
+        let mut image = BinaryImage::new_w_h(self.components.width, self.components.height);
+        for i in self.indices() {
+            for p in self.components.clusters.get_cluster(i).iter() {
+                image.set_pixel(p.x as usize, p.y as usize, true);
+            }
+        }
+        image
+    }
+}
+
+impl Cluster {
+    /// Fraction of the bounding rect covered
+    pub fn extent(&self) -> f64 {
+        self.size() as f64 / (self.rect.width() * self.rect.height()) as f64
+    }
+
+    /// Long side over short side of the bounding rect, at least 1
+    pub fn aspect_ratio(&self) -> f64 {
+        let (w, h) = (self.rect.width() as f64, self.rect.height() as f64);
+        w.max(h) / w.min(h)
+    }
+
+    /// Convex hull of the pixel squares, counter clockwise in image coordinates (y down)
+    pub fn convex_hull(&self) -> Vec<PointI32> {
+        // the outermost corners of each row suffice
+        let rows = self.rect.height() as usize;
+        let mut extremes = vec![(i32::MAX, i32::MIN); rows];
+        for p in self.points.iter() {
+            let row = &mut extremes[(p.y - self.rect.top) as usize];
+            row.0 = row.0.min(p.x);
+            row.1 = row.1.max(p.x + 1);
+        }
+        let mut corners = Vec::new();
+        for (i, &(left, right)) in extremes.iter().enumerate() {
+            if left > right {
+                continue;
+            }
+            let y = self.rect.top + i as i32;
+            corners.extend([
+                PointI32::new(left, y), PointI32::new(left, y + 1),
+                PointI32::new(right, y), PointI32::new(right, y + 1),
+            ]);
+        }
+        convex_hull(corners)
+    }
+
+    /// Fraction of the convex hull covered
+    pub fn solidity(&self) -> f64 {
+        let hull = self.convex_hull();
+        let mut doubled = 0i64;
+        for i in 0..hull.len() {
+            let (a, b) = (hull[i], hull[(i + 1) % hull.len()]);
+            doubled += a.x as i64 * b.y as i64 - b.x as i64 * a.y as i64;
+        }
+        if doubled == 0 {
+            return 0.0;
+        }
+        self.size() as f64 * 2.0 / doubled.abs() as f64
+    }
+}
+
+/// Monotone chain, without collinear points
+pub(crate) fn convex_hull(mut points: Vec<PointI32>) -> Vec<PointI32> {
+    points.sort_by_key(|p| (p.x, p.y));
+    points.dedup();
+    if points.len() < 3 {
+        return points;
+    }
+    let cross = |o: PointI32, a: PointI32, b: PointI32| {
+        (a.x - o.x) as i64 * (b.y - o.y) as i64 - (a.y - o.y) as i64 * (b.x - o.x) as i64
+    };
+    let mut hull: Vec<PointI32> = Vec::with_capacity(points.len() * 2);
+    for pass in 0..2 {
+        let start = hull.len();
+        let iter: Box<dyn Iterator<Item = &PointI32>> = if pass == 0 {
+            Box::new(points.iter())
+        } else {
+            Box::new(points.iter().rev())
+        };
+        for &p in iter {
+            while hull.len() >= start + 2 && cross(hull[hull.len() - 2], hull[hull.len() - 1], p) <= 0 {
+                hull.pop();
+            }
+            hull.push(p);
+        }
+        // the last point is the first of the other chain
+        hull.pop();
+    }
+    hull
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filter_components() {
+        let image = BinaryImage::from_string(&(
+            "*-------------\n".to_owned() +
+            "-----****-----\n" +
+            "-----****--*--\n" +
+            "-----*-----*--\n" +
+            "-----*-----*--\n" +
+            "-----------*--\n" +
+            "-------------*\n"
+        ));
+        let components = Components::new(&image, false);
+        assert_eq!(components.clusters.len(), 4);
+
+        let kept = components.filter().min_area(2).touching_border(false);
+        assert_eq!(kept.indices().len(), 2);
+        let output = kept.to_binary_image();
+        assert!(!output.get_pixel(0, 0) && !output.get_pixel(13, 6));
+        assert!(output.get_pixel(5, 1) && output.get_pixel(11, 2));
+
+        // the bar is elongated and solid, the hook is neither
+        let bar = components.filter().min_aspect_ratio(3.0).min_solidity(0.99).min_area(2);
+        assert_eq!(bar.to_binary_image().area(), 4);
+        let hook = components.clusters.iter().find(|c| c.size() == 10).unwrap();
+        assert_eq!(hook.extent(), 10.0 / 16.0);
+        assert!((hook.solidity() - 10.0 / 13.0).abs() < 1e-9);
+        assert_eq!(components.filter().max_extent(0.9).indices().len(), 1);
+    }
+}
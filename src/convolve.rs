@@ -0,0 +1,157 @@
+use crate::{BorderMode, PlanarImage};
+use crate::fmath::exp;
+
+/// A 2D convolution kernel, centered at (`width / 2`, `height / 2`)
+#[derive(Clone, Debug, PartialEq)]
+pub struct Kernel {
+    width: usize,
+    height: usize,
+    /// row major weights
+    values: Vec<f32>,
+}
+
+impl Kernel {
+    /// `None` unless `values` has `width * height` weights
+    pub fn new(width: usize, height: usize, values: Vec<f32>) -> Option<Self> {
+        if width == 0 || height == 0 || values.len() != width * height {
+            return None;
+        }
+        Some(Self { width, height, values })
+    }
+
+    /// The outer product of a horizontal and a vertical kernel; panics if either is empty
+    pub fn from_separable(kx: &[f32], ky: &[f32]) -> Self {
+        assert!(!kx.is_empty() && !ky.is_empty());
+        let values = ky.iter().flat_map(|&y| kx.iter().map(move |&x| x * y)).collect();
+        Self { width: kx.len(), height: ky.len(), values }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Row major weights
+    pub fn values(&self) -> &[f32] {
+        &self.values
+    }
+
+    /// Scaled so that the weights sum to 1, preserving the mean brightness; unchanged if they sum to 0
+    /// (e.g. edge detection kernels)
+    pub fn normalized(mut self) -> Self {
+        normalize_kernel(&mut self.values);
+        self
+    }
+
+    pub fn sharpen() -> Self {
+        Self { width: 3, height: 3, values: vec![0.0, -1.0, 0.0, -1.0, 5.0, -1.0, 0.0, -1.0, 0.0] }
+    }
+
+    /// The 4-neighbour discrete Laplacian, whose weights sum to 0
+    pub fn laplacian() -> Self {
+        Self { width: 3, height: 3, values: vec![0.0, 1.0, 0.0, 1.0, -4.0, 1.0, 0.0, 1.0, 0.0] }
+    }
+
+    pub fn emboss() -> Self {
+        Self { width: 3, height: 3, values: vec![-2.0, -1.0, 0.0, -1.0, 1.0, 1.0, 0.0, 1.0, 2.0] }
+    }
+}
+
+/// Scales `values` to sum to 1, unless they sum to 0
+pub fn normalize_kernel(values: &mut [f32]) {
+    let sum: f32 = values.iter().sum();
+    if sum.abs() > f32::EPSILON {
+        values.iter_mut().for_each(|v| *v /= sum);
+    }
+}
+
+/// A normalized 1D Gaussian of standard deviation `sigma`, truncated at 3 `sigma`;
+/// the identity `[1.0]` unless `sigma` is positive
+pub fn gaussian_kernel(sigma: f64) -> Vec<f32> {
+    if sigma.is_nan() || sigma <= 0.0 {
+        return vec![1.0];
+    }
+    let radius = (3.0 * sigma).ceil().max(0.0) as i32;
+    let mut values: Vec<f32> = (-radius..=radius)
+        .map(|i| exp(-(i * i) as f64 / (2.0 * sigma * sigma)) as f32)
+        .collect();
+    normalize_kernel(&mut values);
+    values
+}
+
+/// Convolution of each channel, with pixels beyond the edges extended by `border`
+/// (zero where `BorderMode::Constant`). The kernels are applied as is, correlation style (not flipped).
+impl<const C: usize> PlanarImage<f32, C> {
+    pub fn convolve(&self, kernel: &Kernel, border: BorderMode) -> Self {
+        let (width, height) = (self.width(), self.height());
+        let (cx, cy) = ((kernel.width / 2) as i64, (kernel.height / 2) as i64);
+        let mut result = Self::new_w_h(width, height);
+        for c in 0..C {
+            let plane = self.channel(c);
+            let out = result.channel_mut(c);
+            for y in 0..height as i64 {
+                for x in 0..width as i64 {
+                    let mut sum = 0.0;
+                    for (k, &weight) in kernel.values.iter().enumerate() {
+                        let (kx, ky) = ((k % kernel.width) as i64, (k / kernel.width) as i64);
+                        if let (Some(sx), Some(sy)) = (border.resolve(x + kx - cx, width), border.resolve(y + ky - cy, height)) {
+                            sum += weight * plane[sy * width + sx];
+                        }
+                    }
+                    out[y as usize * width + x as usize] = sum;
+                }
+            }
+        }
+        result
+    }
+
+    /// Convolution by `kx` along rows then `ky` along columns, equivalent to `convolve` by
+    /// `Kernel::from_separable(kx, ky)` (but for rounding) in `kx.len() + ky.len()` instead of their product per pixel
+    pub fn convolve_separable(&self, kx: &[f32], ky: &[f32], border: BorderMode) -> Self {
+        let rows = self.convolve(&Kernel::from_separable(kx, &[1.0]), border);
+        rows.convolve(&Kernel::from_separable(&[1.0], ky), border)
+    }
+
+    /// Gaussian blur of standard deviation `sigma`, by `convolve_separable`
+    pub fn gaussian_blur(&self, sigma: f64, border: BorderMode) -> Self {
+        let kernel = gaussian_kernel(sigma);
+        self.convolve_separable(&kernel, &kernel, border)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn separable_matches_full() {
+        let mut image = PlanarImage::<f32, 1>::new_w_h(7, 5);
+        for y in 0..5 {
+            for x in 0..7 {
+                image.set(x, y, 0, ((x * 3 + y * 5) % 7) as f32);
+            }
+        }
+        let (kx, ky) = ([1.0, 2.0, 1.0], [-1.0, 0.0, 1.0]);
+        for border in [BorderMode::Constant, BorderMode::Replicate, BorderMode::Reflect, BorderMode::Wrap] {
+            let full = image.convolve(&Kernel::from_separable(&kx, &ky), border);
+            let separable = image.convolve_separable(&kx, &ky, border);
+            assert!(full.channel(0).iter().zip(separable.channel(0)).all(|(a, b)| (a - b).abs() < 1e-4));
+        }
+
+        // a normalized kernel keeps a flat image flat, but for the zero border
+        let flat = PlanarImage::<f32, 1>::new_w_h_with_value(4, 4, 10.0);
+        let blurred = flat.gaussian_blur(1.0, BorderMode::Replicate);
+        assert!(blurred.channel(0).iter().all(|v| (v - 10.0).abs() < 1e-4));
+        assert!(flat.gaussian_blur(1.0, BorderMode::Constant).get(0, 0, 0) < 10.0);
+        assert_eq!(flat.convolve(&Kernel::sharpen(), BorderMode::Reflect).get(0, 0, 0), 10.0);
+        assert_eq!(Kernel::new(2, 2, vec![1.0, 1.0, 1.0, 1.0]).unwrap().normalized().values(), [0.25; 4]);
+        assert!(Kernel::new(2, 2, vec![1.0]).is_none());
+        assert!(Kernel::new(0, 0, vec![]).is_none());
+        assert_eq!(gaussian_kernel(0.0), [1.0]);
+        assert_eq!(gaussian_kernel(-1.0), [1.0]);
+        assert_eq!(flat.gaussian_blur(0.0, BorderMode::Constant), flat);
+    }
+}
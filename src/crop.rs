@@ -0,0 +1,139 @@
+use crate::{BoundingRect, Color, ColorImage};
+
+/// Pixels within this (sum of absolute channel differences) of the corner color are part of a uniform border
+const BORDER_TOLERANCE: i32 = 24;
+
+/// A crop proposed by `suggest_crop`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct CropSuggestion {
+    pub rect: BoundingRect,
+    /// fraction of the saliency of the image inside `rect`, in [0, 1]
+    pub confidence: f64,
+}
+
+/// Proposes a crop of `aspect_ratio` (width over height): uniform borders (of the corner colors) are trimmed,
+/// the crop is made as small as covers the remaining content (but no larger than the image),
+/// then slid to where it holds the most saliency (see `ColorImage::saliency`) while still covering the content.
+pub fn suggest_crop(image: &ColorImage, aspect_ratio: f64) -> CropSuggestion {
+    assert!(aspect_ratio > 0.0);
+    let (width, height) = (image.width, image.height);
+    if width == 0 || height == 0 {
+        return CropSuggestion { rect: BoundingRect::default(), confidence: 0.0 };
+    }
+    let content = content_rect(image);
+
+    // the smallest crop of the aspect ratio covering the content, within the image
+    let (cw, ch) = (content.width() as f64, content.height() as f64);
+    let (mut w, mut h) = if cw / ch > aspect_ratio { (cw, cw / aspect_ratio) } else { (ch * aspect_ratio, ch) };
+    if w > width as f64 {
+        (w, h) = (width as f64, width as f64 / aspect_ratio);
+    }
+    if h > height as f64 {
+        (w, h) = (height as f64 * aspect_ratio, height as f64);
+    }
+    let (w, h) = ((w.round() as usize).clamp(1, width), (h.round() as usize).clamp(1, height));
+
+    // summed saliency, (width + 1) x (height + 1)
+    let saliency = image.saliency();
+    let mut sum = vec![0.0f64; (width + 1) * (height + 1)];
+    for y in 0..height {
+        for x in 0..width {
+            sum[(y + 1) * (width + 1) + x + 1] = saliency.get(x, y, 0) as f64 +
+                sum[y * (width + 1) + x + 1] + sum[(y + 1) * (width + 1) + x] - sum[y * (width + 1) + x];
+        }
+    }
+    let region = |x: usize, y: usize, w: usize, h: usize| {
+        sum[(y + h) * (width + 1) + x + w] - sum[y * (width + 1) + x + w] - sum[(y + h) * (width + 1) + x] + sum[y * (width + 1) + x]
+    };
+    let total = region(0, 0, width, height);
+
+    // ties go to the position closest to centering the content
+    let center = |x: usize, y: usize| {
+        let dx = (2 * x + w) as f64 - (content.left + content.right) as f64;
+        let dy = (2 * y + h) as f64 - (content.top + content.bottom) as f64;
+        dx * dx + dy * dy
+    };
+    // positions along an axis where the crop covers the content, if it is large enough to
+    let range = |start: i32, end: i32, size: usize, extent: usize| {
+        let (start, end) = (start as usize, end as usize);
+        if size >= end - start {
+            end.saturating_sub(size)..=start.min(extent - size)
+        } else {
+            0..=extent - size
+        }
+    };
+    let (mut best, mut best_saliency) = ((0, 0), f64::MIN);
+    for y in range(content.top, content.bottom, h, height) {
+        for x in range(content.left, content.right, w, width) {
+            let s = region(x, y, w, h);
+            if s > best_saliency + 1e-9 || ((s - best_saliency).abs() <= 1e-9 && center(x, y) < center(best.0, best.1)) {
+                best = (x, y);
+                best_saliency = s;
+            }
+        }
+    }
+    CropSuggestion {
+        rect: BoundingRect::new_x_y_w_h(best.0 as i32, best.1 as i32, w as i32, h as i32),
+        confidence: if total > 0.0 { (best_saliency / total).clamp(0.0, 1.0) } else { 0.0 },
+    }
+}
+
+/// The image without its uniform borders: rows and columns (from the outside in) all within `BORDER_TOLERANCE`
+/// of the top left (for the top and left) or bottom right (for the bottom and right) corner color
+fn content_rect(image: &ColorImage) -> BoundingRect {
+    let (width, height) = (image.width, image.height);
+    let near = |a: Color, b: Color| {
+        (a.r as i32 - b.r as i32).abs() + (a.g as i32 - b.g as i32).abs() + (a.b as i32 - b.b as i32).abs() <= BORDER_TOLERANCE
+    };
+    let (first, last) = (image.get_pixel(0, 0), image.get_pixel(width - 1, height - 1));
+    let row = |y: usize, corner: Color| (0..width).all(|x| near(image.get_pixel(x, y), corner));
+    let column = |x: usize, corner: Color| (0..height).all(|y| near(image.get_pixel(x, y), corner));
+
+    let top = (0..height).find(|&y| !row(y, first)).unwrap_or(0);
+    let bottom = (top..height).rev().find(|&y| !row(y, last)).map_or(height, |y| y + 1);
+    let left = (0..width).find(|&x| !column(x, first)).unwrap_or(0);
+    let right = (left..width).rev().find(|&x| !column(x, last)).map_or(width, |x| x + 1);
+    if top >= bottom || left >= right {
+        // uniform all over
+        return BoundingRect::new_x_y_w_h(0, 0, width as i32, height as i32);
+    }
+    BoundingRect::new_x_y_w_h(left as i32, top as i32, (right - left) as i32, (bottom - top) as i32)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crop_to_content() {
+        // a textured object on a plain background, off to the right
+        let mut image = ColorImage::new_w_h(80, 40);
+        for y in 0..40 {
+            for x in 0..80 {
+                let object = (44..68).contains(&x) && (10..30).contains(&y);
+                let color = if object && (x / 2 + y / 2) % 2 == 0 {
+                    Color::new(200, 30, 30)
+                } else if object {
+                    Color::new(30, 30, 200)
+                } else {
+                    Color::new(240, 240, 240)
+                };
+                image.set_pixel(x, y, &color);
+            }
+        }
+        assert_eq!(content_rect(&image), BoundingRect::new_x_y_w_h(44, 10, 24, 20));
+
+        let square = suggest_crop(&image, 1.0);
+        assert_eq!((square.rect.width(), square.rect.height()), (24, 24));
+        assert!(square.rect.left <= 44 && square.rect.right >= 68);
+        assert!(square.rect.top <= 10 && square.rect.bottom >= 30);
+        assert!(square.confidence > 0.5);
+
+        let wide = suggest_crop(&image, 3.0);
+        assert_eq!((wide.rect.width(), wide.rect.height()), (60, 20));
+        assert_eq!(wide.rect.top, 10);
+        // wider than the image allows: full width
+        let wider = suggest_crop(&image, 5.0);
+        assert_eq!((wider.rect.width(), wider.rect.height()), (80, 16));
+    }
+}
@@ -0,0 +1,161 @@
+use std::collections::VecDeque;
+use crate::{Color, ColorImage};
+
+/// Parameters of `ColorImage::defringe`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DefringeConfig {
+    /// pixels of this color (ignoring alpha) are keyed out; `Color::default()` for none, as in `RunnerConfig`
+    pub key_color: Color,
+    /// pixels less opaque than this are keyed out as well
+    pub alpha_threshold: u8,
+    /// width (in pixels) of the fringe around keyed out pixels
+    pub width: usize,
+}
+
+impl Default for DefringeConfig {
+    fn default() -> Self {
+        Self {
+            key_color: Color::default(),
+            alpha_threshold: 128,
+            width: 1,
+        }
+    }
+}
+
+const NEIGHBOURS: [(i32, i32); 8] = [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)];
+
+impl DefringeConfig {
+    pub fn is_keyed(&self, color: Color) -> bool {
+        color.a < self.alpha_threshold ||
+            (self.key_color != Color::default() &&
+            (color.r, color.g, color.b) == (self.key_color.r, self.key_color.g, self.key_color.b))
+    }
+}
+
+impl ColorImage {
+    /// Color decontamination of keyed images: the pixels within `width` of a keyed out pixel are mixed
+    /// with the background, so their color is replaced by extrapolating from the interior, ring by ring,
+    /// each pixel taking the mean of its (8-connected) neighbours already settled.
+    /// Alpha and keyed out pixels are left as they are.
+    pub fn defringe(&self, config: &DefringeConfig) -> ColorImage {
+        let len = self.width * self.height;
+        let neighbours = |i: usize| neighbours(self.width, self.height, i);
+        let distance = key_distance(self, config);
+
+        // settle from the interior outwards
+        let mut image = self.clone();
+        let mut settled: Vec<bool> = distance.iter().map(|&d| d == usize::MAX).collect();
+        let mut ring: Vec<usize> = (0..len)
+            .filter(|&i| !settled[i] && distance[i] > 0 && neighbours(i).any(|n| settled[n]))
+            .collect();
+        let mut queued = vec![false; len];
+        ring.iter().for_each(|&i| queued[i] = true);
+        while !ring.is_empty() {
+            let colors: Vec<_> = ring.iter().map(|&i| {
+                let (mut sum, mut count) = ([0u32; 3], 0);
+                for n in neighbours(i).filter(|&n| settled[n]) {
+                    let c = image.get_pixel_at(n);
+                    sum[0] += c.r as u32;
+                    sum[1] += c.g as u32;
+                    sum[2] += c.b as u32;
+                    count += 1;
+                }
+                let alpha = image.get_pixel_at(i).a;
+                Color::new_rgba(
+                    ((sum[0] + count / 2) / count) as u8,
+                    ((sum[1] + count / 2) / count) as u8,
+                    ((sum[2] + count / 2) / count) as u8,
+                    alpha,
+                )
+            }).collect();
+            for (&i, color) in ring.iter().zip(colors.iter()) {
+                image.set_pixel_at(i, color);
+                settled[i] = true;
+            }
+            let mut next = Vec::new();
+            for &i in ring.iter() {
+                for n in neighbours(i) {
+                    if !settled[n] && !queued[n] && distance[n] > 0 {
+                        queued[n] = true;
+                        next.push(n);
+                    }
+                }
+            }
+            ring = next;
+        }
+        image
+    }
+}
+
+/// Indices of the 8-connected neighbours of pixel `i`
+pub(crate) fn neighbours(width: usize, height: usize, i: usize) -> impl Iterator<Item = usize> {
+    let (x, y) = ((i % width) as i32, (i / width) as i32);
+    NEIGHBOURS.iter().filter_map(move |&(dx, dy)| {
+        let (nx, ny) = (x + dx, y + dy);
+        (nx >= 0 && ny >= 0 && nx < width as i32 && ny < height as i32).then(|| ny as usize * width + nx as usize)
+    })
+}
+
+/// Chessboard distance of each pixel to the nearest keyed out pixel: 0 if keyed out,
+/// `usize::MAX` if farther than `config.width`
+pub(crate) fn key_distance(image: &ColorImage, config: &DefringeConfig) -> Vec<usize> {
+    let mut distance = vec![usize::MAX; image.width * image.height];
+    let mut queue = VecDeque::new();
+    for (i, color) in image.iter().enumerate() {
+        if config.is_keyed(color) {
+            distance[i] = 0;
+            queue.push_back(i);
+        }
+    }
+    while let Some(i) = queue.pop_front() {
+        if distance[i] == config.width {
+            continue;
+        }
+        for n in neighbours(image.width, image.height, i) {
+            if distance[n] == usize::MAX {
+                distance[n] = distance[i] + 1;
+                queue.push_back(n);
+            }
+        }
+    }
+    distance
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defringe_green_screen() {
+        // green screen on the left, a red object on the right, with a column of mixed pixels in between
+        let green = Color::new(0, 255, 0);
+        let red = Color::new(250, 10, 0);
+        let mut image = ColorImage::new_w_h(8, 4);
+        for y in 0..4 {
+            for x in 0..8 {
+                let color = match x {
+                    0..=2 => green,
+                    3 => Color::new(125, 130, 0),
+                    _ => red,
+                };
+                image.set_pixel(x, y, &color);
+            }
+        }
+        let config = DefringeConfig { key_color: green, ..DefringeConfig::default() };
+        let clean = image.defringe(&config);
+        for y in 0..4 {
+            assert_eq!(clean.get_pixel(1, y), green);
+            assert_eq!(clean.get_pixel(3, y), red);
+            assert_eq!(clean.get_pixel(6, y), red);
+        }
+
+        // transparent pixels are keyed out too, and the alpha of the fringe is kept
+        let mut image = ColorImage::new_w_h(3, 1);
+        image.set_pixel(0, 0, &Color::new_rgba(0, 0, 0, 0));
+        image.set_pixel(1, 0, &Color::new_rgba(60, 60, 60, 200));
+        image.set_pixel(2, 0, &Color::new(0, 0, 200));
+        let clean = image.defringe(&DefringeConfig::default());
+        assert_eq!(clean.get_pixel(1, 0), Color::new_rgba(0, 0, 200, 200));
+        assert_eq!(clean.get_pixel(0, 0), Color::new_rgba(0, 0, 0, 0));
+    }
+}
@@ -0,0 +1,219 @@
+use crate::{BorderMode, Color, ColorImage};
+
+/// Halftone periods (in pixels) searched by `detect_halftone`
+const MIN_PERIOD: usize = 2;
+const MAX_PERIOD: usize = 12;
+
+/// A halftone screen found in an image
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Halftone {
+    /// distance between dots, in pixels
+    pub period: usize,
+    /// autocorrelation of the fine detail at `period`, in (0, 1]
+    pub strength: f64,
+}
+
+/// Parameters of `ColorImage::descreen`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct DescreenConfig {
+    /// the halftone period in pixels; 0 to use `detect_halftone`
+    pub period: usize,
+    /// the minimum autocorrelation for `detect_halftone` to report a screen
+    pub min_strength: f64,
+    /// the minimum contrast (in luma) of the dots against their surroundings
+    pub min_contrast: f32,
+    /// how the blur windows extend beyond the edges of the image
+    pub border: BorderMode,
+}
+
+impl Default for DescreenConfig {
+    fn default() -> Self {
+        Self {
+            period: 0,
+            min_strength: 0.3,
+            min_contrast: 16.0,
+            border: BorderMode::Replicate,
+        }
+    }
+}
+
+impl ColorImage {
+    /// Looks for a periodic pattern of dots, by the autocorrelation of the fine detail (luma minus its
+    /// local mean) along rows and columns. Returns the shortest period at which the autocorrelation
+    /// peaks with at least `min_strength` and close to the strongest peak, as multiples of the period peak as well.
+    pub fn detect_halftone(&self, min_strength: f64) -> Option<Halftone> {
+        let (width, height) = (self.width, self.height);
+        let detail = self.fine_detail(MAX_PERIOD, BorderMode::Replicate);
+        let energy: f64 = detail.iter().map(|&v| (v * v) as f64).sum();
+        if energy == 0.0 {
+            return None;
+        }
+        // lags from MIN_PERIOD - 1 to MAX_PERIOD + 1, so that peaks can be told from a decay
+        let correlation: Vec<f64> = (MIN_PERIOD - 1..=MAX_PERIOD + 1).map(|d| {
+            let mut sum = 0.0;
+            for y in 0..height {
+                for x in 0..width {
+                    let v = detail[y * width + x] as f64;
+                    if x + d < width {
+                        sum += v * detail[y * width + x + d] as f64;
+                    }
+                    if y + d < height {
+                        sum += v * detail[(y + d) * width + x] as f64;
+                    }
+                }
+            }
+            sum / (2.0 * energy)
+        }).collect();
+        let peaks: Vec<Halftone> = (1..correlation.len() - 1)
+            .filter(|&i| correlation[i] > correlation[i - 1] && correlation[i] >= correlation[i + 1])
+            .filter(|&i| correlation[i] >= min_strength)
+            .map(|i| Halftone { period: MIN_PERIOD - 1 + i, strength: correlation[i] })
+            .collect();
+        let strongest = peaks.iter().map(|p| p.strength).fold(f64::MIN, f64::max);
+        peaks.into_iter().find(|p| p.strength >= 0.9 * strongest)
+    }
+
+    /// Removes a halftone screen by a box blur one period wide, which cancels the dot pattern.
+    /// The blur is selective: it applies where the fine detail changes sign often, as it does across
+    /// dots of at least `min_contrast`, but not along a single edge. Alpha is left as it is.
+    /// Returns a copy of the image if no screen is given or detected.
+    pub fn descreen(&self, config: &DescreenConfig) -> ColorImage {
+        let period = match config.period {
+            0 => match self.detect_halftone(config.min_strength) {
+                Some(halftone) => halftone.period,
+                None => return self.clone(),
+            },
+            period => period,
+        };
+        let (width, height) = (self.width, self.height);
+
+        // sign changes of the fine detail, counted over a window two periods wide
+        let detail = self.fine_detail(period, config.border);
+        let mut crossings = vec![0.0; width * height];
+        for y in 0..height {
+            for x in 0..width {
+                let i = y * width + x;
+                let v = detail[i];
+                let crosses = |j: usize| {
+                    let u = detail[j];
+                    v.signum() != u.signum() && (v - u).abs() >= config.min_contrast
+                };
+                if (x + 1 < width && crosses(i + 1)) || (y + 1 < height && crosses(i + width)) {
+                    crossings[i] = 1.0;
+                }
+            }
+        }
+        let density = box_blur(&crossings, width, height, 2 * period, config.border);
+        // a straight edge crosses each row or column of the window once, a screen about twice per period
+        let mask: Vec<f32> = density
+            .iter()
+            .map(|&d| if d * period as f32 >= 1.0 { 1.0 } else { 0.0 })
+            .collect();
+        let weight = box_blur(&mask, width, height, period, config.border);
+
+        let mut image = self.clone();
+        for c in 0..3 {
+            let channel: Vec<f32> = self.pixels.iter().skip(c).step_by(4).map(|&v| v as f32).collect();
+            let blurred = box_blur(&channel, width, height, period, config.border);
+            for i in 0..width * height {
+                let v = weight[i] * blurred[i] + (1.0 - weight[i]) * channel[i];
+                image.pixels[i * 4 + c] = v.round().clamp(0.0, 255.0) as u8;
+            }
+        }
+        image
+    }
+
+    /// Luma minus its box blur over `period`
+    fn fine_detail(&self, period: usize, border: BorderMode) -> Vec<f32> {
+        let luma: Vec<f32> = self.iter().map(|c: Color| 0.299 * c.r as f32 + 0.587 * c.g as f32 + 0.114 * c.b as f32).collect();
+        let mean = box_blur(&luma, self.width, self.height, period, border);
+        luma.iter().zip(mean.iter()).map(|(v, m)| v - m).collect()
+    }
+}
+
+/// Mean over a `size` x `size` window, extended beyond the edges by `border` (zero where `Constant`).
+/// An even window is applied as two passes offset by one pixel, so the result is not shifted.
+fn box_blur(values: &[f32], width: usize, height: usize, size: usize, border: BorderMode) -> Vec<f32> {
+    let passes: &[(i32, i32)] = if size % 2 == 1 {
+        &[(size as i32 / 2, size as i32 / 2)]
+    } else {
+        &[(size as i32 / 2, size as i32 / 2 - 1), (size as i32 / 2 - 1, size as i32 / 2)]
+    };
+    let mut values = values.to_vec();
+    for &(before, after) in passes.iter() {
+        let n = (before + after + 1) as f32;
+        let mut rows = vec![0.0; values.len()];
+        for y in 0..height {
+            for x in 0..width as i32 {
+                let sum: f32 = (x - before..=x + after)
+                    .map(|sx| border.resolve(sx as i64, width).map_or(0.0, |sx| values[y * width + sx]))
+                    .sum();
+                rows[y * width + x as usize] = sum / n;
+            }
+        }
+        for y in 0..height as i32 {
+            for x in 0..width {
+                let sum: f32 = (y - before..=y + after)
+                    .map(|sy| border.resolve(sy as i64, height).map_or(0.0, |sy| rows[sy * width + x]))
+                    .sum();
+                values[y as usize * width + x] = sum / n;
+            }
+        }
+    }
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn descreen_dots() {
+        // 2x2 black dots every 4 pixels on white on the left, flat grey on the right
+        let (width, height) = (48, 32);
+        let mut image = ColorImage::new_w_h(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let color = if x >= 32 {
+                    Color::new(100, 100, 100)
+                } else if x % 4 < 2 && y % 4 < 2 {
+                    Color::new(0, 0, 0)
+                } else {
+                    Color::new(255, 255, 255)
+                };
+                image.set_pixel(x, y, &color);
+            }
+        }
+        let halftone = image.detect_halftone(0.3).unwrap();
+        assert_eq!(halftone.period, 4);
+
+        let clean = image.descreen(&DescreenConfig::default());
+        for y in 4..28 {
+            for x in 4..26 {
+                assert!((clean.get_pixel(x, y).r as i32 - 191).abs() <= 2, "{} {} {:?}", x, y, clean.get_pixel(x, y));
+            }
+            for x in 38..48 {
+                assert_eq!(clean.get_pixel(x, y), Color::new(100, 100, 100));
+            }
+        }
+
+        let mut flat = ColorImage::new_w_h(16, 16);
+        for y in 0..16 {
+            for x in 0..16 {
+                flat.set_pixel(x, y, &if x < 8 { Color::new(0, 0, 0) } else { Color::new(255, 255, 255) });
+            }
+        }
+        // the screen tiles seamlessly, so wrapped borders clean it up to the edges
+        let mut screen = ColorImage::new_w_h(32, 32);
+        for y in 0..32 {
+            for x in 0..32 {
+                screen.set_pixel(x, y, &image.get_pixel(x, y));
+            }
+        }
+        let wrapped = screen.descreen(&DescreenConfig { period: 4, border: BorderMode::Wrap, ..DescreenConfig::default() });
+        assert!((wrapped.get_pixel(0, 0).r as i32 - 191).abs() <= 2);
+
+        assert!(flat.detect_halftone(0.3).is_none());
+        assert_eq!(flat.descreen(&DescreenConfig { period: 4, ..DescreenConfig::default() }).pixels, flat.pixels);
+    }
+}
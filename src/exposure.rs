@@ -0,0 +1,131 @@
+use crate::{BorderMode, Color, ColorImage, Kernel, PlanarImage, collapse_pyramid, gaussian_pyramid, laplacian_pyramid, pyramid_levels};
+
+/// Parameters of `exposure_fusion`: the exponents of the quality measures weighting each pixel of each input
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct ExposureFusionConfig {
+    /// of the absolute Laplacian of the gray, favouring detail
+    pub contrast: f32,
+    /// of the standard deviation of the color channels, favouring vivid colors
+    pub saturation: f32,
+    /// of the closeness of each channel to mid gray, a Gaussian of `exposure_sigma` about 0.5 (channels in [0, 1])
+    pub exposedness: f32,
+    pub exposure_sigma: f32,
+}
+
+impl Default for ExposureFusionConfig {
+    fn default() -> Self {
+        Self {
+            contrast: 1.0,
+            saturation: 1.0,
+            exposedness: 1.0,
+            exposure_sigma: 0.2,
+        }
+    }
+}
+
+/// Exposure fusion (Mertens et al.) of aligned shots of the same size taken at different exposures:
+/// each pixel of each shot is weighted by its contrast, saturation and exposedness, and the shots blended
+/// by their Laplacian pyramids under the Gaussian pyramids of the normalized weights, so that the seams
+/// between where different shots prevail are not visible. The result is opaque.
+pub fn exposure_fusion(images: &[ColorImage], config: &ExposureFusionConfig) -> ColorImage {
+    assert!(!images.is_empty());
+    let (width, height) = (images[0].width, images[0].height);
+    assert!(images.iter().all(|image| image.width == width && image.height == height));
+
+    let shots: Vec<PlanarImage<f32, 3>> = images.iter()
+        .map(|image| PlanarImage::from_color_image(image).map(|[r, g, b, _]| [r as f32 / 255.0, g as f32 / 255.0, b as f32 / 255.0]))
+        .collect();
+    let laplacian = Kernel::laplacian();
+    let mut weights: Vec<PlanarImage<f32, 1>> = shots.iter()
+        .map(|shot| {
+            let gray = shot.map(|[r, g, b]| [(r + g + b) / 3.0]);
+            let contrast = gray.convolve(&laplacian, BorderMode::Reflect);
+            let mut weight = PlanarImage::new_w_h(width, height);
+            for y in 0..height {
+                for x in 0..width {
+                    let rgb = shot.get_pixel(x, y);
+                    let mean = rgb.iter().sum::<f32>() / 3.0;
+                    let saturation = (rgb.iter().map(|v| (v - mean) * (v - mean)).sum::<f32>() / 3.0).sqrt();
+                    let sigma = config.exposure_sigma;
+                    let exposedness = rgb.iter().map(|v| (-(v - 0.5) * (v - 0.5) / (2.0 * sigma * sigma)).exp()).product::<f32>();
+                    let w = contrast.get(x, y, 0).abs().powf(config.contrast) *
+                        saturation.powf(config.saturation) * exposedness.powf(config.exposedness);
+                    weight.set(x, y, 0, w + 1e-12);
+                }
+            }
+            weight
+        })
+        .collect();
+    for i in 0..width * height {
+        let total: f32 = weights.iter().map(|w| w.channel(0)[i]).sum();
+        weights.iter_mut().for_each(|w| w.channel_mut(0)[i] /= total);
+    }
+
+    let levels = pyramid_levels(width, height, 8);
+    let mut fused: Vec<PlanarImage<f32, 3>> = Vec::new();
+    for (shot, weight) in shots.iter().zip(weights.iter()) {
+        let bands = laplacian_pyramid(shot, levels);
+        let masks = gaussian_pyramid(weight, levels);
+        if fused.is_empty() {
+            fused = bands.iter().map(|b| PlanarImage::new_w_h(b.width(), b.height())).collect();
+        }
+        for ((out, band), mask) in fused.iter_mut().zip(bands.iter()).zip(masks.iter()) {
+            for c in 0..3 {
+                let (band, mask) = (band.channel(c), mask.channel(0));
+                for (i, v) in out.channel_mut(c).iter_mut().enumerate() {
+                    *v += band[i] * mask[i];
+                }
+            }
+        }
+    }
+    let fused = collapse_pyramid(&fused);
+    let mut image = ColorImage::new_w_h(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            let channel = |v: f32| (v * 255.0).round().clamp(0.0, 255.0) as u8;
+            let [r, g, b] = fused.get_pixel(x, y);
+            image.set_pixel(x, y, &Color::new(channel(r), channel(g), channel(b)));
+        }
+    }
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BoundingRect;
+
+    #[test]
+    fn fuse_two_exposures() {
+        // a checkered scene with a dark half and a bright half; each exposure clips one of them
+        let scene = |x: usize, y: usize| {
+            let base = if x < 16 { 0.1 } else { 0.8 };
+            base + if (x / 2 + y / 2) & 1 == 0 { 0.1 } else { 0.0 }
+        };
+        let shoot = |gain: f32| {
+            let mut image = ColorImage::new_w_h(32, 16);
+            for y in 0..16 {
+                for x in 0..32 {
+                    let v = ((scene(x, y) * gain).min(1.0) * 255.0).round() as u8;
+                    image.set_pixel(x, y, &Color::new(v, v / 2, v / 3));
+                }
+            }
+            image
+        };
+        let (dark, bright) = (shoot(1.0 / 1.6), shoot(4.0));
+        let fused = exposure_fusion(&[dark.clone(), bright.clone()], &ExposureFusionConfig::default());
+        assert_eq!((fused.width, fused.height), (32, 16));
+        let contrast = |image: &ColorImage, left: i32| {
+            let rect = BoundingRect::new_x_y_w_h(left, 4, 4, 4);
+            let values: Vec<i32> = rect.scanlines().flat_map(|(y, l, r)| (l..r).map(move |x| (x, y)))
+                .map(|(x, y)| image.get_pixel(x as usize, y as usize).r as i32).collect();
+            values.iter().max().unwrap() - values.iter().min().unwrap()
+        };
+        // the detail of the shadows comes from the bright shot, that of the highlights from the dark one
+        assert!(contrast(&fused, 4) > contrast(&dark, 4));
+        assert!(contrast(&fused, 24) > contrast(&bright, 24));
+
+        let single = exposure_fusion(std::slice::from_ref(&dark), &ExposureFusionConfig::default());
+        assert!(single.iter().zip(dark.iter()).all(|(a, b)| (a.r as i32 - b.r as i32).abs() <= 1));
+    }
+}
@@ -0,0 +1,65 @@
+use crate::{BorderMode, ColorImage, PlanarImage};
+
+impl ColorImage {
+    /// Luma of each pixel (Rec. 601 weights), in [0, 255]
+    pub fn luma(&self) -> PlanarImage<f32, 1> {
+        let mut luma = PlanarImage::new_w_h(self.width, self.height);
+        for (i, c) in self.iter().enumerate() {
+            luma.channel_mut(0)[i] = 0.299 * c.r as f32 + 0.587 * c.g as f32 + 0.114 * c.b as f32;
+        }
+        luma
+    }
+
+    /// Horizontal and vertical Sobel derivative of luma; borders are extended by `BorderMode::Replicate`
+    pub fn sobel(&self) -> PlanarImage<f32, 2> {
+        self.sobel_with_border(BorderMode::Replicate)
+    }
+
+    /// `sobel`, with pixels beyond the edges extended by `border` (zero where `Constant`)
+    pub fn sobel_with_border(&self, border: BorderMode) -> PlanarImage<f32, 2> {
+        let luma = self.luma();
+        let (w, h) = (self.width as i64, self.height as i64);
+        let at = |x: i64, y: i64| border.pixel(&luma, x, y).map_or(0.0, |[v]| v);
+        let mut gradient = PlanarImage::new_w_h(self.width, self.height);
+        for y in 0..h {
+            for x in 0..w {
+                let gx = at(x + 1, y - 1) + 2.0 * at(x + 1, y) + at(x + 1, y + 1)
+                    - at(x - 1, y - 1) - 2.0 * at(x - 1, y) - at(x - 1, y + 1);
+                let gy = at(x - 1, y + 1) + 2.0 * at(x, y + 1) + at(x + 1, y + 1)
+                    - at(x - 1, y - 1) - 2.0 * at(x, y - 1) - at(x + 1, y - 1);
+                gradient.set_pixel(x as usize, y as usize, [gx, gy]);
+            }
+        }
+        gradient
+    }
+
+    /// Magnitude of the Sobel gradient
+    pub fn gradient_magnitude(&self) -> PlanarImage<f32, 1> {
+        self.sobel().map(|[gx, gy]| [(gx * gx + gy * gy).sqrt()])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Color;
+
+    #[test]
+    fn sobel_vertical_edge() {
+        let mut image = ColorImage::new_w_h(4, 3);
+        for y in 0..3 {
+            for x in 2..4 {
+                image.set_pixel(x, y, &Color::new(255, 255, 255));
+            }
+        }
+        let gradient = image.sobel();
+        assert_eq!(gradient.get_pixel(0, 1), [0.0, 0.0]);
+        assert!(gradient.get(1, 1, 0) > 0.0);
+        assert!(gradient.get(2, 1, 0) > 0.0);
+        assert_eq!(gradient.get(2, 1, 1), 0.0);
+        assert_eq!(image.gradient_magnitude().get(3, 0, 0), 0.0);
+        // the white edge against the zero border
+        assert!(image.sobel_with_border(BorderMode::Constant).get(3, 1, 0) < 0.0);
+        assert!(image.sobel_with_border(BorderMode::Wrap).get(0, 1, 0) < 0.0);
+    }
+}
@@ -0,0 +1,186 @@
+use crate::{BorderMode, Color, ColorImage, Matrix, PlanarImage};
+
+/// How `ColorImage::estimate_illumination` models the background of a light page or whiteboard
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum IlluminationModel {
+    /// a morphological closing (a rolling ball, of a square footprint) of `radius` pixels,
+    /// which must be larger than the widest stroke; follows sharp shadows
+    Morphological { radius: usize },
+    /// a quadratic surface least squares fitted to the background, iteratively leaving out the ink; smooth gradients only
+    Quadratic,
+}
+
+impl Default for IlluminationModel {
+    fn default() -> Self {
+        Self::Morphological { radius: 32 }
+    }
+}
+
+/// Grid of pixels sampled in fitting `IlluminationModel::Quadratic`
+const QUADRATIC_SAMPLES: usize = 64;
+
+impl ColorImage {
+    /// The brightness of the light background under each pixel, per RGB channel, as if there were no ink
+    pub fn estimate_illumination(&self, model: IlluminationModel) -> PlanarImage<f32, 3> {
+        let mut background = PlanarImage::new_w_h(self.width, self.height);
+        for c in 0..3 {
+            let mut plane = PlanarImage::<f32, 1>::new_w_h(self.width, self.height);
+            for (i, p) in self.pixels.chunks_exact(4).enumerate() {
+                plane.channel_mut(0)[i] = p[c] as f32;
+            }
+            let estimate = match model {
+                IlluminationModel::Morphological { radius } => close_downscaled(&plane, radius),
+                IlluminationModel::Quadratic => fit_quadratic(&plane),
+            };
+            background.channel_mut(c).copy_from_slice(estimate.channel(0));
+        }
+        background
+    }
+
+    /// Evens out uneven illumination (e.g. shadows across a whiteboard photo) by dividing each pixel
+    /// by its `estimate_illumination`, so the background becomes uniformly white while ink keeps its contrast
+    pub fn flatten_illumination(&self, model: IlluminationModel) -> ColorImage {
+        let background = self.estimate_illumination(model);
+        let mut result = self.clone();
+        for (i, p) in result.pixels.chunks_exact_mut(4).enumerate() {
+            for (c, v) in p.iter_mut().take(3).enumerate() {
+                let b = background.channel(c)[i].max(1.0);
+                *v = (*v as f32 * 255.0 / b).round().clamp(0.0, 255.0) as u8;
+            }
+        }
+        result
+    }
+
+    /// `flatten_illumination` then the background, the uniformly white pixels, set to `color`
+    pub fn remove_background(&self, model: IlluminationModel, threshold: u8, color: Color) -> ColorImage {
+        let mut result = self.flatten_illumination(model);
+        for p in result.pixels.chunks_exact_mut(4) {
+            if p[0] >= threshold && p[1] >= threshold && p[2] >= threshold {
+                p.copy_from_slice(&[color.r, color.g, color.b, color.a]);
+            }
+        }
+        result
+    }
+}
+
+/// A closing of `radius` computed on a copy downscaled by block maxima (in effect, part of the dilation),
+/// then upscaled bilinearly, so that large radii stay cheap
+fn close_downscaled(plane: &PlanarImage<f32, 1>, radius: usize) -> PlanarImage<f32, 1> {
+    let (width, height) = (plane.width(), plane.height());
+    let factor = (radius / 8).max(1);
+    let (sw, sh) = (width.div_ceil(factor), height.div_ceil(factor));
+    let mut small = PlanarImage::<f32, 1>::new_w_h_with_value(sw, sh, f32::MIN);
+    for y in 0..height {
+        for x in 0..width {
+            let (bx, by) = (x / factor, y / factor);
+            small.set(bx, by, 0, small.get(bx, by, 0).max(plane.get(x, y, 0)));
+        }
+    }
+    let closed = small.close(radius.div_ceil(factor));
+    let mut result = PlanarImage::new_w_h(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            // the center of the pixel in the coordinates of the small pixel centers
+            let u = (x as f64 + 0.5) / factor as f64 - 0.5;
+            let v = (y as f64 + 0.5) / factor as f64 - 0.5;
+            let (x0, y0) = (u.floor(), v.floor());
+            let (fx, fy) = ((u - x0) as f32, (v - y0) as f32);
+            let at = |x: f64, y: f64| BorderMode::Replicate.pixel(&closed, x as i64, y as i64).unwrap()[0];
+            let top = at(x0, y0) * (1.0 - fx) + at(x0 + 1.0, y0) * fx;
+            let bottom = at(x0, y0 + 1.0) * (1.0 - fx) + at(x0 + 1.0, y0 + 1.0) * fx;
+            result.set(x, y, 0, top * (1.0 - fy) + bottom * fy);
+        }
+    }
+    result
+}
+
+/// Terms of the quadratic surface at (x, y) normalized to [-1, 1]
+fn quadratic_terms(x: f64, y: f64) -> [f64; 6] {
+    [1.0, x, y, x * x, x * y, y * y]
+}
+
+fn fit_quadratic(plane: &PlanarImage<f32, 1>) -> PlanarImage<f32, 1> {
+    let (width, height) = (plane.width(), plane.height());
+    let normalized = |x: usize, y: usize| {
+        (2.0 * (x as f64 + 0.5) / width as f64 - 1.0, 2.0 * (y as f64 + 0.5) / height as f64 - 1.0)
+    };
+    let mut samples = Vec::new();
+    for j in 0..QUADRATIC_SAMPLES.min(height) {
+        for i in 0..QUADRATIC_SAMPLES.min(width) {
+            let (x, y) = (i * width / QUADRATIC_SAMPLES.min(width), j * height / QUADRATIC_SAMPLES.min(height));
+            let (u, v) = normalized(x, y);
+            samples.push((quadratic_terms(u, v), plane.get(x, y, 0) as f64));
+        }
+    }
+
+    let mut coefficients = [0.0; 6];
+    let mut background = vec![true; samples.len()];
+    for round in 0..4 {
+        let mut ata = Matrix::<6, 6>::default();
+        let mut atb = [0.0; 6];
+        for ((terms, value), _) in samples.iter().zip(background.iter()).filter(|(_, &b)| b) {
+            for r in 0..6 {
+                for c in 0..6 {
+                    ata.m[r][c] += terms[r] * terms[c];
+                }
+                atb[r] += terms[r] * value;
+            }
+        }
+        match ata.inv() {
+            Some(inverse) => coefficients = inverse.dot_mv(&atb),
+            None => break,
+        }
+        if round == 3 {
+            break;
+        }
+        // ink is darker than the background; leave out samples well below the surface
+        let residuals: Vec<f64> = samples.iter()
+            .map(|(terms, value)| value - Matrix::<6, 6>::dot_vv(terms, &coefficients))
+            .collect();
+        let spread = (residuals.iter().map(|r| r * r).sum::<f64>() / residuals.len() as f64).sqrt();
+        for (b, r) in background.iter_mut().zip(residuals.iter()) {
+            *b = *r > -spread;
+        }
+    }
+
+    let mut result = PlanarImage::new_w_h(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            let (u, v) = normalized(x, y);
+            result.set(x, y, 0, Matrix::<6, 6>::dot_vv(&quadratic_terms(u, v), &coefficients) as f32);
+        }
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BoundingRect;
+
+    #[test]
+    fn flatten_shadowed_page() {
+        // white paper shaded from 255 down to 135 left to right, with dark strokes
+        let mut page = ColorImage::new_w_h(120, 60);
+        for y in 0..60 {
+            for x in 0..120 {
+                let v = 255 - x as u8;
+                page.set_pixel(x, y, &Color::new(v, v, v));
+            }
+        }
+        for i in 0..5 {
+            page.fill_rect(&BoundingRect::new_x_y_w_h(10 + i * 22, 10, 3, 40), &Color::new(20, 20, 20));
+        }
+        for model in [IlluminationModel::Morphological { radius: 8 }, IlluminationModel::Quadratic] {
+            let flat = page.flatten_illumination(model);
+            // the paper is white throughout (but for the closing rounding off the edges), the strokes stay dark
+            for x in [0, 50, 100, 119] {
+                assert!(flat.get_pixel(x, 5).r >= 235, "{:?} {} {:?}", model, x, flat.get_pixel(x, 5));
+            }
+            assert!(flat.get_pixel(11, 30).r < 40 && flat.get_pixel(99, 30).r < 40);
+        }
+        let clean = page.remove_background(IlluminationModel::Morphological { radius: 16 }, 240, Color::new_rgba(0, 0, 0, 0));
+        assert_eq!(clean.get_pixel(90, 5).a, 0);
+        assert_eq!(clean.get_pixel(11, 30).a, 255);
+    }
+}
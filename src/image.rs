@@ -3,7 +3,7 @@ use std::fmt::Write;
 
 pub use bit_vec::BitVec;
 
-use crate::{BoundingRect, Color, ColorName, ColorType, Field, PointF32, PointF64, PointI32};
+use crate::{BoundingRect, Color, ColorName, ColorType, Field, PointF32, PointF64, PointI32, linear_to_srgb, srgb_to_linear};
 
 /// Image with 1 bit per pixel
 #[derive(Debug, Clone, Default)]
@@ -23,6 +23,8 @@ pub struct ScalerField<T> {
 pub type MonoImageItem = u16;
 /// Image with grayscale values
 pub type MonoImage = ScalerField<MonoImageItem>;
+/// Image with 8 bit grayscale values
+pub type GrayImage = ScalerField<u8>;
 
 /// Image with 4 bytes per pixel
 #[derive(Clone, Default)]
@@ -39,10 +41,17 @@ pub struct ColorImageIter<'a> {
     stop: usize,
 }
 
+/// Number of pixels of a `width` x `height` image; panics (instead of wrapping around) if it does not fit in memory
+pub(crate) fn checked_area(width: usize, height: usize) -> usize {
+    width.checked_mul(height)
+        .filter(|&n| n < isize::MAX as usize / 4)
+        .unwrap_or_else(|| panic!("{}x{} image is too large", width, height))
+}
+
 impl BinaryImage {
     pub fn new_w_h(width: usize, height: usize) -> BinaryImage {
         BinaryImage {
-            pixels: BitVec::from_elem(width * height, false),
+            pixels: BitVec::from_elem(checked_area(width, height), false),
             width,
             height,
         }
@@ -62,13 +71,22 @@ impl BinaryImage {
     }
 
     pub fn get_pixel_safe(&self, x: i32, y: i32) -> bool {
-        if  x >= 0 && x < self.width as i32 &&
-            y >= 0 && y < self.height as i32 {
+        if  x >= 0 && (x as usize) < self.width &&
+            y >= 0 && (y as usize) < self.height {
             return self.get_pixel(x as usize, y as usize);
         }
         false
     }
 
+    /// The pixel at (x, y), or None if outside the image
+    pub fn get_pixel_checked(&self, x: usize, y: usize) -> Option<bool> {
+        if x < self.width && y < self.height {
+            self.pixels.get(y * self.width + x)
+        } else {
+            None
+        }
+    }
+
     pub fn set_pixel(&mut self, x: usize, y: usize, v: bool) {
         let i = y * self.width + x;
         self.pixels.set(i, v);
@@ -83,8 +101,8 @@ impl BinaryImage {
     }
 
     pub fn set_pixel_safe(&mut self, x: i32, y: i32, v: bool) -> bool {
-        if  x >= 0 && x < self.width as i32 &&
-            y >= 0 && y < self.height as i32 {
+        if  x >= 0 && (x as usize) < self.width &&
+            y >= 0 && (y as usize) < self.height {
             self.set_pixel(x as usize, y as usize, v);
             return true;
         }
@@ -95,6 +113,16 @@ impl BinaryImage {
         self.set_pixel_safe(p.x, p.y, v);
     }
 
+    /// Sets the pixel at (x, y); returns false (and does nothing) if outside the image
+    pub fn set_pixel_checked(&mut self, x: usize, y: usize, v: bool) -> bool {
+        if x < self.width && y < self.height {
+            self.set_pixel(x, y, v);
+            true
+        } else {
+            false
+        }
+    }
+
     pub fn bounding_rect(&self) -> BoundingRect {
         let mut rect = BoundingRect::default();
         for y in 0..self.height {
@@ -239,6 +267,16 @@ impl<T> ScalerField<T> where T: Default {
     }
 }
 
+impl<T> ScalerField<T> {
+    pub fn width(&self) -> usize {
+        self.field.width()
+    }
+
+    pub fn height(&self) -> usize {
+        self.field.height()
+    }
+}
+
 impl<T> ScalerField<T> where T: Clone {
     pub fn get_pixel(&self, x: usize, y: usize) -> T {
         self.field.get(self.field.index_at(x, y)).unwrap()
@@ -247,6 +285,15 @@ impl<T> ScalerField<T> where T: Clone {
     pub fn set_pixel(&mut self, x: usize, y: usize, v: T) {
         self.field.replace(self.field.index_at(x, y), v);
     }
+
+    /// The pixel at (x, y), or None if outside the image
+    pub fn get_pixel_checked(&self, x: usize, y: usize) -> Option<T> {
+        if x < self.width() && y < self.height() {
+            self.field.get(self.field.index_at(x, y))
+        } else {
+            None
+        }
+    }
 }
 
 impl Iterator for ColorImageIter<'_> {
@@ -270,7 +317,7 @@ impl ColorImage {
 
     pub fn new_w_h(width: usize, height: usize) -> Self {
         Self {
-            pixels: vec![0; width * height * 4],
+            pixels: vec![0; checked_area(width, height) * 4],
             width,
             height,
         }
@@ -294,13 +341,22 @@ impl ColorImage {
     }
 
     pub fn get_pixel_safe(&self, x: i32, y: i32) -> Option<Color> {
-        if  x >= 0 && x < self.width as i32 &&
-            y >= 0 && y < self.height as i32 {
+        if  x >= 0 && (x as usize) < self.width &&
+            y >= 0 && (y as usize) < self.height {
             return Some(self.get_pixel(x as usize, y as usize));
         }
         None
     }
 
+    /// The pixel at (x, y), or None if outside the image (or beyond the pixel buffer, if it is too short)
+    pub fn get_pixel_checked(&self, x: usize, y: usize) -> Option<Color> {
+        if x >= self.width || y >= self.height {
+            return None;
+        }
+        let index = (y * self.width + x) * 4;
+        self.pixels.get(index..index + 4).map(|p| Color::new_rgba(p[0], p[1], p[2], p[3]))
+    }
+
     pub fn get_pixel_at(&self, index: usize) -> Color {
         let index = index * 4;
         let r = self.pixels[index];
@@ -316,6 +372,15 @@ impl ColorImage {
         self.set_pixel_at(index, color);
     }
 
+    /// Sets the pixel at (x, y); returns false (and does nothing) if outside the image or the pixel buffer
+    pub fn set_pixel_checked(&mut self, x: usize, y: usize, color: &Color) -> bool {
+        if x >= self.width || y >= self.height || (y * self.width + x + 1) * 4 > self.pixels.len() {
+            return false;
+        }
+        self.set_pixel(x, y, color);
+        true
+    }
+
     pub fn set_pixel_at(&mut self, index: usize, color: &Color) {
         let index = index * 4;
         self.pixels[index] = color.r;
@@ -335,6 +400,39 @@ impl ColorImage {
         image
     }
 
+    /// Shrinks the image by an integer `factor`, averaging each `factor` x `factor` block in linear light.
+    /// Blocks at the right and bottom edges may be partial.
+    pub fn downscale_linear(&self, factor: usize) -> ColorImage {
+        assert!(factor > 0);
+        let width = self.width.div_ceil(factor);
+        let height = self.height.div_ceil(factor);
+        let mut image = ColorImage::new_w_h(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let mut sum = [0.0; 4];
+                let mut count = 0;
+                for yy in y * factor..std::cmp::min((y + 1) * factor, self.height) {
+                    for xx in x * factor..std::cmp::min((x + 1) * factor, self.width) {
+                        let c = self.get_pixel(xx, yy);
+                        sum[0] += srgb_to_linear(c.r);
+                        sum[1] += srgb_to_linear(c.g);
+                        sum[2] += srgb_to_linear(c.b);
+                        sum[3] += c.a as f64;
+                        count += 1;
+                    }
+                }
+                let n = count as f64;
+                image.set_pixel(x, y, &Color::new_rgba(
+                    linear_to_srgb(sum[0] / n),
+                    linear_to_srgb(sum[1] / n),
+                    linear_to_srgb(sum[2] / n),
+                    (sum[3] / n).round() as u8,
+                ));
+            }
+        }
+        image
+    }
+
     pub fn sample_pixel_at(&self, p: PointF32) -> Color {
         bilinear_interpolate(self, p)
     }
@@ -402,6 +500,50 @@ mod tests {
         assert_eq!(crop.get_pixel(1, 1), true);
     }
 
+    #[test]
+    fn checked_access_and_degenerate_sizes() {
+        let mut color = ColorImage::new_w_h(3, 2);
+        assert!(color.set_pixel_checked(2, 1, &Color::new(1, 2, 3)));
+        assert!(!color.set_pixel_checked(3, 0, &Color::new(1, 2, 3)));
+        assert_eq!(color.get_pixel_checked(2, 1), Some(Color::new(1, 2, 3)));
+        assert_eq!(color.get_pixel_checked(0, 2), None);
+        assert_eq!(color.get_pixel_safe(i32::MAX, 0), None);
+        color.pixels.truncate(8);
+        assert_eq!(color.get_pixel_checked(2, 0), None);
+
+        let mut binary = BinaryImage::new_w_h(2, 2);
+        assert!(binary.set_pixel_checked(1, 1, true));
+        assert_eq!(binary.get_pixel_checked(1, 1), Some(true));
+        assert_eq!(binary.get_pixel_checked(2, 0), None);
+        let mono = MonoImage::new_w_h(2, 1);
+        assert_eq!(mono.get_pixel_checked(1, 0), Some(0));
+        assert_eq!(mono.get_pixel_checked(0, 1), None);
+
+        // empty images pass through without indexing out of bounds
+        for (w, h) in [(0, 0), (0, 3), (3, 0)] {
+            let empty = ColorImage::new_w_h(w, h);
+            assert_eq!(empty.saliency().width(), w);
+            crate::suggest_crop(&empty, 1.0);
+            let clusters = crate::color_clusters::Runner::new(Default::default(), empty).run();
+            assert_eq!(clusters.output_len(), 0);
+            let mask = BinaryImage::new_w_h(w, h);
+            assert_eq!(mask.to_clusters(false).len(), 0);
+            assert_eq!(mask.perimeter(crate::Perimeter::Marching), 0.0);
+            assert_eq!(mask.bounding_rect(), BoundingRect::default());
+            let gray = crate::PlanarImage::<f32, 1>::new_w_h(w, h);
+            assert!(crate::Spectrum::new(&gray).data.iter().all(|&c| c == crate::Complex::default()));
+            assert_eq!(gray.low_pass(0.1).width(), w);
+            assert_eq!(crate::phase_correlate(&gray, &gray).response, 0.0);
+            assert_eq!(crate::align_similarity(&gray, &gray), crate::SimilarityTransform::default());
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "too large")]
+    fn huge_image_panics() {
+        ColorImage::new_w_h(usize::MAX / 2, 3);
+    }
+
     #[test]
     fn image_as_string() {
         let mut image = BinaryImage::new_w_h(2,2);
@@ -420,6 +562,21 @@ mod tests {
         }
     }
 
+    #[test]
+    fn downscale_linear_test() {
+        let mut image = ColorImage::new_w_h(3, 2);
+        for x in 0..3 {
+            image.set_pixel(x, 0, &Color::new(0, 0, 0));
+            image.set_pixel(x, 1, &Color::new(255, 255, 255));
+        }
+        let small = image.downscale_linear(2);
+        assert_eq!(small.width, 2);
+        assert_eq!(small.height, 1);
+        // half black, half white in linear light is brighter than 128 in sRGB
+        assert_eq!(small.get_pixel(0, 0), Color::new(188, 188, 188));
+        assert_eq!(small.get_pixel(1, 0), Color::new(188, 188, 188));
+    }
+
     #[test]
     fn rotate_test() {
         assert_eq!(
@@ -0,0 +1,260 @@
+use crate::{BinaryImage, BoundingRect, Color, ColorImage, ScalerField};
+
+/// Read access to a 2D array of pixels, regardless of how the pixels are stored
+pub trait Image {
+    type Pixel: Copy;
+
+    fn width(&self) -> usize;
+
+    fn height(&self) -> usize;
+
+    /// Returns the pixel at (x, y). Panics if out of bounds.
+    fn pixel(&self, x: usize, y: usize) -> Self::Pixel;
+
+    /// Returns the pixel at (x, y), or `None` if out of bounds.
+    fn pixel_safe(&self, x: i32, y: i32) -> Option<Self::Pixel> {
+        if  x >= 0 && (x as usize) < self.width() &&
+            y >= 0 && (y as usize) < self.height() {
+            Some(self.pixel(x as usize, y as usize))
+        } else {
+            None
+        }
+    }
+
+    /// Iterates over the pixels of scanline `y`
+    fn row(&self, y: usize) -> ImageRow<'_, Self> where Self: Sized {
+        assert!(y < self.height());
+        ImageRow {
+            image: self,
+            x: 0,
+            y,
+        }
+    }
+
+    /// A window onto the region `rect` of this image; `rect` is clipped to the image
+    fn view(&self, rect: BoundingRect) -> ImageView<'_, Self> where Self: Sized {
+        let mut rect = rect;
+        rect.clip(BoundingRect::new_x_y_w_h(0, 0, self.width() as i32, self.height() as i32));
+        if rect.width() < 0 || rect.height() < 0 {
+            rect = BoundingRect::default();
+        }
+        ImageView {
+            image: self,
+            rect,
+        }
+    }
+}
+
+/// Write access to a 2D array of pixels
+pub trait ImageMut: Image {
+    /// Constructs an image of `width` by `height` filled with the default pixel
+    fn new_image(width: usize, height: usize) -> Self;
+
+    /// Sets the pixel at (x, y). Panics if out of bounds.
+    fn put_pixel(&mut self, x: usize, y: usize, v: Self::Pixel);
+
+    /// Sets the pixel at (x, y), or does nothing if out of bounds.
+    fn put_pixel_safe(&mut self, x: i32, y: i32, v: Self::Pixel) {
+        if  x >= 0 && (x as usize) < self.width() &&
+            y >= 0 && (y as usize) < self.height() {
+            self.put_pixel(x as usize, y as usize, v);
+        }
+    }
+}
+
+/// A pixel with a single numeric value, e.g. of a grayscale image or a distance field
+pub trait ScalarPixel: Copy {
+    fn scalar(self) -> f64;
+}
+
+macro_rules! scalar_pixel {
+    ($($t:ty),*) => {
+        $(impl ScalarPixel for $t {
+            fn scalar(self) -> f64 {
+                self as f64
+            }
+        })*
+    };
+}
+
+scalar_pixel!(u8, u16, u32, i32, usize, f32, f64);
+
+impl<T: ScalarPixel> ScalarPixel for [T; 1] {
+    fn scalar(self) -> f64 {
+        self[0].scalar()
+    }
+}
+
+/// Iterate over the pixels of a scanline of an `Image`
+pub struct ImageRow<'a, I: Image> {
+    image: &'a I,
+    x: usize,
+    y: usize,
+}
+
+/// A rectangular window onto an `Image`; (0, 0) of the view is the left-top of `rect`
+pub struct ImageView<'a, I: Image> {
+    image: &'a I,
+    rect: BoundingRect,
+}
+
+impl<I: Image> Iterator for ImageRow<'_, I> {
+    type Item = I::Pixel;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.x < self.image.width() {
+            let p = self.image.pixel(self.x, self.y);
+            self.x += 1;
+            Some(p)
+        } else {
+            None
+        }
+    }
+}
+
+impl<I: Image> ImageView<'_, I> {
+    /// The region of the underlying image being viewed
+    pub fn rect(&self) -> BoundingRect {
+        self.rect
+    }
+
+    /// Copies the viewed region into a new image
+    pub fn to_image<O>(&self) -> O where O: ImageMut<Pixel = I::Pixel> {
+        let mut image = O::new_image(self.width(), self.height());
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                image.put_pixel(x, y, self.pixel(x, y));
+            }
+        }
+        image
+    }
+}
+
+impl<I: Image> Image for ImageView<'_, I> {
+    type Pixel = I::Pixel;
+
+    fn width(&self) -> usize {
+        self.rect.width() as usize
+    }
+
+    fn height(&self) -> usize {
+        self.rect.height() as usize
+    }
+
+    fn pixel(&self, x: usize, y: usize) -> Self::Pixel {
+        assert!(x < self.width() && y < self.height());
+        self.image.pixel(x + self.rect.left as usize, y + self.rect.top as usize)
+    }
+}
+
+impl Image for BinaryImage {
+    type Pixel = bool;
+
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn pixel(&self, x: usize, y: usize) -> bool {
+        self.get_pixel(x, y)
+    }
+}
+
+impl ImageMut for BinaryImage {
+    fn new_image(width: usize, height: usize) -> Self {
+        Self::new_w_h(width, height)
+    }
+
+    fn put_pixel(&mut self, x: usize, y: usize, v: bool) {
+        self.set_pixel(x, y, v);
+    }
+}
+
+impl Image for ColorImage {
+    type Pixel = Color;
+
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn pixel(&self, x: usize, y: usize) -> Color {
+        self.get_pixel(x, y)
+    }
+}
+
+impl ImageMut for ColorImage {
+    fn new_image(width: usize, height: usize) -> Self {
+        Self::new_w_h(width, height)
+    }
+
+    fn put_pixel(&mut self, x: usize, y: usize, v: Color) {
+        self.set_pixel(x, y, &v);
+    }
+}
+
+impl<T> Image for ScalerField<T> where T: Copy {
+    type Pixel = T;
+
+    fn width(&self) -> usize {
+        self.width()
+    }
+
+    fn height(&self) -> usize {
+        self.height()
+    }
+
+    fn pixel(&self, x: usize, y: usize) -> T {
+        self.get_pixel(x, y)
+    }
+}
+
+impl<T> ImageMut for ScalerField<T> where T: Copy + Default {
+    fn new_image(width: usize, height: usize) -> Self {
+        Self::new_w_h(width, height)
+    }
+
+    fn put_pixel(&mut self, x: usize, y: usize, v: T) {
+        self.set_pixel(x, y, v);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::GrayImage;
+
+    fn count<I: Image<Pixel = bool>>(image: &I) -> usize {
+        (0..image.height()).map(|y| image.row(y).filter(|&p| p).count()).sum()
+    }
+
+    #[test]
+    fn generic_row_and_view() {
+        let image = BinaryImage::from_string(&(
+            "*--\n".to_owned() +
+            "-**\n" +
+            "-*-\n"
+        ));
+        assert_eq!(count(&image), 4);
+        let view = image.view(BoundingRect::new_x_y_w_h(1, 1, 5, 5));
+        assert_eq!(view.width(), 2);
+        assert_eq!(view.height(), 2);
+        assert_eq!(count(&view), 3);
+        assert_eq!(view.to_image::<BinaryImage>().to_string(), "**\n*-\n");
+        assert_eq!(view.pixel_safe(2, 0), None);
+    }
+
+    #[test]
+    fn generic_scaler_field() {
+        let mut image = GrayImage::new_image(2, 2);
+        image.put_pixel(1, 0, 7);
+        assert_eq!(image.row(0).collect::<Vec<_>>(), vec![0, 7]);
+        assert_eq!(Image::width(&image), 2);
+    }
+}
@@ -0,0 +1,91 @@
+use crate::{BinaryImage, Color, ColorImage, distance_to_mask};
+
+impl ColorImage {
+    /// Fills the pixels set in `mask` (the size of this image) from their surroundings, in the manner of
+    /// Telea's fast marching method: the hole is filled from its edge inwards, in order of distance
+    /// from the known pixels, each pixel the weighted mean of the known or already filled pixels within
+    /// `radius`. Nearer pixels, and those about as far from the edge (along the same level), weigh more.
+    /// Suits holes a few times `radius` across, e.g. a small object removed before tracing the background.
+    pub fn inpaint(&self, mask: &BinaryImage, radius: usize) -> ColorImage {
+        assert!(mask.width == self.width && mask.height == self.height);
+        assert!(radius > 0);
+        let (width, height) = (self.width, self.height);
+        let mut filled = self.clone();
+        let mut known = mask.negative();
+        if known.pixels.none() {
+            return filled;
+        }
+        let distance = distance_to_mask(&known, f64::MAX);
+        let distance = distance.channel(0);
+
+        let mut order: Vec<usize> = (0..width * height).filter(|&i| mask.pixels[i]).collect();
+        order.sort_by(|&a, &b| distance[a].total_cmp(&distance[b]));
+        let r = radius as i32;
+        for i in order {
+            let (x, y) = ((i % width) as i32, (i / width) as i32);
+            let mut sum = [0.0f64; 4];
+            let mut weights = 0.0;
+            // unweighted, over the whole window, for when no known pixel is within `radius`
+            let (mut window_sum, mut window_count) = ([0.0f64; 4], 0);
+            for ny in (y - r).max(0)..(y + r + 1).min(height as i32) {
+                for nx in (x - r).max(0)..(x + r + 1).min(width as i32) {
+                    let j = ny as usize * width + nx as usize;
+                    if !known.pixels[j] {
+                        continue;
+                    }
+                    let c = filled.get_pixel(nx as usize, ny as usize);
+                    for (s, v) in window_sum.iter_mut().zip([c.r, c.g, c.b, c.a]) {
+                        *s += v as f64;
+                    }
+                    window_count += 1;
+                    let d2 = ((nx - x) * (nx - x) + (ny - y) * (ny - y)) as f64;
+                    if d2 > (r * r) as f64 {
+                        continue;
+                    }
+                    let level = 1.0 / (1.0 + (distance[j] - distance[i]).abs() as f64);
+                    let weight = level / d2;
+                    for (s, v) in sum.iter_mut().zip([c.r, c.g, c.b, c.a]) {
+                        *s += weight * v as f64;
+                    }
+                    weights += weight;
+                }
+            }
+            if weights == 0.0 {
+                // e.g. with `radius` 1, a pixel whose only known neighbours are diagonal
+                (sum, weights) = (window_sum, window_count as f64);
+            }
+            if weights > 0.0 {
+                let channel = |s: f64| (s / weights).round() as u8;
+                filled.set_pixel(x as usize, y as usize, &Color::new_rgba(channel(sum[0]), channel(sum[1]), channel(sum[2]), channel(sum[3])));
+            }
+            known.pixels.set(i, true);
+        }
+        filled
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BoundingRect;
+
+    #[test]
+    fn inpaint_removed_square() {
+        // a square removed from a background half red, half blue
+        let mut image = ColorImage::new_w_h(40, 20);
+        image.fill_rect(&BoundingRect::new_x_y_w_h(0, 0, 20, 20), &Color::new(200, 0, 0));
+        image.fill_rect(&BoundingRect::new_x_y_w_h(20, 0, 20, 20), &Color::new(0, 0, 200));
+        let mut mask = BinaryImage::new_w_h(40, 20);
+        mask.fill_rect(&BoundingRect::new_x_y_w_h(4, 6, 8, 8), true);
+        mask.fill_rect(&BoundingRect::new_x_y_w_h(16, 6, 8, 8), true);
+        image.fill_rect(&BoundingRect::new_x_y_w_h(4, 6, 8, 8), &Color::new(0, 0, 0));
+        image.fill_rect(&BoundingRect::new_x_y_w_h(16, 6, 8, 8), &Color::new(0, 0, 0));
+
+        let filled = image.inpaint(&mask, 3);
+        assert_eq!(filled.get_pixel(8, 10), Color::new(200, 0, 0));
+        // across the edge between the colors, each side is mostly filled from its own
+        assert!(filled.get_pixel(16, 10).r > 150);
+        assert!(filled.get_pixel(23, 10).b > 150);
+        assert_eq!(filled.get_pixel(0, 0), image.get_pixel(0, 0));
+    }
+}
@@ -0,0 +1,57 @@
+use std::collections::HashMap;
+use crate::{Color, ColorImage};
+
+/// Fraction of a turn between consecutive hues; the golden ratio spreads any number of hues evenly
+const GOLDEN_TURN: f64 = 0.618_033_988_749_895;
+
+/// The `i`-th of a sequence of perceptually distinct colors: hues step around the Oklch wheel
+/// by the golden ratio, with lightness alternating between two levels.
+/// `seed` rotates the whole sequence.
+pub fn distinct_color(i: usize, seed: u32) -> Color {
+    let start = (seed as f64 * 0.381_966_011_250_105).fract();
+    let hue = (start + i as f64 * GOLDEN_TURN).fract() * std::f64::consts::TAU;
+    let (lightness, chroma) = if i & 1 == 0 { (0.75, 0.12) } else { (0.6, 0.14) };
+    let rgb = oklab::oklab_to_srgb(oklab::Oklab {
+        l: lightness,
+        a: (chroma * hue.cos()) as f32,
+        b: (chroma * hue.sin()) as f32,
+    });
+    Color::new(rgb.r, rgb.g, rgb.b)
+}
+
+/// Renders a label map (row major, e.g. `Clusters::label_map`) with a `distinct_color` per label,
+/// assigned in order of first appearance so that any labelling gets the most distinct colors
+pub fn colorize_labels(width: usize, height: usize, labels: &[u32], seed: u32) -> ColorImage {
+    assert_eq!(labels.len(), width * height);
+    let mut image = ColorImage::new_w_h(width, height);
+    let mut colors = HashMap::new();
+    for (i, label) in labels.iter().enumerate() {
+        let next = colors.len();
+        let color = *colors.entry(label).or_insert_with(|| distinct_color(next, seed));
+        image.set_pixel_at(i, &color);
+    }
+    image
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn colorize_distinct() {
+        let labels = [7, 7, 3, 3, 100, 7];
+        let image = colorize_labels(3, 2, &labels, 0);
+        assert_eq!(image.get_pixel(0, 0), image.get_pixel(2, 1));
+        assert_eq!(image.get_pixel(0, 0), distinct_color(0, 0));
+        assert_eq!(image.get_pixel(1, 1), distinct_color(2, 0));
+
+        let colors: Vec<Color> = (0..16).map(|i| distinct_color(i, 5)).collect();
+        for (i, a) in colors.iter().enumerate() {
+            for b in colors[i + 1..].iter() {
+                let diff = (a.r as i32 - b.r as i32).abs() + (a.g as i32 - b.g as i32).abs() + (a.b as i32 - b.b as i32).abs();
+                assert!(diff > 20, "{:?} {:?}", a, b);
+            }
+        }
+        assert_ne!(distinct_color(0, 0), distinct_color(0, 1));
+    }
+}
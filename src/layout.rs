@@ -0,0 +1,141 @@
+use crate::{BinaryImage, BoundingRect, ColorImage, Components};
+
+/// Parameters of `analyze_layout`, in pixels; the defaults suit a page scanned at about 150 dpi
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct LayoutConfig {
+    /// pixels of a luma below this are ink
+    pub ink_threshold: f32,
+    /// gaps of the run-length smearing merging glyphs into lines, then lines into blocks;
+    /// see `BinaryImage::smear_horizontal`
+    pub horizontal_gap: usize,
+    pub vertical_gap: usize,
+    /// an ink component at least this long and at most `max_separator_thickness` thick is a rule
+    pub min_separator_length: i32,
+    pub max_separator_thickness: i32,
+    /// a block with an ink component taller than this is not text
+    pub max_text_height: i32,
+    /// a block with more than this fraction of mid-tone pixels (neither paper nor ink) is an image
+    pub max_text_midtones: f64,
+}
+
+impl Default for LayoutConfig {
+    fn default() -> Self {
+        Self {
+            ink_threshold: 128.0,
+            horizontal_gap: 20,
+            vertical_gap: 10,
+            min_separator_length: 50,
+            max_separator_thickness: 4,
+            max_text_height: 40,
+            max_text_midtones: 0.25,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum ZoneKind {
+    Text,
+    /// a photo or a drawing
+    Image,
+    /// a horizontal or vertical rule
+    Separator,
+}
+
+/// A region of a page, see `analyze_layout`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Zone {
+    pub kind: ZoneKind,
+    pub rect: BoundingRect,
+}
+
+/// Segments a scanned page into zones of text, images and separators, from top to bottom, then left to right.
+///
+/// Long thin ink components are taken as separators; the rest of the ink is merged into blocks by
+/// run-length smearing. A block is text unless it has a component taller than a line of text,
+/// or too many mid-tone pixels for black print on paper.
+pub fn analyze_layout(image: &ColorImage, config: &LayoutConfig) -> Vec<Zone> {
+    let luma = image.luma();
+    let (width, height) = (image.width, image.height);
+    let mut ink = BinaryImage::new_w_h(width, height);
+    for (i, &v) in luma.channel(0).iter().enumerate() {
+        ink.set_pixel_index(i, v < config.ink_threshold);
+    }
+
+    let components = Components::new(&ink, true);
+    let is_separator = |r: BoundingRect| {
+        let (long, thick) = (r.width().max(r.height()), r.width().min(r.height()));
+        long >= config.min_separator_length && thick <= config.max_separator_thickness
+    };
+    let separators = components.filter().custom(move |c| is_separator(c.rect)).indices();
+    let mut zones: Vec<Zone> = separators.iter()
+        .map(|&i| Zone { kind: ZoneKind::Separator, rect: components.clusters.get_cluster(i).rect })
+        .collect();
+    let content = components.filter().custom(move |c| !is_separator(c.rect));
+    let glyphs: Vec<BoundingRect> = content.indices().iter().map(|&i| components.clusters.get_cluster(i).rect).collect();
+
+    let blocks = content.to_binary_image()
+        .smear_horizontal(config.horizontal_gap)
+        .smear_vertical(config.vertical_gap)
+        .to_clusters(true);
+    for block in blocks.iter() {
+        let rect = block.rect;
+        let inside = |r: &&BoundingRect| {
+            let c = r.center();
+            c.x >= rect.left && c.x < rect.right && c.y >= rect.top && c.y < rect.bottom
+        };
+        let tallest = glyphs.iter().filter(inside).map(|r| r.height()).max().unwrap_or(0);
+        let mut midtones = 0;
+        for (y, left, right) in rect.scanlines() {
+            for x in left..right {
+                let v = luma.get(x as usize, y as usize, 0);
+                // halfway between ink and white paper
+                if v >= config.ink_threshold && v < (config.ink_threshold + 255.0) / 2.0 {
+                    midtones += 1;
+                }
+            }
+        }
+        let area = (rect.width() * rect.height()) as f64;
+        let kind = if tallest > config.max_text_height || midtones as f64 > config.max_text_midtones * area {
+            ZoneKind::Image
+        } else {
+            ZoneKind::Text
+        };
+        zones.push(Zone { kind, rect });
+    }
+    zones.sort_by_key(|z| (z.rect.top, z.rect.left));
+    zones
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Color;
+
+    #[test]
+    fn page_layout() {
+        let white = Color::new(255, 255, 255);
+        let mut page = ColorImage::new_w_h(200, 160);
+        page.fill_rect(&BoundingRect::new_x_y_w_h(0, 0, 200, 160), &white);
+        // five lines of glyphs
+        for line in 0..5 {
+            for glyph in 0..18 {
+                let rect = BoundingRect::new_x_y_w_h(10 + glyph * 10, 10 + line * 12, 4, 7);
+                page.fill_rect(&rect, &Color::new(0, 0, 0));
+            }
+        }
+        // a rule, and a photo shaded left to right
+        page.fill_rect(&BoundingRect::new_x_y_w_h(10, 80, 180, 2), &Color::new(0, 0, 0));
+        for y in 95..150 {
+            for x in 40..160 {
+                let v = ((x - 40) * 2) as u8;
+                page.set_pixel(x, y, &Color::new(v, v, v));
+            }
+        }
+
+        let zones = analyze_layout(&page, &LayoutConfig::default());
+        assert_eq!(zones.iter().map(|z| z.kind).collect::<Vec<_>>(), [ZoneKind::Text, ZoneKind::Separator, ZoneKind::Image]);
+        assert_eq!(zones[0].rect, BoundingRect::new_x_y_w_h(10, 10, 174, 55));
+        assert_eq!(zones[1].rect, BoundingRect::new_x_y_w_h(10, 80, 180, 2));
+        assert_eq!(zones[2].rect.top, 95);
+    }
+}
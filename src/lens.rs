@@ -0,0 +1,89 @@
+use crate::{CameraIntrinsics, ColorImage, PointF64};
+
+/// Brown–Conrady lens distortion: radial (`k1`, `k2`, `k3`) and tangential (`p1`, `p2`) coefficients,
+/// applied to normalized coordinates, i.e. pixels relative to the principal point over the focal length
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct LensDistortion {
+    pub k1: f64,
+    pub k2: f64,
+    pub k3: f64,
+    pub p1: f64,
+    pub p2: f64,
+}
+
+impl LensDistortion {
+    /// Purely radial distortion; `k1` below 0 for barrel, above 0 for pincushion
+    pub fn radial(k1: f64, k2: f64) -> Self {
+        Self { k1, k2, ..Default::default() }
+    }
+
+    /// Where the lens images the (normalized) point `p`
+    pub fn distort(&self, p: PointF64) -> PointF64 {
+        let (x, y) = (p.x, p.y);
+        let r2 = x * x + y * y;
+        let radial = 1.0 + r2 * (self.k1 + r2 * (self.k2 + r2 * self.k3));
+        PointF64::new(
+            x * radial + 2.0 * self.p1 * x * y + self.p2 * (r2 + 2.0 * x * x),
+            y * radial + self.p1 * (r2 + 2.0 * y * y) + 2.0 * self.p2 * x * y,
+        )
+    }
+
+    /// The (normalized) point which `distort` takes to `p`, by fixed point iteration;
+    /// exact to well under a pixel for the distortion of ordinary lenses
+    pub fn undistort(&self, p: PointF64) -> PointF64 {
+        let mut u = p;
+        for _ in 0..20 {
+            let (x, y) = (u.x, u.y);
+            let r2 = x * x + y * y;
+            let radial = 1.0 + r2 * (self.k1 + r2 * (self.k2 + r2 * self.k3));
+            let tangential = PointF64::new(
+                2.0 * self.p1 * x * y + self.p2 * (r2 + 2.0 * x * x),
+                self.p1 * (r2 + 2.0 * y * y) + 2.0 * self.p2 * x * y,
+            );
+            u = (p - tangential) * (1.0 / radial);
+        }
+        u
+    }
+
+    /// Undistorts points in pixels, of a camera with its principal point at `center` and square pixels
+    /// `focal_length` pixels from the lens, e.g. detected corners of a document before fitting a `PerspectiveTransform`;
+    /// see `CameraIntrinsics::undistort_points` for other cameras
+    pub fn undistort_points(&self, points: &[PointF64], center: PointF64, focal_length: f64) -> Vec<PointF64> {
+        CameraIntrinsics::new(focal_length, focal_length, center.x, center.y).undistort_points(self, points)
+    }
+
+    /// The image as without the distortion, of the same size, with its camera as in `undistort_points`:
+    /// each pixel is sampled bilinearly where the lens distorts it to; pixels from outside the image are transparent
+    pub fn undistort_image(&self, image: &ColorImage, center: PointF64, focal_length: f64) -> ColorImage {
+        CameraIntrinsics::new(focal_length, focal_length, center.x, center.y).undistort_image(self, image)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BoundingRect, Color};
+
+    #[test]
+    fn undistort_barrel() {
+        let lens = LensDistortion { k1: -0.2, k2: 0.05, k3: 0.0, p1: 0.001, p2: -0.002 };
+        let p = PointF64::new(0.4, -0.3);
+        assert!((lens.undistort(lens.distort(p)) - p).norm() < 1e-9);
+
+        // under barrel distortion, a straight edge bows; undistorted, its points line up again
+        let (center, focal) = (PointF64::new(50.0, 40.0), 80.0);
+        let edge: Vec<PointF64> = (0..5)
+            .map(|i| lens.distort((PointF64::new(10.0 + 20.0 * i as f64, 10.0) - center) * (1.0 / focal)) * focal + center)
+            .collect();
+        assert!((edge[2].y - edge[0].y).abs() > 1.0);
+        let straight = lens.undistort_points(&edge, center, focal);
+        assert!(straight.iter().all(|p| (p.y - 10.0).abs() < 1e-6));
+
+        let mut image = ColorImage::new_w_h(100, 80);
+        image.fill_rect(&BoundingRect::new_x_y_w_h(0, 0, 100, 80), &Color::new(255, 255, 255));
+        let undistorted = LensDistortion::radial(0.2, 0.0).undistort_image(&image, center, focal);
+        // the center is unmoved; without pincushion distortion, the corners come from beyond the image
+        assert_eq!(undistorted.get_pixel(50, 40), Color::new(255, 255, 255));
+        assert_eq!(undistorted.get_pixel(0, 0).a, 0);
+    }
+}
@@ -2,34 +2,112 @@ pub mod color_clusters;
 mod numeric;
 mod path;
 mod shape;
+mod background;
+mod blob;
+mod border;
 pub mod bound;
+mod camera;
+mod chamfer;
 pub mod clusters;
 mod color;
+mod color_blindness;
+mod color_depth;
+mod color_diff;
 mod color_stat;
+mod color_transfer;
+mod components;
+mod convolve;
+mod crop;
+mod defringe;
+mod descreen;
 pub mod disjoint_sets;
+mod exposure;
 mod field;
+mod gradient;
+mod illumination;
 mod image;
+mod image_trait;
+mod inpaint;
+mod labels;
+mod layout;
+mod lens;
+mod matting;
+mod morphology;
+mod nms;
+mod palette;
+mod peaks;
+mod phase_correlation;
+pub mod pipeline;
+mod planar;
 mod point;
+mod poisson;
 mod polar;
+mod pose;
+pub mod preset;
+mod pyramid;
+mod saliency;
 mod sampler;
 mod sat;
+mod sdf;
+mod seam;
+mod space;
+mod sprite;
 mod statistic;
+mod stitch;
+mod swt;
+pub mod synth;
+pub mod testing;
 mod transform;
 
 // pub use color_clusters;
 pub use numeric::*;
 pub use path::*;
 pub use shape::*;
+pub use background::*;
+pub use blob::*;
+pub use border::*;
 pub use bound::{Bound, BoundingRect, BoundingRectF64, BoundStat};
+pub use camera::*;
+pub use chamfer::*;
 //pub use clusters;
 pub use color::*;
+pub use color_blindness::*;
+pub use color_depth::*;
+pub use color_diff::*;
 pub use color_stat::*;
+pub use color_transfer::*;
+pub use components::*;
+pub use convolve::*;
+pub use crop::*;
+pub use defringe::*;
+pub use descreen::*;
 pub use disjoint_sets::Forests;
+pub use exposure::*;
 pub use field::*;
+pub use illumination::*;
 pub use image::*;
+pub use image_trait::*;
+pub use labels::*;
+pub use layout::*;
+pub use lens::*;
+pub use matting::*;
+pub use nms::*;
+pub use palette::*;
+pub use peaks::*;
+pub use phase_correlation::*;
+pub use planar::*;
 pub use point::*;
 pub use polar::*;
+pub use pose::*;
+pub use pyramid::*;
+pub use saliency::*;
 pub use sampler::*;
 pub use sat::*;
+pub use sdf::*;
+pub use seam::*;
+pub use space::*;
+pub use sprite::*;
 pub use statistic::*;
+pub use stitch::*;
+pub use swt::*;
 pub use transform::*;
\ No newline at end of file
@@ -0,0 +1,124 @@
+use crate::{Color, ColorImage, DefringeConfig, PlanarImage};
+use crate::defringe::key_distance;
+
+/// Parameters of `ColorImage::alpha_matte`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MattingConfig {
+    /// which pixels are keyed out, and the width of the unknown band along the key boundary
+    pub keying: DefringeConfig,
+    /// foreground and background samples are taken within this distance of an unknown pixel
+    pub search_radius: usize,
+}
+
+impl Default for MattingConfig {
+    fn default() -> Self {
+        Self {
+            keying: DefringeConfig::default(),
+            search_radius: 4,
+        }
+    }
+}
+
+impl ColorImage {
+    /// Soft alpha along the key boundary, by sampling based border matting.
+    ///
+    /// Keyed out pixels have alpha 0, and pixels farther than `keying.width` from them alpha 1.
+    /// In between, each pixel is modelled as a mix `alpha * F + (1 - alpha) * B` of a foreground and a
+    /// background sample within `search_radius`; the pair which best explains its color gives the alpha.
+    /// Pixels which already carry a partial alpha in the image keep it.
+    pub fn alpha_matte(&self, config: &MattingConfig) -> PlanarImage<f32, 1> {
+        let (width, height) = (self.width, self.height);
+        let distance = key_distance(self, &config.keying);
+        let mut matte = PlanarImage::new_w_h(width, height);
+        let r = config.search_radius as i32;
+        for y in 0..height {
+            for x in 0..width {
+                let i = y * width + x;
+                let color = self.get_pixel_at(i);
+                let alpha = match distance[i] {
+                    0 => 0.0,
+                    usize::MAX => 1.0,
+                    _ if color.a < 255 => color.a as f32 / 255.0,
+                    _ => {
+                        let (mut foreground, mut background) = (Vec::new(), Vec::new());
+                        for sy in (y as i32 - r).max(0)..(y as i32 + r + 1).min(height as i32) {
+                            for sx in (x as i32 - r).max(0)..(x as i32 + r + 1).min(width as i32) {
+                                let j = sy as usize * width + sx as usize;
+                                match distance[j] {
+                                    0 => background.push(self.get_pixel_at(j)),
+                                    usize::MAX => foreground.push(self.get_pixel_at(j)),
+                                    _ => {},
+                                }
+                            }
+                        }
+                        best_alpha(color, &foreground, &background)
+                    },
+                };
+                matte.set(x, y, 0, alpha);
+            }
+        }
+        matte
+    }
+}
+
+/// Alpha of the foreground and background pair which best explains `color`
+fn best_alpha(color: Color, foreground: &[Color], background: &[Color]) -> f32 {
+    if foreground.is_empty() {
+        return 0.0;
+    }
+    if background.is_empty() {
+        return 1.0;
+    }
+    let rgb = |c: &Color| [c.r as f64, c.g as f64, c.b as f64];
+    let c = rgb(&color);
+    let mut best = (f64::INFINITY, 1.0);
+    for f in foreground.iter().map(rgb) {
+        for b in background.iter().map(rgb) {
+            let fb = [f[0] - b[0], f[1] - b[1], f[2] - b[2]];
+            let norm = fb[0] * fb[0] + fb[1] * fb[1] + fb[2] * fb[2];
+            if norm == 0.0 {
+                continue;
+            }
+            let alpha = (((c[0] - b[0]) * fb[0] + (c[1] - b[1]) * fb[1] + (c[2] - b[2]) * fb[2]) / norm).clamp(0.0, 1.0);
+            let error: f64 = (0..3).map(|k| (c[k] - (alpha * f[k] + (1.0 - alpha) * b[k])).powi(2)).sum();
+            if error < best.0 {
+                best = (error, alpha);
+            }
+        }
+    }
+    best.1 as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matte_of_mixed_column() {
+        // green screen on the left, red on the right, columns mixed 3:1 and 1:3 in between
+        let (green, red) = (Color::new(0, 200, 0), Color::new(200, 0, 0));
+        let mut image = ColorImage::new_w_h(10, 3);
+        for y in 0..3 {
+            for x in 0..10 {
+                let color = match x {
+                    0..=3 => green,
+                    4 => Color::new(50, 150, 0),
+                    5 => Color::new(150, 50, 0),
+                    _ => red,
+                };
+                image.set_pixel(x, y, &color);
+            }
+        }
+        let config = MattingConfig {
+            keying: DefringeConfig { key_color: green, width: 2, ..DefringeConfig::default() },
+            ..MattingConfig::default()
+        };
+        let matte = image.alpha_matte(&config);
+        for y in 0..3 {
+            assert_eq!(matte.get(2, y, 0), 0.0);
+            assert!((matte.get(4, y, 0) - 0.25).abs() < 1e-6);
+            assert!((matte.get(5, y, 0) - 0.75).abs() < 1e-6);
+            assert_eq!(matte.get(8, y, 0), 1.0);
+        }
+    }
+}
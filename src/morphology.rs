@@ -0,0 +1,115 @@
+use crate::{BorderMode, PlanarImage};
+
+/// Grayscale morphology with a square structuring element of `2 * radius + 1` pixels a side;
+/// borders are extended by `BorderMode::Replicate`, unless given
+impl PlanarImage<f32, 1> {
+    /// Minimum over the neighbourhood of each pixel
+    pub fn erode(&self, radius: usize) -> Self {
+        self.erode_with_border(radius, BorderMode::Replicate)
+    }
+
+    /// `erode`, with pixels beyond the edges extended by `border` (zero where `Constant`)
+    pub fn erode_with_border(&self, radius: usize, border: BorderMode) -> Self {
+        self.rank_filter(radius, border, f32::min)
+    }
+
+    /// Maximum over the neighbourhood of each pixel
+    pub fn dilate(&self, radius: usize) -> Self {
+        self.dilate_with_border(radius, BorderMode::Replicate)
+    }
+
+    /// `dilate`, with pixels beyond the edges extended by `border` (zero where `Constant`)
+    pub fn dilate_with_border(&self, radius: usize, border: BorderMode) -> Self {
+        self.rank_filter(radius, border, f32::max)
+    }
+
+    /// Erosion then dilation, removing bright details smaller than the structuring element
+    pub fn open(&self, radius: usize) -> Self {
+        self.erode(radius).dilate(radius)
+    }
+
+    /// Dilation then erosion, removing dark details smaller than the structuring element
+    pub fn close(&self, radius: usize) -> Self {
+        self.dilate(radius).erode(radius)
+    }
+
+    /// Dilation minus erosion, which is high along edges
+    pub fn morphological_gradient(&self, radius: usize) -> Self {
+        self.dilate(radius).subtract(&self.erode(radius))
+    }
+
+    /// The image minus its opening: bright details smaller than the structuring element,
+    /// on a flattened background (e.g. light text under uneven illumination)
+    pub fn top_hat(&self, radius: usize) -> Self {
+        self.subtract(&self.open(radius))
+    }
+
+    /// The closing minus the image: dark details smaller than the structuring element
+    /// (e.g. dark text on paper under uneven illumination), as bright values
+    pub fn black_hat(&self, radius: usize) -> Self {
+        self.close(radius).subtract(self)
+    }
+
+    /// Separable min or max filter, by rows then by columns
+    fn rank_filter(&self, radius: usize, border: BorderMode, f: fn(f32, f32) -> f32) -> Self {
+        let at = |image: &Self, x: i64, y: i64| border.pixel(image, x, y).map_or(0.0, |[v]| v);
+        let (width, height) = (self.width(), self.height());
+        let mut rows = Self::new_w_h(width, height);
+        let r = radius as i64;
+        for y in 0..height {
+            for x in 0..width {
+                let v = (x as i64 - r..=x as i64 + r).map(|i| at(self, i, y as i64)).reduce(f).unwrap();
+                rows.set(x, y, 0, v);
+            }
+        }
+        let mut result = Self::new_w_h(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let v = (y as i64 - r..=y as i64 + r).map(|i| at(&rows, x as i64, i)).reduce(f).unwrap();
+                result.set(x, y, 0, v);
+            }
+        }
+        result
+    }
+
+    fn subtract(&self, other: &Self) -> Self {
+        let mut result = self.clone();
+        for (v, o) in result.channel_mut(0).iter_mut().zip(other.channel(0)) {
+            *v -= o;
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn top_hat_on_ramp() {
+        // a bright dot and a dark dot on a ramp
+        let mut image = PlanarImage::<f32, 1>::new_w_h(12, 5);
+        for y in 0..5 {
+            for x in 0..12 {
+                image.set(x, y, 0, 10.0 * x as f32);
+            }
+        }
+        image.set(3, 2, 0, 200.0);
+        image.set(8, 2, 0, 0.0);
+
+        let top_hat = image.top_hat(1);
+        assert_eq!(top_hat.get(3, 2, 0), 200.0 - 30.0);
+        assert_eq!(top_hat.get(6, 0, 0), 0.0);
+        let black_hat = image.black_hat(1);
+        assert_eq!(black_hat.get(8, 2, 0), 80.0);
+        assert_eq!(black_hat.get(6, 0, 0), 0.0);
+
+        let gradient = image.morphological_gradient(1);
+        assert_eq!(gradient.get(6, 0, 0), 20.0);
+        assert_eq!(image.erode(1).get(0, 0, 0), 0.0);
+        assert_eq!(image.dilate(2).get(11, 4, 0), 110.0);
+        assert_eq!(image.dilate_with_border(1, BorderMode::Constant).get(0, 0, 0), 10.0);
+        assert_eq!(image.erode_with_border(1, BorderMode::Constant).get(11, 4, 0), 0.0);
+        assert_eq!(image.erode_with_border(1, BorderMode::Wrap).get(11, 0, 0), 0.0);
+    }
+}
@@ -0,0 +1,62 @@
+use crate::{BoundingRect, PointF64};
+
+/// Indices of `items` by decreasing score
+fn by_score<T>(items: &[T], score: impl Fn(&T) -> f64) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..items.len()).collect();
+    order.sort_by(|&a, &b| score(&items[b]).total_cmp(&score(&items[a])));
+    order
+}
+
+/// Non-maximum suppression of scored boxes: from the highest score down, keeps each item whose box
+/// (given by `key`, with its score) overlaps no kept box by an IoU over `iou_threshold`, up to `max` items.
+/// Returns the indices of the kept items, by decreasing score (negate scores where lower is better).
+pub fn nms_boxes<T>(items: &[T], key: impl Fn(&T) -> (BoundingRect, f64), iou_threshold: f64, max: usize) -> Vec<usize> {
+    let mut kept: Vec<(usize, BoundingRect)> = Vec::new();
+    for i in by_score(items, |item| key(item).1) {
+        if kept.len() >= max {
+            break;
+        }
+        let rect = key(&items[i]).0;
+        if kept.iter().all(|&(_, other)| rect.iou(other) <= iou_threshold) {
+            kept.push((i, rect));
+        }
+    }
+    kept.into_iter().map(|(i, _)| i).collect()
+}
+
+/// Non-maximum suppression of scored points, as `nms_boxes`, suppressing points within `radius` of a kept point
+pub fn nms_points<T>(items: &[T], key: impl Fn(&T) -> (PointF64, f64), radius: f64, max: usize) -> Vec<usize> {
+    let mut kept: Vec<(usize, PointF64)> = Vec::new();
+    for i in by_score(items, |item| key(item).1) {
+        if kept.len() >= max {
+            break;
+        }
+        let p = key(&items[i]).0;
+        if kept.iter().all(|&(_, other)| p.distance_to(other) > radius) {
+            kept.push((i, p));
+        }
+    }
+    kept.into_iter().map(|(i, _)| i).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn suppress_boxes_and_points() {
+        let boxes = [
+            (BoundingRect::new_x_y_w_h(0, 0, 10, 10), 0.5),
+            (BoundingRect::new_x_y_w_h(1, 1, 10, 10), 0.9),
+            (BoundingRect::new_x_y_w_h(8, 8, 10, 10), 0.7),
+            (BoundingRect::new_x_y_w_h(30, 0, 4, 4), 0.1),
+        ];
+        assert_eq!(nms_boxes(&boxes, |&b| b, 0.5, usize::MAX), [1, 2, 3]);
+        assert_eq!(nms_boxes(&boxes, |&b| b, 0.0, usize::MAX), [1, 3]);
+        assert_eq!(nms_boxes(&boxes, |&b| b, 0.5, 2), [1, 2]);
+
+        let points = [(PointF64::new(0.0, 0.0), 1.0), (PointF64::new(2.0, 0.0), 3.0), (PointF64::new(5.0, 0.0), 2.0)];
+        assert_eq!(nms_points(&points, |&p| p, 2.5, usize::MAX), [1, 2]);
+        assert_eq!(nms_points(&points, |&p| p, 3.0, usize::MAX), [1]);
+    }
+}
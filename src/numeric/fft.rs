@@ -0,0 +1,278 @@
+use std::ops::{Add, Mul, Sub};
+use crate::{BorderMode, PlanarImage};
+use crate::fmath::{cos, exp, sin};
+
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Complex {
+    pub re: f64,
+    pub im: f64,
+}
+
+impl Complex {
+    pub fn new(re: f64, im: f64) -> Self {
+        Self { re, im }
+    }
+
+    pub fn norm_sqr(&self) -> f64 {
+        self.re * self.re + self.im * self.im
+    }
+
+    pub fn conj(&self) -> Self {
+        Self::new(self.re, -self.im)
+    }
+}
+
+impl Add for Complex {
+    type Output = Self;
+    fn add(self, other: Self) -> Self {
+        Self::new(self.re + other.re, self.im + other.im)
+    }
+}
+
+impl Sub for Complex {
+    type Output = Self;
+    fn sub(self, other: Self) -> Self {
+        Self::new(self.re - other.re, self.im - other.im)
+    }
+}
+
+impl Mul for Complex {
+    type Output = Self;
+    fn mul(self, other: Self) -> Self {
+        Self::new(self.re * other.re - self.im * other.im, self.re * other.im + self.im * other.re)
+    }
+}
+
+impl Mul<f64> for Complex {
+    type Output = Self;
+    fn mul(self, s: f64) -> Self {
+        Self::new(self.re * s, self.im * s)
+    }
+}
+
+/// In place radix-2 FFT; the length must be a power of two.
+/// The inverse is scaled by `1 / len`, so that it undoes the forward transform.
+pub fn fft(data: &mut [Complex], inverse: bool) {
+    let n = data.len();
+    assert!(n.is_power_of_two());
+    // bit reversal permutation
+    let mut j = 0;
+    for i in 1..n {
+        let mut bit = n >> 1;
+        while j & bit != 0 {
+            j ^= bit;
+            bit >>= 1;
+        }
+        j |= bit;
+        if i < j {
+            data.swap(i, j);
+        }
+    }
+    let sign = if inverse { 1.0 } else { -1.0 };
+    let mut len = 2;
+    while len <= n {
+        let angle = sign * 2.0 * std::f64::consts::PI / len as f64;
+        let step = Complex::new(cos(angle), sin(angle));
+        for start in (0..n).step_by(len) {
+            let mut w = Complex::new(1.0, 0.0);
+            for k in 0..len / 2 {
+                let (a, b) = (data[start + k], data[start + k + len / 2] * w);
+                data[start + k] = a + b;
+                data[start + k + len / 2] = a - b;
+                w = w * step;
+            }
+        }
+        len <<= 1;
+    }
+    if inverse {
+        let scale = 1.0 / n as f64;
+        data.iter_mut().for_each(|v| *v = *v * scale);
+    }
+}
+
+/// 2D spectrum of a grayscale image, padded to power of two dimensions.
+///
+/// Frequencies are in cycles per pixel, from -0.5 to 0.5 on each axis; the zero frequency is at index 0.
+pub struct Spectrum {
+    /// padded dimensions
+    pub width: usize,
+    pub height: usize,
+    /// row major coefficients
+    pub data: Vec<Complex>,
+    /// dimensions of the image transformed
+    image_width: usize,
+    image_height: usize,
+}
+
+impl Spectrum {
+    /// Forward transform. The padding repeats the edge pixels, so the borders ring less.
+    /// An empty image has a zero spectrum.
+    pub fn new(image: &PlanarImage<f32, 1>) -> Self {
+        let (image_width, image_height) = (image.width(), image.height());
+        let (width, height) = (image_width.next_power_of_two(), image_height.next_power_of_two());
+        let mut data = vec![Complex::default(); width * height];
+        if image_width == 0 || image_height == 0 {
+            return Self { width, height, data, image_width, image_height };
+        }
+        for y in 0..height {
+            for x in 0..width {
+                let [v] = BorderMode::Replicate.pixel(image, x as i64, y as i64).unwrap();
+                data[y * width + x] = Complex::new(v as f64, 0.0);
+            }
+        }
+        let mut spectrum = Self { width, height, data, image_width, image_height };
+        spectrum.transform(false);
+        spectrum
+    }
+
+    /// Inverse transform, cropped to the original image
+    pub fn to_image(&self) -> PlanarImage<f32, 1> {
+        let data = self.inverse();
+        let mut image = PlanarImage::new_w_h(self.image_width, self.image_height);
+        for y in 0..self.image_height {
+            for x in 0..self.image_width {
+                image.set(x, y, 0, data[y * self.width + x].re as f32);
+            }
+        }
+        image
+    }
+
+    /// Inverse transform of the whole (padded) spectrum, row major. Complex, as the spectrum
+    /// of a real image is no longer symmetric once filtered asymmetrically.
+    pub fn inverse(&self) -> Vec<Complex> {
+        let mut copy = Self { data: self.data.clone(), ..*self };
+        copy.transform(true);
+        copy.data
+    }
+
+    /// Frequency (in cycles per pixel) of the coefficient at (`x`, `y`)
+    pub fn frequency(&self, x: usize, y: usize) -> (f64, f64) {
+        let signed = |i: usize, n: usize| if i <= n / 2 { i as f64 } else { i as f64 - n as f64 };
+        (signed(x, self.width) / self.width as f64, signed(y, self.height) / self.height as f64)
+    }
+
+    /// Multiplies each coefficient by `gain` of its frequency
+    pub fn apply(&mut self, gain: impl Fn(f64, f64) -> f64) {
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let (u, v) = self.frequency(x, y);
+                let i = y * self.width + x;
+                self.data[i] = self.data[i] * gain(u, v);
+            }
+        }
+    }
+
+    /// Fraction of the energy (excluding the mean) at frequencies of at least `cutoff`; a measure of texture
+    pub fn energy_above(&self, cutoff: f64) -> f64 {
+        let (mut above, mut total) = (0.0, 0.0);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if x == 0 && y == 0 {
+                    continue;
+                }
+                let (u, v) = self.frequency(x, y);
+                let energy = self.data[y * self.width + x].norm_sqr();
+                total += energy;
+                if (u * u + v * v).sqrt() >= cutoff {
+                    above += energy;
+                }
+            }
+        }
+        if total == 0.0 { 0.0 } else { above / total }
+    }
+
+    /// Removes periodic noise: coefficients at frequencies of at least `min_frequency` whose magnitude
+    /// is over `ratio` times the mean magnitude of their 5x5 neighbourhood are set to that mean
+    pub fn suppress_peaks(&mut self, min_frequency: f64, ratio: f64) {
+        let magnitude: Vec<f64> = self.data.iter().map(|c| c.norm_sqr().sqrt()).collect();
+        let (w, h) = (self.width as i32, self.height as i32);
+        for y in 0..h {
+            for x in 0..w {
+                let (u, v) = self.frequency(x as usize, y as usize);
+                if (u * u + v * v).sqrt() < min_frequency {
+                    continue;
+                }
+                let mut sum = 0.0;
+                for dy in -2..=2 {
+                    for dx in -2..=2 {
+                        if dx != 0 || dy != 0 {
+                            sum += magnitude[((y + dy).rem_euclid(h) * w + (x + dx).rem_euclid(w)) as usize];
+                        }
+                    }
+                }
+                let mean = sum / 24.0;
+                let i = (y * w + x) as usize;
+                if magnitude[i] > ratio * mean {
+                    self.data[i] = self.data[i] * (mean / magnitude[i]);
+                }
+            }
+        }
+    }
+
+    fn transform(&mut self, inverse: bool) {
+        for row in self.data.chunks_exact_mut(self.width) {
+            fft(row, inverse);
+        }
+        let mut column = vec![Complex::default(); self.height];
+        for x in 0..self.width {
+            for (y, v) in column.iter_mut().enumerate() {
+                *v = self.data[y * self.width + x];
+            }
+            fft(&mut column, inverse);
+            for (y, v) in column.iter().enumerate() {
+                self.data[y * self.width + x] = *v;
+            }
+        }
+    }
+}
+
+impl PlanarImage<f32, 1> {
+    /// Gaussian low-pass filter; `cutoff` (in cycles per pixel) is the standard deviation of the gain
+    pub fn low_pass(&self, cutoff: f64) -> Self {
+        let mut spectrum = Spectrum::new(self);
+        spectrum.apply(|u, v| exp(-(u * u + v * v) / (2.0 * cutoff * cutoff)));
+        spectrum.to_image()
+    }
+
+    /// Gaussian high-pass filter, the complement of `low_pass`
+    pub fn high_pass(&self, cutoff: f64) -> Self {
+        let mut spectrum = Spectrum::new(self);
+        spectrum.apply(|u, v| 1.0 - exp(-(u * u + v * v) / (2.0 * cutoff * cutoff)));
+        spectrum.to_image()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fft_filters() {
+        let mut data: Vec<Complex> = (0..8).map(|i| Complex::new(i as f64, 0.0)).collect();
+        let original = data.clone();
+        fft(&mut data, false);
+        assert!((data[0].re - 28.0).abs() < 1e-9);
+        // a real signal has a conjugate symmetric spectrum
+        assert!((data[3] - data[5].conj()).norm_sqr() < 1e-18);
+        fft(&mut data, true);
+        assert!(data.iter().zip(original.iter()).all(|(a, b)| (*a - *b).norm_sqr() < 1e-18));
+
+        // a flat image plus stripes of period 2 across a 12x6 image (padded to 16x8)
+        let mut image = PlanarImage::new_w_h(12, 6);
+        for y in 0..6 {
+            for x in 0..12 {
+                image.set(x, y, 0, 100.0 + if x % 2 == 0 { 20.0 } else { -20.0 });
+            }
+        }
+        let spectrum = Spectrum::new(&image);
+        assert_eq!((spectrum.width, spectrum.height), (16, 8));
+        assert!(spectrum.energy_above(0.4) > 0.5);
+        let round_trip = spectrum.to_image();
+        assert!((round_trip.get(5, 3, 0) - image.get(5, 3, 0)).abs() < 1e-3);
+
+        let smooth = image.low_pass(0.1);
+        assert!((smooth.get(5, 3, 0) - 100.0).abs() < 5.0);
+        let detail = image.high_pass(0.1);
+        assert!((detail.get(5, 3, 0) + 20.0).abs() < 5.0);
+    }
+}
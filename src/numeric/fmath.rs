@@ -0,0 +1,242 @@
+//! Transcendental functions used on the tracing path. With the `deterministic` feature, they are computed
+//! by `portable` from additions, multiplications, divisions and square roots only, which IEEE 754 rounds
+//! exactly, so their results are bit identical on every platform (the platform `libm` differs between x86, ARM and WASM).
+//! Without the feature, they are the `std` functions.
+//!
+//! Only code calling these functions is covered. That includes clustering and path tracing, simplification
+//! and curve fitting, so the output of `pipeline` is bit identical: the rest of it compares integer color
+//! differences (Oklab conversion avoids `libm`), sums in a fixed order, and Rust never fuses multiply-adds implicitly.
+//! The shape descriptors, the FFT filters and the Gaussian kernels use these functions too. Other modules, e.g. image rotation, phase correlation,
+//! pose estimation and exposure fusion, still call `std` and may differ in the last bits between platforms.
+
+use std::f64::consts::{FRAC_PI_2, PI};
+
+macro_rules! dispatch {
+    ($(#[$doc:meta])* $name:ident($($arg:ident),*)) => {
+        $(#[$doc])*
+        pub fn $name($($arg: f64),*) -> f64 {
+            #[cfg(feature = "deterministic")]
+            return portable::$name($($arg),*);
+            #[cfg(not(feature = "deterministic"))]
+            return dispatch!(@std $name $($arg),*);
+        }
+    };
+    (@std $name:ident $x:ident) => { $x.$name() };
+    (@std $name:ident $x:ident, $y:ident) => { $x.$name($y) };
+}
+
+dispatch!(sin(x));
+dispatch!(cos(x));
+dispatch!(acos(x));
+dispatch!(
+    /// `y.atan2(x)`
+    atan2(y, x)
+);
+dispatch!(powf(x, y));
+dispatch!(exp(x));
+dispatch!(ln(x));
+
+/// Implementations within a few ulps of `std`, after fdlibm
+#[allow(clippy::excessive_precision, clippy::approx_constant)]
+pub mod portable {
+    use super::*;
+
+    const PIO2_HI: f64 = 1.57079632673412561417e+00;
+    const PIO2_LO: f64 = 6.07710050650619224932e-11;
+    const LN2_HI: f64 = 6.93147180369123816490e-01;
+    const LN2_LO: f64 = 1.90821492927058770002e-10;
+
+    const ATAN_HI: [f64; 4] = [
+        4.63647609000806093515e-01, 7.85398163397448278999e-01,
+        9.82793723247329054082e-01, 1.57079632679489655800e+00,
+    ];
+    const ATAN_LO: [f64; 4] = [
+        2.26987774529616870924e-17, 3.06161699786838301793e-17,
+        1.39033110312309984516e-17, 6.12323399573676603587e-17,
+    ];
+    const ATAN_T: [f64; 11] = [
+        3.33333333333329318027e-01, -1.99999999998764832476e-01, 1.42857142725034663711e-01,
+        -1.11111104054623557880e-01, 9.09088713343650656196e-02, -7.69187620504482999495e-02,
+        6.66107313738753120669e-02, -5.83357013379057348645e-02, 4.97687799461593236017e-02,
+        -3.65315727442169155270e-02, 1.62858201153657823623e-02,
+    ];
+
+    /// sin and cos on [-pi/4, pi/4]
+    fn kernel_sin_cos(x: f64) -> (f64, f64) {
+        let z = x * x;
+        let sin = x + x * z * (-1.66666666666666324348e-01 + z * (8.33333333332248946124e-03 +
+            z * (-1.98412698298579493134e-04 + z * (2.75573137070700676789e-06 +
+            z * (-2.50507602534068634195e-08 + z * 1.58969099521155010221e-10)))));
+        let cos = 1.0 - 0.5 * z + z * z * (4.16666666666666019037e-02 + z * (-1.38888888888741095749e-03 +
+            z * (2.48015872894767294178e-05 + z * (-2.75573143513906633035e-07 +
+            z * (2.08757232129817482790e-09 + z * -1.13596475577881948265e-11)))));
+        (sin, cos)
+    }
+
+    /// Reduces by multiples of pi/2; accurate for |x| up to about 1e5
+    fn sin_cos(x: f64) -> (f64, f64) {
+        if !x.is_finite() {
+            return (f64::NAN, f64::NAN);
+        }
+        let k = (x / FRAC_PI_2).round();
+        let (s, c) = kernel_sin_cos((x - k * PIO2_HI) - k * PIO2_LO);
+        match (k as i64).rem_euclid(4) {
+            0 => (s, c),
+            1 => (c, -s),
+            2 => (-s, -c),
+            _ => (-c, s),
+        }
+    }
+
+    pub fn sin(x: f64) -> f64 {
+        sin_cos(x).0
+    }
+
+    pub fn cos(x: f64) -> f64 {
+        sin_cos(x).1
+    }
+
+    pub fn atan(x: f64) -> f64 {
+        if x.is_nan() {
+            return x;
+        }
+        let a = x.abs();
+        let (id, t) = if a < 0.4375 {
+            (None, a)
+        } else if a < 0.6875 {
+            (Some(0), (2.0 * a - 1.0) / (2.0 + a))
+        } else if a < 1.1875 {
+            (Some(1), (a - 1.0) / (a + 1.0))
+        } else if a < 2.4375 {
+            (Some(2), (a - 1.5) / (1.0 + 1.5 * a))
+        } else {
+            (Some(3), -1.0 / a)
+        };
+        let z = t * t;
+        let w = z * z;
+        let s1 = z * (ATAN_T[0] + w * (ATAN_T[2] + w * (ATAN_T[4] + w * (ATAN_T[6] + w * (ATAN_T[8] + w * ATAN_T[10])))));
+        let s2 = w * (ATAN_T[1] + w * (ATAN_T[3] + w * (ATAN_T[5] + w * (ATAN_T[7] + w * ATAN_T[9]))));
+        let result = match id {
+            None => t - t * (s1 + s2),
+            Some(i) => ATAN_HI[i] - ((t * (s1 + s2) - ATAN_LO[i]) - t),
+        };
+        result.copysign(x)
+    }
+
+    pub fn atan2(y: f64, x: f64) -> f64 {
+        if x.is_nan() || y.is_nan() {
+            return f64::NAN;
+        }
+        if x == 0.0 {
+            if y == 0.0 {
+                return if x.is_sign_positive() { y } else { PI.copysign(y) };
+            }
+            return FRAC_PI_2.copysign(y);
+        }
+        let t = atan(y / x);
+        if x > 0.0 { t } else { t + PI.copysign(y) }
+    }
+
+    pub fn acos(x: f64) -> f64 {
+        if !(-1.0..=1.0).contains(&x) {
+            return f64::NAN;
+        }
+        atan2(((1.0 - x) * (1.0 + x)).sqrt(), x)
+    }
+
+    pub fn ln(x: f64) -> f64 {
+        if x.is_nan() || x < 0.0 {
+            return f64::NAN;
+        }
+        if x == 0.0 {
+            return f64::NEG_INFINITY;
+        }
+        if x.is_infinite() {
+            return x;
+        }
+        // x = m * 2^e with m in [sqrt(1/2), sqrt(2))
+        let (mut bits, mut e) = (x.to_bits(), 0i64);
+        if bits >> 52 == 0 {
+            bits = (x * 2f64.powi(54)).to_bits();
+            e -= 54;
+        }
+        e += (bits >> 52) as i64 - 1023;
+        let mut m = f64::from_bits((bits & ((1 << 52) - 1)) | (1023 << 52));
+        if m > std::f64::consts::SQRT_2 {
+            m /= 2.0;
+            e += 1;
+        }
+        // ln(m) = 2 atanh(s)
+        let s = (m - 1.0) / (m + 1.0);
+        let z = s * s;
+        let mut series = 0.0;
+        for n in (0..12).rev() {
+            series = 1.0 / (2 * n + 1) as f64 + z * series;
+        }
+        let e = e as f64;
+        e * LN2_HI + (2.0 * s * series + e * LN2_LO)
+    }
+
+    pub fn exp(x: f64) -> f64 {
+        if x.is_nan() {
+            return x;
+        }
+        if x > 709.782712893384 {
+            return f64::INFINITY;
+        }
+        if x < -745.1332191019411 {
+            return 0.0;
+        }
+        let k = (x / std::f64::consts::LN_2).round();
+        let r = (x - k * LN2_HI) - k * LN2_LO;
+        let mut p = 1.0;
+        for n in (1..=20).rev() {
+            p = 1.0 + r * p / n as f64;
+        }
+        // scale by 2^k in two steps, so that neither factor overflows
+        let k = k as i64;
+        let (k1, k2) = (k / 2, k - k / 2);
+        let scale = |k: i64| f64::from_bits(((k + 1023) as u64) << 52);
+        p * scale(k1) * scale(k2)
+    }
+
+    /// Only for `x >= 0`; negative `x` gives NaN
+    pub fn powf(x: f64, y: f64) -> f64 {
+        if y == 0.0 || x == 1.0 {
+            return 1.0;
+        }
+        if x == 0.0 {
+            return if y > 0.0 { 0.0 } else { f64::INFINITY };
+        }
+        exp(y * ln(x))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn portable_matches_std() {
+        let close = |a: f64, b: f64| (a - b).abs() <= 4.0 * f64::EPSILON * b.abs().max(1.0);
+        for i in -200..=200 {
+            let x = i as f64 * 0.0731;
+            assert!(close(portable::sin(x), x.sin()), "sin {}", x);
+            assert!(close(portable::cos(x), x.cos()), "cos {}", x);
+            assert!(close(portable::atan(x), x.atan()), "atan {}", x);
+            assert!(close(portable::atan2(x, 1.3 - x), x.atan2(1.3 - x)), "atan2 {}", x);
+            assert!(close(portable::exp(x), x.exp()), "exp {}", x);
+            let u = (i as f64 / 200.0).clamp(-1.0, 1.0);
+            assert!(close(portable::acos(u), u.acos()), "acos {}", u);
+            let p = (i + 201) as f64 / 97.0;
+            assert!(close(portable::ln(p), p.ln()), "ln {}", p);
+            assert!(close(portable::powf(p, 2.4), p.powf(2.4)), "powf {}", p);
+        }
+        assert_eq!(portable::atan2(0.0, -1.0), PI);
+        assert_eq!(portable::atan2(-1.0, 0.0), -FRAC_PI_2);
+        assert_eq!(portable::acos(1.0), 0.0);
+        assert_eq!(portable::powf(0.0, 2.0), 0.0);
+        assert!(close(portable::ln(1e-310), (1e-310_f64).ln()));
+        assert_eq!(acos(1.0), 0.0);
+    }
+}
@@ -0,0 +1,157 @@
+use crate::{Color, PointF64};
+
+/// A static KD-tree over points in `K` dimensions, each carrying a value of type `V`.
+///
+/// The tree is stored implicitly: the median of each subrange is its root.
+#[derive(Clone, Debug, Default)]
+pub struct KdTree<const K: usize, V> {
+    nodes: Vec<([f64; K], V)>,
+}
+
+impl<const K: usize, V> KdTree<K, V> {
+    pub fn new(mut items: Vec<([f64; K], V)>) -> Self {
+        build(&mut items, 0);
+        Self { nodes: items }
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    /// The item nearest to `query` (by Euclidean distance), and the squared distance to it
+    pub fn nearest(&self, query: &[f64; K]) -> Option<(&[f64; K], &V, f64)> {
+        let mut best = None;
+        self.search(query, 0, self.nodes.len(), 0, f64::MAX, &mut |i, d| {
+            if best.is_none_or(|(_, bd)| d < bd) {
+                best = Some((i, d));
+            }
+            best.map_or(f64::MAX, |(_, bd)| bd)
+        });
+        best.map(|(i, d)| (&self.nodes[i].0, &self.nodes[i].1, d))
+    }
+
+    /// Up to `k` items nearest to `query`, nearest first, with squared distances
+    pub fn k_nearest(&self, query: &[f64; K], k: usize) -> Vec<(&[f64; K], &V, f64)> {
+        let mut found: Vec<(usize, f64)> = Vec::with_capacity(k + 1);
+        if k > 0 {
+            self.search(query, 0, self.nodes.len(), 0, f64::MAX, &mut |i, d| {
+                if found.len() < k || d < found[found.len() - 1].1 {
+                    let at = found.partition_point(|&(_, fd)| fd <= d);
+                    found.insert(at, (i, d));
+                    found.truncate(k);
+                }
+                if found.len() < k { f64::MAX } else { found[k - 1].1 }
+            });
+        }
+        found.into_iter().map(|(i, d)| (&self.nodes[i].0, &self.nodes[i].1, d)).collect()
+    }
+
+    /// `visit` is called with each candidate and returns the squared search radius, which is also returned
+    fn search(
+        &self, query: &[f64; K], lo: usize, hi: usize, axis: usize, radius: f64,
+        visit: &mut impl FnMut(usize, f64) -> f64,
+    ) -> f64 {
+        if lo >= hi || K == 0 {
+            return radius;
+        }
+        let mid = (lo + hi) / 2;
+        let point = &self.nodes[mid].0;
+        let mut radius = visit(mid, squared_distance(point, query));
+        let delta = query[axis] - point[axis];
+        let next = (axis + 1) % K;
+        let (near, far) = if delta < 0.0 { ((lo, mid), (mid + 1, hi)) } else { ((mid + 1, hi), (lo, mid)) };
+        radius = self.search(query, near.0, near.1, next, radius, visit);
+        if delta * delta < radius {
+            radius = self.search(query, far.0, far.1, next, radius, visit);
+        }
+        radius
+    }
+}
+
+fn build<const K: usize, V>(items: &mut [([f64; K], V)], axis: usize) {
+    if items.len() <= 1 || K == 0 {
+        return;
+    }
+    let mid = items.len() / 2;
+    items.select_nth_unstable_by(mid, |a, b| a.0[axis].total_cmp(&b.0[axis]));
+    let (left, right) = items.split_at_mut(mid);
+    build(left, (axis + 1) % K);
+    build(&mut right[1..], (axis + 1) % K);
+}
+
+fn squared_distance<const K: usize>(a: &[f64; K], b: &[f64; K]) -> f64 {
+    a.iter().zip(b.iter()).map(|(a, b)| (a - b) * (a - b)).sum()
+}
+
+/// Nearest color lookup in a palette, by Euclidean distance in RGB
+pub type PaletteTree = KdTree<3, usize>;
+
+impl PaletteTree {
+    /// Each node carries the index of the color in `palette`
+    pub fn from_palette(palette: &[Color]) -> Self {
+        Self::new(palette.iter().enumerate().map(|(i, c)| (color_key(c), i)).collect())
+    }
+
+    /// Index into the palette of the color nearest to `color`; `None` if the palette is empty
+    pub fn nearest_color(&self, color: &Color) -> Option<usize> {
+        self.nearest(&color_key(color)).map(|(_, &i, _)| i)
+    }
+}
+
+fn color_key(color: &Color) -> [f64; 3] {
+    [color.r as f64, color.g as f64, color.b as f64]
+}
+
+/// Nearest neighbour lookup among 2D points
+pub type PointTree = KdTree<2, usize>;
+
+impl PointTree {
+    /// Each node carries the index of the point in `points`
+    pub fn from_points(points: &[PointF64]) -> Self {
+        Self::new(points.iter().enumerate().map(|(i, p)| ([p.x, p.y], i)).collect())
+    }
+
+    /// Index of the point nearest to `p`, and the distance to it
+    pub fn nearest_point(&self, p: PointF64) -> Option<(usize, f64)> {
+        self.nearest(&[p.x, p.y]).map(|(_, &i, d)| (i, d.sqrt()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn kdtree_matches_brute_force() {
+        // a deterministic scatter of points
+        let points: Vec<PointF64> = (0..200)
+            .map(|i| PointF64::new(((i * 37) % 101) as f64, ((i * 59) % 97) as f64 * 0.5))
+            .collect();
+        let tree = PointTree::from_points(&points);
+        assert_eq!(tree.len(), 200);
+        for q in [PointF64::new(3.3, 7.1), PointF64::new(50.0, 20.0), PointF64::new(-10.0, 100.0)] {
+            let expected = points.iter().map(|p| p.distance_to(q)).fold(f64::MAX, f64::min);
+            let (i, d) = tree.nearest_point(q).unwrap();
+            assert_eq!(d, expected);
+            assert_eq!(points[i].distance_to(q), expected);
+
+            let mut all: Vec<f64> = points.iter().map(|p| (p.x - q.x).powi(2) + (p.y - q.y).powi(2)).collect();
+            all.sort_by(f64::total_cmp);
+            let knn: Vec<f64> = tree.k_nearest(&[q.x, q.y], 5).iter().map(|r| r.2).collect();
+            assert_eq!(knn, all[..5].to_vec());
+        }
+    }
+
+    #[test]
+    fn palette_nearest_color() {
+        let palette = [Color::new(0, 0, 0), Color::new(255, 255, 255), Color::new(255, 0, 0)];
+        let tree = PaletteTree::from_palette(&palette);
+        assert_eq!(tree.nearest_color(&Color::new(200, 30, 30)), Some(2));
+        assert_eq!(tree.nearest_color(&Color::new(20, 30, 30)), Some(0));
+        assert_eq!(PaletteTree::from_palette(&[]).nearest_color(&Color::new(0, 0, 0)), None);
+    }
+}
@@ -1,5 +1,12 @@
+mod fft;
+pub mod fmath;
+mod kdtree;
 mod matrix;
 mod perspective;
+mod similarity;
 
+pub use fft::*;
+pub use kdtree::*;
 pub use matrix::*;
-pub use perspective::*;
\ No newline at end of file
+pub use perspective::*;
+pub use similarity::*;
\ No newline at end of file
@@ -105,6 +105,12 @@ impl PerspectiveTransform {
         }
     }
 
+    /// The homography as a 3x3 matrix acting on homogeneous points, normalized to 1 at the bottom right
+    pub fn to_matrix(&self) -> Matrix<3, 3> {
+        let c = &self.coeffs;
+        Matrix::new([[c[0], c[1], c[2]], [c[3], c[4], c[5]], [c[6], c[7], 1.0]])
+    }
+
     pub fn print_coeffs(&self) -> String {
         format!("{:?}", self.coeffs)
     }
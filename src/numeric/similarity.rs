@@ -0,0 +1,64 @@
+use crate::PointF64;
+
+/// Scaling by `scale` and rotation by `angle` (radians, clockwise in image coordinates) about the origin,
+/// then translation by `translation`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SimilarityTransform {
+    pub scale: f64,
+    pub angle: f64,
+    pub translation: PointF64,
+}
+
+impl Default for SimilarityTransform {
+    fn default() -> Self {
+        Self::new(1.0, 0.0, PointF64::new(0.0, 0.0))
+    }
+}
+
+impl SimilarityTransform {
+    pub fn new(scale: f64, angle: f64, translation: PointF64) -> Self {
+        Self { scale, angle, translation }
+    }
+
+    /// Scaling and rotation about `center` instead of the origin, then translation
+    pub fn about(center: PointF64, scale: f64, angle: f64, translation: PointF64) -> Self {
+        let origin = Self::new(scale, angle, PointF64::new(0.0, 0.0)).transform(center);
+        Self::new(scale, angle, center - origin + translation)
+    }
+
+    pub fn transform(&self, point: PointF64) -> PointF64 {
+        point.rotate_about_origin(self.angle) * self.scale + self.translation
+    }
+
+    pub fn transform_inverse(&self, point: PointF64) -> PointF64 {
+        (point - self.translation).rotate_about_origin(-self.angle) * (1.0 / self.scale)
+    }
+
+    /// `other`, then this
+    pub fn compose(&self, other: &Self) -> Self {
+        Self::new(self.scale * other.scale, self.angle + other.angle, self.transform(other.translation))
+    }
+
+    pub fn inverse(&self) -> Self {
+        Self::new(1.0 / self.scale, -self.angle, self.transform_inverse(PointF64::new(0.0, 0.0)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn similarity_roundtrip() {
+        let t = SimilarityTransform::about(PointF64::new(10.0, 5.0), 2.0, std::f64::consts::FRAC_PI_2, PointF64::new(1.0, 0.0));
+        // the center only moves by the translation
+        assert!((t.transform(PointF64::new(10.0, 5.0)) - PointF64::new(11.0, 5.0)).norm() < 1e-9);
+        // a step right turns into two steps down
+        assert!((t.transform(PointF64::new(11.0, 5.0)) - PointF64::new(11.0, 7.0)).norm() < 1e-9);
+        let p = PointF64::new(-3.0, 4.0);
+        assert!((t.transform_inverse(t.transform(p)) - p).norm() < 1e-9);
+        assert!((t.inverse().transform(t.transform(p)) - p).norm() < 1e-9);
+        let twice = t.compose(&t);
+        assert!((twice.transform(p) - t.transform(t.transform(p))).norm() < 1e-9);
+    }
+}
@@ -0,0 +1,240 @@
+use crate::{Color, ciede2000};
+
+/// Chroma below which a color is treated as neutral (grey) when sorting by hue
+const NEUTRAL_CHROMA: f64 = 0.03;
+
+/// Oklch: Oklab in polar form
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct ColorOklch {
+    /// lightness in [0, 1]
+    pub l: f64,
+    /// chroma, 0 for greys and up to about 0.32 for sRGB colors
+    pub c: f64,
+    /// hue angle in degrees, in [0, 360)
+    pub h: f64,
+}
+
+impl Color {
+    pub fn to_oklch(&self) -> ColorOklch {
+        let lab: oklab::Oklab = oklab::Rgb { r: self.r, g: self.g, b: self.b }.into();
+        let (a, b) = (lab.a as f64, lab.b as f64);
+        let h = crate::fmath::atan2(b, a).to_degrees();
+        ColorOklch {
+            l: lab.l as f64,
+            c: (a * a + b * b).sqrt(),
+            h: if h < 0.0 { h + 360.0 } else { h },
+        }
+    }
+}
+
+/// How `sort_palette` orders colors
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum PaletteOrder {
+    /// Around the hue wheel (from red), lighter first within the same hue; greys come last, dark to light
+    #[default]
+    Hue,
+    /// Dark to light
+    Lightness,
+}
+
+/// Sorts `palette` perceptually, in Oklch
+pub fn sort_palette(palette: &mut [Color], order: PaletteOrder) {
+    let key = |c: &Color| {
+        let lch = c.to_oklch();
+        match order {
+            PaletteOrder::Hue if lch.c >= NEUTRAL_CHROMA => (0, lch.h, -lch.l),
+            PaletteOrder::Hue => (1, lch.l, 0.0),
+            PaletteOrder::Lightness => (0, lch.l, lch.h),
+        }
+    };
+    palette.sort_by(|a, b| {
+        let (ka, kb) = (key(a), key(b));
+        ka.0.cmp(&kb.0).then(ka.1.total_cmp(&kb.1)).then(ka.2.total_cmp(&kb.2))
+    });
+}
+
+/// The distinct CSS named colors; `cyan` and `magenta` are the same as `aqua` and `fuchsia`,
+/// and the `grey` spellings as the `gray` ones
+pub const CSS_COLORS: [(&str, u8, u8, u8); 139] = [
+    ("aliceblue", 240, 248, 255),
+    ("antiquewhite", 250, 235, 215),
+    ("aqua", 0, 255, 255),
+    ("aquamarine", 127, 255, 212),
+    ("azure", 240, 255, 255),
+    ("beige", 245, 245, 220),
+    ("bisque", 255, 228, 196),
+    ("black", 0, 0, 0),
+    ("blanchedalmond", 255, 235, 205),
+    ("blue", 0, 0, 255),
+    ("blueviolet", 138, 43, 226),
+    ("brown", 165, 42, 42),
+    ("burlywood", 222, 184, 135),
+    ("cadetblue", 95, 158, 160),
+    ("chartreuse", 127, 255, 0),
+    ("chocolate", 210, 105, 30),
+    ("coral", 255, 127, 80),
+    ("cornflowerblue", 100, 149, 237),
+    ("cornsilk", 255, 248, 220),
+    ("crimson", 220, 20, 60),
+    ("darkblue", 0, 0, 139),
+    ("darkcyan", 0, 139, 139),
+    ("darkgoldenrod", 184, 134, 11),
+    ("darkgray", 169, 169, 169),
+    ("darkgreen", 0, 100, 0),
+    ("darkkhaki", 189, 183, 107),
+    ("darkmagenta", 139, 0, 139),
+    ("darkolivegreen", 85, 107, 47),
+    ("darkorange", 255, 140, 0),
+    ("darkorchid", 153, 50, 204),
+    ("darkred", 139, 0, 0),
+    ("darksalmon", 233, 150, 122),
+    ("darkseagreen", 143, 188, 143),
+    ("darkslateblue", 72, 61, 139),
+    ("darkslategray", 47, 79, 79),
+    ("darkturquoise", 0, 206, 209),
+    ("darkviolet", 148, 0, 211),
+    ("deeppink", 255, 20, 147),
+    ("deepskyblue", 0, 191, 255),
+    ("dimgray", 105, 105, 105),
+    ("dodgerblue", 30, 144, 255),
+    ("firebrick", 178, 34, 34),
+    ("floralwhite", 255, 250, 240),
+    ("forestgreen", 34, 139, 34),
+    ("fuchsia", 255, 0, 255),
+    ("gainsboro", 220, 220, 220),
+    ("ghostwhite", 248, 248, 255),
+    ("gold", 255, 215, 0),
+    ("goldenrod", 218, 165, 32),
+    ("gray", 128, 128, 128),
+    ("green", 0, 128, 0),
+    ("greenyellow", 173, 255, 47),
+    ("honeydew", 240, 255, 240),
+    ("hotpink", 255, 105, 180),
+    ("indianred", 205, 92, 92),
+    ("indigo", 75, 0, 130),
+    ("ivory", 255, 255, 240),
+    ("khaki", 240, 230, 140),
+    ("lavender", 230, 230, 250),
+    ("lavenderblush", 255, 240, 245),
+    ("lawngreen", 124, 252, 0),
+    ("lemonchiffon", 255, 250, 205),
+    ("lightblue", 173, 216, 230),
+    ("lightcoral", 240, 128, 128),
+    ("lightcyan", 224, 255, 255),
+    ("lightgoldenrodyellow", 250, 250, 210),
+    ("lightgray", 211, 211, 211),
+    ("lightgreen", 144, 238, 144),
+    ("lightpink", 255, 182, 193),
+    ("lightsalmon", 255, 160, 122),
+    ("lightseagreen", 32, 178, 170),
+    ("lightskyblue", 135, 206, 250),
+    ("lightslategray", 119, 136, 153),
+    ("lightsteelblue", 176, 196, 222),
+    ("lightyellow", 255, 255, 224),
+    ("lime", 0, 255, 0),
+    ("limegreen", 50, 205, 50),
+    ("linen", 250, 240, 230),
+    ("maroon", 128, 0, 0),
+    ("mediumaquamarine", 102, 205, 170),
+    ("mediumblue", 0, 0, 205),
+    ("mediumorchid", 186, 85, 211),
+    ("mediumpurple", 147, 112, 219),
+    ("mediumseagreen", 60, 179, 113),
+    ("mediumslateblue", 123, 104, 238),
+    ("mediumspringgreen", 0, 250, 154),
+    ("mediumturquoise", 72, 209, 204),
+    ("mediumvioletred", 199, 21, 133),
+    ("midnightblue", 25, 25, 112),
+    ("mintcream", 245, 255, 250),
+    ("mistyrose", 255, 228, 225),
+    ("moccasin", 255, 228, 181),
+    ("navajowhite", 255, 222, 173),
+    ("navy", 0, 0, 128),
+    ("oldlace", 253, 245, 230),
+    ("olive", 128, 128, 0),
+    ("olivedrab", 107, 142, 35),
+    ("orange", 255, 165, 0),
+    ("orangered", 255, 69, 0),
+    ("orchid", 218, 112, 214),
+    ("palegoldenrod", 238, 232, 170),
+    ("palegreen", 152, 251, 152),
+    ("paleturquoise", 175, 238, 238),
+    ("palevioletred", 219, 112, 147),
+    ("papayawhip", 255, 239, 213),
+    ("peachpuff", 255, 218, 185),
+    ("peru", 205, 133, 63),
+    ("pink", 255, 192, 203),
+    ("plum", 221, 160, 221),
+    ("powderblue", 176, 224, 230),
+    ("purple", 128, 0, 128),
+    ("rebeccapurple", 102, 51, 153),
+    ("red", 255, 0, 0),
+    ("rosybrown", 188, 143, 143),
+    ("royalblue", 65, 105, 225),
+    ("saddlebrown", 139, 69, 19),
+    ("salmon", 250, 128, 114),
+    ("sandybrown", 244, 164, 96),
+    ("seagreen", 46, 139, 87),
+    ("seashell", 255, 245, 238),
+    ("sienna", 160, 82, 45),
+    ("silver", 192, 192, 192),
+    ("skyblue", 135, 206, 235),
+    ("slateblue", 106, 90, 205),
+    ("slategray", 112, 128, 144),
+    ("snow", 255, 250, 250),
+    ("springgreen", 0, 255, 127),
+    ("steelblue", 70, 130, 180),
+    ("tan", 210, 180, 140),
+    ("teal", 0, 128, 128),
+    ("thistle", 216, 191, 216),
+    ("tomato", 255, 99, 71),
+    ("turquoise", 64, 224, 208),
+    ("violet", 238, 130, 238),
+    ("wheat", 245, 222, 179),
+    ("white", 255, 255, 255),
+    ("whitesmoke", 245, 245, 245),
+    ("yellow", 255, 255, 0),
+    ("yellowgreen", 154, 205, 50),
+];
+
+/// The CSS named color nearest to `color` (by CIEDE2000), and its difference
+pub fn css_color_name(color: Color) -> (&'static str, f64) {
+    let lab = color.to_lab();
+    CSS_COLORS
+        .iter()
+        .map(|&(name, r, g, b)| (name, ciede2000(lab, Color::new(r, g, b).to_lab())))
+        .fold(("", f64::MAX), |best, next| if next.1 < best.1 { next } else { best })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sort_and_name() {
+        let mut palette = [
+            Color::new(255, 255, 255),
+            Color::new(0, 0, 255),
+            Color::new(0, 0, 0),
+            Color::new(255, 0, 0),
+            Color::new(0, 128, 0),
+        ];
+        sort_palette(&mut palette, PaletteOrder::Hue);
+        assert_eq!(palette, [
+            Color::new(255, 0, 0),
+            Color::new(0, 128, 0),
+            Color::new(0, 0, 255),
+            Color::new(0, 0, 0),
+            Color::new(255, 255, 255),
+        ]);
+        sort_palette(&mut palette, PaletteOrder::Lightness);
+        assert_eq!(palette[0], Color::new(0, 0, 0));
+        assert_eq!(palette[4], Color::new(255, 255, 255));
+
+        assert_eq!(css_color_name(Color::new(255, 0, 0)), ("red", 0.0));
+        assert_eq!(css_color_name(Color::new(250, 130, 110)).0, "salmon");
+        assert_eq!(css_color_name(Color::new(30, 30, 30)).0, "black");
+        let grey = Color::new(128, 128, 128).to_oklch();
+        assert!(grey.c < 1e-3 && (grey.l - 0.6).abs() < 0.01);
+    }
+}
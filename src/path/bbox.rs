@@ -0,0 +1,122 @@
+use crate::{BoundingRectF64, CompoundPath, CompoundPathElement, Path, PointF64, PointType, Spline};
+
+/// Exact bounding box of a cubic bezier curve given by 4 control points.
+///
+/// Besides the end points, the extremes are at the roots of the derivative on each axis.
+pub fn cubic_bounding_box(curve: &[PointF64]) -> BoundingRectF64 {
+    assert_eq!(curve.len(), 4);
+    let (p0, p1, p2, p3) = (curve[0], curve[1], curve[2], curve[3]);
+    let mut rect = BoundingRectF64::default();
+    rect.add_point(p0);
+    rect.add_point(p3);
+    let axis = |f: fn(&PointF64) -> f64| {
+        let (p0, p1, p2, p3) = (f(&p0), f(&p1), f(&p2), f(&p3));
+        // derivative / 3 = a t^2 + b t + c
+        let a = -p0 + 3.0 * p1 - 3.0 * p2 + p3;
+        let b = 2.0 * (p0 - 2.0 * p1 + p2);
+        let c = p1 - p0;
+        quadratic_roots(a, b, c)
+    };
+    for t in axis(|p| p.x).into_iter().chain(axis(|p| p.y)).flatten() {
+        if 0.0 < t && t < 1.0 {
+            rect.add_point(cubic_point(curve, t));
+        }
+    }
+    rect
+}
+
+fn quadratic_roots(a: f64, b: f64, c: f64) -> [Option<f64>; 2] {
+    const EPSILON: f64 = 1e-12;
+    if a.abs() < EPSILON {
+        if b.abs() < EPSILON {
+            return [None, None];
+        }
+        return [Some(-c / b), None];
+    }
+    let discriminant = b * b - 4.0 * a * c;
+    if discriminant < 0.0 {
+        return [None, None];
+    }
+    let sqrt = discriminant.sqrt();
+    [Some((-b + sqrt) / (2.0 * a)), Some((-b - sqrt) / (2.0 * a))]
+}
+
+fn cubic_point(curve: &[PointF64], t: f64) -> PointF64 {
+    let s = 1.0 - t;
+    curve[0] * (s * s * s) + curve[1] * (3.0 * s * s * t) + curve[2] * (3.0 * s * t * t) + curve[3] * (t * t * t)
+}
+
+impl<T> Path<T> where T: PointType {
+    /// Bounding box of all points; empty if the path is empty
+    pub fn bounding_box(&self) -> BoundingRectF64 {
+        let mut rect = BoundingRectF64::default();
+        for p in self.iter() {
+            rect.add_point(p.to_point_f64());
+        }
+        rect
+    }
+}
+
+impl Spline {
+    /// Tight bounding box of the curves, which is generally smaller than that of the control points
+    pub fn bounding_box(&self) -> BoundingRectF64 {
+        let mut rect = BoundingRectF64::default();
+        if let Some(&first) = self.points.first() {
+            rect.add_point(first);
+        }
+        for curve in self.get_control_points() {
+            rect.merge(cubic_bounding_box(curve));
+        }
+        rect
+    }
+}
+
+impl CompoundPathElement {
+    pub fn bounding_box(&self) -> BoundingRectF64 {
+        match self {
+            CompoundPathElement::PathI32(path) => path.bounding_box(),
+            CompoundPathElement::PathF64(path) => path.bounding_box(),
+            CompoundPathElement::Spline(spline) => spline.bounding_box(),
+        }
+    }
+}
+
+impl CompoundPath {
+    /// Tight bounding box of all elements
+    pub fn bounding_box(&self) -> BoundingRectF64 {
+        let mut rect = BoundingRectF64::default();
+        for path in self.iter() {
+            rect.merge(path.bounding_box());
+        }
+        rect
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cubic_tight_bounds() {
+        // symmetric arch; the control points reach y = -4, the curve only -3
+        let curve = [
+            PointF64::new(0.0, 0.0),
+            PointF64::new(0.0, -4.0),
+            PointF64::new(4.0, -4.0),
+            PointF64::new(4.0, 0.0),
+        ];
+        let rect = cubic_bounding_box(&curve);
+        assert_eq!(rect.left_top, PointF64::new(0.0, -3.0));
+        assert_eq!(rect.right_bottom, PointF64::new(4.0, 0.0));
+
+        let mut spline = Spline::new(curve[0]);
+        spline.add(curve[1], curve[2], curve[3]);
+        let mut compound = CompoundPath::new();
+        compound.add_spline(spline);
+        compound.add_path_f64(Path::from_points(vec![PointF64::new(-1.0, 1.0)]));
+        let rect = compound.bounding_box();
+        assert_eq!(rect.left_top, PointF64::new(-1.0, -3.0));
+        assert_eq!(rect.right_bottom, PointF64::new(4.0, 1.0));
+        assert!(CompoundPath::new().bounding_box().is_empty());
+    }
+}
@@ -0,0 +1,219 @@
+use std::f64::consts::PI;
+use crate::{CompoundPath, CompoundPathElement, PathF64, PointF64};
+use crate::fmath::{atan2, cos, sin};
+
+/// Tolerances of `PathF64::beautify`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct BeautifyConfig {
+    /// Edges whose directions differ by less than this (radians) are made parallel,
+    /// and by less than this from 90 degrees are made perpendicular
+    pub angle_tolerance: f64,
+    /// Edges whose lengths differ by less than this fraction are made equal
+    pub length_tolerance: f64,
+}
+
+impl Default for BeautifyConfig {
+    fn default() -> Self {
+        Self {
+            angle_tolerance: 5.0_f64.to_radians(),
+            length_tolerance: 0.05,
+        }
+    }
+}
+
+impl PathF64 {
+    /// Regularizes a closed polygon (first point repeated at the end): near parallel edges are made parallel,
+    /// near right angles to the longest edge are made exact, and near equal edge lengths are equalized.
+    ///
+    /// Directions are kept exact; lengths are then adjusted as little as possible for the polygon to close,
+    /// and the result is placed at the same vertex centroid. Returns a copy if the polygon cannot be regularized.
+    pub fn beautify(&self, config: &BeautifyConfig) -> PathF64 {
+        let mut points = self.path.clone();
+        points.dedup();
+        let n = points.len().saturating_sub(1);
+        if n < 3 || points[0] != points[n] {
+            return self.clone();
+        }
+        let edges: Vec<PointF64> = points.windows(2).map(|w| w[1] - w[0]).collect();
+        let lengths: Vec<f64> = edges.iter().map(|e| e.norm()).collect();
+
+        let directions = snap_directions(&edges, &lengths, config.angle_tolerance);
+        let lengths = equalize_lengths(&lengths, config.length_tolerance);
+        let lengths = match close_polygon(&directions, &lengths) {
+            Some(lengths) => lengths,
+            None => return self.clone(),
+        };
+
+        let mut result = Vec::with_capacity(n + 1);
+        let mut p = PointF64::default();
+        for (d, l) in directions.iter().zip(lengths.iter()) {
+            result.push(p);
+            p += *d * *l;
+        }
+        let shift = points[0..n].iter().fold(PointF64::default(), |a, &b| a + b) / n as f64 -
+            result.iter().fold(PointF64::default(), |a, &b| a + b) / n as f64;
+        for p in result.iter_mut() {
+            *p += shift;
+        }
+        result.push(result[0]);
+        PathF64::from_points(result)
+    }
+}
+
+impl CompoundPath {
+    /// Applies `PathF64::beautify` to every path; integer paths are converted to `PathF64`.
+    /// Splines are left untouched.
+    pub fn beautify(&self, config: &BeautifyConfig) -> CompoundPath {
+        CompoundPath {
+            paths: self.paths.iter().map(|path| {
+                match path {
+                    CompoundPathElement::PathI32(path) => CompoundPathElement::PathF64(
+                        path.to_path_f64().beautify(config)
+                    ),
+                    CompoundPathElement::PathF64(path) => CompoundPathElement::PathF64(path.beautify(config)),
+                    CompoundPathElement::Spline(spline) => CompoundPathElement::Spline(spline.clone()),
+                }
+            }).collect()
+        }
+    }
+}
+
+/// Difference of two undirected angles, in [0, PI / 2]
+fn line_angle_diff(a: f64, b: f64) -> f64 {
+    let d = (a - b).rem_euclid(PI);
+    d.min(PI - d)
+}
+
+/// Unit direction of each edge, after grouping near parallel edges and squaring groups to the longest edge
+fn snap_directions(edges: &[PointF64], lengths: &[f64], tolerance: f64) -> Vec<PointF64> {
+    let angles: Vec<f64> = edges.iter().map(|e| atan2(e.y, e.x)).collect();
+    let mut order: Vec<usize> = (0..edges.len()).collect();
+    order.sort_by(|&a, &b| lengths[b].partial_cmp(&lengths[a]).unwrap());
+
+    // groups of parallel edges: (sum of length weighted doubled angle vectors, line angle).
+    // doubling maps opposite directions onto each other
+    let mut groups: Vec<(PointF64, f64)> = Vec::new();
+    let mut group_of = vec![0; edges.len()];
+    for &i in order.iter() {
+        let line = angles[i].rem_euclid(PI);
+        let found = groups.iter().position(|&(_, angle)| line_angle_diff(angle, line) < tolerance);
+        let g = match found {
+            Some(g) => g,
+            None => {
+                groups.push((PointF64::default(), line));
+                groups.len() - 1
+            },
+        };
+        let (sum, _) = &mut groups[g];
+        *sum += PointF64::new(cos(2.0 * line), sin(2.0 * line)) * lengths[i];
+        groups[g].1 = (atan2(sum.y, sum.x) / 2.0).rem_euclid(PI);
+        group_of[i] = g;
+    }
+
+    // the group of the longest edge is the reference
+    let reference = groups[0].1;
+    for group in groups.iter_mut() {
+        if line_angle_diff(group.1, reference + PI / 2.0) < tolerance {
+            group.1 = reference + PI / 2.0;
+        } else if line_angle_diff(group.1, reference) < tolerance {
+            group.1 = reference;
+        }
+    }
+
+    (0..edges.len()).map(|i| {
+        let line = groups[group_of[i]].1;
+        // keep the original orientation along the line
+        let angle = if cos(angles[i] - line) >= 0.0 { line } else { line + PI };
+        PointF64::new(cos(angle), sin(angle))
+    }).collect()
+}
+
+/// Replaces runs of near equal lengths (in sorted order) by their mean
+fn equalize_lengths(lengths: &[f64], tolerance: f64) -> Vec<f64> {
+    let mut order: Vec<usize> = (0..lengths.len()).collect();
+    order.sort_by(|&a, &b| lengths[a].partial_cmp(&lengths[b]).unwrap());
+    let mut result = lengths.to_vec();
+    let mut start = 0;
+    while start < order.len() {
+        let first = lengths[order[start]];
+        let mut end = start + 1;
+        while end < order.len() && lengths[order[end]] <= first * (1.0 + tolerance) {
+            end += 1;
+        }
+        let mean = order[start..end].iter().map(|&i| lengths[i]).sum::<f64>() / (end - start) as f64;
+        for &i in order[start..end].iter() {
+            result[i] = mean;
+        }
+        start = end;
+    }
+    result
+}
+
+/// The lengths closest to `lengths` (least squares) for which the edges along `directions` sum to zero.
+/// `None` if the directions do not span the plane or an edge would vanish.
+fn close_polygon(directions: &[PointF64], lengths: &[f64]) -> Option<Vec<f64>> {
+    let residual = directions.iter().zip(lengths.iter()).fold(PointF64::default(), |a, (&d, &l)| a + d * l);
+    // solve (D D^T) m = residual, with D the 2 by n matrix of directions
+    let (mut a, mut b, mut c) = (0.0, 0.0, 0.0);
+    for d in directions.iter() {
+        a += d.x * d.x;
+        b += d.x * d.y;
+        c += d.y * d.y;
+    }
+    let det = a * c - b * b;
+    if det.abs() < 1e-9 {
+        return None;
+    }
+    let m = PointF64::new(
+        (c * residual.x - b * residual.y) / det,
+        (a * residual.y - b * residual.x) / det,
+    );
+    let result: Vec<f64> = directions.iter().zip(lengths.iter())
+        .map(|(d, &l)| l - (d.x * m.x + d.y * m.y))
+        .collect();
+    if result.iter().any(|&l| l <= 0.0) {
+        return None;
+    }
+    Some(result)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn beautify_square() {
+        let path = PathF64::from_points(vec![
+            PointF64::new(0.0, 0.0),
+            PointF64::new(10.1, 0.2),
+            PointF64::new(10.0, 9.9),
+            PointF64::new(-0.2, 10.2),
+            PointF64::new(0.0, 0.0),
+        ]);
+        let result = path.beautify(&BeautifyConfig::default());
+        assert_eq!(result.len(), 5);
+        let edges: Vec<PointF64> = result.path.windows(2).map(|w| w[1] - w[0]).collect();
+        for i in 0..4 {
+            let (a, b) = (edges[i], edges[(i + 1) % 4]);
+            // square corners and equal sides
+            assert!((a.x * b.x + a.y * b.y).abs() < 1e-9);
+            assert!((a.norm() - b.norm()).abs() < 1e-9);
+        }
+        assert!((result.path[0] - result.path[4]).norm() < 1e-9);
+    }
+
+    #[test]
+    fn beautify_keeps_distinct_features() {
+        // a right triangle with legs of clearly different lengths
+        let path = PathF64::from_points(vec![
+            PointF64::new(0.0, 0.0),
+            PointF64::new(8.0, 0.0),
+            PointF64::new(0.0, 4.0),
+            PointF64::new(0.0, 0.0),
+        ]);
+        let result = path.beautify(&BeautifyConfig::default());
+        for (a, b) in result.path.iter().zip(path.path.iter()) {
+            assert!((*a - *b).norm() < 1e-9);
+        }
+    }
+}
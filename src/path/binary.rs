@@ -0,0 +1,267 @@
+use crate::{CompoundPath, CompoundPathElement, PathF64, PathI32, PointF64, PointI32, Spline};
+
+/// Binary format of `CompoundPath`:
+///
+/// + header: `MAGIC`, `VERSION`, precision (`NO_PRECISION` for lossless)
+/// + number of elements, then each element as a tag, the number of points and the points
+///
+/// Integers are LEB128 varints; coordinates are stored as zigzag encoded deltas from the previous point.
+/// Float coordinates are rounded to `precision` decimal places, or stored as raw `f64` in lossless mode.
+const MAGIC: &[u8; 3] = b"VCP";
+const VERSION: u8 = 1;
+const NO_PRECISION: u8 = 0xFF;
+
+const TAG_PATH_I32: u8 = 0;
+const TAG_PATH_F64: u8 = 1;
+const TAG_SPLINE: u8 = 2;
+
+impl CompoundPath {
+    /// Serializes into a compact binary format. `precision` is the number of decimal places kept
+    /// for float coordinates (as in `to_svg_string`); `None` keeps them exactly.
+    pub fn to_bytes(&self, precision: Option<u32>) -> Vec<u8> {
+        let mut writer = Writer { bytes: Vec::new(), scale: precision.map(|p| 10f64.powi(p as i32)) };
+        writer.bytes.extend_from_slice(MAGIC);
+        writer.bytes.push(VERSION);
+        writer.bytes.push(precision.map_or(NO_PRECISION, |p| {
+            assert!(p < NO_PRECISION as u32);
+            p as u8
+        }));
+        writer.varint(self.paths.len() as u64);
+        for element in self.iter() {
+            match element {
+                CompoundPathElement::PathI32(path) => {
+                    writer.bytes.push(TAG_PATH_I32);
+                    writer.varint(path.len() as u64);
+                    let mut prev = PointI32::default();
+                    for &p in path.iter() {
+                        writer.signed((p.x - prev.x) as i64);
+                        writer.signed((p.y - prev.y) as i64);
+                        prev = p;
+                    }
+                },
+                CompoundPathElement::PathF64(path) => {
+                    writer.bytes.push(TAG_PATH_F64);
+                    writer.points_f64(&path.path);
+                },
+                CompoundPathElement::Spline(spline) => {
+                    writer.bytes.push(TAG_SPLINE);
+                    writer.points_f64(&spline.points);
+                },
+            }
+        }
+        writer.bytes
+    }
+
+    /// Deserializes from the output of `to_bytes`; returns `None` if the data is malformed
+    /// or of an unsupported version
+    pub fn from_bytes(bytes: &[u8]) -> Option<Self> {
+        if bytes.len() < 5 || &bytes[0..3] != MAGIC || bytes[3] != VERSION {
+            return None;
+        }
+        let scale = if bytes[4] == NO_PRECISION { None } else { Some(10f64.powi(bytes[4] as i32)) };
+        let mut reader = Reader { bytes, pos: 5, scale };
+        let count = reader.varint()?;
+        let mut paths = CompoundPath::new();
+        for _ in 0..count {
+            let tag = reader.byte()?;
+            let len = reader.varint()? as usize;
+            // every point takes at least 2 bytes
+            if len > bytes.len() {
+                return None;
+            }
+            match tag {
+                TAG_PATH_I32 => {
+                    let mut path = PathI32::new();
+                    let mut prev = PointI32::default();
+                    for _ in 0..len {
+                        prev.x = prev.x.checked_add(i32::try_from(reader.signed()?).ok()?)?;
+                        prev.y = prev.y.checked_add(i32::try_from(reader.signed()?).ok()?)?;
+                        path.add(prev);
+                    }
+                    paths.add_path_i32(path);
+                },
+                TAG_PATH_F64 => paths.add_path_f64(PathF64::from_points(reader.points_f64(len)?)),
+                TAG_SPLINE => paths.add_spline(Spline { points: reader.points_f64(len)? }),
+                _ => return None,
+            }
+        }
+        if reader.pos != bytes.len() {
+            return None;
+        }
+        Some(paths)
+    }
+}
+
+struct Writer {
+    bytes: Vec<u8>,
+    scale: Option<f64>,
+}
+
+impl Writer {
+    fn varint(&mut self, mut v: u64) {
+        while v >= 0x80 {
+            self.bytes.push((v as u8) | 0x80);
+            v >>= 7;
+        }
+        self.bytes.push(v as u8);
+    }
+
+    fn signed(&mut self, v: i64) {
+        self.varint(((v << 1) ^ (v >> 63)) as u64);
+    }
+
+    fn points_f64(&mut self, points: &[PointF64]) {
+        self.varint(points.len() as u64);
+        match self.scale {
+            Some(scale) => {
+                let (mut px, mut py) = (0, 0);
+                for p in points {
+                    let (x, y) = ((p.x * scale).round() as i64, (p.y * scale).round() as i64);
+                    self.signed(x - px);
+                    self.signed(y - py);
+                    (px, py) = (x, y);
+                }
+            },
+            None => {
+                for p in points {
+                    self.bytes.extend_from_slice(&p.x.to_le_bytes());
+                    self.bytes.extend_from_slice(&p.y.to_le_bytes());
+                }
+            },
+        }
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    scale: Option<f64>,
+}
+
+impl Reader<'_> {
+    fn byte(&mut self) -> Option<u8> {
+        let b = *self.bytes.get(self.pos)?;
+        self.pos += 1;
+        Some(b)
+    }
+
+    fn varint(&mut self) -> Option<u64> {
+        let mut v = 0u64;
+        for shift in (0..64).step_by(7) {
+            let b = self.byte()?;
+            v |= ((b & 0x7F) as u64) << shift;
+            if b & 0x80 == 0 {
+                return Some(v);
+            }
+        }
+        None
+    }
+
+    fn signed(&mut self) -> Option<i64> {
+        let v = self.varint()?;
+        Some(((v >> 1) as i64) ^ -((v & 1) as i64))
+    }
+
+    fn f64(&mut self) -> Option<f64> {
+        let bytes = self.bytes.get(self.pos..self.pos + 8)?;
+        self.pos += 8;
+        Some(f64::from_le_bytes(bytes.try_into().unwrap()))
+    }
+
+    fn points_f64(&mut self, len: usize) -> Option<Vec<PointF64>> {
+        let mut points = Vec::with_capacity(len);
+        match self.scale {
+            Some(scale) => {
+                let (mut x, mut y) = (0, 0);
+                for _ in 0..len {
+                    x = self.signed()?.checked_add(x)?;
+                    y = self.signed()?.checked_add(y)?;
+                    points.push(PointF64::new(x as f64 / scale, y as f64 / scale));
+                }
+            },
+            None => {
+                for _ in 0..len {
+                    points.push(PointF64::new(self.f64()?, self.f64()?));
+                }
+            },
+        }
+        Some(points)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sample() -> CompoundPath {
+        let mut paths = CompoundPath::new();
+        paths.add_path_i32(PathI32::from_points(vec![
+            PointI32::new(100, 100), PointI32::new(104, 100), PointI32::new(104, 97), PointI32::new(100, 100),
+        ]));
+        paths.add_path_f64(PathF64::from_points(vec![PointF64::new(1.25, -3.5), PointF64::new(2.0, 0.125)]));
+        let mut spline = Spline::new(PointF64::new(0.1, 0.2));
+        spline.add(PointF64::new(1.0, 1.0), PointF64::new(2.0, 1.5), PointF64::new(3.333, 0.0));
+        paths.add_spline(spline);
+        paths
+    }
+
+    fn all_points(paths: &CompoundPath) -> Vec<(f64, f64)> {
+        paths.iter().flat_map(|e| match e {
+            CompoundPathElement::PathI32(p) => p.iter().map(|p| (p.x as f64, p.y as f64)).collect::<Vec<_>>(),
+            CompoundPathElement::PathF64(p) => p.iter().map(|p| (p.x, p.y)).collect(),
+            CompoundPathElement::Spline(s) => s.iter().map(|p| (p.x, p.y)).collect(),
+        }).collect()
+    }
+
+    #[test]
+    fn binary_round_trip_lossless() {
+        let paths = sample();
+        let bytes = paths.to_bytes(None);
+        let decoded = CompoundPath::from_bytes(&bytes).unwrap();
+        assert_eq!(all_points(&decoded), all_points(&paths));
+        assert!(matches!(decoded.paths[0], CompoundPathElement::PathI32(_)));
+        assert!(matches!(decoded.paths[2], CompoundPathElement::Spline(_)));
+    }
+
+    #[test]
+    fn binary_round_trip_quantized() {
+        let paths = sample();
+        let bytes = paths.to_bytes(Some(2));
+        assert!(bytes.len() < paths.to_bytes(None).len() / 2);
+        let decoded = CompoundPath::from_bytes(&bytes).unwrap();
+        for (a, b) in all_points(&decoded).iter().zip(all_points(&paths).iter()) {
+            assert!((a.0 - b.0).abs() <= 0.0051 && (a.1 - b.1).abs() <= 0.0051);
+        }
+        assert!(CompoundPath::from_bytes(&bytes[..bytes.len() - 1]).is_none());
+        assert!(CompoundPath::from_bytes(b"VCP\x02\xFF\x00").is_none());
+    }
+
+    #[test]
+    fn binary_overflow() {
+        let header = |tag: u8, precision: u8| {
+            let mut writer = Writer { bytes: MAGIC.to_vec(), scale: None };
+            writer.bytes.extend_from_slice(&[VERSION, precision]);
+            writer.varint(1);
+            writer.bytes.push(tag);
+            writer.varint(2);
+            writer
+        };
+        let mut writer = header(TAG_PATH_I32, NO_PRECISION);
+        for delta in [i32::MAX as i64, 0, 1, 0] {
+            writer.signed(delta);
+        }
+        assert!(CompoundPath::from_bytes(&writer.bytes).is_none());
+
+        let mut writer = header(TAG_PATH_I32, NO_PRECISION);
+        for delta in [i32::MAX as i64 + 1, 0, 0, 0] {
+            writer.signed(delta);
+        }
+        assert!(CompoundPath::from_bytes(&writer.bytes).is_none());
+
+        let mut writer = header(TAG_PATH_F64, 0);
+        for delta in [i64::MAX, 0, 1, 0] {
+            writer.signed(delta);
+        }
+        assert!(CompoundPath::from_bytes(&writer.bytes).is_none());
+    }
+}
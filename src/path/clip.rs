@@ -0,0 +1,129 @@
+use crate::{BoundingRect, PathF64, PointF64};
+
+/// Clips the segment `a`-`b` to `rect` (the closed region `[left, right] x [top, bottom]`) by Liang–Barsky;
+/// None if it lies entirely outside
+pub fn clip_segment(a: PointF64, b: PointF64, rect: &BoundingRect) -> Option<(PointF64, PointF64)> {
+    let d = b - a;
+    let (mut t0, mut t1) = (0.0f64, 1.0f64);
+    // p t <= q for each edge
+    let edges = [
+        (-d.x, a.x - rect.left as f64),
+        (d.x, rect.right as f64 - a.x),
+        (-d.y, a.y - rect.top as f64),
+        (d.y, rect.bottom as f64 - a.y),
+    ];
+    for (p, q) in edges {
+        if p == 0.0 {
+            if q < 0.0 {
+                // parallel to and outside of this edge
+                return None;
+            }
+        } else {
+            let t = q / p;
+            if p < 0.0 {
+                t0 = t0.max(t);
+            } else {
+                t1 = t1.min(t);
+            }
+            if t0 > t1 {
+                return None;
+            }
+        }
+    }
+    Some((a + d * t0, a + d * t1))
+}
+
+/// Clips the polygon through `points` (implicitly closed) to `rect` by Sutherland–Hodgman.
+/// The result may have degenerate edges along the rect where a concave polygon leaves and re-enters it.
+pub fn clip_polygon(points: &[PointF64], rect: &BoundingRect) -> Vec<PointF64> {
+    let (left, top, right, bottom) = (rect.left as f64, rect.top as f64, rect.right as f64, rect.bottom as f64);
+    // signed distance inside each edge, non-negative if inside
+    let edges: [&dyn Fn(PointF64) -> f64; 4] = [
+        &|p| p.x - left,
+        &|p| right - p.x,
+        &|p| p.y - top,
+        &|p| bottom - p.y,
+    ];
+    let mut output = points.to_vec();
+    for inside in edges {
+        let input = std::mem::take(&mut output);
+        for (i, &current) in input.iter().enumerate() {
+            let previous = input[(i + input.len() - 1) % input.len()];
+            let (dc, dp) = (inside(current), inside(previous));
+            if (dc >= 0.0) != (dp >= 0.0) {
+                output.push(previous + (current - previous) * (dp / (dp - dc)));
+            }
+            if dc >= 0.0 {
+                output.push(current);
+            }
+        }
+    }
+    output
+}
+
+impl PathF64 {
+    /// The pieces of this open polyline inside `rect`, see `clip_segment`
+    pub fn clip_polyline(&self, rect: &BoundingRect) -> Vec<PathF64> {
+        let mut pieces: Vec<PathF64> = Vec::new();
+        let mut current = PathF64::new();
+        for i in 1..self.len() {
+            match clip_segment(self[i - 1], self[i], rect) {
+                Some((a, b)) => {
+                    if current.is_empty() {
+                        current.add(a);
+                    }
+                    current.add(b);
+                    if b != self[i] {
+                        // leaves the rect
+                        pieces.push(std::mem::take(&mut current));
+                    }
+                },
+                None => if !current.is_empty() {
+                    pieces.push(std::mem::take(&mut current));
+                },
+            }
+        }
+        if !current.is_empty() {
+            pieces.push(current);
+        }
+        pieces
+    }
+
+    /// This closed path as a polygon clipped to `rect`, see `clip_polygon`
+    pub fn clip_polygon(&self, rect: &BoundingRect) -> PathF64 {
+        let mut points: Vec<PointF64> = self.iter().copied().collect();
+        if points.len() > 1 && points[0] == points[points.len() - 1] {
+            points.pop();
+        }
+        PathF64::from_points(clip_polygon(&points, rect))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn clip_to_rect() {
+        let rect = BoundingRect::new_x_y_w_h(0, 0, 10, 10);
+        let p = |x: f64, y: f64| PointF64::new(x, y);
+        assert_eq!(clip_segment(p(-5.0, 5.0), p(15.0, 5.0), &rect), Some((p(0.0, 5.0), p(10.0, 5.0))));
+        assert_eq!(clip_segment(p(2.0, 2.0), p(4.0, 4.0), &rect), Some((p(2.0, 2.0), p(4.0, 4.0))));
+        assert_eq!(clip_segment(p(-5.0, -1.0), p(15.0, -1.0), &rect), None);
+        assert_eq!(clip_segment(p(-5.0, 4.0), p(4.0, -5.0), &rect), None);
+
+        // a triangle poking out of the right edge
+        let clipped = clip_polygon(&[p(5.0, 2.0), p(15.0, 5.0), p(5.0, 8.0)], &rect);
+        assert_eq!(clipped.len(), 4);
+        assert!(clipped.iter().all(|q| q.x <= 10.0));
+        assert!(clipped.contains(&p(10.0, 3.5)) && clipped.contains(&p(10.0, 6.5)));
+        assert!(clip_polygon(&[p(20.0, 20.0), p(30.0, 20.0), p(30.0, 30.0)], &rect).is_empty());
+
+        // a zigzag leaving and re-entering through the top
+        let zigzag = PathF64::from_points(vec![p(1.0, 1.0), p(3.0, -1.0), p(5.0, 1.0), p(7.0, 5.0)]);
+        let pieces = zigzag.clip_polyline(&rect);
+        assert_eq!(pieces.len(), 2);
+        assert_eq!(pieces[0].iter().copied().collect::<Vec<_>>(), [p(1.0, 1.0), p(2.0, 0.0)]);
+        assert_eq!(pieces[1].iter().copied().collect::<Vec<_>>(), [p(4.0, 0.0), p(5.0, 1.0), p(7.0, 5.0)]);
+    }
+}
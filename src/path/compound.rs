@@ -119,6 +119,20 @@ impl CompoundPath {
             }).collect()
         }
     }
+
+    /// Applies `f` to every path, for refinements of the traced outline; integer paths are converted to `PathF64`.
+    /// Splines are left untouched, as refinement is meant to happen before curve fitting.
+    pub(super) fn map_paths_f64(&self, f: impl Fn(&PathF64) -> PathF64) -> CompoundPath {
+        CompoundPath {
+            paths: self.paths.iter().map(|path| {
+                match path {
+                    CompoundPathElement::PathI32(path) => CompoundPathElement::PathF64(f(&path.to_path_f64())),
+                    CompoundPathElement::PathF64(path) => CompoundPathElement::PathF64(f(path)),
+                    CompoundPathElement::Spline(spline) => CompoundPathElement::Spline(spline.clone()),
+                }
+            }).collect()
+        }
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,245 @@
+//! Fitting of cubic Bezier curves to points, generic over the float type so that `SplineF32` is fitted
+//! in single precision. A port of `flo_curves::bezier::fit_curve` (whose `Coordinate` is `f64` only),
+//! with the same steps in the same order; `Spline` itself still fits by `flo_curves`.
+
+use num_traits::Float;
+use crate::Point2;
+
+/// Maximum number of iterations to perform when trying to improve the curve fit
+const MAX_ITERATIONS: usize = 4;
+
+/// How far out of the error bounds we can be (as a ratio of the maximum error) and still attempt to fit the curve
+const FIT_ATTEMPT_RATIO: f64 = 4.0;
+
+/// Maximum number of points to fit at once (curves with more points are divided before fitting)
+const MAX_POINTS_TO_FIT: usize = 100;
+
+/// Control points `[start, control 1, control 2, end]` of a cubic Bezier curve
+pub(crate) type Cubic<T> = [Point2<T>; 4];
+
+#[inline]
+fn lit<T: Float>(v: f64) -> T {
+    T::from(v).unwrap()
+}
+
+/// Creates Bezier curves that fit `points` within `max_error`, by the algorithm of Philip J. Schneider (Graphics Gems).
+/// Returns `None` for fewer than 2 points.
+pub(crate) fn fit_curve<T: Float>(points: &[Point2<T>], max_error: T) -> Option<Vec<Cubic<T>>> {
+    if points.len() < 2 {
+        return None;
+    }
+    let mut curves = vec![];
+    let num_blocks = (points.len() - 1) / MAX_POINTS_TO_FIT + 1;
+    for block in 0..num_blocks {
+        let start = block * MAX_POINTS_TO_FIT;
+        let num_points = MAX_POINTS_TO_FIT.min(points.len() - start);
+        // one point left outside of a block is ignored
+        if num_points < 2 {
+            continue;
+        }
+        let block_points = &points[start..start + num_points];
+        let start_tangent = unit_vector(block_points[1] - block_points[0]);
+        let end_tangent = unit_vector(block_points[num_points - 2] - block_points[num_points - 1]);
+        curves.extend(fit_curve_cubic(block_points, start_tangent, end_tangent, max_error));
+    }
+    Some(curves)
+}
+
+fn fit_curve_cubic<T: Float>(points: &[Point2<T>], start_tangent: Point2<T>, end_tangent: Point2<T>, max_error: T) -> Vec<Cubic<T>> {
+    if points.len() <= 2 {
+        return vec![fit_line(points[0], points[1])];
+    }
+
+    let mut chords = chords_for_points(points);
+    let mut curve = generate_bezier(points, &chords, start_tangent, end_tangent);
+    let (mut error, mut split_pos) = max_error_for_curve(points, &chords, &curve);
+
+    // iterate to improve the fit if we are not too far out
+    if error > max_error && error < max_error * lit(FIT_ATTEMPT_RATIO) {
+        for _ in 0..MAX_ITERATIONS {
+            chords = reparameterize(points, &chords, &curve);
+            curve = generate_bezier(points, &chords, start_tangent, end_tangent);
+            (error, split_pos) = max_error_for_curve(points, &chords, &curve);
+            if error <= max_error {
+                break;
+            }
+        }
+    }
+
+    if error <= max_error {
+        vec![curve]
+    } else {
+        // split at the point of the biggest error and fit the two sides
+        let center_tangent = tangent_between(points[split_pos - 1], points[split_pos], points[split_pos + 1]);
+        let mut curves = fit_curve_cubic(&points[0..split_pos + 1], start_tangent, center_tangent, max_error);
+        curves.extend(fit_curve_cubic(&points[split_pos..], center_tangent * lit(-1.0), end_tangent, max_error));
+        curves
+    }
+}
+
+/// A straight line, with the control points about a third of the way from either end
+fn fit_line<T: Float>(p1: Point2<T>, p2: Point2<T>) -> Cubic<T> {
+    let direction = p2 - p1;
+    [p1, p1 + direction * lit(0.33), p1 + direction * lit(0.66), p2]
+}
+
+/// Chord length parameterization, an estimate of `t` of each point on the fitted curve
+fn chords_for_points<T: Float>(points: &[Point2<T>]) -> Vec<T> {
+    let mut distances = Vec::with_capacity(points.len());
+    let mut total = T::zero();
+    distances.push(total);
+    for p in 1..points.len() {
+        total = total + distance(points[p - 1], points[p]);
+        distances.push(total);
+    }
+    for d in distances.iter_mut() {
+        *d = *d / total;
+    }
+    distances
+}
+
+/// Least squares fit of the control points along the end tangents
+fn generate_bezier<T: Float>(points: &[Point2<T>], chords: &[T], start_tangent: Point2<T>, end_tangent: Point2<T>) -> Cubic<T> {
+    let three: T = lit(3.0);
+    let a: Vec<_> = chords.iter().map(|&chord| {
+        let inverse_chord = T::one() - chord;
+        let b1 = three * chord * (inverse_chord * inverse_chord);
+        let b2 = three * chord * chord * inverse_chord;
+        (start_tangent * b1, end_tangent * b2)
+    }).collect();
+
+    let zero = T::zero();
+    let mut c = [[zero, zero], [zero, zero]];
+    let mut x = [zero, zero];
+
+    let first_point = points[0];
+    let last_point = points[points.len() - 1];
+
+    for (i, &point) in points.iter().enumerate() {
+        c[0][0] = c[0][0] + a[i].0.dot(a[i].0);
+        c[0][1] = c[0][1] + a[i].0.dot(a[i].1);
+        c[1][0] = c[0][1];
+        c[1][1] = c[1][1] + a[i].1.dot(a[i].1);
+
+        let chord = chords[i];
+        let inverse_chord = T::one() - chord;
+        let b0 = inverse_chord * inverse_chord * inverse_chord;
+        let b1 = three * chord * (inverse_chord * inverse_chord);
+        let b2 = three * chord * chord * inverse_chord;
+        let b3 = chord * chord * chord;
+
+        let tmp = point - ((first_point * b0) + (first_point * b1) + (last_point * b2) + (last_point * b3));
+
+        x[0] = x[0] + a[i].0.dot(tmp);
+        x[1] = x[1] + a[i].1.dot(tmp);
+    }
+
+    let det_c0_c1 = c[0][0] * c[1][1] - c[1][0] * c[0][1];
+    let det_c0_x = c[0][0] * x[1] - c[1][0] * x[0];
+    let det_x_c1 = x[0] * c[1][1] - x[1] * c[0][1];
+
+    let singular = det_c0_c1.abs() < lit(1.0e-4);
+    let alpha_l = if singular { zero } else { det_x_c1 / det_c0_c1 };
+    let alpha_r = if singular { zero } else { det_c0_x / det_c0_c1 };
+
+    let seg_length = distance(first_point, last_point);
+    let epsilon = lit::<T>(1.0e-6) * seg_length;
+
+    // the Wu/Barsky heuristic, when alpha is negative
+    let (alpha_l, alpha_r) = if alpha_l < epsilon || alpha_r < epsilon {
+        let dist = seg_length / three;
+        (dist, dist)
+    } else {
+        (alpha_l, alpha_r)
+    };
+    [first_point, first_point + start_tangent * alpha_l, last_point + end_tangent * alpha_r, last_point]
+}
+
+/// The maximum distance of the points from the curve at their chords, and the index of that point
+fn max_error_for_curve<T: Float>(points: &[Point2<T>], chords: &[T], curve: &Cubic<T>) -> (T, usize) {
+    let mut biggest_error_squared = T::zero();
+    let mut biggest_error_offset = 0;
+    for (i, (&point, &chord)) in points.iter().zip(chords.iter()).enumerate() {
+        let offset = point - point_at(curve, chord);
+        let error_squared = offset.dot(offset);
+        if error_squared > biggest_error_squared {
+            biggest_error_squared = error_squared;
+            biggest_error_offset = i;
+        }
+    }
+    (biggest_error_squared.sqrt(), biggest_error_offset)
+}
+
+fn tangent_between<T: Float>(p1: Point2<T>, p2: Point2<T>, p3: Point2<T>) -> Point2<T> {
+    let v1 = p1 - p2;
+    let v2 = p2 - p3;
+    unit_vector((v1 + v2) * lit(0.5))
+}
+
+/// Improves the chords by one step of Newton-Raphson
+fn reparameterize<T: Float>(points: &[Point2<T>], chords: &[T], curve: &Cubic<T>) -> Vec<T> {
+    points.iter().zip(chords.iter())
+        .map(|(&point, &chord)| newton_raphson_root_find(curve, point, chord))
+        .collect()
+}
+
+fn newton_raphson_root_find<T: Float>(curve: &Cubic<T>, point: Point2<T>, t: T) -> T {
+    let [start, cp1, cp2, end] = *curve;
+    let qt = point_at(curve, t);
+
+    let (two, three): (T, T) = (lit(2.0), lit(3.0));
+    let qn1 = (cp1 - start) * three;
+    let qn2 = (cp2 - cp1) * three;
+    let qn3 = (end - cp2) * three;
+    let qnn1 = (qn2 - qn1) * two;
+    let qnn2 = (qn3 - qn2) * two;
+
+    // Q'(t) and Q''(t)
+    let qnt = de_casteljau2(t, de_casteljau2(t, qn1, qn2), de_casteljau2(t, qn2, qn3));
+    let qnnt = de_casteljau2(t, qnn1, qnn2);
+
+    let numerator = (qt - point).dot(qnt);
+    let denominator = qnt.dot(qnt) + (qt - point).dot(qnnt);
+    if denominator == T::zero() {
+        t
+    } else {
+        t - numerator / denominator
+    }
+}
+
+/// The point at `t` in the Bernstein basis
+fn point_at<T: Float>(curve: &Cubic<T>, t: T) -> Point2<T> {
+    let [w1, w2, w3, w4] = *curve;
+    let three: T = lit(3.0);
+    let t_squared = t * t;
+    let t_cubed = t_squared * t;
+    let one_minus_t = T::one() - t;
+    let one_minus_t_squared = one_minus_t * one_minus_t;
+    let one_minus_t_cubed = one_minus_t_squared * one_minus_t;
+    w1 * one_minus_t_cubed
+        + w2 * three * one_minus_t_squared * t
+        + w3 * three * one_minus_t * t_squared
+        + w4 * t_cubed
+}
+
+#[inline]
+fn de_casteljau2<T: Float>(t: T, w1: Point2<T>, w2: Point2<T>) -> Point2<T> {
+    w1 * (T::one() - t) + w2 * t
+}
+
+#[inline]
+fn distance<T: Float>(a: Point2<T>, b: Point2<T>) -> T {
+    let offset = a - b;
+    offset.dot(offset).sqrt()
+}
+
+/// `v` scaled to length 1, or zero if `v` is zero
+fn unit_vector<T: Float>(v: Point2<T>) -> Point2<T> {
+    let magnitude = v.dot(v).sqrt();
+    if magnitude == T::zero() {
+        Point2::new(T::zero(), T::zero())
+    } else {
+        v * (T::one() / magnitude)
+    }
+}
+
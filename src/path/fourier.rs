@@ -0,0 +1,144 @@
+use crate::{Complex, Path, PointF64, PointType, fft};
+use crate::fmath::{atan2, cos, sin};
+
+/// Number of points closed contours are resampled to, at least; a power of two
+const MIN_SAMPLES: usize = 64;
+
+/// Fourier descriptors of a closed contour: the Fourier coefficients of its outline (as complex points,
+/// resampled evenly by arc length), normalized for translation (the mean is dropped), scale (`|c1| = 1`),
+/// direction (`|c1| >= |c-1|`), and rotation and start point (`c1` and `c-1` are made real),
+/// so that the same shape traced from different images compares close.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct FourierDescriptors {
+    /// number of harmonics `k` kept on either side
+    pub harmonics: usize,
+    /// `c-k ..= c-1, c1 ..= ck`, as `(re, im)`
+    pub coefficients: Vec<(f64, f64)>,
+}
+
+impl FourierDescriptors {
+    /// Descriptors of `points` (implicitly closed) keeping `harmonics` on either side;
+    /// None if the contour has no length
+    pub fn from_points(points: &[PointF64], harmonics: usize) -> Option<Self> {
+        assert!(harmonics > 0);
+        let samples = resample_closed(points, MIN_SAMPLES.max(4 * harmonics).next_power_of_two())?;
+        let n = samples.len();
+        let mut data: Vec<Complex> = samples.iter().map(|p| Complex::new(p.x, p.y)).collect();
+        fft(&mut data, false);
+        let coefficient = |k: i64| data[k.rem_euclid(n as i64) as usize] * (1.0 / n as f64);
+
+        let mut positive: Vec<Complex> = (1..=harmonics as i64).map(coefficient).collect();
+        let mut negative: Vec<Complex> = (1..=harmonics as i64).map(|k| coefficient(-k)).collect();
+        if negative[0].norm_sqr() > positive[0].norm_sqr() {
+            // traced the other way round
+            std::mem::swap(&mut positive, &mut negative);
+        }
+        let scale = positive[0].norm_sqr().sqrt();
+        if scale < 1e-12 {
+            return None;
+        }
+        // a rotation by phi and a start shift by alpha turn ck by phi + k alpha
+        let phase = |c: Complex| atan2(c.im, c.re);
+        let (p1, q1) = (phase(positive[0]), phase(negative[0]));
+        let (phi, alpha) = ((p1 + q1) / 2.0, (p1 - q1) / 2.0);
+        let normalize = |c: Complex, k: f64| {
+            let angle = -(phi + k * alpha);
+            c * Complex::new(cos(angle), sin(angle)) * (1.0 / scale)
+        };
+        let mut coefficients = Vec::with_capacity(2 * harmonics);
+        for (i, &c) in negative.iter().enumerate().rev() {
+            let c = normalize(c, -(i as f64 + 1.0));
+            coefficients.push((c.re, c.im));
+        }
+        for (i, &c) in positive.iter().enumerate() {
+            let c = normalize(c, i as f64 + 1.0);
+            coefficients.push((c.re, c.im));
+        }
+        Some(Self { harmonics, coefficients })
+    }
+
+    /// Euclidean distance between the coefficients, 0 for the same shape; the normalization leaves
+    /// a half turn of the start point undetermined, so the smaller distance with either is taken.
+    /// Both must keep the same number of harmonics.
+    pub fn distance(&self, other: &Self) -> f64 {
+        assert_eq!(self.harmonics, other.harmonics);
+        let h = self.harmonics as i64;
+        let (mut same, mut flipped) = (0.0, 0.0);
+        for (i, (a, b)) in self.coefficients.iter().zip(other.coefficients.iter()).enumerate() {
+            let k = if (i as i64) < h { i as i64 - h } else { i as i64 - h + 1 };
+            // shifting alpha and phi by half a turn scales ck by (-1)^(k + 1)
+            let sign = if (k + 1) % 2 == 0 { 1.0 } else { -1.0 };
+            same += (a.0 - b.0).powi(2) + (a.1 - b.1).powi(2);
+            flipped += (a.0 - sign * b.0).powi(2) + (a.1 - sign * b.1).powi(2);
+        }
+        same.min(flipped).sqrt()
+    }
+
+    /// Similarity in (0, 1], 1 for the same shape
+    pub fn similarity(&self, other: &Self) -> f64 {
+        1.0 / (1.0 + self.distance(other))
+    }
+}
+
+/// `count` points evenly spaced along the closed polyline through `points`
+fn resample_closed(points: &[PointF64], count: usize) -> Option<Vec<PointF64>> {
+    let mut points = points.to_vec();
+    if points.len() > 1 && points[0] == points[points.len() - 1] {
+        points.pop();
+    }
+    let n = points.len();
+    let segment = |i: usize| (points[i], points[(i + 1) % n]);
+    let lengths: Vec<f64> = (0..n).map(|i| { let (a, b) = segment(i); (b - a).norm() }).collect();
+    let total: f64 = lengths.iter().sum();
+    if n < 2 || total <= 0.0 {
+        return None;
+    }
+    let mut samples = Vec::with_capacity(count);
+    let (mut i, mut start) = (0, 0.0);
+    for s in 0..count {
+        let target = total * s as f64 / count as f64;
+        while i + 1 < n && start + lengths[i] < target {
+            start += lengths[i];
+            i += 1;
+        }
+        let (a, b) = segment(i);
+        let t = if lengths[i] > 0.0 { ((target - start) / lengths[i]).clamp(0.0, 1.0) } else { 0.0 };
+        samples.push(a + (b - a) * t);
+    }
+    Some(samples)
+}
+
+impl<T> Path<T> where T: PointType {
+    /// See `FourierDescriptors`; the path is implicitly closed
+    pub fn fourier_descriptors(&self, harmonics: usize) -> Option<FourierDescriptors> {
+        let points: Vec<PointF64> = self.iter().map(|p| p.to_point_f64()).collect();
+        FourierDescriptors::from_points(&points, harmonics)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PathF64;
+
+    #[test]
+    fn normalized_descriptors() {
+        let polygon = |corners: &[(f64, f64)]| PathF64::from_points(corners.iter().map(|&(x, y)| PointF64::new(x, y)).collect());
+        let l = [(0.0, 0.0), (2.0, 0.0), (2.0, 4.0), (4.0, 4.0), (4.0, 6.0), (0.0, 6.0)];
+        // the same L moved, scaled, turned a quarter, started elsewhere and traced backwards
+        let moved: Vec<(f64, f64)> = l.iter().map(|&(x, y)| (10.0 - 3.0 * y, 5.0 + 3.0 * x)).collect();
+        let mut reordered = moved.clone();
+        reordered.rotate_left(2);
+        reordered.reverse();
+        let square = [(0.0, 0.0), (6.0, 0.0), (6.0, 6.0), (0.0, 6.0)];
+
+        let a = polygon(&l).fourier_descriptors(8).unwrap();
+        assert_eq!(a.coefficients.len(), 16);
+        let b = polygon(&reordered).fourier_descriptors(8).unwrap();
+        assert!(a.distance(&b) < 0.05, "{}", a.distance(&b));
+        let c = polygon(&square).fourier_descriptors(8).unwrap();
+        assert!(a.distance(&c) > 4.0 * a.distance(&b) + 0.1);
+        assert!(a.similarity(&b) > a.similarity(&c));
+        assert!(polygon(&[(1.0, 1.0), (1.0, 1.0)]).fourier_descriptors(8).is_none());
+    }
+}
@@ -0,0 +1,139 @@
+use std::fmt::Write;
+use crate::{CompoundPath, CompoundPathElement, FillRule, NumberFormat, PathF64, PointF64};
+
+/// A polygon with holes; rings are closed (the first point is repeated at the end)
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GeoPolygon {
+    pub exterior: Vec<PointF64>,
+    pub holes: Vec<Vec<PointF64>>,
+}
+
+/// Polygons in the layout of a GeoJSON `MultiPolygon`
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct GeoMultiPolygon {
+    pub polygons: Vec<GeoPolygon>,
+}
+
+impl GeoMultiPolygon {
+    /// Groups the outlines of `path` (e.g. from `Cluster::to_compound_path`) into polygons.
+    ///
+    /// Clockwise paths (in image coordinates) are exteriors and anti-clockwise paths are holes,
+    /// as traced by this crate; each hole goes to the smallest exterior enclosing it.
+    /// Splines are flattened to within `tolerance`.
+    ///
+    /// Coordinates are kept as-is, so that exteriors are counter-clockwise when read with y pointing up
+    /// as required by RFC 7946; map them to geographic coordinates as needed.
+    pub fn from_compound_path(path: &CompoundPath, tolerance: f64) -> Self {
+        let mut exteriors = Vec::new();
+        let mut holes = Vec::new();
+        for element in path.iter() {
+            let mut ring: Vec<PointF64> = match element {
+                CompoundPathElement::PathI32(path) => path.iter().map(|p| p.to_point_f64()).collect(),
+                CompoundPathElement::PathF64(path) => path.path.clone(),
+                CompoundPathElement::Spline(spline) => spline.to_path_f64(tolerance).path,
+            };
+            if ring.len() >= 2 && ring[0] == ring[ring.len() - 1] {
+                ring.pop();
+            }
+            if ring.len() < 3 {
+                continue;
+            }
+            let area = signed_area(&ring);
+            ring.push(ring[0]);
+            if area > 0.0 {
+                exteriors.push((area, ring));
+            } else if area < 0.0 {
+                holes.push(ring);
+            }
+        }
+
+        let mut polygons: Vec<GeoPolygon> = exteriors.iter()
+            .map(|(_, ring)| GeoPolygon { exterior: ring.clone(), holes: vec![] })
+            .collect();
+        for hole in holes {
+            // a point on the hole's edge; it never lies on an exterior
+            let probe = (hole[0] + hole[1]) * 0.5;
+            let owner = exteriors.iter()
+                .enumerate()
+                .filter(|(_, (_, ring))| PathF64::from_points(ring.clone()).contains_point(probe, FillRule::NonZero))
+                .min_by(|a, b| a.1.0.total_cmp(&b.1.0))
+                .map(|(i, _)| i);
+            if let Some(i) = owner {
+                polygons[i].holes.push(hole);
+            }
+        }
+        Self { polygons }
+    }
+
+    /// The GeoJSON geometry object, e.g. `{"type":"MultiPolygon","coordinates":[...]}`
+    pub fn to_geojson_string(&self, precision: Option<u32>) -> String {
+        let mut string = String::from(r#"{"type":"MultiPolygon","coordinates":["#);
+        for (i, polygon) in self.polygons.iter().enumerate() {
+            if i > 0 {
+                string.push(',');
+            }
+            string.push('[');
+            for (j, ring) in std::iter::once(&polygon.exterior).chain(polygon.holes.iter()).enumerate() {
+                if j > 0 {
+                    string.push(',');
+                }
+                string.push('[');
+                for (k, p) in ring.iter().enumerate() {
+                    if k > 0 {
+                        string.push(',');
+                    }
+                    write!(string, "[{},{}]",
+                        f64::number_format(p.x, precision), f64::number_format(p.y, precision)).unwrap();
+                }
+                string.push(']');
+            }
+            string.push(']');
+        }
+        string.push_str("]}");
+        string
+    }
+}
+
+/// Shoelace formula; positive for clockwise rings in image coordinates
+fn signed_area(ring: &[PointF64]) -> f64 {
+    let n = ring.len();
+    (0..n).map(|i| {
+        let (a, b) = (ring[i], ring[(i + 1) % n]);
+        a.x * b.y - b.x * a.y
+    }).sum::<f64>() / 2.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{BinaryImage, PathSimplifyMode, PointI32};
+    use crate::clusters::Cluster;
+
+    #[test]
+    fn geojson_with_hole() {
+        let image = BinaryImage::from_string(&(
+            "*****--\n".to_owned() +
+            "*---*--\n" +
+            "*****-*\n"
+        ));
+        let path = Cluster::image_to_compound_path(
+            &PointI32::new(0, 0), &image, PathSimplifyMode::None, 0.0, 0.0, 0, 0.0
+        );
+        let mut combined = path;
+        combined.append(Cluster::image_to_compound_path(
+            &PointI32::new(6, 2), &BinaryImage::from_string("*\n"), PathSimplifyMode::None, 0.0, 0.0, 0, 0.0
+        ));
+        let geo = GeoMultiPolygon::from_compound_path(&combined, 0.1);
+        assert_eq!(geo.polygons.len(), 2);
+        let ring = &geo.polygons[0];
+        assert_eq!(ring.holes.len(), 1);
+        assert!(signed_area(&ring.exterior[1..]) > 0.0);
+        assert!(signed_area(&ring.holes[0][1..]) < 0.0);
+        assert_eq!(geo.polygons[1].holes.len(), 0);
+        assert_eq!(ring.exterior.first(), ring.exterior.last());
+        assert_eq!(
+            GeoMultiPolygon { polygons: vec![geo.polygons[1].clone()] }.to_geojson_string(None),
+            r#"{"type":"MultiPolygon","coordinates":[[[[6,2],[7,2],[7,3],[6,3],[6,2]]]]}"#
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use crate::{CompoundPath, CompoundPathElement, PathF64, PointF64, Spline};
+
+/// A segment is near axis-aligned if its slope (or inverse slope) is at most this
+const SNAP_SLOPE: f64 = 0.1;
+
+impl PathF64 {
+    /// Pulls near horizontal and near vertical segments onto the nearest grid line,
+    /// where grid lines lie at multiples of `spacing` (1 to snap to pixel edges).
+    ///
+    /// `strength` in [0, 1] is how far a segment may be from a grid line to be snapped,
+    /// as a fraction of half the spacing; 1 snaps every near axis-aligned segment.
+    /// Closed paths are expected to repeat the first point at the end.
+    pub fn snap_to_grid(&self, spacing: f64, strength: f64) -> PathF64 {
+        let segments = (1..self.path.len()).map(|i| (i - 1, i + 1));
+        PathF64::from_points(snap_points(&self.path, segments, spacing, strength))
+    }
+}
+
+impl Spline {
+    /// Like `PathF64::snap_to_grid`, for curves which are straight and near axis-aligned
+    /// (all 4 control points lie on the line)
+    pub fn snap_to_grid(&self, spacing: f64, strength: f64) -> Spline {
+        let segments = (0..self.num_curves()).map(|i| (i * 3, i * 3 + 4));
+        Spline {
+            points: snap_points(&self.points, segments, spacing, strength),
+        }
+    }
+}
+
+impl CompoundPath {
+    /// Applies `snap_to_grid` to every path; integer paths are converted to `PathF64`
+    pub fn snap_to_grid(&self, spacing: f64, strength: f64) -> CompoundPath {
+        CompoundPath {
+            paths: self.paths.iter().map(|path| {
+                match path {
+                    CompoundPathElement::PathI32(path) => CompoundPathElement::PathF64(
+                        path.to_path_f64().snap_to_grid(spacing, strength)
+                    ),
+                    CompoundPathElement::PathF64(path) => CompoundPathElement::PathF64(
+                        path.snap_to_grid(spacing, strength)
+                    ),
+                    CompoundPathElement::Spline(spline) => CompoundPathElement::Spline(
+                        spline.snap_to_grid(spacing, strength)
+                    ),
+                }
+            }).collect()
+        }
+    }
+}
+
+/// Snaps the runs `points[start..end]` given by `segments`. If a point is shared by runs snapping
+/// the same coordinate onto different lines, the first run wins.
+fn snap_points(
+    points: &[PointF64],
+    segments: impl Iterator<Item = (usize, usize)>,
+    spacing: f64,
+    strength: f64,
+) -> Vec<PointF64> {
+    assert!(spacing > 0.0);
+    let tolerance = strength.clamp(0.0, 1.0) * spacing * 0.5;
+    let mut snapped_x: Vec<Option<f64>> = vec![None; points.len()];
+    let mut snapped_y: Vec<Option<f64>> = vec![None; points.len()];
+
+    let snap = |values: &mut dyn Iterator<Item = f64>| -> Option<f64> {
+        let values: Vec<f64> = values.collect();
+        let mean = values.iter().sum::<f64>() / values.len() as f64;
+        let line = (mean / spacing).round() * spacing;
+        if values.iter().all(|v| (v - line).abs() <= tolerance) {
+            Some(line)
+        } else {
+            None
+        }
+    };
+
+    for (start, end) in segments {
+        let run = &points[start..end];
+        let d = run[run.len() - 1] - run[0];
+        let (dx, dy) = (d.x.abs(), d.y.abs());
+        // every point close to the line, and monotonic along it
+        let straight = |along: fn(&PointF64) -> f64, across: fn(&PointF64) -> f64, length: f64| {
+            let direction = along(&run[run.len() - 1]) - along(&run[0]);
+            run.iter().all(|p| (across(p) - across(&run[0])).abs() <= SNAP_SLOPE * length) &&
+                run.windows(2).all(|w| (along(&w[1]) - along(&w[0])) * direction >= 0.0)
+        };
+        let (targets, line) = if dx > 0.0 && dy <= SNAP_SLOPE * dx && straight(|p| p.x, |p| p.y, dx) {
+            (&mut snapped_y, snap(&mut run.iter().map(|p| p.y)))
+        } else if dy > 0.0 && dx <= SNAP_SLOPE * dy && straight(|p| p.y, |p| p.x, dy) {
+            (&mut snapped_x, snap(&mut run.iter().map(|p| p.x)))
+        } else {
+            continue;
+        };
+        if let Some(line) = line {
+            for target in targets[start..end].iter_mut() {
+                target.get_or_insert(line);
+            }
+        }
+    }
+
+    let len = points.len();
+    if len > 1 && points[0] == points[len - 1] {
+        // the repeated end point stays the same as the start point
+        for snapped in [&mut snapped_x, &mut snapped_y] {
+            let merged = snapped[0].or(snapped[len - 1]);
+            snapped[0] = merged;
+            snapped[len - 1] = merged;
+        }
+    }
+
+    points.iter().enumerate().map(|(i, p)| {
+        PointF64::new(snapped_x[i].unwrap_or(p.x), snapped_y[i].unwrap_or(p.y))
+    }).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn snap_to_grid_rectangle() {
+        let path = PathF64::from_points(vec![
+            PointF64::new(1.1, 0.95),
+            PointF64::new(9.02, 1.05),
+            PointF64::new(8.9, 5.1),
+            PointF64::new(1.05, 5.0),
+            PointF64::new(1.1, 0.95),
+        ]);
+        let snapped = path.snap_to_grid(1.0, 0.5);
+        assert_eq!(snapped.path, vec![
+            PointF64::new(1.0, 1.0),
+            PointF64::new(9.0, 1.0),
+            PointF64::new(9.0, 5.0),
+            PointF64::new(1.0, 5.0),
+            PointF64::new(1.0, 1.0),
+        ]);
+        // too far from the grid lines at 0 and 4
+        assert_eq!(path.snap_to_grid(4.0, 0.4).path[1], PointF64::new(9.02, 1.05));
+    }
+
+    #[test]
+    fn snap_to_grid_spline() {
+        let mut spline = Spline::new(PointF64::new(0.0, 2.1));
+        spline.add(PointF64::new(2.0, 2.05), PointF64::new(4.0, 1.95), PointF64::new(6.0, 2.0));
+        spline.add(PointF64::new(7.0, 4.0), PointF64::new(5.0, 6.0), PointF64::new(3.0, 7.0));
+        let snapped = spline.snap_to_grid(1.0, 1.0);
+        assert!(snapped.points[0..4].iter().all(|p| p.y == 2.0));
+        assert_eq!(&snapped.points[4..], &spline.points[4..]);
+    }
+}
@@ -0,0 +1,173 @@
+use crate::{CompoundPath, CompoundPathElement, Path, PointF64, PointType, Spline, distance_to_segment};
+
+/// Maximum deviation from the curve when hit-testing splines, in path units
+const SPLINE_TOLERANCE: f64 = 0.01;
+
+/// How the inside of a (self-intersecting or compound) path is determined
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum FillRule {
+    /// inside if the winding number is non-zero
+    #[default]
+    NonZero,
+    /// inside if the winding number is odd
+    EvenOdd,
+}
+
+impl FillRule {
+    fn is_inside(self, winding: i32) -> bool {
+        match self {
+            FillRule::NonZero => winding != 0,
+            FillRule::EvenOdd => winding % 2 != 0,
+        }
+    }
+}
+
+impl<T> Path<T> where T: PointType {
+    /// Number of times the path (implicitly closed) winds around `p`; positive for clockwise in image coordinates
+    pub fn winding_number(&self, p: PointF64) -> i32 {
+        winding_number(self.iter().map(|q| q.to_point_f64()), p)
+    }
+
+    /// Returns true if `p` is inside the path, which is implicitly closed
+    pub fn contains_point(&self, p: PointF64, rule: FillRule) -> bool {
+        rule.is_inside(self.winding_number(p))
+    }
+
+    /// Distance from `p` to the nearest point on the outline (implicitly closed)
+    pub fn distance_to_point(&self, p: PointF64) -> f64 {
+        distance_to_outline(self.iter().map(|q| q.to_point_f64()), p)
+    }
+}
+
+impl Spline {
+    /// Number of times the spline (implicitly closed) winds around `p`
+    pub fn winding_number(&self, p: PointF64) -> i32 {
+        self.to_path_f64(SPLINE_TOLERANCE).winding_number(p)
+    }
+
+    /// Returns true if `p` is inside the spline, which is implicitly closed
+    pub fn contains_point(&self, p: PointF64, rule: FillRule) -> bool {
+        rule.is_inside(self.winding_number(p))
+    }
+
+    /// Distance from `p` to the nearest point on the curves
+    pub fn distance_to_point(&self, p: PointF64) -> f64 {
+        self.to_path_f64(SPLINE_TOLERANCE).distance_to_point(p)
+    }
+}
+
+impl CompoundPathElement {
+    pub fn winding_number(&self, p: PointF64) -> i32 {
+        match self {
+            CompoundPathElement::PathI32(path) => path.winding_number(p),
+            CompoundPathElement::PathF64(path) => path.winding_number(p),
+            CompoundPathElement::Spline(spline) => spline.winding_number(p),
+        }
+    }
+
+    pub fn distance_to_point(&self, p: PointF64) -> f64 {
+        match self {
+            CompoundPathElement::PathI32(path) => path.distance_to_point(p),
+            CompoundPathElement::PathF64(path) => path.distance_to_point(p),
+            CompoundPathElement::Spline(spline) => spline.distance_to_point(p),
+        }
+    }
+}
+
+impl CompoundPath {
+    /// Sum of the winding numbers of all elements
+    pub fn winding_number(&self, p: PointF64) -> i32 {
+        self.iter().map(|path| path.winding_number(p)).sum()
+    }
+
+    /// Returns true if `p` is inside the shape; holes are paths of opposite direction (`NonZero`)
+    /// or simply nested paths (`EvenOdd`)
+    pub fn contains_point(&self, p: PointF64, rule: FillRule) -> bool {
+        rule.is_inside(self.winding_number(p))
+    }
+
+    /// Distance from `p` to the nearest outline of any element; `f64::MAX` if empty
+    pub fn distance_to_point(&self, p: PointF64) -> f64 {
+        self.iter().map(|path| path.distance_to_point(p)).fold(f64::MAX, f64::min)
+    }
+}
+
+fn closed_edges(points: impl Iterator<Item = PointF64> + Clone) -> impl Iterator<Item = (PointF64, PointF64)> {
+    let first = points.clone().next();
+    let next = points.clone().skip(1).chain(first);
+    points.zip(next)
+}
+
+fn winding_number(points: impl Iterator<Item = PointF64> + Clone, p: PointF64) -> i32 {
+    let mut winding = 0;
+    for (a, b) in closed_edges(points) {
+        // which side of a -> b is p on
+        let side = (b.x - a.x) * (p.y - a.y) - (p.x - a.x) * (b.y - a.y);
+        if a.y <= p.y {
+            if b.y > p.y && side > 0.0 {
+                winding += 1;
+            }
+        } else if b.y <= p.y && side < 0.0 {
+            winding -= 1;
+        }
+    }
+    winding
+}
+
+fn distance_to_outline(points: impl Iterator<Item = PointF64> + Clone, p: PointF64) -> f64 {
+    closed_edges(points)
+        .map(|(a, b)| distance_to_segment(p, a, b))
+        .fold(f64::MAX, f64::min)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{PathF64, PathI32, PointI32};
+
+    fn square(left: i32, top: i32, size: i32, clockwise: bool) -> PathI32 {
+        let mut corners = vec![
+            PointI32::new(left, top),
+            PointI32::new(left + size, top),
+            PointI32::new(left + size, top + size),
+            PointI32::new(left, top + size),
+        ];
+        if !clockwise {
+            corners.reverse();
+        }
+        PathI32::from_points(corners)
+    }
+
+    #[test]
+    fn path_contains_and_distance() {
+        let path = square(0, 0, 10, true);
+        assert_eq!(path.winding_number(PointF64::new(5.0, 5.0)), 1);
+        assert!(path.contains_point(PointF64::new(5.0, 5.0), FillRule::NonZero));
+        assert!(!path.contains_point(PointF64::new(15.0, 5.0), FillRule::EvenOdd));
+        assert_eq!(path.distance_to_point(PointF64::new(5.0, 3.0)), 3.0);
+        assert_eq!(path.distance_to_point(PointF64::new(13.0, 14.0)), 5.0);
+        let reversed = PathF64::from_points(square(0, 0, 10, false).iter().map(|p| p.to_point_f64()).collect());
+        assert_eq!(reversed.winding_number(PointF64::new(5.0, 5.0)), -1);
+    }
+
+    #[test]
+    fn compound_fill_rules() {
+        let mut compound = CompoundPath::new();
+        compound.add_path_i32(square(0, 0, 10, true));
+        compound.add_path_i32(square(3, 3, 4, true));
+        let center = PointF64::new(5.0, 5.0);
+        assert!(compound.contains_point(center, FillRule::NonZero));
+        assert!(!compound.contains_point(center, FillRule::EvenOdd));
+        assert!(compound.contains_point(PointF64::new(1.0, 1.0), FillRule::EvenOdd));
+        assert_eq!(compound.distance_to_point(center), 2.0);
+    }
+
+    #[test]
+    fn spline_contains() {
+        let mut spline = Spline::new(PointF64::new(0.0, 0.0));
+        spline.add(PointF64::new(10.0, 0.0), PointF64::new(10.0, 10.0), PointF64::new(0.0, 10.0));
+        assert!(spline.contains_point(PointF64::new(3.0, 5.0), FillRule::NonZero));
+        assert!(!spline.contains_point(PointF64::new(9.0, 0.5), FillRule::NonZero));
+        assert!((spline.distance_to_point(PointF64::new(-1.0, 5.0)) - 1.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+use crate::{Image, PathF64, PointF64, ScalarPixel};
+
+/// Extracts the iso-contours of `image` at value `iso` by marching squares.
+///
+/// Samples are taken at pixel centers, i.e. pixel (x, y) is at (x + 0.5, y + 0.5), and contour points are
+/// linearly interpolated along cell edges. Outside of the image is regarded as below `iso`, so every contour
+/// is closed (the first point is repeated at the end); contours touching the border run along the image edge.
+/// Saddle cells are resolved by the average of their four corners.
+pub fn marching_squares<I>(image: &I, iso: f64) -> Vec<PathF64>
+    where I: Image, I::Pixel: ScalarPixel {
+    // the grid is padded by one sample on each side
+    let (gw, gh) = (image.width() + 2, image.height() + 2);
+    let sample = |x: usize, y: usize| -> Option<f64> {
+        if x == 0 || y == 0 || x == gw - 1 || y == gh - 1 {
+            None
+        } else {
+            Some(image.pixel(x - 1, y - 1).scalar())
+        }
+    };
+    let inside = |v: Option<f64>| v.is_some_and(|v| v >= iso);
+
+    // an edge is identified by its first sample and whether it is vertical
+    let point_on = |(x, y, vertical): (usize, usize, bool)| -> PointF64 {
+        let (x1, y1) = if vertical { (x, y + 1) } else { (x + 1, y) };
+        let t = match (sample(x, y), sample(x1, y1)) {
+            (Some(a), Some(b)) if a != b => ((iso - a) / (b - a)).clamp(0.0, 1.0),
+            (None, Some(_)) => 0.0,
+            (Some(_), None) => 1.0,
+            _ => 0.5,
+        };
+        // a padding sample is moved onto the image border
+        let pos = |i: usize| i as f64 - 0.5;
+        let (ax, ay) = (pos(x).max(0.0), pos(y).max(0.0));
+        let (bx, by) = (pos(x1).min(image.width() as f64), pos(y1).min(image.height() as f64));
+        PointF64::new(ax + (bx - ax) * t, ay + (by - ay) * t)
+    };
+
+    let mut segments: Vec<[(usize, usize, bool); 2]> = Vec::new();
+    for y in 0..gh - 1 {
+        for x in 0..gw - 1 {
+            let corners = [sample(x, y), sample(x + 1, y), sample(x + 1, y + 1), sample(x, y + 1)];
+            let case = corners.iter().enumerate()
+                .fold(0, |case, (i, &v)| if inside(v) { case | (1 << i) } else { case });
+            if case == 0 || case == 15 {
+                continue;
+            }
+            let top = (x, y, false);
+            let right = (x + 1, y, true);
+            let bottom = (x, y + 1, false);
+            let left = (x, y, true);
+            if case == 5 || case == 10 {
+                // saddle; corners are all inside the image as the padding is never inside
+                let center = corners.iter().map(|v| v.unwrap()).sum::<f64>() / 4.0;
+                if (case == 5) == (center >= iso) {
+                    segments.push([top, right]);
+                    segments.push([bottom, left]);
+                } else {
+                    segments.push([left, top]);
+                    segments.push([right, bottom]);
+                }
+                continue;
+            }
+            let crossed: Vec<_> = [top, right, bottom, left]
+                .into_iter()
+                .zip([(0, 1), (1, 2), (3, 2), (0, 3)])
+                .filter(|&(_, (a, b))| inside(corners[a]) != inside(corners[b]))
+                .map(|(edge, _)| edge)
+                .collect();
+            segments.push([crossed[0], crossed[1]]);
+        }
+    }
+
+    // every crossed edge is shared by exactly two segments, so the segments chain into loops
+    let mut by_edge: HashMap<_, Vec<usize>> = HashMap::new();
+    for (i, segment) in segments.iter().enumerate() {
+        for &edge in segment.iter() {
+            by_edge.entry(edge).or_default().push(i);
+        }
+    }
+    let mut visited = vec![false; segments.len()];
+    let mut contours = Vec::new();
+    for start in 0..segments.len() {
+        if visited[start] {
+            continue;
+        }
+        visited[start] = true;
+        let first = segments[start][0];
+        let mut path = PathF64::new();
+        path.add(point_on(first));
+        let mut edge = segments[start][1];
+        let mut current = start;
+        while edge != first {
+            path.add(point_on(edge));
+            let next = by_edge[&edge].iter().copied().find(|&s| s != current && !visited[s]);
+            match next {
+                Some(next) => {
+                    visited[next] = true;
+                    edge = if segments[next][0] == edge { segments[next][1] } else { segments[next][0] };
+                    current = next;
+                },
+                None => break,
+            }
+        }
+        path.add(point_on(first));
+        contours.push(path);
+    }
+    contours
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PlanarImage;
+
+    #[test]
+    fn marching_squares_single_pixel() {
+        let mut image = PlanarImage::<f32, 1>::new_w_h(3, 3);
+        image.set(1, 1, 0, 1.0);
+        let contours = marching_squares(&image, 0.5);
+        assert_eq!(contours.len(), 1);
+        let contour = &contours[0];
+        // a diamond around the center, half way between pixel centers
+        assert_eq!(contour.len(), 5);
+        assert_eq!(contour.path[0], contour.path[4]);
+        for p in contour.iter() {
+            assert_eq!((p.x - 1.5).abs() + (p.y - 1.5).abs(), 0.5);
+        }
+    }
+
+    #[test]
+    fn marching_squares_border() {
+        let image = PlanarImage::<f32, 1>::new_w_h_with_value(2, 2, 1.0);
+        let contours = marching_squares(&image, 0.5);
+        assert_eq!(contours.len(), 1);
+        let mut xs: Vec<_> = contours[0].iter().map(|p| p.x).collect();
+        xs.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(xs[0], 0.0);
+        assert_eq!(*xs.last().unwrap(), 2.0);
+    }
+}
@@ -1,17 +1,39 @@
+mod bbox;
+mod beautify;
+mod binary;
+mod clip;
 mod compound;
+mod fit;
+mod fourier;
+mod geojson;
+mod grid;
+mod hit;
+mod marching;
 mod paths;
 pub mod reduce;
 mod simplify;
 mod smooth;
+mod snake;
 mod spline;
+mod spline_f32;
+mod subpixel;
 mod walker;
 mod util;
 
+pub use bbox::*;
+pub use beautify::*;
+pub use clip::*;
 pub use compound::*;
+pub use fourier::*;
+pub use geojson::*;
+pub use hit::*;
+pub use marching::*;
 pub use paths::*;
 //pub use reduce::*;
 pub use simplify::*;
 //pub use smooth::*;
+pub use snake::*;
 pub use spline::*;
+pub use spline_f32::*;
 pub use walker::*;
 pub use util::*;
\ No newline at end of file
@@ -1,7 +1,7 @@
 use std::fmt::{Debug, Write};
 use std::ops::{Add, AddAssign, Index, IndexMut, Mul, Range, RangeFrom, RangeInclusive, Sub};
 
-use crate::{BinaryImage, Point2, PointF64, PointI32, Shape, ToSvgString};
+use crate::{BinaryImage, Point2, PointF32, PointF64, PointI32, Shape, ToSvgString};
 use super::{PathSimplify, PathSimplifyMode, PathWalker, smooth::SubdivideSmooth, reduce::reduce};
 
 #[derive(Clone, Debug, Default)]
@@ -13,6 +13,8 @@ pub struct Path<T> {
 
 /// Path of 2D PointI32
 pub type PathI32 = Path<PointI32>;
+/// Path of 2D PointF32
+pub type PathF32 = Path<PointF32>;
 /// Path of 2D PointF64
 pub type PathF64 = Path<PointF64>;
 
@@ -298,6 +300,7 @@ impl PathI32 {
     /// Takes an enum PathSimplifyMode which indicates the required operation:
     /// 
     /// - Polygon - Walk path and simplify it
+    /// - Orthogonal, Octilinear - Walk path and constrain it to axis aligned (and diagonal) segments
     /// - Otherwise - Walk path only
     pub fn image_to_path(image: &BinaryImage, clockwise: bool, mode: PathSimplifyMode) -> PathI32 {
         match mode {
@@ -305,6 +308,10 @@ impl PathI32 {
                 let path = Self::image_to_path_baseline(image, clockwise);
                 path.simplify(clockwise)
             },
+            PathSimplifyMode::Orthogonal | PathSimplifyMode::Octilinear => {
+                let path = Self::image_to_path_baseline(image, clockwise);
+                PathSimplify::orthogonalize(&path, matches!(mode, PathSimplifyMode::Octilinear))
+            },
             // Otherwise
             PathSimplifyMode::None | PathSimplifyMode::Spline => {
                 Self::image_to_path_baseline(image, clockwise)
@@ -563,4 +570,34 @@ mod tests {
             "M2,3 L4,3 L0,0 ".to_owned()
         );
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_image_to_path_orthogonal() {
+        let image = BinaryImage::from_string(&(
+            "-*****\n".to_owned() +
+            "******\n" +
+            "******\n"
+        ));
+        let path = PathI32::image_to_path(&image, true, PathSimplifyMode::Orthogonal);
+        let mut corners = path.path[1..].to_vec();
+        corners.sort_by_key(|p| (p.x, p.y));
+        assert_eq!(corners, vec![
+            PointI32::new(0, 0), PointI32::new(0, 3), PointI32::new(6, 0), PointI32::new(6, 3),
+        ]);
+        assert_eq!(path.path[0], path.path[4]);
+    }
+
+    #[test]
+    fn test_image_to_path_octilinear() {
+        let image = BinaryImage::from_string(&(
+            "*---\n".to_owned() +
+            "**--\n" +
+            "***-\n" +
+            "****\n"
+        ));
+        let path = PathI32::image_to_path(&image, true, PathSimplifyMode::Octilinear);
+        assert_eq!(path.path, vec![
+            PointI32::new(0, 0), PointI32::new(4, 4), PointI32::new(0, 4), PointI32::new(0, 0),
+        ]);
+    }
+}
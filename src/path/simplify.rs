@@ -4,11 +4,19 @@ use super::util::signed_area;
 
 pub(crate) struct PathSimplify;
 
-#[derive(Copy, Clone, Debug)]
+/// A unit jog is only removed if one of the runs on its sides is at most this long
+const MAX_JOG_RUN: i32 = 2;
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
 pub enum PathSimplifyMode {
     None,
     Polygon,
     Spline,
+    /// Polygon with horizontal and vertical segments only
+    Orthogonal,
+    /// Polygon with horizontal, vertical and 45 degree segments only
+    Octilinear,
 }
 
 #[derive(Copy, Clone)]
@@ -60,6 +68,99 @@ impl PathSimplify {
         result
     }
 
+    /// Returns a copy of a closed path (as walked on pixel edges) cleaned up into an orthogonal polygon,
+    /// by removing 1 pixel jogs next to short runs.
+    /// If `diagonal`, staircases of 1 pixel steps are first replaced by 45 degree segments.
+    pub fn orthogonalize(path: &PathI32, diagonal: bool) -> PathI32 {
+        let len = path.path.len();
+        if len < 5 || path.path[0] != path.path[len - 1] {
+            return path.clone();
+        }
+        let mut v = path.path[0..len - 1].to_vec();
+        Self::remove_redundant(&mut v);
+        if diagonal {
+            Self::replace_staircases(&mut v);
+        }
+        while Self::remove_jog(&mut v) {
+            Self::remove_redundant(&mut v);
+        }
+        if !v.is_empty() {
+            v.push(v[0]);
+        }
+        PathI32::from_points(v)
+    }
+
+    /// Replaces each run of alternating unit steps with a diagonal of equal horizontal and vertical extent
+    fn replace_staircases(v: &mut Vec<PointI32>) {
+        let n = v.len();
+        let step = |i: usize| v[(i + 1) % n] - v[i];
+        let is_unit = |d: PointI32| d.x.abs() + d.y.abs() == 1;
+        let mut result = Vec::with_capacity(n);
+        let mut i = 0;
+        while i < n {
+            result.push(v[i]);
+            let mut k = 0;
+            while i + k < n && is_unit(step(i + k)) &&
+                (k < 2 || step(i + k) == step(i + k - 2)) &&
+                (k < 1 || step(i + k) != step(i + k - 1)) {
+                k += 1;
+            }
+            // equal number of horizontal and vertical steps
+            k -= k % 2;
+            i += if k >= 2 { k } else { 1 };
+        }
+        *v = result;
+        Self::remove_redundant(v);
+    }
+
+    /// Removes one axis aligned unit jog between two runs in the same direction,
+    /// by moving the shorter run onto the line of the longer one. Returns false if there is none.
+    fn remove_jog(v: &mut [PointI32]) -> bool {
+        let n = v.len();
+        if n < 4 {
+            return false;
+        }
+        let axis_dir = |d: PointI32| if d.x == 0 || d.y == 0 {
+            Some(PointI32::new(d.x.signum(), d.y.signum()))
+        } else {
+            None
+        };
+        let length = |d: PointI32| d.x.abs() + d.y.abs();
+        for i in 0..n {
+            let (h, j, k) = ((i + n - 1) % n, (i + 1) % n, (i + 2) % n);
+            let (a, jog, b) = (v[i] - v[h], v[j] - v[i], v[k] - v[j]);
+            if length(jog) != 1 || axis_dir(a).is_none() || axis_dir(a) != axis_dir(b) ||
+                std::cmp::min(length(a), length(b)) > MAX_JOG_RUN {
+                continue;
+            }
+            if length(a) <= length(b) {
+                v[h] += jog;
+                v[i] += jog;
+            } else {
+                v[j] -= jog;
+                v[k] -= jog;
+            }
+            return true;
+        }
+        false
+    }
+
+    /// Removes repeated and collinear points of a cyclic list of points
+    fn remove_redundant(v: &mut Vec<PointI32>) {
+        let mut i = 0;
+        while v.len() > 2 && i < v.len() {
+            let n = v.len();
+            let (prev, curr, next) = (v[(i + n - 1) % n], v[i], v[(i + 1) % n]);
+            let (a, b) = (curr - prev, next - curr);
+            if a.x * b.y - a.y * b.x == 0 {
+                v.remove(i);
+                i = i.saturating_sub(1);
+            } else {
+                i += 1;
+            }
+        }
+    }
+
     pub fn limit_penalties(path: &PathI32) -> PathI32 {
         let tolerance = 1.0;
         let path = &path.path;
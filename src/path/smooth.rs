@@ -1,9 +1,11 @@
-use crate::{Path, PathF64, PointF64, Point2};
+use num_traits::Float;
+use crate::{Path, PathF64, PointF32, PointF64, Point2};
 use flo_curves::{bezier, BezierCurveFactory};
 
 /// Handles Path Smoothing
 pub(crate) struct SubdivideSmooth;
 
+use super::fit::fit_curve;
 use super::util::{angle, find_intersection, find_mid_point, norm, normalize, signed_angle_difference};
 
 impl SubdivideSmooth {
@@ -48,7 +50,7 @@ impl SubdivideSmooth {
     /// 
     /// Note that the length of output is 1 less than that of the original path,
     /// because the last point of the original path is always equal to the first point for paths of walked polygons (closed path).
-    pub fn find_splice_points(path: &PathF64, threshold: f64) -> Vec<bool> {
+    pub fn find_splice_points<T: Float + Into<f64>>(path: &Path<Point2<T>>, threshold: f64) -> Vec<bool> {
 
         let path = &path.path[0..(path.path.len()-1)];
         let len = path.len();
@@ -63,8 +65,8 @@ impl SubdivideSmooth {
             let prev = if i==0 {len-1} else {i-1};
             let next = (i+1) % len;
 
-            let v1: PointF64 = path[i]-path[prev];
-            let v2: PointF64 = path[next]-path[i];
+            let v1: Point2<T> = path[i]-path[prev];
+            let v2: Point2<T> = path[next]-path[i];
 
             let angle_v1: f64 = angle(&normalize(&v1));
             let angle_v2: f64 = angle(&normalize(&v2));
@@ -118,6 +120,21 @@ impl SubdivideSmooth {
         }
     }
 
+    /// `fit_points_with_bezier` in single precision, by the port of the fitter in `fit`
+    pub(crate) fn fit_points_with_bezier_f32(points: &[PointF32]) -> [PointF32; 4] {
+        match fit_curve(points, 10.0) {
+            Some(curves) if !curves.is_empty() => {
+                let p1 = points[0];
+                let p4 = points[points.len()-1];
+
+                let [_, p2, p3, _] = curves[0];
+
+                Self::retract_handles(&p1, &p2, &p3, &p4)
+            },
+            _ => [PointF32::default(); 4],
+        }
+    }
+
     /// Takes a path forming a polygon and a slice of bool representing corner positions.
     /// 
     /// Use the 4-point scheme to subdivide while keeping corners. 
@@ -214,13 +231,13 @@ impl SubdivideSmooth {
         mid_out + vector_out.get_normalized() * new_magnitude
     }
 
-    fn retract_handles(a: &PointF64, b: &PointF64, c: &PointF64, d: &PointF64) -> [PointF64; 4] {
-        let da: PointF64 = *a-*d;
-        let ab: PointF64 = *b-*a;
+    fn retract_handles<T: Float + Into<f64>>(a: &Point2<T>, b: &Point2<T>, c: &Point2<T>, d: &Point2<T>) -> [Point2<T>; 4] {
+        let da: Point2<T> = *a-*d;
+        let ab: Point2<T> = *b-*a;
         // signed angle DAB
         let dab = signed_angle_difference(&angle(&normalize(&da)), &angle(&normalize(&ab)));
 
-        let bc: PointF64 = *c-*b;
+        let bc: Point2<T> = *c-*b;
         // signed angle ABC
         let abc = signed_angle_difference(&angle(&normalize(&ab)), &angle(&normalize(&bc)));
 
@@ -0,0 +1,146 @@
+use crate::{CompoundPath, PathF64, PlanarImage, PointF64};
+use super::subpixel::sample_bilinear;
+
+/// Parameters of `PathF64::snake`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SnakeConfig {
+    /// weight of elasticity, which keeps vertices evenly spaced
+    pub alpha: f64,
+    /// weight of stiffness, which resists bending
+    pub beta: f64,
+    /// weight of the image energy, which pulls vertices onto strong edges
+    pub gamma: f64,
+    /// distance between the candidate positions of a vertex, in pixels
+    pub step: f64,
+    /// a vertex moves by at most `search_radius` steps in x and y per iteration
+    pub search_radius: i32,
+    pub max_iterations: usize,
+}
+
+impl Default for SnakeConfig {
+    fn default() -> Self {
+        Self {
+            alpha: 1.0,
+            beta: 1.0,
+            gamma: 1.2,
+            step: 1.0,
+            search_radius: 1,
+            max_iterations: 100,
+        }
+    }
+}
+
+impl PathF64 {
+    /// Refines a closed path as an active contour (greedy snake, Williams & Shah),
+    /// so that a rough outline locks onto the nearby edges of `edges`,
+    /// which is any edge strength map, e.g. `ColorImage::gradient_magnitude`.
+    ///
+    /// In every iteration each vertex moves to the candidate position in its neighbourhood of the
+    /// least weighted sum of continuity, curvature and (negated) edge strength, each normalized over the neighbourhood.
+    /// Iterations stop early once no vertex moves. Closed paths are expected to repeat the first point at the end.
+    pub fn snake(&self, edges: &PlanarImage<f32, 1>, config: &SnakeConfig) -> PathF64 {
+        let len = self.path.len();
+        let closed = len > 1 && self.path[0] == self.path[len - 1];
+        let count = if closed { len - 1 } else { len };
+        if count < 3 {
+            return self.clone();
+        }
+        let mut points = self.path[..count].to_vec();
+        let radius = config.search_radius.max(0);
+        let side = (2 * radius + 1) as usize;
+        let mut candidates = vec![(PointF64::default(), 0.0, 0.0, 0.0); side * side];
+
+        for _ in 0..config.max_iterations {
+            let spacing = (0..count)
+                .map(|i| (points[(i + 1) % count] - points[i]).norm())
+                .sum::<f64>() / count as f64;
+            let mut moved = 0;
+            for i in 0..count {
+                let prev = points[(i + count - 1) % count];
+                let next = points[(i + 1) % count];
+                for (k, candidate) in candidates.iter_mut().enumerate() {
+                    let dx = (k % side) as i32 - radius;
+                    let dy = (k / side) as i32 - radius;
+                    let p = points[i] + PointF64::new(dx as f64, dy as f64) * config.step;
+                    let continuity = (spacing - (p - prev).norm()).powi(2);
+                    let curvature = (prev - p * 2.0 + next).norm().powi(2);
+                    let edge = sample_bilinear(edges, p) as f64;
+                    *candidate = (p, continuity, curvature, edge);
+                }
+                let range = |term: fn(&(PointF64, f64, f64, f64)) -> f64| {
+                    candidates.iter().map(term).fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), v| (lo.min(v), hi.max(v)))
+                };
+                let (_, max_continuity) = range(|c| c.1);
+                let (_, max_curvature) = range(|c| c.2);
+                let (min_edge, max_edge) = range(|c| c.3);
+                let normalize = |v: f64, max: f64| if max > 0.0 { v / max } else { 0.0 };
+
+                let mut best = (f64::INFINITY, points[i]);
+                for &(p, continuity, curvature, edge) in candidates.iter() {
+                    let edge = if max_edge > min_edge { (min_edge - edge) / (max_edge - min_edge) } else { 0.0 };
+                    let energy = config.alpha * normalize(continuity, max_continuity) +
+                        config.beta * normalize(curvature, max_curvature) +
+                        config.gamma * edge;
+                    // prefer staying put on ties
+                    if energy < best.0 - 1e-9 || (energy <= best.0 + 1e-9 && p == points[i]) {
+                        best = (energy, p);
+                    }
+                }
+                if best.1 != points[i] {
+                    points[i] = best.1;
+                    moved += 1;
+                }
+            }
+            if moved == 0 {
+                break;
+            }
+        }
+
+        if closed {
+            points.push(points[0]);
+        }
+        PathF64::from_points(points)
+    }
+}
+
+impl CompoundPath {
+    /// Applies `PathF64::snake` to every path, see `map_paths_f64`
+    pub fn snake(&self, edges: &PlanarImage<f32, 1>, config: &SnakeConfig) -> CompoundPath {
+        self.map_paths_f64(|path| path.snake(edges, config))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Color, ColorImage};
+
+    #[test]
+    fn snake_locks_onto_disc() {
+        // a dark disc of radius 10 on white
+        let mut image = ColorImage::new_w_h(40, 40);
+        for y in 0..40 {
+            for x in 0..40 {
+                let (dx, dy) = (x as f64 + 0.5 - 20.0, y as f64 + 0.5 - 20.0);
+                let inside = dx * dx + dy * dy < 100.0;
+                image.set_pixel(x, y, &if inside { Color::new(0, 0, 0) } else { Color::new(255, 255, 255) });
+            }
+        }
+        let edges = image.gradient_magnitude();
+        // a rough circle of radius 13 around the disc
+        let mut points: Vec<PointF64> = (0..32).map(|i| {
+            let angle = i as f64 / 32.0 * std::f64::consts::TAU;
+            PointF64::new(20.0 + 13.0 * angle.cos(), 20.0 + 13.0 * angle.sin())
+        }).collect();
+        points.push(points[0]);
+        let path = PathF64::from_points(points);
+
+        let refined = path.snake(&edges, &SnakeConfig::default());
+        assert_eq!(refined.len(), 33);
+        assert_eq!(refined.path[0], refined.path[32]);
+        for p in refined.iter() {
+            let r = (*p - PointF64::new(20.0, 20.0)).norm();
+            assert!((9.0..=11.0).contains(&r), "{}", r);
+        }
+    }
+}
@@ -1,5 +1,6 @@
 use std::{cmp::Ordering};
-use crate::{BinaryImage, PathF64, PointF64, PathSimplifyMode};
+use num_traits::Float;
+use crate::{BinaryImage, Path, PathF64, Point2, PointF64, PathSimplifyMode, distance_to_segment};
 use super::{PathI32, smooth::SubdivideSmooth};
 
 #[derive(Debug, Default, Clone)]
@@ -80,17 +81,26 @@ impl Spline {
     /// 
     /// Splice threshold is specified in radians.
     pub fn from_path_f64(path: &PathF64, splice_threshold: f64) -> Self {
+        Self {
+            points: Self::fit_path(path, splice_threshold, SubdivideSmooth::fit_points_with_bezier),
+        }
+    }
+
+    /// The control points of `from_path_f64`, each curve between splice points fitted by `fit`
+    pub(crate) fn fit_path<T: Float + Into<f64>>(
+        path: &Path<Point2<T>>,
+        splice_threshold: f64,
+        fit: fn(&[Point2<T>]) -> [Point2<T>; 4],
+    ) -> Vec<Point2<T>> {
         // First locate all the splice points
-        let splice_points = SubdivideSmooth::find_splice_points(&path, splice_threshold);
+        let splice_points = SubdivideSmooth::find_splice_points(path, splice_threshold);
         let path = &path.path[0..path.len()-1];
         let len = path.len();
         if len<=1 {
-            return Self::new(PointF64 {x:0.0,y:0.0});
+            return vec![Point2::new(T::zero(), T::zero())];
         }
         if len==2 {
-            let mut result = Self::new(path[0]);
-            result.add(path[1], path[1], path[1]);
-            return result;
+            return vec![path[0], path[1], path[1], path[1]];
         }
 
         // This vector stores the indices
@@ -108,24 +118,24 @@ impl Spline {
         }
         let num_cut_points = cut_points.len();
 
-        let mut result = Self::new(PointF64 {x:0.0,y:0.0}); // Dummy initialization
+        let mut points = Vec::with_capacity(1 + 3 * num_cut_points);
         for i in 0..num_cut_points {
             let j = (i+1)%num_cut_points;
 
             let current = cut_points[i];
             let next = cut_points[j];
             let subpath = Self::get_circular_subpath(path, current, next);
-            let bezier_points = SubdivideSmooth::fit_points_with_bezier(&subpath);
+            let bezier_points = fit(&subpath);
 
             // Only the first curve need to add the first point
             if i==0 {
-                result = Self::new(bezier_points[0]);
+                points.push(bezier_points[0]);
             }
             // Subsequent curves take their first point from previous curve's last point
-            result.add(bezier_points[1], bezier_points[2], bezier_points[3]);
+            points.extend_from_slice(&bezier_points[1..]);
         }
 
-        result
+        points
     }
 
     /// Converts spline to svg path. Panic if the length of spline is not valid (not 1+3n for some integer n)
@@ -163,10 +173,47 @@ impl Spline {
         result.concat()
     }
 
-    fn get_circular_subpath(path: &[PointF64], from: usize, to: usize) -> Vec<PointF64> {
+    /// Samples the spline into a polyline, with more points where the curvature is high.
+    ///
+    /// Each curve is subdivided recursively until its control points are within `tolerance` (in pixels)
+    /// of the chord, so no point of the polyline deviates from the curve by more than `tolerance`.
+    pub fn to_path_f64(&self, tolerance: f64) -> PathF64 {
+        assert!(tolerance > 0.0);
+        let mut path = PathF64::new();
+        if let Some(&first) = self.points.first() {
+            path.add(first);
+        }
+        for curve in self.get_control_points() {
+            Self::flatten_curve([curve[0], curve[1], curve[2], curve[3]], tolerance, 0, &mut path);
+        }
+        path
+    }
+
+    /// Maximum depth of subdivision, limiting the number of points from a degenerate curve
+    const MAX_SUBDIVISION: usize = 16;
+
+    fn flatten_curve(curve: [PointF64; 4], tolerance: f64, depth: usize, path: &mut PathF64) {
+        let [p0, p1, p2, p3] = curve;
+        if depth >= Self::MAX_SUBDIVISION ||
+            (distance_to_segment(p1, p0, p3) <= tolerance && distance_to_segment(p2, p0, p3) <= tolerance) {
+            path.add(p3);
+            return;
+        }
+        // de Casteljau at t = 0.5
+        let p01 = (p0 + p1) * 0.5;
+        let p12 = (p1 + p2) * 0.5;
+        let p23 = (p2 + p3) * 0.5;
+        let p012 = (p01 + p12) * 0.5;
+        let p123 = (p12 + p23) * 0.5;
+        let mid = (p012 + p123) * 0.5;
+        Self::flatten_curve([p0, p01, p012, mid], tolerance, depth + 1, path);
+        Self::flatten_curve([mid, p123, p23, p3], tolerance, depth + 1, path);
+    }
+
+    fn get_circular_subpath<P: Copy>(path: &[P], from: usize, to: usize) -> Vec<P> {
 
         let len = path.len();
-        let mut subpath: Vec<PointF64> = vec![];
+        let mut subpath: Vec<P> = vec![];
     
         match from.cmp(&to) {
             Ordering::Less => {
@@ -211,4 +258,42 @@ mod tests {
             "M2 3 C4 3 4 5 6 5 ".to_owned()
         );
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_spline_to_path_adaptive() {
+        let mut spline = Spline::new(PointF64::new(0.0, 0.0));
+        // a straight line needs no subdivision
+        spline.add(PointF64::new(1.0, 0.0), PointF64::new(2.0, 0.0), PointF64::new(3.0, 0.0));
+        // a sharp bend needs many points
+        spline.add(PointF64::new(13.0, 0.0), PointF64::new(13.0, 10.0), PointF64::new(3.0, 10.0));
+        let coarse = spline.to_path_f64(1.0);
+        let fine = spline.to_path_f64(0.01);
+        assert_eq!(coarse.path[1], PointF64::new(3.0, 0.0));
+        assert_eq!(fine.path[1], PointF64::new(3.0, 0.0));
+        assert!(fine.len() > coarse.len() * 4);
+        assert_eq!(*fine.path.last().unwrap(), PointF64::new(3.0, 10.0));
+    }
+
+    #[test]
+    fn from_path_f64_control_points() {
+        // an arc and three sides, fitted as before the single precision fitter was added
+        let mut path = PathF64::new();
+        for &(x, y) in &[(40.0, 20.0), (39.3, 14.8), (37.3, 10.0), (34.1, 5.9), (30.0, 2.7), (25.2, 0.7), (20.0, 0.0),
+            (14.8, 0.7), (10.0, 2.7), (5.9, 5.9), (2.7, 10.0), (0.7, 14.8), (0.0, 20.0), (5.0, 24.0), (20.0, 30.0), (35.0, 24.0), (40.0, 20.0)] {
+            path.add(PointF64::new(x, y));
+        }
+        let spline = Spline::from_path_f64(&path, 45.0_f64.to_radians());
+        let expected = [
+            (40.0, 20.0), (38.918329901615905, 11.964736412003926), (36.3837716235227, 7.682455901286006), (30.0, 2.7),
+            (24.340554405477437, 0.3418976689489326), (20.878304291837118, -0.11823327005499651), (14.8, 0.7),
+            (7.393163126953083, 3.7861820304362155), (3.7861820304362137, 7.393163126953086), (0.7, 14.8),
+            (0.469, 16.516000000000002), (0.238, 18.232), (0.0, 20.0),
+            (5.000000000000001, 24.0), (5.000000000000001, 24.0), (20.0, 30.0),
+            (35.0, 24.0), (35.0, 24.0), (40.0, 20.0),
+        ];
+        assert_eq!(spline.points.len(), expected.len());
+        for (p, &(x, y)) in spline.points.iter().zip(expected.iter()) {
+            assert!(p.distance_to(PointF64::new(x, y)) < 1e-9, "{:?}", p);
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use crate::{PathF32, PathF64, PointF32, PointF64, Spline};
+use super::smooth::SubdivideSmooth;
+
+/// A `Spline` stored in single precision, for holding huge path sets in half the memory.
+///
+/// Fitting runs in single precision too, so the control points differ slightly from those of `Spline`:
+/// by well under 1/100 pixel anywhere in an image up to 16384 pixels a side.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SplineF32 {
+    /// as in `Spline::points`
+    pub points: Vec<PointF32>,
+}
+
+impl SplineF32 {
+    /// Fits `path` as `Spline::from_path_f64` does, in single precision
+    pub fn from_path_f32(path: &PathF32, splice_threshold: f64) -> Self {
+        Self {
+            points: Spline::fit_path(path, splice_threshold, SubdivideSmooth::fit_points_with_bezier_f32),
+        }
+    }
+
+    /// Rounds `path` to `f32`, then fits it by `from_path_f32`
+    pub fn from_path_f64(path: &PathF64, splice_threshold: f64) -> Self {
+        let path = PathF32 {
+            path: path.path.iter().map(|p| p.to_point_f32()).collect(),
+        };
+        Self::from_path_f32(&path, splice_threshold)
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn num_curves(&self) -> usize {
+        if !self.points.is_empty() { (self.points.len() - 1) / 3 } else { 0 }
+    }
+
+    /// See `Spline::is_empty`
+    pub fn is_empty(&self) -> bool {
+        self.points.len() <= 3
+    }
+
+    pub fn to_spline(&self) -> Spline {
+        Spline {
+            points: self.points.iter().map(|p| PointF64::new(p.x as f64, p.y as f64)).collect(),
+        }
+    }
+
+    /// See `Spline::to_svg_string`
+    pub fn to_svg_string(&self, close: bool, offset: &PointF64, precision: Option<u32>) -> String {
+        self.to_spline().to_svg_string(close, offset, precision)
+    }
+}
+
+impl Spline {
+    /// Rounds the control points to `f32`, see `SplineF32`
+    pub fn to_spline_f32(&self) -> SplineF32 {
+        SplineF32 {
+            points: self.points.iter().map(|p| PointF32::new(p.x as f32, p.y as f32)).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn spline_f32_accuracy() {
+        let mut path = PathF64::new();
+        for i in 0..=16 {
+            let angle = i as f64 / 16.0 * std::f64::consts::TAU;
+            path.add(PointF64::new(8000.3 + 100.0 * angle.cos(), 15000.7 + 100.0 * angle.sin()));
+        }
+        let spline = Spline::from_path_f64(&path, 1.0);
+        let compact = SplineF32::from_path_f64(&path, 1.0);
+        assert_eq!(compact.len(), spline.len());
+        assert_eq!(compact.num_curves(), spline.num_curves());
+        assert_eq!(std::mem::size_of_val(&compact.points[..]) * 2, std::mem::size_of_val(&spline.points[..]));
+        for (a, b) in compact.to_spline().points.iter().zip(spline.points.iter()) {
+            assert!(a.distance_to(*b) < 1e-2);
+        }
+        assert_eq!(compact.to_svg_string(false, &PointF64::default(), Some(0)),
+            spline.to_svg_string(false, &PointF64::default(), Some(0)));
+    }
+}
@@ -0,0 +1,88 @@
+use crate::{BorderMode, CompoundPath, PathF64, PlanarImage, PointF64};
+
+/// Minimum contrast between the two sides of a boundary for a vertex to be moved
+const MIN_CONTRAST: f32 = 8.0;
+
+impl PathF64 {
+    /// Moves each vertex of a traced boundary (vertices on pixel corners) along the boundary normal,
+    /// to where the intensity ramp across the boundary says the true (anti-aliased) edge lies.
+    ///
+    /// `intensity` is any channel which differs across the boundary, e.g. alpha or luma.
+    /// Each vertex moves by at most half a pixel. Closed paths are expected to repeat the first point at the end.
+    pub fn refine_subpixel(&self, intensity: &PlanarImage<f32, 1>) -> PathF64 {
+        let len = self.path.len();
+        if len < 3 {
+            return self.clone();
+        }
+        let closed = self.path[0] == self.path[len - 1];
+        let count = if closed { len - 1 } else { len };
+
+        let mut path = self.clone();
+        for i in 0..count {
+            let (prev, next) = if closed {
+                (self.path[(i + count - 1) % count], self.path[(i + 1) % count])
+            } else {
+                (self.path[i.saturating_sub(1)], self.path[std::cmp::min(i + 1, len - 1)])
+            };
+            let tangent = next - prev;
+            if tangent.is_zero() {
+                continue;
+            }
+            let tangent = tangent.get_normalized();
+            let normal = PointF64::new(-tangent.y, tangent.x);
+            let p = self.path[i];
+            let at = |d: f64| sample_bilinear(intensity, p + normal * d);
+
+            let (inner, outer) = (at(-1.5), at(1.5));
+            if (inner - outer).abs() < MIN_CONTRAST {
+                continue;
+            }
+            // coverage of the pixels on either side of the vertex by the inner side
+            let cover_in = ((at(-0.5) - outer) / (inner - outer)).clamp(0.0, 1.0);
+            let cover_out = ((at(0.5) - outer) / (inner - outer)).clamp(0.0, 1.0);
+            let offset = (cover_in + cover_out - 1.0).clamp(-0.5, 0.5) as f64;
+            path.path[i] = p + normal * offset;
+        }
+        if closed {
+            path.path[len - 1] = path.path[0];
+        }
+        path
+    }
+}
+
+impl CompoundPath {
+    /// Applies `PathF64::refine_subpixel` to every path, see `map_paths_f64`
+    pub fn refine_subpixel(&self, intensity: &PlanarImage<f32, 1>) -> CompoundPath {
+        self.map_paths_f64(|path| path.refine_subpixel(intensity))
+    }
+}
+
+/// Samples at a point in pixel coordinates, where pixel (x, y) covers [x, x + 1) * [y, y + 1)
+pub(super) fn sample_bilinear(image: &PlanarImage<f32, 1>, p: PointF64) -> f32 {
+    image.sample_bilinear(p.x - 0.5, p.y - 0.5, BorderMode::Replicate)[0]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn refine_subpixel_vertical_edge() {
+        // left 3 columns are solid, the 4th is 40% covered
+        let mut intensity = PlanarImage::<f32, 1>::new_w_h(8, 8);
+        for y in 0..8 {
+            for x in 0..3 {
+                intensity.set(x, y, 0, 255.0);
+            }
+            intensity.set(3, y, 0, 102.0);
+        }
+        let path = PathF64::from_points(vec![
+            PointF64::new(3.0, 6.0),
+            PointF64::new(3.0, 4.0),
+            PointF64::new(3.0, 2.0),
+        ]);
+        let refined = path.refine_subpixel(&intensity);
+        assert!((refined.path[1].x - 3.4).abs() < 1e-6);
+        assert_eq!(refined.path[1].y, 4.0);
+    }
+}
@@ -1,4 +1,5 @@
 use std::f64::{NAN, consts::{PI}};
+use num_traits::Float;
 
 use crate::{Point2, PointF64, PointI32};
 
@@ -22,8 +23,8 @@ pub struct Intersection {
 /// Adapted from https://github.com/tyt2y3/vaserenderer/blob/master/csharp/Assets/Vaser/Vec2Ext.cs#L107
 ///
 /// Which in turn originates from http://paulbourke.net/geometry/lineline2d/
-pub fn find_intersection(p1: &PointF64, p2: &PointF64, p3: &PointF64, p4: &PointF64)
-    -> Option<(PointF64, Intersection)>
+pub fn find_intersection<T: Float>(p1: &Point2<T>, p2: &Point2<T>, p3: &Point2<T>, p4: &Point2<T>)
+    -> Option<(Point2<T>, Intersection)>
 {
     let (denom, numera, numerb);
     denom  = (p4.y-p3.y) * (p2.x-p1.x) - (p4.x-p3.x) * (p2.y-p1.y);
@@ -44,11 +45,11 @@ pub fn find_intersection(p1: &PointF64, p2: &PointF64, p3: &PointF64, p4: &Point
     let mub = numerb / denom;
 
     Some((
-        PointF64 {
+        Point2 {
             x: p1.x + mua * (p2.x - p1.x),
             y: p1.y + mua * (p2.y - p1.y),
         },
-        Intersection { mua, mub }
+        Intersection { mua: mua.to_f64().unwrap(), mub: mub.to_f64().unwrap() }
     ))
 }
 
@@ -82,15 +83,16 @@ impl PartialEq for Intersection {
 }
 
 #[inline]
-fn negligible(v: f64) -> bool {
-    const EPSILON: f64 = 1e-7;    
-    -EPSILON < v && v < EPSILON
+fn negligible<T: Float>(v: T) -> bool {
+    let epsilon = T::from(1e-7).unwrap();
+    -epsilon < v && v < epsilon
 }
 
-pub(super) fn find_mid_point(p1: &PointF64, p2: &PointF64) -> PointF64 {
-    let x = (p1.x + p2.x) / 2.0;
-    let y = (p1.y + p2.y) / 2.0;
-    PointF64 {x, y}
+pub(super) fn find_mid_point<T: Float>(p1: &Point2<T>, p2: &Point2<T>) -> Point2<T> {
+    let two = T::one() + T::one();
+    let x = (p1.x + p2.x) / two;
+    let y = (p1.y + p2.y) / two;
+    Point2 {x, y}
 }
 
 pub(super) fn norm<T>(p: &Point2<T>) -> f64
@@ -108,9 +110,9 @@ where T: std::ops::Add<Output = T> + std::ops::Mul<Output = T> + Copy + Into<f64
 
 pub(super) fn angle(p: &PointF64) -> f64 {
     if p.y.is_sign_negative() {
-        -p.x.acos()
+        -crate::fmath::acos(p.x)
     } else {
-        p.x.acos()
+        crate::fmath::acos(p.x)
     }
 }
 
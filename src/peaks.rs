@@ -0,0 +1,137 @@
+use crate::{PlanarImage, PointF64, ScalarPixel, nms_points};
+
+/// Parameters of `find_peaks` and `PlanarImage::find_peaks`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PeakConfig {
+    /// peaks lower than this are ignored
+    pub min_height: f64,
+    /// how far a peak must stand out above the higher of the lowest points on either side
+    /// before reaching a higher peak (or the end)
+    pub min_prominence: f64,
+    /// of peaks closer than this, only the highest is kept
+    pub min_distance: usize,
+}
+
+impl Default for PeakConfig {
+    fn default() -> Self {
+        Self {
+            min_height: f64::MIN,
+            min_prominence: 0.0,
+            min_distance: 1,
+        }
+    }
+}
+
+/// A peak of a 1D profile
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Peak {
+    /// the first index of the top, if flat
+    pub index: usize,
+    pub value: f64,
+    pub prominence: f64,
+}
+
+/// A peak of a 2D map
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Peak2D {
+    pub x: usize,
+    pub y: usize,
+    pub value: f64,
+    pub prominence: f64,
+}
+
+/// Local maxima of `profile` (e.g. a projection profile or a histogram), from left to right
+pub fn find_peaks<T: ScalarPixel>(profile: &[T], config: &PeakConfig) -> Vec<Peak> {
+    let values: Vec<f64> = profile.iter().map(|v| v.scalar()).collect();
+    let n = values.len();
+    let mut peaks = Vec::new();
+    let mut i = 1;
+    while i + 1 < n {
+        if values[i] > values[i - 1] {
+            // the end of a possibly flat top
+            let mut end = i;
+            while end + 1 < n && values[end + 1] == values[i] {
+                end += 1;
+            }
+            if end + 1 < n && values[end + 1] < values[i] && values[i] >= config.min_height {
+                let prominence = prominence(&values, i, end);
+                if prominence >= config.min_prominence {
+                    peaks.push(Peak { index: i, value: values[i], prominence });
+                }
+            }
+            i = end + 1;
+        } else {
+            i += 1;
+        }
+    }
+
+    // the highest first; others within `min_distance` of a kept peak are dropped
+    let items: Vec<(PointF64, f64)> = peaks.iter().map(|p| (PointF64::new(p.index as f64, 0.0), p.value)).collect();
+    let mut kept = nms_points(&items, |&item| item, config.min_distance as f64 - 1.0, usize::MAX);
+    kept.sort_unstable();
+    kept.into_iter().map(|k| peaks[k]).collect()
+}
+
+/// The height of the top `start..=end` above the higher of the minima on either side,
+/// up to a higher value or the end
+fn prominence(values: &[f64], start: usize, end: usize) -> f64 {
+    let top = values[start];
+    let left = values[..start].iter().rev().take_while(|&&v| v <= top).copied().fold(top, f64::min);
+    let right = values[end + 1..].iter().take_while(|&&v| v <= top).copied().fold(top, f64::min);
+    top - left.max(right)
+}
+
+impl PlanarImage<f32, 1> {
+    /// Local maxima of a response map, the maximum within `min_distance` in each direction (a square window).
+    /// In 2D, the prominence is the height above the minimum within the window.
+    /// Peaks are sorted by decreasing value.
+    pub fn find_peaks(&self, config: &PeakConfig) -> Vec<Peak2D> {
+        let radius = config.min_distance.max(1);
+        let (high, low) = (self.dilate(radius), self.erode(radius));
+        let mut peaks = Vec::new();
+        for y in 0..self.height() {
+            for x in 0..self.width() {
+                let value = self.get(x, y, 0);
+                let prominence = (value - low.get(x, y, 0)) as f64;
+                if value == high.get(x, y, 0) && value as f64 >= config.min_height &&
+                    prominence > 0.0 && prominence >= config.min_prominence {
+                    peaks.push(Peak2D { x, y, value: value as f64, prominence });
+                }
+            }
+        }
+        // a flat top is found at each of its pixels; keep one
+        let items: Vec<(PointF64, f64)> = peaks.iter()
+            .map(|p| (PointF64::new(p.x as f64, p.y as f64), p.value))
+            .collect();
+        nms_points(&items, |&item| item, radius as f64, usize::MAX)
+            .into_iter()
+            .map(|k| peaks[k])
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn peaks_of_profile_and_map() {
+        let profile = [0usize, 5, 1, 2, 1, 8, 8, 3, 4, 0];
+        let peaks = find_peaks(&profile, &PeakConfig::default());
+        assert_eq!(peaks.iter().map(|p| (p.index, p.prominence)).collect::<Vec<_>>(),
+            [(1, 4.0), (3, 1.0), (5, 8.0), (8, 1.0)]);
+        let prominent = find_peaks(&profile, &PeakConfig { min_prominence: 2.0, ..Default::default() });
+        assert_eq!(prominent.iter().map(|p| p.index).collect::<Vec<_>>(), [1, 5]);
+        let apart = find_peaks(&profile, &PeakConfig { min_distance: 3, ..Default::default() });
+        assert_eq!(apart.iter().map(|p| p.index).collect::<Vec<_>>(), [1, 5, 8]);
+
+        let mut map = PlanarImage::<f32, 1>::new_w_h(10, 8);
+        map.set(2, 2, 0, 5.0);
+        map.set(3, 2, 0, 5.0);
+        map.set(7, 5, 0, 9.0);
+        map.set(6, 5, 0, 1.0);
+        let peaks = map.find_peaks(&PeakConfig::default());
+        assert_eq!(peaks.iter().map(|p| (p.x, p.y, p.value)).collect::<Vec<_>>(), [(7, 5, 9.0), (2, 2, 5.0)]);
+        assert!(map.find_peaks(&PeakConfig { min_height: 6.0, ..Default::default() }).len() == 1);
+    }
+}
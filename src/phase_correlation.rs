@@ -0,0 +1,178 @@
+use crate::{BorderMode, Complex, PlanarImage, PointF64, SimilarityTransform, Spectrum};
+
+/// The result of `phase_correlate`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PhaseCorrelation {
+    /// where the content of the first image is found in the second, relative to where it was
+    pub translation: PointF64,
+    /// height of the correlation peak, in [0, 1]; near 1 for a clean shift, low if the images do not match
+    pub response: f64,
+}
+
+/// Translation between two images of the same size by phase correlation: the normalized cross power
+/// spectrum of the (Hann windowed) images is transformed back, and its peak located to a fraction of a pixel.
+/// Shifts are found modulo the padded size, so should be under half of it.
+pub fn phase_correlate(a: &PlanarImage<f32, 1>, b: &PlanarImage<f32, 1>) -> PhaseCorrelation {
+    assert!(a.width() == b.width() && a.height() == b.height());
+    let (fa, fb) = (Spectrum::new(&hann_window(a)), Spectrum::new(&hann_window(b)));
+    let mut cross = Spectrum::new(&PlanarImage::new_w_h(a.width(), a.height()));
+    for (c, (&x, &y)) in cross.data.iter_mut().zip(fa.data.iter().zip(fb.data.iter())) {
+        let product = x.conj() * y;
+        let norm = product.norm_sqr().sqrt();
+        *c = if norm > 1e-12 { product * (1.0 / norm) } else { Complex::default() };
+    }
+    let surface: Vec<f64> = cross.inverse().iter().map(|c| c.re).collect();
+    let (width, height) = (cross.width, cross.height);
+    let peak = (0..surface.len()).fold(0, |best, i| if surface[i] > surface[best] { i } else { best });
+    let (px, py) = (peak % width, peak / width);
+    let at = |x: usize, y: usize| surface[y * width + x];
+    // a parabola through the peak and its neighbours on each axis, wrapping around
+    let refine = |before: f64, center: f64, after: f64| {
+        let curvature = before - 2.0 * center + after;
+        if curvature < -1e-12 { (0.5 * (before - after) / curvature).clamp(-0.5, 0.5) } else { 0.0 }
+    };
+    let dx = refine(at((px + width - 1) % width, py), at(px, py), at((px + 1) % width, py));
+    let dy = refine(at(px, (py + height - 1) % height), at(px, py), at(px, (py + 1) % height));
+    let signed = |i: usize, n: usize| if i > n / 2 { i as f64 - n as f64 } else { i as f64 };
+    PhaseCorrelation {
+        translation: PointF64::new(signed(px, width) + dx, signed(py, height) + dy),
+        response: at(px, py),
+    }
+}
+
+/// The similarity transform mapping points of `a` to the same points in `b` (images of the same size),
+/// e.g. to register two scans. Rotation and scale are found by phase correlation of the log-polar
+/// resampled magnitude spectra, which are invariant to translation; the translation is then found
+/// on `b` with its rotation and scale undone. Suits rotations up to 180° and scales of about 0.5 to 2.
+/// Empty images are aligned by the identity.
+pub fn align_similarity(a: &PlanarImage<f32, 1>, b: &PlanarImage<f32, 1>) -> SimilarityTransform {
+    assert!(a.width() == b.width() && a.height() == b.height());
+    if a.width() == 0 || a.height() == 0 {
+        return SimilarityTransform::default();
+    }
+    let size = a.width().max(a.height()).next_power_of_two();
+    let (la, lb) = (log_polar_spectrum(a, size), log_polar_spectrum(b, size));
+    let shift = phase_correlate(&la, &lb).translation;
+    let angle_step = std::f64::consts::PI / size as f64;
+    let log_step = ((size / 2) as f64).ln() / size as f64;
+    let (angle, scale) = (shift.x * angle_step, (-shift.y * log_step).exp());
+    let center = PointF64::new(a.width() as f64 / 2.0, a.height() as f64 / 2.0);
+
+    // the spectra are symmetric, so the rotation is only known up to a half turn
+    [angle, angle + std::f64::consts::PI]
+        .into_iter()
+        .map(|angle| {
+            let warp = SimilarityTransform::about(center, scale, angle, PointF64::new(0.0, 0.0));
+            let unwarped = warp_image(b, &warp);
+            let correlation = phase_correlate(a, &unwarped);
+            let offset = correlation.translation;
+            let translation = warp.transform(center + offset) - warp.transform(center);
+            (SimilarityTransform::about(center, scale, angle, translation), correlation.response)
+        })
+        .fold(None, |best: Option<(SimilarityTransform, f64)>, candidate| match best {
+            Some(best) if best.1 >= candidate.1 => Some(best),
+            _ => Some(candidate),
+        })
+        .unwrap()
+        .0
+}
+
+/// `image` multiplied by a Hann window, tapering to zero at the edges
+fn hann_window(image: &PlanarImage<f32, 1>) -> PlanarImage<f32, 1> {
+    let (width, height) = (image.width(), image.height());
+    let hann = |i: usize, n: usize| {
+        if n < 2 { 1.0 } else { 0.5 - 0.5 * (std::f64::consts::TAU * i as f64 / (n - 1) as f64).cos() }
+    };
+    let mut windowed = PlanarImage::new_w_h(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            windowed.set(x, y, 0, (image.get(x, y, 0) as f64 * hann(x, width) * hann(y, height)) as f32);
+        }
+    }
+    windowed
+}
+
+/// The log magnitude spectrum of `image`, `size` x `size`: angles over a half turn along x,
+/// the log of the radius (from 1 to `size / 2` frequency bins) along y
+fn log_polar_spectrum(image: &PlanarImage<f32, 1>, size: usize) -> PlanarImage<f32, 1> {
+    let mut square = PlanarImage::new_w_h(size, size);
+    let windowed = hann_window(image);
+    for y in 0..image.height() {
+        for x in 0..image.width() {
+            square.set(x, y, 0, windowed.get(x, y, 0));
+        }
+    }
+    let spectrum = Spectrum::new(&square);
+    let magnitude = |u: i64, v: i64| {
+        let (x, y) = (u.rem_euclid(size as i64) as usize, v.rem_euclid(size as i64) as usize);
+        (1.0 + spectrum.data[y * size + x].norm_sqr().sqrt()).ln()
+    };
+    let log_step = ((size / 2) as f64).ln() / size as f64;
+    let mut polar = PlanarImage::new_w_h(size, size);
+    for j in 0..size {
+        let radius = (j as f64 * log_step).exp();
+        for i in 0..size {
+            let angle = std::f64::consts::PI * i as f64 / size as f64;
+            let (u, v) = (radius * angle.cos(), radius * angle.sin());
+            let (u0, v0) = (u.floor(), v.floor());
+            let (tu, tv) = (u - u0, v - v0);
+            let (u0, v0) = (u0 as i64, v0 as i64);
+            let value = magnitude(u0, v0) * (1.0 - tu) * (1.0 - tv) + magnitude(u0 + 1, v0) * tu * (1.0 - tv) +
+                magnitude(u0, v0 + 1) * (1.0 - tu) * tv + magnitude(u0 + 1, v0 + 1) * tu * tv;
+            polar.set(i, j, 0, value as f32);
+        }
+    }
+    polar
+}
+
+/// `image` sampled (bilinearly) at `transform` of each pixel, 0 outside
+fn warp_image(image: &PlanarImage<f32, 1>, transform: &SimilarityTransform) -> PlanarImage<f32, 1> {
+    let (width, height) = (image.width(), image.height());
+    let mut warped = PlanarImage::new_w_h(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            let p = transform.transform(PointF64::new(x as f64, y as f64));
+            warped.set(x, y, 0, image.sample_bilinear(p.x, p.y, BorderMode::Constant)[0]);
+        }
+    }
+    warped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::synth::SynthRng;
+
+    /// Gaussian blobs at random spots, seen through `view`: the blob at `c` appears at `view(c)`
+    fn blobs(size: usize, view: &SimilarityTransform) -> PlanarImage<f32, 1> {
+        let mut rng = SynthRng::new(11);
+        let centers: Vec<PointF64> = (0..40).map(|_| PointF64::new(rng.range(16.0, 112.0), rng.range(16.0, 112.0))).collect();
+        let mut image = PlanarImage::new_w_h(size, size);
+        for y in 0..size {
+            for x in 0..size {
+                let p = view.transform_inverse(PointF64::new(x as f64, y as f64));
+                let v: f64 = centers.iter().map(|c| (-(p - *c).norm().powi(2) / 18.0).exp()).sum();
+                image.set(x, y, 0, v as f32);
+            }
+        }
+        image
+    }
+
+    #[test]
+    fn register_shift_and_similarity() {
+        let a = blobs(128, &SimilarityTransform::default());
+        let shift = PointF64::new(5.5, -3.0);
+        let b = blobs(128, &SimilarityTransform::new(1.0, 0.0, shift));
+        let correlation = phase_correlate(&a, &b);
+        assert!((correlation.translation - shift).norm() < 0.25, "{:?}", correlation);
+        assert!(correlation.response > 0.2, "{:?}", correlation);
+
+        let center = PointF64::new(64.0, 64.0);
+        let truth = SimilarityTransform::about(center, 1.1, 0.2, PointF64::new(2.0, 1.0));
+        let b = blobs(128, &truth);
+        let found = align_similarity(&a, &b);
+        assert!((found.angle - truth.angle).abs() < 0.03, "{:?}", found);
+        assert!((found.scale - truth.scale).abs() < 0.03, "{:?}", found);
+        assert!((found.transform(center) - truth.transform(center)).norm() < 1.0, "{:?}", found);
+    }
+}
@@ -0,0 +1,49 @@
+use crate::PathSimplifyMode;
+use super::Config;
+
+/// `Config` for logos and other flat art of at most `max_colors` colors: the clusters are quantized
+/// to a locked palette (so that anti-aliased edges and slight shading do not add colors), the clusters
+/// of each palette color merged, and the outlines fitted with splines keeping corners sharper than usual.
+pub fn logo(max_colors: usize) -> Config {
+    assert!(max_colors > 0);
+    Config {
+        max_colors,
+        filter_speckle: 8,
+        mode: PathSimplifyMode::Spline,
+        corner_threshold: 45.0_f64.to_radians(),
+        ..Config::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Color, ColorImage};
+    use crate::pipeline::VectorizeJob;
+
+    #[test]
+    fn logo_palette() {
+        // a red disc shaded in two tones and a blue bar on white
+        let mut image = ColorImage::new_w_h(48, 32);
+        for y in 0..32 {
+            for x in 0..48 {
+                let (dx, dy) = (x as i32 - 16, y as i32 - 16);
+                let color = if dx * dx + dy * dy < 100 {
+                    if x < 16 { Color::new(230, 30, 30) } else { Color::new(150, 20, 20) }
+                } else if (32..40).contains(&x) && (4..28).contains(&y) {
+                    Color::new(20, 40, 180)
+                } else {
+                    Color::new(255, 255, 255)
+                };
+                image.set_pixel(x, y, &color);
+            }
+        }
+        let result = VectorizeJob::new(image.clone(), logo(3)).run();
+        assert_eq!(result.metrics.num_colors, 3);
+        assert_eq!(result.palette.len(), 3);
+        assert_eq!(result.clusters.output_len(), 3);
+
+        let unlimited = VectorizeJob::new(image, Config::default()).run();
+        assert!(unlimited.metrics.num_colors > 3);
+    }
+}
@@ -0,0 +1,212 @@
+//! The whole vectorization pipeline, from image to colored paths, assembled in the right order:
+//! clustering, speckle filtering, path tracing and curve fitting, then measurements of the result.
+//! Ready-made pipelines for common kinds of input are alongside, e.g. `logo` and `whiteboard`.
+
+mod logo;
+mod whiteboard;
+
+pub use logo::*;
+pub use whiteboard::*;
+
+use std::time::Duration;
+use crate::{Color, ColorImage, DescreenConfig, PathSimplifyMode};
+use crate::color_clusters::{Clusters, Runner, RunnerConfig, TracedPath, color_diff};
+
+/// Parameters of `VectorizeJob`; the curve fitting parameters are as in `Cluster::to_compound_path`
+#[derive(Clone, Debug, PartialEq)]
+#[non_exhaustive]
+pub struct Config {
+    pub runner: RunnerConfig,
+    /// halftone screens are removed before clustering, see `ColorImage::descreen`;
+    /// the metrics are then measured against the descreened image
+    pub descreen: bool,
+    /// clusters are clustered on the image downscaled by this factor, see `Runner::run_downscaled`
+    pub downscale: usize,
+    /// if not 0, the colors of the clusters are reduced to at most this many before tracing,
+    /// and the clusters of each color merged, see `Clusters::quantize`
+    pub max_colors: usize,
+    /// clusters smaller than this (in pixels) are not traced
+    pub filter_speckle: usize,
+    pub mode: PathSimplifyMode,
+    pub corner_threshold: f64,
+    pub length_threshold: f64,
+    pub max_iterations: usize,
+    pub splice_threshold: f64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            runner: RunnerConfig::default(),
+            descreen: false,
+            downscale: 1,
+            max_colors: 0,
+            filter_speckle: 4,
+            mode: PathSimplifyMode::Spline,
+            corner_threshold: 60.0_f64.to_radians(),
+            length_threshold: 4.0,
+            max_iterations: 10,
+            splice_threshold: 45.0_f64.to_radians(),
+        }
+    }
+}
+
+/// How well the traced clusters reproduce the image
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct QualityMetrics {
+    pub num_paths: usize,
+    /// number of distinct colors among the paths
+    pub num_colors: usize,
+    /// mean `color_diff` per pixel between the image and the rendered clusters
+    pub mean_color_error: f64,
+    /// fraction of pixels with a `color_diff` over 48
+    pub error_ratio: f64,
+}
+
+/// Time spent in each stage; all zero where the platform has no clock (e.g. wasm)
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Timings {
+    pub clustering: Duration,
+    pub tracing: Duration,
+    pub metrics: Duration,
+}
+
+pub struct VectorizeResult {
+    pub clusters: Clusters,
+    /// in paint order
+    pub paths: Vec<TracedPath>,
+    /// colors of the paths with the area of each (including the clusters drawn on top), by decreasing area
+    pub palette: Vec<(Color, usize)>,
+    pub metrics: QualityMetrics,
+    pub timings: Timings,
+}
+
+/// A vectorization of one image, see the module documentation
+pub struct VectorizeJob {
+    image: ColorImage,
+    config: Config,
+}
+
+/// A pixel is counted as an error in `QualityMetrics::error_ratio` above this difference
+const ERROR_DIFF: i32 = 48;
+
+impl VectorizeJob {
+    pub fn new(image: ColorImage, config: Config) -> Self {
+        Self { image, config }
+    }
+
+    pub fn run(self) -> VectorizeResult {
+        let Self { image, config } = self;
+        let mut timings = Timings::default();
+
+        let clock = Clock::start();
+        let image = if config.descreen { image.descreen(&DescreenConfig::default()) } else { image };
+        let mut clusters = Runner::new(config.runner.clone(), image.clone()).run_downscaled(config.downscale);
+        if config.max_colors > 0 {
+            clusters = clusters.quantize(config.max_colors);
+        }
+        timings.clustering = clock.elapsed();
+
+        let clock = Clock::start();
+        let view = clusters.view();
+        let mut paths = Vec::new();
+        let mut palette: Vec<(Color, usize)> = Vec::new();
+        for &index in view.clusters_output.iter().rev() {
+            let cluster = view.get_cluster(index);
+            if cluster.area() < config.filter_speckle {
+                continue;
+            }
+            let color = cluster.residue_color();
+            paths.push(TracedPath {
+                path: cluster.to_compound_path(
+                    &view, false, config.mode, config.corner_threshold, config.length_threshold,
+                    config.max_iterations, config.splice_threshold,
+                ),
+                color,
+            });
+            match palette.iter_mut().find(|(c, _)| *c == color) {
+                Some(entry) => entry.1 += cluster.area(),
+                None => palette.push((color, cluster.area())),
+            }
+        }
+        palette.sort_by_key(|&(_, area)| std::cmp::Reverse(area));
+        timings.tracing = clock.elapsed();
+
+        let clock = Clock::start();
+        let rendered = view.to_color_image();
+        let (mut sum, mut errors) = (0u64, 0usize);
+        for (a, b) in image.iter().zip(rendered.iter()) {
+            let diff = color_diff(a, b);
+            sum += diff as u64;
+            if diff > ERROR_DIFF {
+                errors += 1;
+            }
+        }
+        let pixels = std::cmp::max(image.width * image.height, 1) as f64;
+        let metrics = QualityMetrics {
+            num_paths: paths.len(),
+            num_colors: palette.len(),
+            mean_color_error: sum as f64 / pixels,
+            error_ratio: errors as f64 / pixels,
+        };
+        timings.metrics = clock.elapsed();
+
+        VectorizeResult {
+            clusters,
+            paths,
+            palette,
+            metrics,
+            timings,
+        }
+    }
+}
+
+/// `Instant`, where the platform has one
+pub(crate) struct Clock(Option<std::time::Instant>);
+
+impl Clock {
+    pub(crate) fn start() -> Self {
+        #[cfg(not(target_arch = "wasm32"))]
+        return Self(Some(std::time::Instant::now()));
+        #[cfg(target_arch = "wasm32")]
+        return Self(None);
+    }
+
+    pub(crate) fn elapsed(&self) -> Duration {
+        self.0.map_or(Duration::ZERO, |start| start.elapsed())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vectorize_job() {
+        // two colored squares on white, and a speckle
+        let mut image = ColorImage::new_w_h(32, 32);
+        for y in 0..32 {
+            for x in 0..32 {
+                let color = if (4..12).contains(&x) && (4..12).contains(&y) {
+                    Color::new(200, 30, 30)
+                } else if (16..28).contains(&x) && (16..28).contains(&y) {
+                    Color::new(30, 30, 200)
+                } else {
+                    Color::new(255, 255, 255)
+                };
+                image.set_pixel(x, y, &color);
+            }
+        }
+        image.set_pixel(30, 2, &Color::new(0, 0, 0));
+
+        let result = VectorizeJob::new(image, Config::default()).run();
+        assert_eq!(result.metrics.num_paths, 3);
+        assert_eq!(result.palette.len(), 3);
+        assert_eq!(result.palette[1], (Color::new(30, 30, 200), 144));
+        assert_eq!(result.palette[2], (Color::new(200, 30, 30), 64));
+        // the speckle is merged into the background
+        assert_eq!(result.metrics.error_ratio, 1.0 / 1024.0);
+        assert!(result.metrics.mean_color_error < 4.0);
+        assert!(result.paths.iter().all(|p| !p.path.paths.is_empty()));
+    }
+}
@@ -0,0 +1,122 @@
+use crate::{BinaryImage, Color, ColorImage, Components, IlluminationModel, PathF64, PointF64, distance_to_mask};
+use crate::reduce::simplify_douglas_peucker;
+
+/// Parameters of `whiteboard`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct WhiteboardConfig {
+    pub illumination: IlluminationModel,
+    /// pixels of a luma below this after flattening the illumination are ink; as the background is then
+    /// uniformly white, this is in effect a threshold adapting to the local brightness of the board
+    pub ink_threshold: f32,
+    /// ink components of fewer pixels than this are specks (dust, noise) and removed
+    pub min_speck_area: usize,
+    /// tolerance (in pixels) of the Douglas-Peucker simplification of the centerlines
+    pub simplify_tolerance: f64,
+    /// centerlines shorter than this (in pixels) are dropped, e.g. small spurs of the skeleton
+    pub min_stroke_length: f64,
+}
+
+impl Default for WhiteboardConfig {
+    fn default() -> Self {
+        Self {
+            illumination: IlluminationModel::default(),
+            ink_threshold: 200.0,
+            min_speck_area: 16,
+            simplify_tolerance: 1.0,
+            min_stroke_length: 4.0,
+        }
+    }
+}
+
+/// A pen stroke, along its centerline
+#[derive(Clone, Debug)]
+pub struct Stroke {
+    /// open polyline through pixel centers
+    pub path: PathF64,
+    /// mean color of the ink along the stroke, after flattening
+    pub color: Color,
+    /// mean width of the ink along the stroke, in pixels
+    pub width: f64,
+}
+
+pub struct WhiteboardResult {
+    /// the image with the illumination evened out, see `ColorImage::flatten_illumination`
+    pub flattened: ColorImage,
+    /// the ink, despeckled
+    pub ink: BinaryImage,
+    pub strokes: Vec<Stroke>,
+}
+
+/// Vectorizes a photo of a whiteboard (or of a page of handwriting) into pen strokes:
+/// illumination correction, thresholding, despeckling, then centerline tracing of the skeleton of the ink
+pub fn whiteboard(image: &ColorImage, config: &WhiteboardConfig) -> WhiteboardResult {
+    let flattened = image.flatten_illumination(config.illumination);
+    let luma = flattened.luma();
+    let mut ink = BinaryImage::new_w_h(image.width, image.height);
+    for (i, &v) in luma.channel(0).iter().enumerate() {
+        ink.set_pixel_index(i, v < config.ink_threshold);
+    }
+    let ink = Components::new(&ink, true).filter().min_area(config.min_speck_area).to_binary_image();
+
+    let mut paper = ink.clone();
+    paper.pixels.negate();
+    // distance from the middle of a stroke of width w to the paper is (w + 1) / 2
+    let distance = distance_to_mask(&paper, f64::MAX);
+
+    let mut strokes = Vec::new();
+    for centerline in ink.to_skeleton().image.trace_centerlines() {
+        let points: Vec<PointF64> = centerline.iter().map(|p| PointF64::new(p.x as f64, p.y as f64)).collect();
+        let length: f64 = points.windows(2).map(|w| w[0].distance_to(w[1])).sum();
+        if length < config.min_stroke_length {
+            continue;
+        }
+        let n = points.len() as f64;
+        let mut sum = [0.0; 3];
+        let mut half_width = 0.0;
+        for p in points.iter() {
+            let (x, y) = (p.x as usize, p.y as usize);
+            let c = flattened.get_pixel(x, y);
+            sum[0] += c.r as f64;
+            sum[1] += c.g as f64;
+            sum[2] += c.b as f64;
+            half_width += distance.get(x, y, 0) as f64;
+        }
+        let tolerance = config.simplify_tolerance;
+        strokes.push(Stroke {
+            path: PathF64::from_points(simplify_douglas_peucker(&points, tolerance * tolerance)),
+            color: Color::new((sum[0] / n).round() as u8, (sum[1] / n).round() as u8, (sum[2] / n).round() as u8),
+            width: (2.0 * half_width / n - 1.0).max(1.0),
+        });
+    }
+    WhiteboardResult { flattened, ink, strokes }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BoundingRect;
+
+    #[test]
+    fn whiteboard_strokes() {
+        // a blue horizontal stroke 3 pixels wide on a board shaded left to right, and a speck
+        let mut board = ColorImage::new_w_h(100, 40);
+        for y in 0..40 {
+            for x in 0..100 {
+                let v = 250 - x as u8;
+                board.set_pixel(x, y, &Color::new(v, v, v));
+            }
+        }
+        board.fill_rect(&BoundingRect::new_x_y_w_h(10, 19, 80, 3), &Color::new(20, 40, 120));
+        board.fill_rect(&BoundingRect::new_x_y_w_h(50, 5, 2, 2), &Color::new(20, 20, 20));
+
+        let result = whiteboard(&board, &WhiteboardConfig::default());
+        assert!(!result.ink.get_pixel(50, 5));
+        assert!(result.ink.get_pixel(50, 20));
+        assert_eq!(result.strokes.len(), 1);
+        let stroke = &result.strokes[0];
+        assert!(stroke.path.len() <= 3);
+        assert!(stroke.path.iter().all(|p| (p.y - 20.0).abs() <= 1.0));
+        assert!((stroke.width - 3.0).abs() <= 1.0, "{}", stroke.width);
+        assert!(stroke.color.b > stroke.color.r + 50);
+    }
+}
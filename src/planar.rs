@@ -0,0 +1,229 @@
+use crate::{Color, ColorImage, Image, ImageMut};
+
+/// Image with `C` channels of `T`, each channel stored in its own contiguous plane.
+///
+/// Intended as the home of intermediate results, e.g. gradient fields (`PlanarImage<f32, 2>`),
+/// distance fields (`PlanarImage<f32, 1>`) and Oklab buffers (`PlanarImage<f32, 3>`).
+#[derive(Clone, Debug, PartialEq)]
+pub struct PlanarImage<T, const C: usize> {
+    planes: [Vec<T>; C],
+    width: usize,
+    height: usize,
+}
+
+impl<T, const C: usize> Default for PlanarImage<T, C> {
+    fn default() -> Self {
+        Self {
+            planes: std::array::from_fn(|_| Vec::new()),
+            width: 0,
+            height: 0,
+        }
+    }
+}
+
+impl<T, const C: usize> PlanarImage<T, C> where T: Copy + Default {
+    pub fn new_w_h(width: usize, height: usize) -> Self {
+        Self::new_w_h_with_value(width, height, T::default())
+    }
+
+    /// Constructs an image with every channel of every pixel set to `value`
+    pub fn new_w_h_with_value(width: usize, height: usize, value: T) -> Self {
+        Self {
+            planes: std::array::from_fn(|_| vec![value; crate::image::checked_area(width, height)]),
+            width,
+            height,
+        }
+    }
+}
+
+impl<T, const C: usize> PlanarImage<T, C> where T: Copy {
+    /// Constructs an image from planes. Returns `None` if any plane is not of size `width * height`.
+    pub fn from_planes(width: usize, height: usize, planes: [Vec<T>; C]) -> Option<Self> {
+        if width.checked_mul(height).is_some_and(|n| planes.iter().all(|p| p.len() == n)) {
+            Some(Self { planes, width, height })
+        } else {
+            None
+        }
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn num_channels(&self) -> usize {
+        C
+    }
+
+    /// Returns all channels of the pixel at (x, y)
+    pub fn get_pixel(&self, x: usize, y: usize) -> [T; C] {
+        let i = y * self.width + x;
+        std::array::from_fn(|c| self.planes[c][i])
+    }
+
+    pub fn set_pixel(&mut self, x: usize, y: usize, v: [T; C]) {
+        let i = y * self.width + x;
+        for (c, v) in v.into_iter().enumerate() {
+            self.planes[c][i] = v;
+        }
+    }
+
+    /// Returns a single channel of the pixel at (x, y)
+    pub fn get(&self, x: usize, y: usize, c: usize) -> T {
+        self.planes[c][y * self.width + x]
+    }
+
+    /// A single channel of the pixel at (x, y), or None if outside the image or `c` is not a channel
+    pub fn get_checked(&self, x: usize, y: usize, c: usize) -> Option<T> {
+        if x < self.width && y < self.height && c < C {
+            Some(self.planes[c][y * self.width + x])
+        } else {
+            None
+        }
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, c: usize, v: T) {
+        self.planes[c][y * self.width + x] = v;
+    }
+
+    /// The plane of channel `c`, in row major order
+    pub fn channel(&self, c: usize) -> &[T] {
+        &self.planes[c]
+    }
+
+    pub fn channel_mut(&mut self, c: usize) -> &mut [T] {
+        &mut self.planes[c]
+    }
+
+    pub fn into_planes(self) -> [Vec<T>; C] {
+        self.planes
+    }
+
+    /// Applies `f` to each pixel, producing an image with possibly different type and channel count
+    pub fn map<U, const D: usize>(&self, f: impl Fn([T; C]) -> [U; D]) -> PlanarImage<U, D>
+        where U: Copy + Default {
+        let mut image = PlanarImage::new_w_h(self.width, self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                image.set_pixel(x, y, f(self.get_pixel(x, y)));
+            }
+        }
+        image
+    }
+}
+
+impl PlanarImage<u8, 4> {
+    /// Splits an RGBA `ColorImage` into 4 planes
+    pub fn from_color_image(image: &ColorImage) -> Self {
+        let mut planar = Self::new_w_h(image.width, image.height);
+        for (i, p) in image.pixels.chunks_exact(4).enumerate() {
+            for (c, &v) in p.iter().enumerate() {
+                planar.planes[c][i] = v;
+            }
+        }
+        planar
+    }
+
+    pub fn to_color_image(&self) -> ColorImage {
+        let mut image = ColorImage::new_w_h(self.width, self.height);
+        for i in 0..self.width * self.height {
+            image.set_pixel_at(i, &Color::new_rgba(
+                self.planes[0][i], self.planes[1][i], self.planes[2][i], self.planes[3][i],
+            ));
+        }
+        image
+    }
+}
+
+impl PlanarImage<f32, 3> {
+    /// Converts the RGB channels of a `ColorImage` into Oklab `l`, `a`, `b` planes
+    pub fn oklab_from_color_image(image: &ColorImage) -> Self {
+        let mut planar = Self::new_w_h(image.width, image.height);
+        for (i, c) in image.iter().enumerate() {
+            let lab: oklab::Oklab = oklab::Rgb { r: c.r, g: c.g, b: c.b }.into();
+            planar.planes[0][i] = lab.l;
+            planar.planes[1][i] = lab.a;
+            planar.planes[2][i] = lab.b;
+        }
+        planar
+    }
+
+    /// Converts Oklab planes back to an opaque `ColorImage`
+    pub fn oklab_to_color_image(&self) -> ColorImage {
+        let mut image = ColorImage::new_w_h(self.width, self.height);
+        for i in 0..self.width * self.height {
+            let rgb = oklab::oklab_to_srgb(oklab::Oklab {
+                l: self.planes[0][i],
+                a: self.planes[1][i],
+                b: self.planes[2][i],
+            });
+            image.set_pixel_at(i, &Color::new(rgb.r, rgb.g, rgb.b));
+        }
+        image
+    }
+}
+
+impl<T, const C: usize> Image for PlanarImage<T, C> where T: Copy {
+    type Pixel = [T; C];
+
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn pixel(&self, x: usize, y: usize) -> [T; C] {
+        self.get_pixel(x, y)
+    }
+}
+
+impl<T, const C: usize> ImageMut for PlanarImage<T, C> where T: Copy + Default {
+    fn new_image(width: usize, height: usize) -> Self {
+        Self::new_w_h(width, height)
+    }
+
+    fn put_pixel(&mut self, x: usize, y: usize, v: [T; C]) {
+        self.set_pixel(x, y, v);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn planar_color_round_trip() {
+        let mut image = ColorImage::new_w_h(2, 2);
+        image.set_pixel(1, 0, &Color::new_rgba(1, 2, 3, 4));
+        let planar = PlanarImage::from_color_image(&image);
+        assert_eq!(planar.get_pixel(1, 0), [1, 2, 3, 4]);
+        assert_eq!(planar.channel(2), &[0, 3, 0, 0]);
+        assert_eq!(planar.to_color_image().pixels, image.pixels);
+    }
+
+    #[test]
+    fn planar_map() {
+        let mut field = PlanarImage::<f32, 2>::new_w_h(2, 1);
+        field.set_pixel(0, 0, [3.0, 4.0]);
+        let magnitude = field.map(|[x, y]| [(x * x + y * y).sqrt()]);
+        assert_eq!(magnitude.channel(0), &[5.0, 0.0]);
+        assert_eq!(PlanarImage::<u8, 1>::from_planes(2, 2, [vec![0; 3]]), None);
+    }
+
+    #[test]
+    fn planar_oklab_round_trip() {
+        let mut image = ColorImage::new_w_h(1, 1);
+        image.set_pixel(0, 0, &Color::new(200, 100, 50));
+        let lab = PlanarImage::oklab_from_color_image(&image);
+        assert!(lab.get(0, 0, 0) > 0.0 && lab.get(0, 0, 0) < 1.0);
+        let back = lab.oklab_to_color_image().get_pixel(0, 0);
+        assert!((back.r as i32 - 200).abs() <= 1);
+        assert!((back.g as i32 - 100).abs() <= 1);
+        assert!((back.b as i32 - 50).abs() <= 1);
+    }
+}
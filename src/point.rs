@@ -400,6 +400,14 @@ impl PointF64 {
     }
 }
 
+/// The distance from `p` to the nearest point of the segment from `a` to `b`
+pub(crate) fn distance_to_segment(p: PointF64, a: PointF64, b: PointF64) -> f64 {
+    let ab = b - a;
+    let len2 = ab.dot(ab);
+    let t = if len2 > 0.0 { ((p - a).dot(ab) / len2).clamp(0.0, 1.0) } else { 0.0 };
+    p.distance_to(a + ab * t)
+}
+
 impl PointType for PointI32 {
     fn from<P: PointType>(p: &P) -> Self {
         p.to_point_i32()
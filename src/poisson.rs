@@ -0,0 +1,147 @@
+use crate::{BinaryImage, Color, ColorImage, PointI32};
+
+/// Neighbours of a pixel, 4-connected
+const NEIGHBOURS: [(i32, i32); 4] = [(-1, 0), (1, 0), (0, -1), (0, 1)];
+
+impl ColorImage {
+    /// Seamless cloning (Pérez et al.): pastes the pixels of `source` under `mask` (of the same size) with its
+    /// top left at `offset`, keeping the gradients of `source` inside the mask while matching this image along
+    /// the edge of the mask, by solving the Poisson equation (with conjugate gradients) on each color channel.
+    /// The alpha of this image is kept; pixels of the mask falling outside this image are skipped.
+    pub fn seamless_clone(&mut self, source: &ColorImage, mask: &BinaryImage, offset: PointI32) {
+        assert!(source.width == mask.width && source.height == mask.height);
+        let (width, height) = (self.width as i32, self.height as i32);
+        let inside = |x: i32, y: i32| x >= 0 && y >= 0 && x < width && y < height;
+
+        // the unknown pixels, in target coordinates, and the index of each in the mask
+        let mut unknowns = Vec::new();
+        let mut index = vec![usize::MAX; mask.width * mask.height];
+        for y in 0..mask.height {
+            for x in 0..mask.width {
+                let (tx, ty) = (x as i32 + offset.x, y as i32 + offset.y);
+                if mask.get_pixel(x, y) && inside(tx, ty) {
+                    index[y * mask.width + x] = unknowns.len();
+                    unknowns.push((x as i32, y as i32));
+                }
+            }
+        }
+        if unknowns.is_empty() {
+            return;
+        }
+        let unknown_at = |x: i32, y: i32| {
+            if x < 0 || y < 0 || x >= mask.width as i32 || y >= mask.height as i32 {
+                return None;
+            }
+            Some(index[y as usize * mask.width + x as usize]).filter(|&i| i != usize::MAX)
+        };
+        // for each unknown, its neighbours in the image: another unknown, or a fixed pixel of this image
+        let neighbours: Vec<Vec<(i32, i32, Option<usize>)>> = unknowns.iter()
+            .map(|&(x, y)| NEIGHBOURS.iter()
+                .map(|&(dx, dy)| (x + dx, y + dy))
+                .filter(|&(nx, ny)| inside(nx + offset.x, ny + offset.y))
+                .map(|(nx, ny)| (nx, ny, unknown_at(nx, ny)))
+                .collect())
+            .collect();
+        let apply = |v: &[f64], out: &mut [f64]| {
+            for (i, list) in neighbours.iter().enumerate() {
+                out[i] = list.len() as f64 * v[i] - list.iter().filter_map(|n| n.2).map(|j| v[j]).sum::<f64>();
+            }
+        };
+
+        let mut solved = vec![[0.0; 3]; unknowns.len()];
+        for channel in 0..3 {
+            let value = |c: Color| [c.r, c.g, c.b][channel] as f64;
+            let guide = |x: i32, y: i32| source.get_pixel_safe(x, y).map(value);
+            let b: Vec<f64> = unknowns.iter().zip(neighbours.iter())
+                .map(|(&(x, y), list)| {
+                    let g = value(source.get_pixel(x as usize, y as usize));
+                    list.iter().map(|&(nx, ny, unknown)| {
+                        let gradient = g - guide(nx, ny).unwrap_or(g);
+                        let fixed = if unknown.is_none() {
+                            value(self.get_pixel((nx + offset.x) as usize, (ny + offset.y) as usize))
+                        } else {
+                            0.0
+                        };
+                        gradient + fixed
+                    }).sum()
+                })
+                .collect();
+            let x0: Vec<f64> = unknowns.iter().map(|&(x, y)| value(source.get_pixel(x as usize, y as usize))).collect();
+            for (s, v) in solved.iter_mut().zip(conjugate_gradient(&apply, &b, x0)) {
+                s[channel] = v;
+            }
+        }
+        for (&(x, y), s) in unknowns.iter().zip(solved.iter()) {
+            let (tx, ty) = ((x + offset.x) as usize, (y + offset.y) as usize);
+            let channel = |v: f64| v.round().clamp(0.0, 255.0) as u8;
+            let a = self.get_pixel(tx, ty).a;
+            self.set_pixel(tx, ty, &Color::new_rgba(channel(s[0]), channel(s[1]), channel(s[2]), a));
+        }
+    }
+}
+
+/// Solves `A x = b` for a symmetric positive definite `A`, given as `apply(x, A x)`, from `x`
+fn conjugate_gradient(apply: &impl Fn(&[f64], &mut [f64]), b: &[f64], mut x: Vec<f64>) -> Vec<f64> {
+    let n = b.len();
+    let dot = |a: &[f64], b: &[f64]| a.iter().zip(b).map(|(a, b)| a * b).sum::<f64>();
+    let mut ax = vec![0.0; n];
+    apply(&x, &mut ax);
+    let mut r: Vec<f64> = b.iter().zip(ax.iter()).map(|(b, a)| b - a).collect();
+    let mut p = r.clone();
+    let mut rr = dot(&r, &r);
+    // converged once off by well under a level per pixel
+    let tolerance = 1e-4 * n as f64;
+    let mut ap = vec![0.0; n];
+    for _ in 0..n.max(16) {
+        if rr <= tolerance {
+            break;
+        }
+        apply(&p, &mut ap);
+        let pap = dot(&p, &ap);
+        // `p` is zero or the system is singular along it, stop rather than divide by zero
+        if pap == 0.0 {
+            break;
+        }
+        let alpha = rr / pap;
+        for i in 0..n {
+            x[i] += alpha * p[i];
+            r[i] -= alpha * ap[i];
+        }
+        let next = dot(&r, &r);
+        let beta = next / rr;
+        rr = next;
+        for i in 0..n {
+            p[i] = r[i] + beta * p[i];
+        }
+    }
+    x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BoundingRect;
+
+    #[test]
+    fn clone_matches_the_target() {
+        // a light patch with a darker dot, cloned onto a mid gray
+        let mut source = ColorImage::new_w_h(15, 15);
+        source.fill_rect(&BoundingRect::new_x_y_w_h(0, 0, 15, 15), &Color::new(200, 200, 200));
+        source.fill_rect(&BoundingRect::new_x_y_w_h(6, 6, 3, 3), &Color::new(150, 150, 150));
+        let mut mask = BinaryImage::new_w_h(15, 15);
+        mask.fill_rect(&BoundingRect::new_x_y_w_h(2, 2, 11, 11), true);
+
+        let mut target = ColorImage::new_w_h(30, 30);
+        target.fill_rect(&BoundingRect::new_x_y_w_h(0, 0, 30, 30), &Color::new(100, 100, 100));
+        target.seamless_clone(&source, &mask, PointI32::new(10, 10));
+        // the flat surroundings take the gray of the target, the dot keeps its contrast
+        assert_eq!(target.get_pixel(13, 13), Color::new(100, 100, 100));
+        let dot = target.get_pixel(17, 17);
+        assert!((dot.r as i32 - 50).abs() <= 1, "{:?}", dot);
+        assert_eq!(target.get_pixel(5, 5), Color::new(100, 100, 100));
+
+        // partly outside the target
+        target.seamless_clone(&source, &mask, PointI32::new(-8, 20));
+        assert_eq!(target.get_pixel(0, 24).r, 100);
+    }
+}
@@ -0,0 +1,158 @@
+use crate::{CameraIntrinsics, Matrix, PerspectiveTransform, PointF64};
+
+/// The pose of a plane (z = 0 in its own coordinates, e.g. a marker) relative to a camera:
+/// a point `p` of the plane is at `rotation` · p + `translation` in camera coordinates
+#[derive(Clone, Debug)]
+pub struct PlanarPose {
+    pub rotation: Matrix<3, 3>,
+    pub translation: [f64; 3],
+    /// root mean square distance, in pixels, between where the pose projects the points scored
+    /// and where the homography maps them
+    pub error: f64,
+}
+
+impl PlanarPose {
+    /// The point `p` of the plane in camera coordinates
+    pub fn transform(&self, p: PointF64) -> [f64; 3] {
+        let r = self.rotation.dot_mv(&[p.x, p.y, 0.0]);
+        [r[0] + self.translation[0], r[1] + self.translation[1], r[2] + self.translation[2]]
+    }
+}
+
+/// Poses of a plane seen through `homography` (from plane coordinates to pixels, e.g. fitted to the
+/// corners of a marker) by the camera of `intrinsics`, best first, scored by reprojecting `points` of the plane
+/// (e.g. those corners). The first candidate comes from the columns of K⁻¹ H, the rotation made orthonormal;
+/// the second, where the plane is not seen head on, is its mirror image about the line of sight through
+/// the centroid of `points`, which under weak perspective looks the same (the planar pose ambiguity).
+/// `points` must not be empty, as it would score both candidates the same.
+/// Empty if the homography is degenerate.
+pub fn decompose_homography(homography: &PerspectiveTransform, intrinsics: &CameraIntrinsics, points: &[PointF64]) -> Vec<PlanarPose> {
+    assert!(!points.is_empty());
+    let h = match intrinsics.normalize_homography(homography) {
+        Some(h) => h,
+        None => return Vec::new(),
+    };
+    let column = |j: usize| [h.m[0][j], h.m[1][j], h.m[2][j]];
+    let (h1, h2, h3) = (column(0), column(1), column(2));
+    let scale = norm(h1) + norm(h2);
+    if scale < 1e-12 {
+        return Vec::new();
+    }
+    // the plane is in front of the camera
+    let lambda = if h3[2] < 0.0 { -2.0 / scale } else { 2.0 / scale };
+    let (r1, r2) = (mul(h1, lambda), mul(h2, lambda));
+    let rotation = match orthonormalize(columns(r1, r2, cross(r1, r2))) {
+        Some(rotation) => rotation,
+        None => return Vec::new(),
+    };
+    let translation = mul(h3, lambda);
+    let mut poses = vec![(rotation.clone(), translation)];
+
+    let n = points.len() as f64;
+    let centroid = points.iter().fold(PointF64::new(0.0, 0.0), |sum, &p| sum + p) * (1.0 / n);
+    let pivot = add(rotation.dot_mv(&[centroid.x, centroid.y, 0.0]), translation);
+    let sight = mul(pivot, 1.0 / norm(pivot));
+    let normal = [rotation.m[0][2], rotation.m[1][2], rotation.m[2][2]];
+    let mirrored = sub(mul(sight, 2.0 * dot(normal, sight)), normal);
+    let axis = cross(normal, mirrored);
+    if norm(axis) > 1e-9 {
+        let turn = axis_angle(mul(axis, 1.0 / norm(axis)), dot(normal, mirrored).clamp(-1.0, 1.0).acos());
+        let flipped = turn.dot_mm_small(&rotation);
+        let translation = sub(pivot, flipped.dot_mv(&[centroid.x, centroid.y, 0.0]));
+        poses.push((flipped, translation));
+    }
+
+    let mut poses: Vec<PlanarPose> = poses.into_iter()
+        .map(|(rotation, translation)| {
+            let mut pose = PlanarPose { rotation, translation, error: 0.0 };
+            let sum: f64 = points.iter()
+                .map(|&p| match intrinsics.project(pose.transform(p)) {
+                    Some(pixel) => (pixel - homography.transform(p)).norm().powi(2),
+                    None => f64::INFINITY,
+                })
+                .sum();
+            pose.error = (sum / n).sqrt();
+            pose
+        })
+        .collect();
+    poses.sort_by(|a, b| a.error.total_cmp(&b.error));
+    poses
+}
+
+fn columns(a: [f64; 3], b: [f64; 3], c: [f64; 3]) -> Matrix<3, 3> {
+    Matrix::new([[a[0], b[0], c[0]], [a[1], b[1], c[1]], [a[2], b[2], c[2]]])
+}
+
+/// The nearest rotation, by iterating the average with the inverse transpose (converging to the polar decomposition)
+fn orthonormalize(mut m: Matrix<3, 3>) -> Option<Matrix<3, 3>> {
+    for _ in 0..20 {
+        let inverse = m.inv()?.transpose();
+        for i in 0..3 {
+            for j in 0..3 {
+                m.m[i][j] = 0.5 * (m.m[i][j] + inverse.m[i][j]);
+            }
+        }
+    }
+    Some(m)
+}
+
+/// Rotation by `angle` about the unit vector `axis`, by Rodrigues' formula
+fn axis_angle(axis: [f64; 3], angle: f64) -> Matrix<3, 3> {
+    let (s, c) = angle.sin_cos();
+    let [x, y, z] = axis;
+    let t = 1.0 - c;
+    Matrix::new([
+        [t * x * x + c, t * x * y - s * z, t * x * z + s * y],
+        [t * x * y + s * z, t * y * y + c, t * y * z - s * x],
+        [t * x * z - s * y, t * y * z + s * x, t * z * z + c],
+    ])
+}
+
+fn dot(a: [f64; 3], b: [f64; 3]) -> f64 {
+    Matrix::<1, 3>::dot_vv(&a, &b)
+}
+
+fn norm(a: [f64; 3]) -> f64 {
+    dot(a, a).sqrt()
+}
+
+fn mul(a: [f64; 3], s: f64) -> [f64; 3] {
+    a.map(|v| v * s)
+}
+
+fn add(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[0] + b[0], a[1] + b[1], a[2] + b[2]]
+}
+
+fn sub(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [a[1] * b[2] - a[2] * b[1], a[2] * b[0] - a[0] * b[2], a[0] * b[1] - a[1] * b[0]]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pose_of_tilted_marker() {
+        let camera = CameraIntrinsics::new(600.0, 600.0, 320.0, 240.0);
+        let rotation = axis_angle([0.0, 1.0, 0.0], 0.3).dot_mm_small(&axis_angle([1.0, 0.0, 0.0], 0.4));
+        let truth = PlanarPose { rotation, translation: [0.1, -0.05, 3.0], error: 0.0 };
+        let corners = [PointF64::new(0.0, 0.0), PointF64::new(1.0, 0.0), PointF64::new(1.0, 1.0), PointF64::new(0.0, 1.0)];
+        let seen = corners.map(|p| camera.project(truth.transform(p)).unwrap());
+        let homography = PerspectiveTransform::from_point_f64(&corners, &seen);
+
+        let poses = decompose_homography(&homography, &camera, &corners);
+        assert_eq!(poses.len(), 2);
+        let best = &poses[0];
+        assert!(best.rotation.eq(&truth.rotation, 1e-6), "{:?}", best);
+        assert!(best.translation.iter().zip(truth.translation).all(|(a, b)| (a - b).abs() < 1e-6));
+        assert!(best.error < 1e-6);
+        // the mirrored pose is near, but projects the corners elsewhere
+        assert!(poses[1].error > best.error);
+        assert!((norm(poses[1].translation) - norm(truth.translation)).abs() < 0.5);
+    }
+}
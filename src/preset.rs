@@ -0,0 +1,325 @@
+//! Named `pipeline::Config`s, stored as plain text so that applications can keep user presets
+//! and share tuning profiles:
+//!
+//! ```text
+//! visioncortex-preset 1
+//! name = poster
+//! runner.deepen_diff = 32
+//! mode = polygon
+//! ```
+//!
+//! The first line carries the format version. Keys not given keep their default, and unknown keys are
+//! ignored, so presets written by other versions of the crate still load.
+//!
+//! This is the serialization of `pipeline::Config` and the configs nested in it (of the runner and of
+//! path fitting). It is written by hand rather than derived with serde, so that the crate keeps to its
+//! few dependencies.
+
+use crate::{Color, PathSimplifyMode};
+use crate::color_clusters::{AverageColor, ColorSpace, KeyingAction, ScanOrder, Segmentation};
+use crate::pipeline::Config;
+
+/// Version of the preset format written
+pub const PRESET_VERSION: u32 = 1;
+
+const HEADER: &str = "visioncortex-preset";
+
+#[derive(Clone)]
+pub struct Preset {
+    pub name: String,
+    pub config: Config,
+}
+
+impl Preset {
+    pub fn new(name: &str, config: Config) -> Self {
+        Self {
+            name: name.to_owned(),
+            config,
+        }
+    }
+
+    /// Writes every field, defaults included
+    pub fn to_preset_string(&self) -> String {
+        let mut out = format!("{} {}\nname = {}\n", HEADER, PRESET_VERSION, self.name);
+        for (key, value) in write_config(&self.config) {
+            out += &format!("{} = {}\n", key, value);
+        }
+        out
+    }
+
+    /// Reads a preset written by `to_preset_string`. Returns `None` if the header is missing,
+    /// the version is newer than `PRESET_VERSION`, or a known key has an invalid value.
+    pub fn from_preset_string(string: &str) -> Option<Self> {
+        let mut lines = string.lines().map(str::trim).filter(|l| !l.is_empty() && !l.starts_with('#'));
+        let version: u32 = lines.next()?.strip_prefix(HEADER)?.trim().parse().ok()?;
+        if version > PRESET_VERSION {
+            return None;
+        }
+        let mut preset = Preset::new("", Config::default());
+        for line in lines {
+            let (key, value) = line.split_once('=')?;
+            let (key, value) = (key.trim(), value.trim());
+            if key == "name" {
+                preset.name = value.to_owned();
+            } else {
+                read_field(&mut preset.config, key, value)?;
+            }
+        }
+        Some(preset)
+    }
+}
+
+/// A set of presets by name
+#[derive(Clone, Default)]
+pub struct PresetRegistry {
+    presets: Vec<Preset>,
+}
+
+impl PresetRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a preset, replacing any of the same name
+    pub fn add(&mut self, preset: Preset) {
+        match self.presets.iter_mut().find(|p| p.name == preset.name) {
+            Some(existing) => *existing = preset,
+            None => self.presets.push(preset),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Preset> {
+        self.presets.iter().find(|p| p.name == name)
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Preset> {
+        let index = self.presets.iter().position(|p| p.name == name)?;
+        Some(self.presets.remove(index))
+    }
+
+    /// Names in the order added
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.presets.iter().map(|p| p.name.as_str())
+    }
+
+    /// All presets, one after another
+    pub fn to_preset_string(&self) -> String {
+        self.presets.iter().map(|p| p.to_preset_string()).collect::<Vec<_>>().join("\n")
+    }
+
+    /// Reads presets written by `to_preset_string`; `None` if any of them is invalid
+    pub fn from_preset_string(string: &str) -> Option<Self> {
+        let mut registry = Self::new();
+        let mut start = None;
+        let mut offset = 0;
+        for line in string.split_inclusive('\n') {
+            if line.trim_start().starts_with(HEADER) {
+                if let Some(start) = start {
+                    registry.add(Preset::from_preset_string(&string[start..offset])?);
+                }
+                start = Some(offset);
+            }
+            offset += line.len();
+        }
+        if let Some(start) = start {
+            registry.add(Preset::from_preset_string(&string[start..])?);
+        }
+        Some(registry)
+    }
+}
+
+fn write_config(config: &Config) -> Vec<(&'static str, String)> {
+    let runner = &config.runner;
+    vec![
+        ("runner.diagonal", runner.diagonal.to_string()),
+        ("runner.hierarchical", runner.hierarchical.to_string()),
+        ("runner.batch_size", runner.batch_size.to_string()),
+        ("runner.good_min_area", runner.good_min_area.to_string()),
+        ("runner.good_max_area", runner.good_max_area.to_string()),
+        ("runner.is_same_color_a", runner.is_same_color_a.to_string()),
+        ("runner.is_same_color_b", runner.is_same_color_b.to_string()),
+        ("runner.deepen_diff", runner.deepen_diff.to_string()),
+        ("runner.hollow_neighbours", runner.hollow_neighbours.to_string()),
+        ("runner.key_color", to_hex_rgba(runner.key_color)),
+        ("runner.keying_action", match runner.keying_action {
+            KeyingAction::Keep => "keep",
+            KeyingAction::Discard => "discard",
+        }.to_owned()),
+        ("runner.color_space", match runner.color_space {
+            ColorSpace::RGB => "rgb",
+            ColorSpace::Oklab => "oklab",
+        }.to_owned()),
+        ("runner.memory_cap", runner.memory_cap.to_string()),
+        ("runner.scan_order", match runner.scan_order {
+            ScanOrder::RowMajor => "row_major".to_owned(),
+            ScanOrder::Hilbert => "hilbert".to_owned(),
+            ScanOrder::Priority { tile_size } => format!("priority {}", tile_size),
+        }),
+        ("runner.segmentation", match runner.segmentation {
+            Segmentation::Hierarchical => "hierarchical".to_owned(),
+            Segmentation::Felzenszwalb { k, min_size } => format!("felzenszwalb {} {}", k, min_size),
+            Segmentation::MeanShift { spatial, range } => format!("mean_shift {} {}", spatial, range),
+        }),
+        ("runner.average_color", match runner.average_color {
+            AverageColor::Mean => "mean".to_owned(),
+            AverageColor::TrimmedMean { trim } => format!("trimmed_mean {}", trim),
+            AverageColor::Mode { bits } => format!("mode {}", bits),
+        }),
+        ("runner.jpeg_tolerance", runner.jpeg_tolerance.to_string()),
+        ("runner.trace", runner.trace.to_string()),
+        ("descreen", config.descreen.to_string()),
+        ("downscale", config.downscale.to_string()),
+        ("max_colors", config.max_colors.to_string()),
+        ("filter_speckle", config.filter_speckle.to_string()),
+        ("mode", match config.mode {
+            PathSimplifyMode::None => "none",
+            PathSimplifyMode::Polygon => "polygon",
+            PathSimplifyMode::Spline => "spline",
+            PathSimplifyMode::Orthogonal => "orthogonal",
+            PathSimplifyMode::Octilinear => "octilinear",
+        }.to_owned()),
+        ("corner_threshold", config.corner_threshold.to_string()),
+        ("length_threshold", config.length_threshold.to_string()),
+        ("max_iterations", config.max_iterations.to_string()),
+        ("splice_threshold", config.splice_threshold.to_string()),
+    ]
+}
+
+fn read_field(config: &mut Config, key: &str, value: &str) -> Option<()> {
+    let runner = &mut config.runner;
+    match key {
+        "runner.diagonal" => runner.diagonal = value.parse().ok()?,
+        "runner.hierarchical" => runner.hierarchical = value.parse().ok()?,
+        "runner.batch_size" => runner.batch_size = value.parse().ok()?,
+        "runner.good_min_area" => runner.good_min_area = value.parse().ok()?,
+        "runner.good_max_area" => runner.good_max_area = value.parse().ok()?,
+        "runner.is_same_color_a" => runner.is_same_color_a = value.parse().ok()?,
+        "runner.is_same_color_b" => runner.is_same_color_b = value.parse().ok()?,
+        "runner.deepen_diff" => runner.deepen_diff = value.parse().ok()?,
+        "runner.hollow_neighbours" => runner.hollow_neighbours = value.parse().ok()?,
+        "runner.key_color" => runner.key_color = parse_hex_color(value)?,
+        "runner.keying_action" => runner.keying_action = match value {
+            "keep" => KeyingAction::Keep,
+            "discard" => KeyingAction::Discard,
+            _ => return None,
+        },
+        "runner.color_space" => runner.color_space = match value {
+            "rgb" => ColorSpace::RGB,
+            "oklab" => ColorSpace::Oklab,
+            _ => return None,
+        },
+        "runner.memory_cap" => runner.memory_cap = value.parse().ok()?,
+        "runner.scan_order" => {
+            let mut words = value.split_whitespace();
+            runner.scan_order = match words.next()? {
+                "row_major" => ScanOrder::RowMajor,
+                "hilbert" => ScanOrder::Hilbert,
+                "priority" => ScanOrder::Priority { tile_size: words.next()?.parse().ok().filter(|&size| size > 0)? },
+                _ => return None,
+            };
+        },
+        "runner.segmentation" => {
+            let mut words = value.split_whitespace();
+            runner.segmentation = match words.next()? {
+                "hierarchical" => Segmentation::Hierarchical,
+                "felzenszwalb" => Segmentation::Felzenszwalb {
+                    k: words.next()?.parse().ok()?,
+                    min_size: words.next()?.parse().ok()?,
+                },
+                "mean_shift" => Segmentation::MeanShift {
+                    spatial: words.next()?.parse().ok()?,
+                    range: words.next()?.parse().ok()?,
+                },
+                _ => return None,
+            };
+        },
+        "runner.average_color" => {
+            let mut words = value.split_whitespace();
+            runner.average_color = match words.next()? {
+                "mean" => AverageColor::Mean,
+                "trimmed_mean" => AverageColor::TrimmedMean { trim: words.next()?.parse().ok()? },
+                "mode" => AverageColor::Mode { bits: words.next()?.parse().ok()? },
+                _ => return None,
+            };
+        },
+        "runner.jpeg_tolerance" => runner.jpeg_tolerance = value.parse().ok()?,
+        "runner.trace" => runner.trace = value.parse().ok()?,
+        "descreen" => config.descreen = value.parse().ok()?,
+        "downscale" => config.downscale = value.parse().ok()?,
+        "max_colors" => config.max_colors = value.parse().ok()?,
+        "filter_speckle" => config.filter_speckle = value.parse().ok()?,
+        "mode" => config.mode = match value {
+            "none" => PathSimplifyMode::None,
+            "polygon" => PathSimplifyMode::Polygon,
+            "spline" => PathSimplifyMode::Spline,
+            "orthogonal" => PathSimplifyMode::Orthogonal,
+            "octilinear" => PathSimplifyMode::Octilinear,
+            _ => return None,
+        },
+        "corner_threshold" => config.corner_threshold = value.parse().ok()?,
+        "length_threshold" => config.length_threshold = value.parse().ok()?,
+        "max_iterations" => config.max_iterations = value.parse().ok()?,
+        "splice_threshold" => config.splice_threshold = value.parse().ok()?,
+        _ => {},
+    }
+    Some(())
+}
+
+/// `#RRGGBBAA`, with alpha, as the default key color (transparent black) means no keying
+fn to_hex_rgba(color: Color) -> String {
+    format!("{}{:02X}", color.to_hex_string(), color.a)
+}
+
+/// `#RRGGBBAA` as written by `to_hex_rgba`, or `#RRGGBB` (opaque) as written by `Color::to_hex_string`
+fn parse_hex_color(value: &str) -> Option<Color> {
+    let hex = value.strip_prefix('#')?;
+    if hex.len() != 6 && hex.len() != 8 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    let a = if hex.len() == 8 { channel(6)? } else { 255 };
+    Some(Color::new_rgba(channel(0)?, channel(2)?, channel(4)?, a))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preset_round_trip() {
+        let mut config = Config::default();
+        config.runner.deepen_diff = 32;
+        config.runner.key_color = Color::new(1, 2, 254);
+        config.runner.segmentation = Segmentation::Felzenszwalb { k: 300.5, min_size: 4 };
+        config.runner.average_color = AverageColor::TrimmedMean { trim: 0.25 };
+        config.runner.trace = true;
+        config.mode = PathSimplifyMode::Octilinear;
+        config.corner_threshold = 1.0 / 3.0;
+        let preset = Preset::new("poster", config);
+        let string = preset.to_preset_string();
+        let read = Preset::from_preset_string(&string).unwrap();
+        assert_eq!(read.name, "poster");
+        assert_eq!(read.config, preset.config);
+        assert_eq!(read.to_preset_string(), string);
+
+        // the default key color is transparent, i.e. no keying
+        let default = Preset::from_preset_string(&Preset::new("default", Config::default()).to_preset_string()).unwrap();
+        assert_eq!(default.config, Config::default());
+        assert_eq!(parse_hex_color("#0102FE"), Some(Color::new(1, 2, 254)));
+
+        // missing keys default, unknown keys are ignored
+        let partial = Preset::from_preset_string("visioncortex-preset 1\nname = x\nfuture.key = 1\nmode = polygon\n").unwrap();
+        assert!(matches!(partial.config.mode, PathSimplifyMode::Polygon));
+        assert_eq!(partial.config.runner.deepen_diff, Config::default().runner.deepen_diff);
+        assert!(Preset::from_preset_string("visioncortex-preset 2\n").is_none());
+        assert!(Preset::from_preset_string("visioncortex-preset 1\nmode = bezier\n").is_none());
+        assert!(Preset::from_preset_string("visioncortex-preset 1\nrunner.scan_order = priority 0\n").is_none());
+
+        let mut registry = PresetRegistry::new();
+        registry.add(preset.clone());
+        registry.add(Preset::new("default", Config::default()));
+        let registry = PresetRegistry::from_preset_string(&registry.to_preset_string()).unwrap();
+        assert_eq!(registry.names().collect::<Vec<_>>(), vec!["poster", "default"]);
+        assert_eq!(registry.get("poster").unwrap().config.runner.deepen_diff, 32);
+    }
+}
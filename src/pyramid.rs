@@ -0,0 +1,115 @@
+use crate::{BorderMode, PlanarImage};
+
+/// The 5 tap binomial kernel of Burt & Adelson
+const BINOMIAL: [f32; 5] = [1.0 / 16.0, 4.0 / 16.0, 6.0 / 16.0, 4.0 / 16.0, 1.0 / 16.0];
+
+impl<const C: usize> PlanarImage<f32, C> {
+    /// Blurred by the binomial kernel, then every other pixel in each direction; half the size, rounded up
+    pub fn pyr_down(&self) -> Self {
+        let blurred = self.convolve_separable(&BINOMIAL, &BINOMIAL, BorderMode::Reflect);
+        let (width, height) = (self.width().div_ceil(2), self.height().div_ceil(2));
+        let mut down = Self::new_w_h(width, height);
+        for c in 0..C {
+            let (from, to) = (blurred.channel(c), down.channel_mut(c));
+            for y in 0..height {
+                for x in 0..width {
+                    to[y * width + x] = from[2 * y * self.width() + 2 * x];
+                }
+            }
+        }
+        down
+    }
+
+    /// Bilinearly interpolated to `width` x `height`, about twice the size as the inverse of `pyr_down`
+    pub fn pyr_up(&self, width: usize, height: usize) -> Self {
+        let (w, h) = (self.width(), self.height());
+        let mut up = Self::new_w_h(width, height);
+        if w == 0 || h == 0 {
+            return up;
+        }
+        let source = |i: usize| (i as f64 + 0.5) / 2.0 - 0.5;
+        for y in 0..height {
+            for x in 0..width {
+                up.set_pixel(x, y, self.sample_bilinear(source(x), source(y), BorderMode::Replicate));
+            }
+        }
+        up
+    }
+}
+
+/// `image` then each `pyr_down` of the last, `levels` in all (at least 1)
+pub fn gaussian_pyramid<const C: usize>(image: &PlanarImage<f32, C>, levels: usize) -> Vec<PlanarImage<f32, C>> {
+    let mut pyramid = vec![image.clone()];
+    while pyramid.len() < levels.max(1) {
+        let next = pyramid.last().unwrap().pyr_down();
+        pyramid.push(next);
+    }
+    pyramid
+}
+
+/// The differences between successive levels of the `gaussian_pyramid` (each minus the next `pyr_up`),
+/// and last the coarsest level itself; `collapse_pyramid` reconstructs the image exactly
+pub fn laplacian_pyramid<const C: usize>(image: &PlanarImage<f32, C>, levels: usize) -> Vec<PlanarImage<f32, C>> {
+    let mut pyramid = gaussian_pyramid(image, levels);
+    for i in 0..pyramid.len() - 1 {
+        let up = pyramid[i + 1].pyr_up(pyramid[i].width(), pyramid[i].height());
+        for c in 0..C {
+            pyramid[i].channel_mut(c).iter_mut().zip(up.channel(c)).for_each(|(v, u)| *v -= u);
+        }
+    }
+    pyramid
+}
+
+/// The image of a `laplacian_pyramid`, adding up the levels from the coarsest
+pub fn collapse_pyramid<const C: usize>(pyramid: &[PlanarImage<f32, C>]) -> PlanarImage<f32, C> {
+    let mut levels = pyramid.iter().rev();
+    let mut image = match levels.next() {
+        Some(coarsest) => coarsest.clone(),
+        None => return PlanarImage::default(),
+    };
+    for level in levels {
+        let mut up = image.pyr_up(level.width(), level.height());
+        for c in 0..C {
+            up.channel_mut(c).iter_mut().zip(level.channel(c)).for_each(|(u, v)| *u += v);
+        }
+        image = up;
+    }
+    image
+}
+
+/// The number of levels until the smaller side is reduced to about `min_size` pixels
+pub fn pyramid_levels(width: usize, height: usize, min_size: usize) -> usize {
+    let mut size = width.min(height);
+    let mut levels = 1;
+    while size.div_ceil(2) >= min_size.max(1) && size > 1 {
+        size = size.div_ceil(2);
+        levels += 1;
+    }
+    levels
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn laplacian_roundtrip() {
+        let mut image = PlanarImage::<f32, 1>::new_w_h(13, 9);
+        for y in 0..9 {
+            for x in 0..13 {
+                image.set(x, y, 0, ((x * 7 + y * 3) % 11) as f32);
+            }
+        }
+        let pyramid = laplacian_pyramid(&image, 4);
+        assert_eq!(pyramid.len(), 4);
+        assert_eq!((pyramid[1].width(), pyramid[1].height()), (7, 5));
+        assert_eq!((pyramid[3].width(), pyramid[3].height()), (2, 2));
+        let collapsed = collapse_pyramid(&pyramid);
+        assert!(collapsed.channel(0).iter().zip(image.channel(0)).all(|(a, b)| (a - b).abs() < 1e-4));
+
+        // a flat image stays flat all the way down
+        let flat = PlanarImage::<f32, 1>::new_w_h_with_value(8, 8, 3.0);
+        assert!(gaussian_pyramid(&flat, 3)[2].channel(0).iter().all(|&v| (v - 3.0).abs() < 1e-6));
+        assert_eq!(pyramid_levels(64, 100, 8), 4);
+    }
+}
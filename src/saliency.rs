@@ -0,0 +1,129 @@
+use crate::{BorderMode, BoundingRect, Color, ColorImage, PlanarImage, Spectrum};
+use crate::fmath::{exp, ln};
+
+/// Size (in pixels) of the longer side of the image the saliency is computed on
+const SALIENCY_SIZE: usize = 64;
+/// Standard deviation (in cycles per pixel) of the low-pass filter smoothing the saliency,
+/// about a spatial deviation of 2.5 pixels at `SALIENCY_SIZE`
+const SALIENCY_SMOOTHING: f64 = 0.064;
+
+impl ColorImage {
+    /// Saliency map by the spectral residual method (Hou and Zhang, 2007): the parts of the log amplitude spectrum
+    /// of the luma which stand out from their neighbourhood are the unexpected, hence salient, content.
+    /// Computed on a reduced image and scaled back to the size of this image, normalized to [0, 1].
+    pub fn saliency(&self) -> PlanarImage<f32, 1> {
+        let (width, height) = (self.width, self.height);
+        if width == 0 || height == 0 {
+            return PlanarImage::new_w_h(width, height);
+        }
+        let scale = (width.max(height) as f64 / SALIENCY_SIZE as f64).max(1.0);
+        let (w, h) = (((width as f64 / scale).round() as usize).max(1), ((height as f64 / scale).round() as usize).max(1));
+
+        // box downscale of the luma
+        let mut small = PlanarImage::<f32, 1>::new_w_h(w, h);
+        for y in 0..h {
+            for x in 0..w {
+                let (x0, x1) = (x * width / w, ((x + 1) * width / w).max(x * width / w + 1));
+                let (y0, y1) = (y * height / h, ((y + 1) * height / h).max(y * height / h + 1));
+                let mut sum = 0.0;
+                for sy in y0..y1 {
+                    for sx in x0..x1 {
+                        let c: Color = self.get_pixel(sx, sy);
+                        sum += 0.299 * c.r as f32 + 0.587 * c.g as f32 + 0.114 * c.b as f32;
+                    }
+                }
+                small.set(x, y, 0, sum / ((x1 - x0) * (y1 - y0)) as f32);
+            }
+        }
+
+        // spectral residual: log amplitude minus its 3x3 mean, with the phase kept
+        let mut spectrum = Spectrum::new(&small);
+        let (sw, sh) = (spectrum.width as i32, spectrum.height as i32);
+        let log_amplitude: Vec<f64> = spectrum.data.iter().map(|c| ln(c.norm_sqr().sqrt() + 1e-9)).collect();
+        for y in 0..sh {
+            for x in 0..sw {
+                let mut mean = 0.0;
+                for dy in -1..=1 {
+                    for dx in -1..=1 {
+                        mean += log_amplitude[((y + dy).rem_euclid(sh) * sw + (x + dx).rem_euclid(sw)) as usize];
+                    }
+                }
+                let i = (y * sw + x) as usize;
+                let residual = log_amplitude[i] - mean / 9.0;
+                let amplitude = spectrum.data[i].norm_sqr().sqrt();
+                spectrum.data[i] = if amplitude > 0.0 {
+                    spectrum.data[i] * (exp(residual) / amplitude)
+                } else {
+                    Default::default()
+                };
+            }
+        }
+        let energy = spectrum.inverse();
+        let mut map = PlanarImage::<f32, 1>::new_w_h(w, h);
+        for y in 0..h {
+            for x in 0..w {
+                map.set(x, y, 0, energy[y * spectrum.width + x].norm_sqr() as f32);
+            }
+        }
+        let map = map.low_pass(SALIENCY_SMOOTHING);
+
+        // bilinear upscale, normalized
+        let (min, max) = map.channel(0).iter().fold((f32::MAX, f32::MIN), |(lo, hi), &v| (lo.min(v), hi.max(v)));
+        let range = if max > min { max - min } else { 1.0 };
+        let mut saliency = PlanarImage::new_w_h(width, height);
+        for y in 0..height {
+            for x in 0..width {
+                let fx = (x as f64 + 0.5) * w as f64 / width as f64 - 0.5;
+                let fy = (y as f64 + 0.5) * h as f64 / height as f64 - 0.5;
+                let [v] = map.sample_bilinear(fx, fy, BorderMode::Replicate);
+                saliency.set(x, y, 0, ((v - min) / range).clamp(0.0, 1.0));
+            }
+        }
+        saliency
+    }
+}
+
+/// A priority function for `ScanOrder::Priority` (see `Builder::priority`): the mean of `saliency` over the tile
+pub fn saliency_priority(saliency: PlanarImage<f32, 1>) -> impl Fn(&ColorImage, &BoundingRect) -> f64 {
+    move |_image: &ColorImage, tile: &BoundingRect| {
+        let mut sum = 0.0;
+        for y in tile.top..tile.bottom {
+            for x in tile.left..tile.right {
+                sum += saliency.get(x as usize, y as usize, 0) as f64;
+            }
+        }
+        sum / (tile.width() * tile.height()).max(1) as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn odd_one_out_is_salient() {
+        // a grid of grey dots, and a larger black square at the top left
+        let mut image = ColorImage::new_w_h(96, 64);
+        for y in 0..64 {
+            for x in 0..96 {
+                let dot = x % 8 < 2 && y % 8 < 2;
+                let square = (12..28).contains(&x) && (12..28).contains(&y);
+                let color = if square {
+                    Color::new(0, 0, 0)
+                } else if dot {
+                    Color::new(150, 150, 150)
+                } else {
+                    Color::new(255, 255, 255)
+                };
+                image.set_pixel(x, y, &color);
+            }
+        }
+        let saliency = image.saliency();
+        assert_eq!((saliency.width(), saliency.height()), (96, 64));
+        assert!(saliency.channel(0).iter().all(|&v| (0.0..=1.0).contains(&v)));
+        let mean = |left: i32, top: i32| {
+            saliency_priority(saliency.clone())(&image, &BoundingRect::new_x_y_w_h(left, top, 24, 24))
+        };
+        assert!(mean(8, 8) > 2.0 * mean(64, 32));
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{BinaryImage, BoundingRect};
+use crate::{BinaryImage, BoundingRect, ImageMut};
 
 /// For sampling and resizing binary images
 pub struct Sampler {
@@ -37,42 +37,43 @@ impl Sampler {
 
     /// Resize an image of any size into a square image while keeping the aspect ratio of content.
     /// Would empty fill expanded area.
-    pub fn resample_square_image(
-        image: &BinaryImage,
+    pub fn resample_square_image<I: ImageMut>(
+        image: &I,
         crop: BoundingRect,
         new_size: usize,
-    ) -> BinaryImage {
-        let mut new_image = BinaryImage::new_w_h(new_size, new_size);
+    ) -> I where I::Pixel: Default {
+        let mut new_image = I::new_image(new_size, new_size);
         let new_size = new_size as i32;
         let crop = if !crop.is_empty() {
             crop
         } else {
-            BoundingRect::new_x_y_w_h(0, 0, image.width as i32, image.height as i32)
+            BoundingRect::new_x_y_w_h(0, 0, image.width() as i32, image.height() as i32)
         };
-        let image_size = std::cmp::max(crop.width(), crop.height()) as i32;
-        let ox = (image_size - crop.width() as i32) >> 1;
-        let oy = (image_size - crop.height() as i32) >> 1;
+        let image_size = std::cmp::max(crop.width(), crop.height());
+        let ox = (image_size - crop.width()) >> 1;
+        let oy = (image_size - crop.height()) >> 1;
         for y in 0..new_size {
             for x in 0..new_size {
                 let xx = x * image_size / new_size - ox + crop.left;
                 let yy = y * image_size / new_size - oy + crop.top;
-                new_image.set_pixel(x as usize, y as usize, image.get_pixel_safe(xx, yy));
+                new_image.put_pixel(x as usize, y as usize, image.pixel_safe(xx, yy).unwrap_or_default());
             }
         }
         new_image
     }
 
-    pub fn resample_image(image: &BinaryImage, new_width: usize, new_height: usize) -> BinaryImage {
+    pub fn resample_image<I: ImageMut>(image: &I, new_width: usize, new_height: usize) -> I
+        where I::Pixel: Default {
         Self::resample_image_with_crop(image, Default::default(), new_width, new_height)
     }
 
-    pub fn resample_image_with_crop(
-        image: &BinaryImage,
+    pub fn resample_image_with_crop<I: ImageMut>(
+        image: &I,
         crop: BoundingRect,
         new_width: usize,
         new_height: usize,
-    ) -> BinaryImage {
-        let mut new_image = BinaryImage::new_w_h(new_width, new_height);
+    ) -> I where I::Pixel: Default {
+        let mut new_image = I::new_image(new_width, new_height);
         Self::resample_image_with_crop_to_image(
             image,
             crop,
@@ -82,15 +83,18 @@ impl Sampler {
         new_image
     }
 
-    pub fn resample_image_with_crop_to_image(
-        src: &BinaryImage,
+    /// Nearest neighbour resampling of `src_rect` in `src` onto `dst_rect` in `dst`, for any kind of image.
+    /// Samples falling outside of `src` take the default pixel value.
+    pub fn resample_image_with_crop_to_image<I: ImageMut>(
+        src: &I,
         src_rect: BoundingRect,
-        dst: &mut BinaryImage,
+        dst: &mut I,
         dst_rect: BoundingRect,
-    ) {
-        Self::resample_image_with_crop_to_image_overlay(src, src_rect, dst, dst_rect, false);
+    ) where I::Pixel: Default {
+        resample_into(src, src_rect, dst, dst_rect, |_| true);
     }
 
+    /// As `resample_image_with_crop_to_image`, but with `overlay` only the set pixels are drawn onto `dst`
     pub fn resample_image_with_crop_to_image_overlay(
         src: &BinaryImage,
         src_rect: BoundingRect,
@@ -98,30 +102,30 @@ impl Sampler {
         dst_rect: BoundingRect,
         overlay: bool,
     ) {
-        let src_rect = if !src_rect.is_empty() {
-            src_rect
-        } else {
-            BoundingRect::new_x_y_w_h(0, 0, src.width as i32, src.height as i32)
-        };
-        for y in 0..dst_rect.height() {
-            for x in 0..dst_rect.width() {
-                let s = 1;
-                let xx =
-                    s * x as i32 * src_rect.width() / dst_rect.width() as i32
-                    + s * src_rect.left;
-                let yy =
-                    s * y as i32 * src_rect.height() / dst_rect.height() as i32
-                    + s * src_rect.top;
-                let pixel = src.get_pixel_safe(xx / s, yy / s);
-                if !overlay || pixel {
-                    // overlay: set pixel only if pixel is true
-                    // otherwise: set pixel anyway
-                    dst.set_pixel(
-                        (dst_rect.left + x) as usize,
-                        (dst_rect.top + y) as usize,
-                        pixel,
-                    );
-                }
+        resample_into(src, src_rect, dst, dst_rect, |pixel| !overlay || pixel);
+    }
+}
+
+/// Nearest neighbour resampling, drawing only the samples for which `draw` is true
+fn resample_into<I: ImageMut>(
+    src: &I,
+    src_rect: BoundingRect,
+    dst: &mut I,
+    dst_rect: BoundingRect,
+    draw: impl Fn(I::Pixel) -> bool,
+) where I::Pixel: Default {
+    let src_rect = if !src_rect.is_empty() {
+        src_rect
+    } else {
+        BoundingRect::new_x_y_w_h(0, 0, src.width() as i32, src.height() as i32)
+    };
+    for y in 0..dst_rect.height() {
+        for x in 0..dst_rect.width() {
+            let xx = x * src_rect.width() / dst_rect.width() + src_rect.left;
+            let yy = y * src_rect.height() / dst_rect.height() + src_rect.top;
+            let pixel = src.pixel_safe(xx, yy).unwrap_or_default();
+            if draw(pixel) {
+                dst.put_pixel((dst_rect.left + x) as usize, (dst_rect.top + y) as usize, pixel);
             }
         }
     }
@@ -314,4 +318,28 @@ mod tests {
         assert_eq!(new_image.get_pixel(1, 0), false);
         assert_eq!(new_image.get_pixel(1, 1), true);
     }
+
+    #[test]
+    fn resample_generic_color_image() {
+        use crate::{Color, ColorImage};
+        let mut image = ColorImage::new_w_h(2, 1);
+        image.set_pixel(1, 0, &Color::new(1, 2, 3));
+        let new_image = Sampler::resample_image(&image, 4, 2);
+        assert_eq!(new_image.get_pixel(1, 1), Color::default());
+        assert_eq!(new_image.get_pixel(2, 1), Color::new(1, 2, 3));
+        assert_eq!(new_image.get_pixel(3, 0), Color::new(1, 2, 3));
+    }
+
+    #[test]
+    fn resample_square_generic_gray_image() {
+        use crate::GrayImage;
+        let mut image = GrayImage::new_w_h(2, 1);
+        image.set_pixel(0, 0, 5);
+        image.set_pixel(1, 0, 9);
+        let new_image = Sampler::resample_square_image(&image, Default::default(), 2);
+        // the expanded area is filled with the default
+        assert_eq!(new_image.get_pixel(0, 0), 5);
+        assert_eq!(new_image.get_pixel(1, 0), 9);
+        assert_eq!(new_image.get_pixel(0, 1), 0);
+    }
 }
@@ -0,0 +1,194 @@
+use crate::{BinaryImage, CompoundPath, CompoundPathElement, PlanarImage, PointF64, distance_to_segment};
+
+/// Maximum deviation of the flattened curves from splines, in pixels
+const CURVE_TOLERANCE: f64 = 0.05;
+
+/// Signed distance field of a mask, in pixels: negative inside, positive outside,
+/// zero on the boundary between pixels, clamped to [-spread, spread].
+///
+/// Distances are exact Euclidean, computed by the two pass algorithm of Felzenszwalb & Huttenlocher.
+pub fn sdf_from_mask(mask: &BinaryImage, spread: f64) -> PlanarImage<f32, 1> {
+    let (width, height) = (mask.width, mask.height);
+    let to_inside = squared_distance_to(mask, true);
+    let to_outside = squared_distance_to(mask, false);
+    let mut sdf = PlanarImage::new_w_h(width, height);
+    for (i, v) in sdf.channel_mut(0).iter_mut().enumerate() {
+        // measured between pixel centers, the boundary is half a pixel away
+        let d = if mask.pixels[i] {
+            0.5 - to_outside[i].sqrt()
+        } else {
+            to_inside[i].sqrt() - 0.5
+        };
+        *v = d.clamp(-spread, spread) as f32;
+    }
+    sdf
+}
+
+/// Distance from each pixel center to the center of the nearest set pixel of `mask`,
+/// clamped to [0, spread]; a distance transform of an edge map, as used in chamfer matching.
+pub fn distance_to_mask(mask: &BinaryImage, spread: f64) -> PlanarImage<f32, 1> {
+    let to_set = squared_distance_to(mask, true);
+    let mut distance = PlanarImage::new_w_h(mask.width, mask.height);
+    for (v, d) in distance.channel_mut(0).iter_mut().zip(to_set) {
+        *v = d.sqrt().min(spread) as f32;
+    }
+    distance
+}
+
+/// Signed distance field of the shape enclosed by `path` (even-odd rule), sampled at pixel centers:
+/// negative inside, positive outside, clamped to [-spread, spread].
+///
+/// The field covers (0, 0) to the right-bottom of the path plus `spread`, i.e. it is in the
+/// same coordinates as traced output.
+pub fn sdf_from_path(path: &CompoundPath, spread: f64) -> PlanarImage<f32, 1> {
+    let polygons = flatten(path);
+    let (mut right, mut bottom) = (0.0_f64, 0.0_f64);
+    for p in polygons.iter().flatten() {
+        right = right.max(p.x);
+        bottom = bottom.max(p.y);
+    }
+    let width = (right + spread).ceil().max(0.0) as usize;
+    let height = (bottom + spread).ceil().max(0.0) as usize;
+
+    let mut sdf = PlanarImage::new_w_h(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            let p = PointF64::new(x as f64 + 0.5, y as f64 + 0.5);
+            let mut distance = f64::MAX;
+            let mut inside = false;
+            for polygon in polygons.iter() {
+                for (&a, &b) in polygon.iter().zip(polygon.iter().skip(1)) {
+                    distance = distance.min(distance_to_segment(p, a, b));
+                    if (a.y > p.y) != (b.y > p.y) &&
+                        p.x < a.x + (p.y - a.y) * (b.x - a.x) / (b.y - a.y) {
+                        inside = !inside;
+                    }
+                }
+            }
+            let d = if inside { -distance } else { distance };
+            sdf.set(x, y, 0, d.clamp(-spread, spread) as f32);
+        }
+    }
+    sdf
+}
+
+/// Converts each element into a closed polyline
+pub(crate) fn flatten(path: &CompoundPath) -> Vec<Vec<PointF64>> {
+    path.iter().map(|element| {
+        let mut points: Vec<PointF64> = match element {
+            CompoundPathElement::PathI32(path) => path.iter().map(|p| p.to_point_f64()).collect(),
+            CompoundPathElement::PathF64(path) => path.path.clone(),
+            CompoundPathElement::Spline(spline) => spline.to_path_f64(CURVE_TOLERANCE).path,
+        };
+        if let (Some(&first), Some(&last)) = (points.first(), points.last()) {
+            if first != last {
+                points.push(first);
+            }
+        }
+        points
+    }).collect()
+}
+
+/// Squared distance from each pixel to the nearest pixel with value `target`
+fn squared_distance_to(mask: &BinaryImage, target: bool) -> Vec<f64> {
+    let (width, height) = (mask.width, mask.height);
+    let infinity = ((width * width + height * height) as f64) * 2.0 + 1.0;
+    let mut grid: Vec<f64> = (0..width * height)
+        .map(|i| if mask.pixels[i] == target { 0.0 } else { infinity })
+        .collect();
+    let mut buffer = Vec::new();
+    for x in 0..width {
+        buffer.clear();
+        buffer.extend((0..height).map(|y| grid[y * width + x]));
+        let column = distance_transform_1d(&buffer);
+        for (y, v) in column.into_iter().enumerate() {
+            grid[y * width + x] = v;
+        }
+    }
+    for y in 0..height {
+        let row = distance_transform_1d(&grid[y * width..(y + 1) * width]);
+        grid[y * width..(y + 1) * width].copy_from_slice(&row);
+    }
+    grid
+}
+
+/// Lower envelope of parabolas rooted at each sample
+fn distance_transform_1d(f: &[f64]) -> Vec<f64> {
+    let n = f.len();
+    if n == 0 {
+        return vec![];
+    }
+    let mut d = vec![0.0; n];
+    let mut v = vec![0; n];
+    let mut z = vec![0.0; n + 1];
+    let mut k = 0;
+    z[0] = f64::NEG_INFINITY;
+    z[1] = f64::INFINITY;
+    let intersect = |q: usize, p: usize| {
+        ((f[q] + (q * q) as f64) - (f[p] + (p * p) as f64)) / (2.0 * q as f64 - 2.0 * p as f64)
+    };
+    for q in 1..n {
+        let mut s = intersect(q, v[k]);
+        while s <= z[k] {
+            k -= 1;
+            s = intersect(q, v[k]);
+        }
+        k += 1;
+        v[k] = q;
+        z[k] = s;
+        z[k + 1] = f64::INFINITY;
+    }
+    k = 0;
+    for (q, d) in d.iter_mut().enumerate() {
+        while z[k + 1] < q as f64 {
+            k += 1;
+        }
+        let dq = q as f64 - v[k] as f64;
+        *d = dq * dq + f[v[k]];
+    }
+    d
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::PathI32;
+    use crate::PointI32;
+
+    #[test]
+    fn sdf_from_mask_square() {
+        let mask = BinaryImage::from_string(&(
+            "-----\n".to_owned() +
+            "-***-\n" +
+            "-***-\n" +
+            "-***-\n" +
+            "-----\n"
+        ));
+        let sdf = sdf_from_mask(&mask, 2.0);
+        assert_eq!(sdf.get(2, 2, 0), -1.5);
+        assert_eq!(sdf.get(1, 2, 0), -0.5);
+        assert_eq!(sdf.get(0, 2, 0), 0.5);
+        assert_eq!(sdf.get(0, 0, 0), (2.0_f64.sqrt() - 0.5) as f32);
+
+        let distance = distance_to_mask(&mask, 1.2);
+        assert_eq!(distance.get(2, 2, 0), 0.0);
+        assert_eq!(distance.get(0, 2, 0), 1.0);
+        assert_eq!(distance.get(0, 0, 0), 1.2);
+    }
+
+    #[test]
+    fn sdf_from_path_square() {
+        let mut path = PathI32::new();
+        for &(x, y) in [(1, 1), (4, 1), (4, 4), (1, 4), (1, 1)].iter() {
+            path.add(PointI32::new(x, y));
+        }
+        let mut compound = CompoundPath::new();
+        compound.add_path_i32(path);
+        let sdf = sdf_from_path(&compound, 2.0);
+        assert_eq!(sdf.width(), 6);
+        assert_eq!(sdf.get(2, 2, 0), -1.5);
+        assert_eq!(sdf.get(0, 2, 0), 0.5);
+        assert_eq!(sdf.get(4, 4, 0), (0.5_f64.sqrt()) as f32);
+        assert_eq!(sdf.get(5, 5, 0), 2.0);
+    }
+}
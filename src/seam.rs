@@ -0,0 +1,151 @@
+use crate::{BorderMode, Color, ColorImage, PlanarImage};
+
+/// The vertical seam of the least total energy, by dynamic programming: the x of its pixel on each row,
+/// each within one of that on the row above
+pub fn find_vertical_seam(energy: &PlanarImage<f32, 1>) -> Vec<usize> {
+    let (width, height) = (energy.width(), energy.height());
+    if width == 0 || height == 0 {
+        return Vec::new();
+    }
+    // least energy of a seam from the top row to each pixel
+    let mut cost = energy.channel(0).to_vec();
+    for y in 1..height {
+        for x in 0..width {
+            let above = &cost[(y - 1) * width..y * width];
+            let least = above[x.saturating_sub(1)..(x + 2).min(width)].iter().copied().fold(f32::MAX, f32::min);
+            cost[y * width + x] += least;
+        }
+    }
+    let row = |y: usize| &cost[y * width..(y + 1) * width];
+    let least_in = |y: usize, from: usize, to: usize| {
+        (from..to).fold(from, |best, x| if row(y)[x] < row(y)[best] { x } else { best })
+    };
+    let mut seam = vec![least_in(height - 1, 0, width)];
+    for y in (0..height - 1).rev() {
+        let x = *seam.last().unwrap();
+        seam.push(least_in(y, x.saturating_sub(1), (x + 2).min(width)));
+    }
+    seam.reverse();
+    seam
+}
+
+impl ColorImage {
+    /// The image one pixel narrower, without the pixel at `seam[y]` on each row
+    pub fn remove_vertical_seam(&self, seam: &[usize]) -> ColorImage {
+        assert!(self.width > 0 && seam.len() == self.height);
+        let mut carved = ColorImage::new_w_h(self.width - 1, self.height);
+        for (y, &s) in seam.iter().enumerate() {
+            for x in 0..carved.width {
+                carved.set_pixel(x, y, &self.get_pixel(if x < s { x } else { x + 1 }, y));
+            }
+        }
+        carved
+    }
+
+    /// Content-aware resize to `width` x `height`: seams of the least energy (`gradient_magnitude`)
+    /// are removed one by one to shrink, or to enlarge, the seams that would be removed first are each
+    /// duplicated (blended with the pixels to their right). Columns are carved before rows.
+    /// An empty image has no pixels to duplicate, so it is resized to a transparent image.
+    pub fn seam_carve(&self, width: usize, height: usize) -> ColorImage {
+        assert!(width > 0 && height > 0);
+        let carved = carve_columns(self, width);
+        transpose(&carve_columns(&transpose(&carved), height))
+    }
+}
+
+fn carve_columns(image: &ColorImage, width: usize) -> ColorImage {
+    if image.width == 0 || image.height == 0 {
+        return ColorImage::new_w_h(width, image.height);
+    }
+    let mut image = image.clone();
+    while image.width > width {
+        let seam = find_vertical_seam(&image.gradient_magnitude());
+        image = image.remove_vertical_seam(&seam);
+    }
+    while image.width < width {
+        // at most half of the columns at a time, so that the duplicated seams are each of different pixels
+        let count = (width - image.width).min(image.width.div_ceil(2));
+        image = insert_seams(&image, count);
+    }
+    image
+}
+
+/// Duplicates the first `count` seams that removal would take
+fn insert_seams(image: &ColorImage, count: usize) -> ColorImage {
+    let (width, height) = (image.width, image.height);
+    // the original x of each pixel of the carved copy
+    let mut origin: Vec<Vec<usize>> = vec![(0..width).collect(); height];
+    let mut duplicated = vec![vec![false; width]; height];
+    let mut carved = image.clone();
+    for _ in 0..count {
+        let seam = find_vertical_seam(&carved.gradient_magnitude());
+        for (y, &x) in seam.iter().enumerate() {
+            duplicated[y][origin[y].remove(x)] = true;
+        }
+        carved = carved.remove_vertical_seam(&seam);
+    }
+
+    let mut enlarged = ColorImage::new_w_h(width + count, height);
+    for (y, row) in duplicated.iter().enumerate() {
+        let mut out = 0;
+        for (x, &dup) in row.iter().enumerate() {
+            let c = image.get_pixel(x, y);
+            enlarged.set_pixel(out, y, &c);
+            out += 1;
+            if dup {
+                let next = image.get_pixel(BorderMode::Replicate.resolve(x as i64 + 1, width).unwrap(), y);
+                let mix = |a: u8, b: u8| ((a as u16 + b as u16).div_ceil(2)) as u8;
+                enlarged.set_pixel(out, y, &Color::new_rgba(mix(c.r, next.r), mix(c.g, next.g), mix(c.b, next.b), mix(c.a, next.a)));
+                out += 1;
+            }
+        }
+    }
+    enlarged
+}
+
+fn transpose(image: &ColorImage) -> ColorImage {
+    let mut transposed = ColorImage::new_w_h(image.height, image.width);
+    for y in 0..image.height {
+        for x in 0..image.width {
+            transposed.set_pixel(y, x, &image.get_pixel(x, y));
+        }
+    }
+    transposed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::BoundingRect;
+
+    #[test]
+    fn carve_around_content() {
+        // a black square in one corner of a flat image, and a red stripe in another
+        let mut image = ColorImage::new_w_h(30, 20);
+        image.fill_rect(&BoundingRect::new_x_y_w_h(0, 0, 30, 20), &Color::new(255, 255, 255));
+        image.fill_rect(&BoundingRect::new_x_y_w_h(2, 2, 6, 6), &Color::new(0, 0, 0));
+        image.fill_rect(&BoundingRect::new_x_y_w_h(24, 0, 2, 20), &Color::new(200, 0, 0));
+
+        let seam = find_vertical_seam(&image.gradient_magnitude());
+        assert_eq!(seam.len(), 20);
+        assert!(seam.windows(2).all(|w| w[0].abs_diff(w[1]) <= 1));
+
+        let count = |image: &ColorImage, color: Color| image.iter().filter(|&c| c == color).count();
+        let smaller = image.seam_carve(20, 14);
+        assert_eq!((smaller.width, smaller.height), (20, 14));
+        // the flat background is removed, the content is kept
+        assert_eq!(count(&smaller, Color::new(0, 0, 0)), 36);
+        assert_eq!(count(&smaller, Color::new(200, 0, 0)), 2 * 14);
+
+        let larger = image.seam_carve(45, 20);
+        assert_eq!((larger.width, larger.height), (45, 20));
+        assert_eq!(count(&larger, Color::new(0, 0, 0)), 36);
+    }
+
+    #[test]
+    fn carve_empty() {
+        let carved = ColorImage::new_w_h(0, 5).seam_carve(3, 4);
+        assert_eq!((carved.width, carved.height), (3, 4));
+        assert_eq!(ColorImage::new_w_h(4, 0).seam_carve(2, 2).width, 2);
+    }
+}
@@ -0,0 +1,115 @@
+use crate::{BinaryImage, PathI32, PointI32};
+
+/// 4-neighbours first, so that a walk takes the straighter step
+const NEIGHBOURS: [(i32, i32); 8] = [(1, 0), (0, 1), (-1, 0), (0, -1), (1, 1), (-1, 1), (-1, -1), (1, -1)];
+
+/// The 8-neighbours in order around a pixel
+const RING: [(i32, i32); 8] = [(1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1), (0, -1), (1, -1)];
+
+impl BinaryImage {
+    /// Polylines along the pixels of a one pixel thin image (e.g. `to_skeleton().image`), for centerline tracing.
+    ///
+    /// Each polyline runs from an end or a junction (a pixel with 3 or more branches around it) to the next end or junction;
+    /// a closed loop without junctions comes out as a polyline back to its first point. Single pixels are left out.
+    pub fn trace_centerlines(&self) -> Vec<PathI32> {
+        let (width, height) = (self.width as i32, self.height as i32);
+        let index = |p: PointI32| (p.y * width + p.x) as usize;
+        let neighbours = |p: PointI32| NEIGHBOURS.iter()
+            .map(move |&(dx, dy)| PointI32::new(p.x + dx, p.y + dy))
+            .filter(|&n| self.get_pixel_safe(n.x, n.y));
+        // the number of separate runs of set pixels around each pixel: 1 at an end, 3 or more at a junction
+        let branches = |p: PointI32| (0..8)
+            .filter(|&i| {
+                let (a, b) = (RING[i], RING[(i + 1) % 8]);
+                !self.get_pixel_safe(p.x + a.0, p.y + a.1) && self.get_pixel_safe(p.x + b.0, p.y + b.1)
+            })
+            .count();
+        let junction: Vec<bool> = (0..width * height)
+            .map(|i| {
+                let p = PointI32::new(i % width, i / width);
+                self.get_pixel(p.x as usize, p.y as usize) && branches(p) >= 3
+            })
+            .collect();
+        let mut visited = vec![false; (width * height) as usize];
+
+        // ends first, then junctions, then whatever remains (loops)
+        let set: Vec<PointI32> = (0..height)
+            .flat_map(|y| (0..width).map(move |x| PointI32::new(x, y)))
+            .filter(|p| self.get_pixel(p.x as usize, p.y as usize))
+            .collect();
+        let seeds = set.iter().filter(|&&p| branches(p) == 1)
+            .chain(set.iter().filter(|&&p| junction[index(p)]))
+            .chain(set.iter())
+            .copied()
+            .collect::<Vec<_>>();
+
+        let mut paths = Vec::new();
+        for seed in seeds {
+            let is_junction = junction[index(seed)];
+            if visited[index(seed)] {
+                continue;
+            }
+            // a junction starts a walk along each of its branches
+            loop {
+                let first = neighbours(seed).find(|&n| !visited[index(n)] && !junction[index(n)])
+                    .or_else(|| if is_junction { None } else { neighbours(seed).find(|&n| junction[index(n)]) });
+                let Some(first) = first else { break };
+                if !is_junction {
+                    visited[index(seed)] = true;
+                }
+                let mut path = PathI32::new();
+                path.add(seed);
+                let (mut previous, mut current) = (seed, first);
+                loop {
+                    path.add(current);
+                    if junction[index(current)] {
+                        break;
+                    }
+                    visited[index(current)] = true;
+                    // on to a junction as soon as one is next to it, or back to the seed of a loop
+                    let next = neighbours(current).find(|&n| n != previous && (junction[index(n)] || !visited[index(n)]))
+                        .or_else(|| neighbours(current).find(|&n| n == seed && n != previous));
+                    match next {
+                        Some(next) => (previous, current) = (current, next),
+                        None => break,
+                    }
+                    if current == seed {
+                        path.add(seed);
+                        break;
+                    }
+                }
+                paths.push(path);
+                if !is_junction {
+                    break;
+                }
+            }
+        }
+        paths
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn centerlines_of_t_and_loop() {
+        let image = BinaryImage::from_string(&(
+            "*****----\n".to_owned() +
+            "--*---**-\n" +
+            "--*--*--*\n" +
+            "-----*--*\n" +
+            "------**-\n"
+        ));
+        let mut paths: Vec<Vec<(i32, i32)>> = image.trace_centerlines().iter()
+            .map(|path| path.iter().map(|p| (p.x, p.y)).collect())
+            .collect();
+        paths.sort();
+        assert_eq!(paths, [
+            vec![(0, 0), (1, 0), (2, 0)],
+            vec![(2, 2), (2, 1), (2, 0)],
+            vec![(4, 0), (3, 0), (2, 0)],
+            vec![(6, 1), (7, 1), (8, 2), (8, 3), (7, 4), (6, 4), (5, 3), (5, 2), (6, 1)],
+        ]);
+    }
+}
@@ -0,0 +1,153 @@
+use crate::{BinaryImage, Perimeter, PointF64, PointI32};
+use crate::clusters::Cluster as BinaryCluster;
+use crate::color_clusters::{Cluster, ClustersView};
+use crate::components::convex_hull;
+use crate::fmath::{atan2, cos, sin};
+
+/// Scale and rotation invariant measures of a binary shape, e.g. as features for simple classifiers.
+/// Holes are part of the shape's perimeter, but not of its area.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct ShapeDescriptors {
+    /// number of pixels
+    pub area: f64,
+    /// length of the outline, by `Perimeter::Marching`
+    pub perimeter: f64,
+    /// 4π area / perimeter², highest for a disc; digital discs reach about 0.9, as staircased outlines are longer
+    pub circularity: f64,
+    /// area over the area of the minimum area (rotated) bounding rect, 1 for a rectangle
+    pub rectangularity: f64,
+    /// 1 - minor / major axis of the ellipse of the same second moments, 0 for a disc or a square
+    pub elongation: f64,
+    /// eccentricity of the same ellipse, in [0, 1)
+    pub eccentricity: f64,
+    /// angle (radians, in (-π/2, π/2], y down) of the major axis of the ellipse
+    pub orientation: f64,
+    /// sides (long, short) of the minimum area bounding rect
+    pub min_area_rect: (f64, f64),
+}
+
+impl ShapeDescriptors {
+    /// Descriptors of the set pixels of `image`; all zero if there are none
+    pub fn from_image(image: &BinaryImage) -> Self {
+        let points: Vec<PointI32> = (0..image.height)
+            .flat_map(|y| (0..image.width).map(move |x| (x, y)))
+            .filter(|&(x, y)| image.get_pixel(x, y))
+            .map(|(x, y)| PointI32::new(x as i32, y as i32))
+            .collect();
+        if points.is_empty() {
+            return Self::default();
+        }
+        let area = points.len() as f64;
+        let perimeter = image.perimeter(Perimeter::Marching);
+
+        // second central moments of the pixel squares
+        let n = area;
+        let mean = points.iter().fold(PointF64::new(0.0, 0.0), |m, p| PointF64::new(m.x + p.x as f64 + 0.5, m.y + p.y as f64 + 0.5));
+        let (cx, cy) = (mean.x / n, mean.y / n);
+        let (mut mu20, mut mu02, mut mu11) = (0.0, 0.0, 0.0);
+        for p in points.iter() {
+            let (dx, dy) = (p.x as f64 + 0.5 - cx, p.y as f64 + 0.5 - cy);
+            mu20 += dx * dx + 1.0 / 12.0;
+            mu02 += dy * dy + 1.0 / 12.0;
+            mu11 += dx * dy;
+        }
+        let (mu20, mu02, mu11) = (mu20 / n, mu02 / n, mu11 / n);
+        let spread = (((mu20 - mu02) / 2.0).powi(2) + mu11 * mu11).sqrt();
+        let (major, minor) = ((mu20 + mu02) / 2.0 + spread, ((mu20 + mu02) / 2.0 - spread).max(0.0));
+
+        let min_area_rect = min_area_rect(&points);
+        Self {
+            area,
+            perimeter,
+            circularity: if perimeter > 0.0 { 4.0 * std::f64::consts::PI * area / (perimeter * perimeter) } else { 0.0 },
+            rectangularity: area / (min_area_rect.0 * min_area_rect.1),
+            elongation: 1.0 - (minor / major).sqrt(),
+            eccentricity: (1.0 - minor / major).sqrt(),
+            orientation: 0.5 * atan2(2.0 * mu11, mu20 - mu02),
+            min_area_rect,
+        }
+    }
+}
+
+/// Sides (long, short) of the minimum area rect enclosing the pixel squares, by rotating calipers over the hull
+fn min_area_rect(points: &[PointI32]) -> (f64, f64) {
+    let corners: Vec<PointI32> = points
+        .iter()
+        .flat_map(|p| [*p, PointI32::new(p.x + 1, p.y), PointI32::new(p.x, p.y + 1), PointI32::new(p.x + 1, p.y + 1)])
+        .collect();
+    let hull = convex_hull(corners);
+    let mut best = (f64::MAX, 0.0, 0.0);
+    for i in 0..hull.len() {
+        let (a, b) = (hull[i], hull[(i + 1) % hull.len()]);
+        let angle = atan2((b.y - a.y) as f64, (b.x - a.x) as f64);
+        let (u, v) = ((cos(angle), sin(angle)), (-sin(angle), cos(angle)));
+        let (mut lo, mut hi) = ((f64::MAX, f64::MAX), (f64::MIN, f64::MIN));
+        for p in hull.iter() {
+            let (pu, pv) = (p.x as f64 * u.0 + p.y as f64 * u.1, p.x as f64 * v.0 + p.y as f64 * v.1);
+            lo = (lo.0.min(pu), lo.1.min(pv));
+            hi = (hi.0.max(pu), hi.1.max(pv));
+        }
+        let (w, h) = (hi.0 - lo.0, hi.1 - lo.1);
+        if w * h < best.0 - 1e-9 {
+            best = (w * h, w.max(h), w.min(h));
+        }
+    }
+    (best.1, best.2)
+}
+
+impl Cluster {
+    /// See `ShapeDescriptors`; holes of the cluster are excluded
+    pub fn shape_descriptors(&self, parent: &ClustersView) -> ShapeDescriptors {
+        ShapeDescriptors::from_image(&self.to_image(parent))
+    }
+}
+
+impl BinaryCluster {
+    /// See `ShapeDescriptors`
+    pub fn shape_descriptors(&self) -> ShapeDescriptors {
+        ShapeDescriptors::from_image(&self.to_binary_image())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn descriptors_of_basic_shapes() {
+        let rect = |w: usize, h: usize| {
+            let mut image = BinaryImage::new_w_h(w, h);
+            image.pixels.set_all();
+            ShapeDescriptors::from_image(&image)
+        };
+        let square = rect(10, 10);
+        assert_eq!(square.area, 100.0);
+        assert!((square.rectangularity - 1.0).abs() < 1e-9);
+        assert!(square.elongation.abs() < 1e-9);
+        assert!((0.8..0.85).contains(&square.circularity));
+
+        let bar = rect(20, 5);
+        assert!((bar.elongation - 0.75).abs() < 1e-9);
+        assert!(bar.orientation.abs() < 1e-9);
+        assert_eq!(bar.min_area_rect, (20.0, 5.0));
+
+        // a disc, and a diagonal bar whose minimum area rect is rotated
+        let mut disc = BinaryImage::new_w_h(41, 41);
+        let mut diagonal = BinaryImage::new_w_h(30, 30);
+        for y in 0..41 {
+            for x in 0..41 {
+                disc.set_pixel(x, y, (x as i32 - 20).pow(2) + (y as i32 - 20).pow(2) <= 400);
+                if x < 30 && y < 30 {
+                    diagonal.set_pixel(x, y, (x as i32 - y as i32).abs() <= 2);
+                }
+            }
+        }
+        let disc = ShapeDescriptors::from_image(&disc);
+        assert!(disc.circularity > square.circularity + 0.03 && disc.elongation < 0.01);
+        let diagonal = ShapeDescriptors::from_image(&diagonal);
+        assert!(diagonal.rectangularity > 0.7 && diagonal.min_area_rect.1 < 5.0);
+        assert!((diagonal.orientation - std::f64::consts::FRAC_PI_4).abs() < 1e-6);
+        assert!(diagonal.elongation > 0.8);
+        assert_eq!(ShapeDescriptors::from_image(&BinaryImage::new_w_h(3, 3)), ShapeDescriptors::default());
+    }
+}
@@ -0,0 +1,155 @@
+use crate::{BinaryImage, BoundingRect, Color, ColorImage, ImageMut, PointF64, PointI32, distance_to_segment};
+use super::rasterizer::bresenham;
+
+/// Points of the outline of a circle, by the midpoint circle algorithm; may repeat points
+fn circle_points(center: PointI32, radius: i32) -> Vec<PointI32> {
+    let mut points = Vec::new();
+    let (mut x, mut y, mut error) = (radius, 0, 1 - radius);
+    while x >= y {
+        for (dx, dy) in [(x, y), (y, x), (-y, x), (-x, y), (-x, -y), (-y, -x), (y, -x), (x, -y)] {
+            points.push(PointI32::new(center.x + dx, center.y + dy));
+        }
+        y += 1;
+        if error < 0 {
+            error += 2 * y + 1;
+        } else {
+            x -= 1;
+            error += 2 * (y - x) + 1;
+        }
+    }
+    points
+}
+
+/// Points of the segments through `points`, and back to the first if `closed`
+fn polyline_points(points: &[PointI32], closed: bool) -> impl Iterator<Item = PointI32> + '_ {
+    let segments = if closed && points.len() > 2 { points.len() } else { points.len().saturating_sub(1) };
+    (0..segments).flat_map(move |i| bresenham(points[i], points[(i + 1) % points.len()]))
+}
+
+fn draw_line<I: ImageMut>(image: &mut I, p0: PointI32, p1: PointI32, v: I::Pixel) {
+    for p in bresenham(p0, p1) {
+        image.put_pixel_safe(p.x, p.y, v);
+    }
+}
+
+fn draw_polyline<I: ImageMut>(image: &mut I, points: &[PointI32], closed: bool, v: I::Pixel) {
+    for p in polyline_points(points, closed) {
+        image.put_pixel_safe(p.x, p.y, v);
+    }
+}
+
+fn draw_circle<I: ImageMut>(image: &mut I, center: PointI32, radius: i32, v: I::Pixel) {
+    for p in circle_points(center, radius) {
+        image.put_pixel_safe(p.x, p.y, v);
+    }
+}
+
+fn fill_rect<I: ImageMut>(image: &mut I, rect: &BoundingRect, v: I::Pixel) {
+    for (y, left, right) in rect.scanlines() {
+        for x in left..right {
+            image.put_pixel_safe(x, y, v);
+        }
+    }
+}
+
+/// Drawing primitives for debug overlays and test fixtures; pixels outside the image are skipped
+impl BinaryImage {
+    pub fn draw_line(&mut self, p0: PointI32, p1: PointI32, v: bool) {
+        draw_line(self, p0, p1, v);
+    }
+
+    pub fn draw_polyline(&mut self, points: &[PointI32], closed: bool, v: bool) {
+        draw_polyline(self, points, closed, v);
+    }
+
+    pub fn draw_circle(&mut self, center: PointI32, radius: i32, v: bool) {
+        draw_circle(self, center, radius, v);
+    }
+
+    pub fn fill_rect(&mut self, rect: &BoundingRect, v: bool) {
+        fill_rect(self, rect, v);
+    }
+}
+
+impl ColorImage {
+    pub fn draw_line(&mut self, p0: PointI32, p1: PointI32, color: &Color) {
+        draw_line(self, p0, p1, *color);
+    }
+
+    pub fn draw_polyline(&mut self, points: &[PointI32], closed: bool, color: &Color) {
+        draw_polyline(self, points, closed, *color);
+    }
+
+    pub fn draw_circle(&mut self, center: PointI32, radius: i32, color: &Color) {
+        draw_circle(self, center, radius, *color);
+    }
+
+    pub fn fill_rect(&mut self, rect: &BoundingRect, color: &Color) {
+        fill_rect(self, rect, *color);
+    }
+
+    /// Draws a line `width` wide between pixel centers `p0` and `p1`, anti-aliased:
+    /// each pixel is blended with `color` by how much of it the line covers, estimated from its distance
+    pub fn draw_thick_line(&mut self, p0: PointF64, p1: PointF64, width: f64, color: &Color) {
+        let half = width / 2.0;
+        let reach = half + 1.0;
+        let left = (p0.x.min(p1.x) - reach).floor().max(0.0) as usize;
+        let top = (p0.y.min(p1.y) - reach).floor().max(0.0) as usize;
+        let right = ((p0.x.max(p1.x) + reach).ceil().max(0.0) as usize).min(self.width);
+        let bottom = ((p0.y.max(p1.y) + reach).ceil().max(0.0) as usize).min(self.height);
+        for y in top..bottom {
+            for x in left..right {
+                let distance = distance_to_segment(PointF64::new(x as f64, y as f64), p0, p1);
+                let coverage = (half + 0.5 - distance).clamp(0.0, 1.0);
+                if coverage <= 0.0 {
+                    continue;
+                }
+                let under = self.get_pixel(x, y);
+                let mix = |a: u8, b: u8| (a as f64 * (1.0 - coverage) + b as f64 * coverage).round() as u8;
+                self.set_pixel(x, y, &Color::new_rgba(
+                    mix(under.r, color.r), mix(under.g, color.g), mix(under.b, color.b), mix(under.a, color.a),
+                ));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn draw_primitives() {
+        let mut image = BinaryImage::new_w_h(7, 7);
+        image.draw_circle(PointI32::new(3, 3), 3, true);
+        assert_eq!(image.to_string(),
+            "--***--\n".to_owned() +
+            "-*---*-\n" +
+            "*-----*\n" +
+            "*-----*\n" +
+            "*-----*\n" +
+            "-*---*-\n" +
+            "--***--\n"
+        );
+        let mut image = BinaryImage::new_w_h(5, 4);
+        image.draw_polyline(&[PointI32::new(0, 0), PointI32::new(4, 0), PointI32::new(4, 3)], true, true);
+        image.fill_rect(&BoundingRect::new_x_y_w_h(-1, 3, 2, 5), true);
+        assert_eq!(image.to_string(),
+            "*****\n".to_owned() +
+            "-**-*\n" +
+            "---**\n" +
+            "*---*\n"
+        );
+
+        let (white, black) = (Color::new(255, 255, 255), Color::new(0, 0, 0));
+        let mut image = ColorImage::new_w_h(10, 5);
+        image.fill_rect(&BoundingRect::new_x_y_w_h(0, 0, 10, 5), &white);
+        image.draw_thick_line(PointF64::new(1.0, 2.0), PointF64::new(8.0, 2.0), 1.0, &black);
+        assert_eq!(image.get_pixel(4, 2), black);
+        assert_eq!(image.get_pixel(4, 0), white);
+        assert_eq!(image.get_pixel(0, 2), white);
+        // a pixel whose center is on the edge of the line is half covered
+        image.draw_thick_line(PointF64::new(1.0, 0.0), PointF64::new(8.0, 0.0), 2.0, &black);
+        assert_eq!(image.get_pixel(4, 1), Color::new(128, 128, 128));
+    }
+}
@@ -0,0 +1,184 @@
+use crate::{BinaryImage, PointI32, sdf_from_mask};
+use crate::clusters::{Cluster, Clusters};
+
+impl BinaryImage {
+    /// Number of set pixels in each column
+    pub fn column_projection(&self) -> Vec<usize> {
+        let mut projection = vec![0; self.width];
+        for y in 0..self.height {
+            for (x, count) in projection.iter_mut().enumerate() {
+                if self.get_pixel(x, y) {
+                    *count += 1;
+                }
+            }
+        }
+        projection
+    }
+
+    /// Columns at which to split horizontally touching glyphs: the middle of each valley of the column projection
+    /// which is at most `valley_ratio` times the lower of the highest columns on either side.
+    /// Each piece is at least `min_width` wide. A cut at `x` puts column `x` in the piece to the right.
+    pub fn projection_cuts(&self, min_width: usize, valley_ratio: f64) -> Vec<usize> {
+        let projection = self.column_projection();
+        let width = projection.len();
+        let mut cuts = Vec::new();
+        let mut last = 0;
+        let mut x = 1;
+        while x + 1 < width {
+            if projection[x] >= projection[x - 1] {
+                x += 1;
+                continue;
+            }
+            // a run of equal values after a descent
+            let mut end = x;
+            while end + 1 < width && projection[end + 1] == projection[x] {
+                end += 1;
+            }
+            if end + 1 < width && projection[end + 1] > projection[x] {
+                let cut = x + (end - x) / 2;
+                let left = projection[last..cut].iter().copied().max().unwrap_or(0);
+                let right = projection[cut..].iter().copied().max().unwrap_or(0);
+                if cut - last >= min_width && width - cut >= min_width &&
+                    projection[x] as f64 <= valley_ratio * std::cmp::min(left, right) as f64 {
+                    cuts.push(cut);
+                    last = cut;
+                }
+            }
+            x = end + 1;
+        }
+        cuts
+    }
+
+    /// Splits each connected component at its `projection_cuts`
+    pub fn split_by_projection(&self, min_width: usize, valley_ratio: f64) -> Clusters {
+        let mut result = Clusters::default();
+        for component in self.to_clusters(false) {
+            let left = component.rect.left;
+            let cuts = component.to_binary_image().projection_cuts(min_width, valley_ratio);
+            let mut pieces: Vec<Cluster> = (0..=cuts.len()).map(|_| Cluster::default()).collect();
+            for &p in component.points.iter() {
+                let piece = cuts.partition_point(|&cut| cut as i32 + left <= p.x);
+                pieces[piece].add(p);
+            }
+            for piece in pieces.into_iter().filter(|p| p.size() > 0) {
+                result.add_cluster(piece);
+            }
+        }
+        result
+    }
+
+    /// Splits touching blobs along the necks between them, by a watershed of the distance transform.
+    ///
+    /// Each blob grows from a maximum of the distance to the background; two blobs are kept apart only if
+    /// the neck between them is at least `min_depth` pixels thinner (in half width) than the lower of their peaks.
+    pub fn split_by_distance_watershed(&self, min_depth: f64) -> Clusters {
+        let (width, height) = (self.width, self.height);
+        let sdf = sdf_from_mask(self, (width + height) as f64);
+        let depth: Vec<f64> = sdf.channel(0).iter().map(|&d| -d as f64).collect();
+        let mut order: Vec<usize> = (0..width * height).filter(|&i| self.pixels[i]).collect();
+        order.sort_by(|&a, &b| depth[b].partial_cmp(&depth[a]).unwrap());
+
+        // basins by union find; the peak of a root is the deepest depth of the basin
+        let mut parent: Vec<usize> = Vec::new();
+        let mut peak: Vec<f64> = Vec::new();
+        fn find(parent: &mut [usize], mut i: usize) -> usize {
+            while parent[i] != i {
+                parent[i] = parent[parent[i]];
+                i = parent[i];
+            }
+            i
+        }
+
+        let mut basin = vec![usize::MAX; width * height];
+        for &i in order.iter() {
+            let (x, y) = (i % width, i / width);
+            let mut roots: Vec<usize> = Vec::new();
+            let neighbours = [
+                (x > 0).then(|| i - 1),
+                (x + 1 < width).then(|| i + 1),
+                (y > 0).then(|| i - width),
+                (y + 1 < height).then(|| i + width),
+            ];
+            for n in neighbours.into_iter().flatten() {
+                if basin[n] != usize::MAX {
+                    let root = find(&mut parent, basin[n]);
+                    if !roots.contains(&root) {
+                        roots.push(root);
+                    }
+                }
+            }
+            if roots.is_empty() {
+                basin[i] = parent.len();
+                parent.push(parent.len());
+                peak.push(depth[i]);
+                continue;
+            }
+            roots.sort_by(|&a, &b| peak[b].partial_cmp(&peak[a]).unwrap());
+            let highest = roots[0];
+            for &other in roots[1..].iter() {
+                if peak[other] - depth[i] < min_depth {
+                    parent[other] = highest;
+                }
+            }
+            basin[i] = highest;
+        }
+
+        let mut index_of = vec![usize::MAX; parent.len()];
+        let mut clusters: Vec<Cluster> = Vec::new();
+        for (i, &b) in basin.iter().enumerate() {
+            if b == usize::MAX {
+                continue;
+            }
+            let root = find(&mut parent, b);
+            if index_of[root] == usize::MAX {
+                index_of[root] = clusters.len();
+                clusters.push(Cluster::default());
+            }
+            clusters[index_of[root]].add(PointI32::new((i % width) as i32, (i / width) as i32));
+        }
+        let mut result = Clusters::default();
+        for cluster in clusters {
+            result.add_cluster(cluster);
+        }
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// two 5x5 blocks touching by a thin bridge
+    fn touching_blocks() -> BinaryImage {
+        BinaryImage::from_string(&(
+            "*****--*****\n".to_owned() +
+            "*****--*****\n" +
+            "************\n" +
+            "*****--*****\n" +
+            "*****--*****\n"
+        ))
+    }
+
+    #[test]
+    fn split_touching_by_projection() {
+        let image = touching_blocks();
+        assert_eq!(image.column_projection()[4..8], [5, 1, 1, 5]);
+        assert_eq!(image.projection_cuts(2, 0.5), vec![5]);
+        let pieces = image.split_by_projection(2, 0.5);
+        assert_eq!(pieces.len(), 2);
+        assert_eq!(pieces.get_cluster(0).size(), 25);
+        assert_eq!(pieces.get_cluster(1).size(), 27);
+        // too deep a valley required
+        assert_eq!(image.split_by_projection(2, 0.1).len(), 1);
+    }
+
+    #[test]
+    fn split_touching_by_watershed() {
+        let image = touching_blocks();
+        let pieces = image.split_by_distance_watershed(1.0);
+        assert_eq!(pieces.len(), 2);
+        assert!(pieces.iter().all(|p| p.size() >= 25));
+        assert!(pieces.iter().any(|p| p.rect.left == 0 && p.rect.right <= 7));
+        assert_eq!(image.split_by_distance_watershed(10.0).len(), 1);
+    }
+}
@@ -1,12 +1,24 @@
+mod centerline;
+mod descriptors;
+mod draw;
 mod geometry;
+mod glyph;
 mod image_operations;
+mod perimeter;
 mod processor;
 pub mod rasterizer;
+mod rlsa;
 mod skeleton;
+mod symmetry;
+mod zernike;
 mod arc;
 
+pub use descriptors::*;
 pub use geometry::*;
 pub use image_operations::*;
+pub use perimeter::*;
 pub use processor::*;
 pub use skeleton::*;
+pub use symmetry::*;
+pub use zernike::*;
 pub use arc::*;
\ No newline at end of file
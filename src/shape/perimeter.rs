@@ -0,0 +1,108 @@
+use crate::BinaryImage;
+
+const NEIGHBOURS_4: [(i32, i32); 4] = [(0, -1), (-1, 0), (1, 0), (0, 1)];
+const NEIGHBOURS_8: [(i32, i32); 8] = [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)];
+
+/// Definitions of the perimeter of a binary shape. They differ by up to 40% on the same shape,
+/// so descriptors such as circularity depend on which is used. Pixels outside the image count as unset.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum Perimeter {
+    /// Set pixels with an unset 4-neighbour; what `Cluster::perimeter` counts
+    #[default]
+    Inner4,
+    /// Set pixels with an unset 8-neighbour
+    Inner8,
+    /// Unset pixels with a set 4-neighbour
+    Outer4,
+    /// Unset pixels with a set 8-neighbour
+    Outer8,
+    /// Edges between set and unset pixels; overestimates diagonal boundaries by up to √2
+    Crack,
+    /// Length of the marching squares contour through the pixel centers; closest to the length of
+    /// the continuous outline, as diagonal boundaries are not staircased
+    Marching,
+}
+
+impl BinaryImage {
+    /// Perimeter of the set pixels, by `kind`; holes count towards the perimeter
+    pub fn perimeter(&self, kind: Perimeter) -> f64 {
+        let (width, height) = (self.width as i32, self.height as i32);
+        let get = |x: i32, y: i32| self.get_pixel_safe(x, y);
+        let count = |set: bool, neighbours: &[(i32, i32)]| {
+            // unset pixels just outside the image can border set pixels too
+            let margin = if set { 0 } else { 1 };
+            let mut count = 0;
+            for y in -margin..height + margin {
+                for x in -margin..width + margin {
+                    if get(x, y) == set && neighbours.iter().any(|&(dx, dy)| get(x + dx, y + dy) != set) {
+                        count += 1;
+                    }
+                }
+            }
+            count as f64
+        };
+        match kind {
+            Perimeter::Inner4 => count(true, &NEIGHBOURS_4),
+            Perimeter::Inner8 => count(true, &NEIGHBOURS_8),
+            Perimeter::Outer4 => count(false, &NEIGHBOURS_4),
+            Perimeter::Outer8 => count(false, &NEIGHBOURS_8),
+            Perimeter::Crack => {
+                let mut edges = 0;
+                for y in 0..height {
+                    for x in 0..width {
+                        if get(x, y) {
+                            edges += NEIGHBOURS_4.iter().filter(|&&(dx, dy)| !get(x + dx, y + dy)).count();
+                        }
+                    }
+                }
+                edges as f64
+            },
+            Perimeter::Marching => {
+                let half_diagonal = std::f64::consts::SQRT_2 / 2.0;
+                let mut length = 0.0;
+                // cells of 2x2 pixel centers, including those straddling the image border
+                for y in -1..height {
+                    for x in -1..width {
+                        let corners = [get(x, y), get(x + 1, y), get(x + 1, y + 1), get(x, y + 1)];
+                        length += match corners.iter().filter(|&&c| c).count() {
+                            1 | 3 => half_diagonal,
+                            // two adjacent corners are cut straight across, two opposite by two diagonals
+                            2 if corners[0] == corners[2] => 2.0 * half_diagonal,
+                            2 => 1.0,
+                            _ => 0.0,
+                        };
+                    }
+                }
+                length
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn perimeters_of_square() {
+        // a 3x3 square, away from the border and against it
+        for offset in [0, 2] {
+            let mut image = BinaryImage::new_w_h(3 + 2 * offset, 3 + 2 * offset);
+            for y in 0..3 {
+                for x in 0..3 {
+                    image.set_pixel(x + offset, y + offset, true);
+                }
+            }
+            assert_eq!(image.perimeter(Perimeter::Inner4), 8.0);
+            assert_eq!(image.perimeter(Perimeter::Inner8), 8.0);
+            assert_eq!(image.perimeter(Perimeter::Outer4), 12.0);
+            assert_eq!(image.perimeter(Perimeter::Outer8), 16.0);
+            assert_eq!(image.perimeter(Perimeter::Crack), 12.0);
+            assert!((image.perimeter(Perimeter::Marching) - (8.0 + 2.0 * std::f64::consts::SQRT_2)).abs() < 1e-9);
+        }
+        // a plus: the center has no unset 4-neighbour, but has unset 8-neighbours
+        let plus = BinaryImage::from_string("-*-\n***\n-*-\n");
+        assert_eq!(plus.perimeter(Perimeter::Inner4), 4.0);
+        assert_eq!(plus.perimeter(Perimeter::Inner8), 5.0);
+    }
+}
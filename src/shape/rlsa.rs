@@ -0,0 +1,78 @@
+use crate::BinaryImage;
+
+/// Run-length smearing (RLSA), for layout analysis: filling the gaps between characters merges them into words,
+/// lines or blocks. Only gaps between set pixels are filled, not those at the image borders.
+impl BinaryImage {
+    /// Fills each horizontal run of unset pixels at most `gap` long
+    pub fn smear_horizontal(&self, gap: usize) -> BinaryImage {
+        let mut result = self.clone();
+        for y in 0..self.height {
+            smear_line(self.width, gap, |x| self.get_pixel(x, y), |x| result.set_pixel(x, y, true));
+        }
+        result
+    }
+
+    /// Fills each vertical run of unset pixels at most `gap` long
+    pub fn smear_vertical(&self, gap: usize) -> BinaryImage {
+        let mut result = self.clone();
+        for x in 0..self.width {
+            smear_line(self.height, gap, |y| self.get_pixel(x, y), |y| result.set_pixel(x, y, true));
+        }
+        result
+    }
+
+    /// The classic RLSA: the intersection of the horizontal and vertical smears,
+    /// followed by a horizontal smear by `final_gap` to join what the intersection broke up
+    pub fn rlsa(&self, horizontal_gap: usize, vertical_gap: usize, final_gap: usize) -> BinaryImage {
+        let horizontal = self.smear_horizontal(horizontal_gap);
+        let vertical = self.smear_vertical(vertical_gap);
+        let mut both = horizontal;
+        both.pixels.and(&vertical.pixels);
+        both.smear_horizontal(final_gap)
+    }
+}
+
+/// Calls `fill` for each position of the runs of `len` positions, where `get` is false, between two where it is true
+fn smear_line(len: usize, gap: usize, get: impl Fn(usize) -> bool, mut fill: impl FnMut(usize)) {
+    let mut last = None;
+    for i in 0..len {
+        if get(i) {
+            if let Some(last) = last {
+                if i - last - 1 <= gap {
+                    (last + 1..i).for_each(&mut fill);
+                }
+            }
+            last = Some(i);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn smear_gaps() {
+        let image = BinaryImage::from_string(&(
+            "*-*---*-\n".to_owned() +
+            "--------\n" +
+            "*-*--**-\n"
+        ));
+        assert_eq!(image.smear_horizontal(2).to_string(),
+            "***---*-\n".to_owned() +
+            "--------\n" +
+            "*******-\n"
+        );
+        assert_eq!(image.smear_vertical(1).to_string(),
+            "*-*---*-\n".to_owned() +
+            "*-*---*-\n" +
+            "*-*--**-\n"
+        );
+        // the empty row breaks the horizontal smear, so the intersection leaves it empty
+        assert_eq!(image.rlsa(3, 1, 3).to_string(),
+            "*******-\n".to_owned() +
+            "--------\n" +
+            "*******-\n"
+        );
+    }
+}
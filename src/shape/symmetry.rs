@@ -0,0 +1,302 @@
+use std::f64::consts::PI;
+use crate::{BinaryImage, PathF64, PointF64, sdf_from_mask};
+use crate::fmath::{cos, sin};
+
+/// Number of candidate axes tried in [0, PI) before refinement
+const ANGLE_STEPS: usize = 180;
+/// Highest order of rotational symmetry looked for
+const MAX_ORDER: u32 = 12;
+/// Number of points a path is resampled to when measuring symmetry
+const PATH_SAMPLES: usize = 128;
+
+/// A symmetry of a shape
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Symmetry {
+    /// Mirroring about the line through `center` at `angle` (radians from the x axis)
+    Reflective { center: PointF64, angle: f64 },
+    /// Rotation by `2 * PI / order` about `center`
+    Rotational { center: PointF64, order: u32 },
+}
+
+/// A symmetry found in a shape, with `confidence` in [0, 1] (1 being exactly symmetric)
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SymmetryMatch {
+    pub symmetry: Symmetry,
+    pub confidence: f64,
+}
+
+impl Symmetry {
+    /// Maps `p` to its image under the symmetry
+    pub fn apply(&self, p: PointF64) -> PointF64 {
+        match *self {
+            Self::Reflective { center, angle } => reflect(p, center, angle),
+            Self::Rotational { center, order } => rotate(p, center, 2.0 * PI / order as f64),
+        }
+    }
+
+    /// The number of distinct transforms in the symmetry group generated, including identity
+    fn group_size(&self) -> u32 {
+        match *self {
+            Self::Reflective { .. } => 2,
+            Self::Rotational { order, .. } => order,
+        }
+    }
+}
+
+impl BinaryImage {
+    /// The axis of reflection under which the shape best overlaps itself.
+    /// Confidence is the intersection over union of the shape and its mirror image.
+    pub fn reflective_symmetry(&self) -> Option<SymmetryMatch> {
+        let center = self.pixel_centroid()?;
+        Some(best_reflection(center, |s| self.symmetry_overlap(s)))
+    }
+
+    /// The highest order of rotational symmetry (at least 2) with confidence at least `min_confidence`.
+    /// Confidence is the intersection over union of the shape and its rotated image.
+    pub fn rotational_symmetry(&self, min_confidence: f64) -> Option<SymmetryMatch> {
+        let center = self.pixel_centroid()?;
+        best_rotation(center, min_confidence, |s| self.symmetry_overlap(s))
+    }
+
+    /// Averages the shape with its images under `symmetry`, by averaging their signed distance fields,
+    /// so the result is exactly symmetric (up to pixel rounding).
+    pub fn symmetrize(&self, symmetry: &Symmetry) -> BinaryImage {
+        let spread = (self.width + self.height) as f64;
+        let sdf = sdf_from_mask(self, spread);
+        let n = symmetry.group_size();
+        let mut result = BinaryImage::new_w_h(self.width, self.height);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                let mut p = PointF64::new(x as f64 + 0.5, y as f64 + 0.5);
+                let mut sum = 0.0;
+                for _ in 0..n {
+                    sum += match self.pixel_at_f64(p) {
+                        Some((px, py)) => sdf.get(px, py, 0) as f64,
+                        None => spread,
+                    };
+                    p = symmetry.apply(p);
+                }
+                result.set_pixel(x, y, sum < 0.0);
+            }
+        }
+        result
+    }
+
+    fn pixel_centroid(&self) -> Option<PointF64> {
+        let (mut sum, mut count) = (PointF64::default(), 0);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if self.get_pixel(x, y) {
+                    sum += PointF64::new(x as f64 + 0.5, y as f64 + 0.5);
+                    count += 1;
+                }
+            }
+        }
+        if count == 0 {
+            return None;
+        }
+        Some(sum / count as f64)
+    }
+
+    /// The pixel covering `p`, if within the image
+    fn pixel_at_f64(&self, p: PointF64) -> Option<(usize, usize)> {
+        let (x, y) = (p.x.floor(), p.y.floor());
+        if x >= 0.0 && y >= 0.0 && x < self.width as f64 && y < self.height as f64 {
+            Some((x as usize, y as usize))
+        } else {
+            None
+        }
+    }
+
+    /// Coverage at `p` interpolated bilinearly between pixel centers, so that overlap varies smoothly with the transform
+    fn coverage_at(&self, p: PointF64) -> f64 {
+        let (u, v) = (p.x - 0.5, p.y - 0.5);
+        let (x0, y0) = (u.floor(), v.floor());
+        let (fx, fy) = (u - x0, v - y0);
+        let at = |x: f64, y: f64| if self.get_pixel_safe(x as i32, y as i32) { 1.0 } else { 0.0 };
+        let top = at(x0, y0) * (1.0 - fx) + at(x0 + 1.0, y0) * fx;
+        let bottom = at(x0, y0 + 1.0) * (1.0 - fx) + at(x0 + 1.0, y0 + 1.0) * fx;
+        top * (1.0 - fy) + bottom * fy
+    }
+
+    fn symmetry_overlap(&self, symmetry: &Symmetry) -> f64 {
+        let (mut area, mut matched) = (0.0, 0.0);
+        for y in 0..self.height {
+            for x in 0..self.width {
+                if !self.get_pixel(x, y) {
+                    continue;
+                }
+                area += 1.0;
+                matched += self.coverage_at(symmetry.apply(PointF64::new(x as f64 + 0.5, y as f64 + 0.5)));
+            }
+        }
+        matched / (2.0 * area - matched)
+    }
+}
+
+impl PathF64 {
+    /// Like `BinaryImage::reflective_symmetry`, for a closed path (first point repeated at the end).
+    /// Confidence is 1 minus the mean distance of the mirrored outline to the outline, relative to the mean radius.
+    pub fn reflective_symmetry(&self) -> Option<SymmetryMatch> {
+        let (center, samples) = self.symmetry_samples()?;
+        Some(best_reflection(center, |s| self.symmetry_fit(&samples, center, s)))
+    }
+
+    /// Like `BinaryImage::rotational_symmetry`, for a closed path; confidence is as in `reflective_symmetry`.
+    pub fn rotational_symmetry(&self, min_confidence: f64) -> Option<SymmetryMatch> {
+        let (center, samples) = self.symmetry_samples()?;
+        best_rotation(center, min_confidence, |s| self.symmetry_fit(&samples, center, s))
+    }
+
+    /// The area centroid and points evenly spaced along the outline
+    fn symmetry_samples(&self) -> Option<(PointF64, Vec<PointF64>)> {
+        let points = &self.path;
+        if points.len() < 4 {
+            return None;
+        }
+        let (mut area, mut moment) = (0.0, PointF64::default());
+        let mut perimeter = 0.0;
+        for w in points.windows(2) {
+            let cross = w[0].x * w[1].y - w[1].x * w[0].y;
+            area += cross;
+            moment += (w[0] + w[1]) * cross;
+            perimeter += (w[1] - w[0]).norm();
+        }
+        if area == 0.0 {
+            return None;
+        }
+        let center = moment / (3.0 * area);
+
+        let step = perimeter / PATH_SAMPLES as f64;
+        let mut samples = Vec::with_capacity(PATH_SAMPLES);
+        let mut along = 0.0;
+        for w in points.windows(2) {
+            let length = (w[1] - w[0]).norm();
+            while along < length && samples.len() < PATH_SAMPLES {
+                samples.push(w[0] + (w[1] - w[0]) * (along / length));
+                along += step;
+            }
+            along -= length;
+        }
+        Some((center, samples))
+    }
+
+    fn symmetry_fit(&self, samples: &[PointF64], center: PointF64, symmetry: &Symmetry) -> f64 {
+        let radius = samples.iter().map(|&p| (p - center).norm()).sum::<f64>() / samples.len() as f64;
+        let error = samples.iter().map(|&p| self.distance_to_point(symmetry.apply(p))).sum::<f64>() / samples.len() as f64;
+        (1.0 - error / radius).max(0.0)
+    }
+}
+
+/// Searches the axis with the best `score` on a coarse grid of angles, then refines around it
+fn best_reflection(center: PointF64, score: impl Fn(&Symmetry) -> f64) -> SymmetryMatch {
+    let step = PI / ANGLE_STEPS as f64;
+    let evaluate = |angle: f64| {
+        let symmetry = Symmetry::Reflective { center, angle };
+        SymmetryMatch { symmetry, confidence: score(&symmetry) }
+    };
+    let better = |a: SymmetryMatch, b: SymmetryMatch| if b.confidence > a.confidence { b } else { a };
+    let coarse = (1..ANGLE_STEPS)
+        .map(|i| evaluate(i as f64 * step))
+        .fold(evaluate(0.0), better);
+    let angle = match coarse.symmetry {
+        Symmetry::Reflective { angle, .. } => angle,
+        Symmetry::Rotational { .. } => unreachable!(),
+    };
+    (-9..=9)
+        .map(|i| evaluate((angle + i as f64 * step / 10.0).rem_euclid(PI)))
+        .fold(coarse, better)
+}
+
+fn best_rotation(center: PointF64, min_confidence: f64, score: impl Fn(&Symmetry) -> f64) -> Option<SymmetryMatch> {
+    (2..=MAX_ORDER).rev().find_map(|order| {
+        let symmetry = Symmetry::Rotational { center, order };
+        let confidence = score(&symmetry);
+        if confidence >= min_confidence {
+            Some(SymmetryMatch { symmetry, confidence })
+        } else {
+            None
+        }
+    })
+}
+
+fn reflect(p: PointF64, center: PointF64, angle: f64) -> PointF64 {
+    let u = PointF64::new(cos(angle), sin(angle));
+    let d = p - center;
+    let along = d.x * u.x + d.y * u.y;
+    center + u * (2.0 * along) - d
+}
+
+fn rotate(p: PointF64, center: PointF64, angle: f64) -> PointF64 {
+    let (sin, cos) = angle.sin_cos();
+    let d = p - center;
+    center + PointF64::new(d.x * cos - d.y * sin, d.x * sin + d.y * cos)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mask_symmetry() {
+        // an isosceles triangle, mirrored about the vertical
+        let image = BinaryImage::from_string(&(
+            "---*---\n".to_owned() +
+            "--***--\n" +
+            "-*****-\n" +
+            "*******\n"
+        ));
+        let found = image.reflective_symmetry().unwrap();
+        assert!(found.confidence > 0.999);
+        match found.symmetry {
+            Symmetry::Reflective { center, angle } => {
+                assert_eq!(center.x, 3.5);
+                assert!((angle - PI / 2.0).abs() < 1e-6);
+            },
+            _ => panic!(),
+        }
+        assert_eq!(image.rotational_symmetry(0.9), None);
+
+        let plus = BinaryImage::from_string(&(
+            "-*-\n".to_owned() +
+            "***\n" +
+            "-*-\n"
+        ));
+        let found = plus.rotational_symmetry(0.9).unwrap();
+        assert_eq!(found.symmetry, Symmetry::Rotational { center: PointF64::new(1.5, 1.5), order: 4 });
+    }
+
+    #[test]
+    fn mask_symmetrize() {
+        let image = BinaryImage::from_string(&(
+            "-----\n".to_owned() +
+            "-***-\n" +
+            "-****\n" +
+            "-***-\n"
+        ));
+        let symmetry = Symmetry::Reflective { center: PointF64::new(2.5, 2.5), angle: PI / 2.0 };
+        let result = image.symmetrize(&symmetry);
+        for y in 0..4 {
+            for x in 0..5 {
+                assert_eq!(result.get_pixel(x, y), result.get_pixel(4 - x, y));
+            }
+        }
+        assert!(result.get_pixel(2, 2));
+    }
+
+    #[test]
+    fn path_symmetry() {
+        // a rectangle has 2 axes and rotational order 2
+        let rect = PathF64::from_points(vec![
+            PointF64::new(0.0, 0.0),
+            PointF64::new(8.0, 0.0),
+            PointF64::new(8.0, 4.0),
+            PointF64::new(0.0, 4.0),
+            PointF64::new(0.0, 0.0),
+        ]);
+        let found = rect.reflective_symmetry().unwrap();
+        assert!(found.confidence > 0.999);
+        let found = rect.rotational_symmetry(0.99).unwrap();
+        assert_eq!(found.symmetry, Symmetry::Rotational { center: PointF64::new(4.0, 2.0), order: 2 });
+    }
+}
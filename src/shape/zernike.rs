@@ -0,0 +1,153 @@
+use crate::BinaryImage;
+use crate::clusters::Cluster as BinaryCluster;
+use crate::color_clusters::{Cluster, ClustersView};
+use crate::fmath::{atan2, cos, sin};
+
+/// Magnitudes of the Zernike moments of a binary shape, mapped onto the unit disc about its centroid
+/// (with the radius of its farthest pixel), so they are invariant to translation, scale and rotation.
+/// More discriminative than low order moment invariants, e.g. for matching glyphs and symbols.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ZernikeMoments {
+    /// the highest order `n`
+    pub order: usize,
+    /// `|A(n, m)|` for `n` in `0..=order`, `m` in `0..=n` with `n - m` even, by `n` then `m`
+    pub magnitudes: Vec<f64>,
+}
+
+impl ZernikeMoments {
+    /// Moments of the set pixels of `image`, up to `order`; all zero if there are none
+    pub fn from_image(image: &BinaryImage, order: usize) -> Self {
+        let mut points = Vec::new();
+        for y in 0..image.height {
+            for x in 0..image.width {
+                if image.get_pixel(x, y) {
+                    points.push((x as f64 + 0.5, y as f64 + 0.5));
+                }
+            }
+        }
+        let count = (0..=order).map(|n| n / 2 + 1).sum();
+        if points.is_empty() {
+            return Self { order, magnitudes: vec![0.0; count] };
+        }
+        let n = points.len() as f64;
+        let (cx, cy) = points.iter().fold((0.0, 0.0), |(sx, sy), p| (sx + p.0, sy + p.1));
+        let (cx, cy) = (cx / n, cy / n);
+        // to the far corner of the farthest pixel
+        let radius = points.iter()
+            .map(|p| ((p.0 - cx).powi(2) + (p.1 - cy).powi(2)).sqrt())
+            .fold(0.0, f64::max) + std::f64::consts::FRAC_1_SQRT_2;
+
+        let polar: Vec<(f64, f64)> = points.iter()
+            .map(|p| {
+                let (dx, dy) = ((p.0 - cx) / radius, (p.1 - cy) / radius);
+                ((dx * dx + dy * dy).sqrt(), atan2(dy, dx))
+            })
+            .collect();
+        let mut magnitudes = Vec::with_capacity(count);
+        for nn in 0..=order {
+            for m in (nn % 2..=nn).step_by(2) {
+                let coefficients = radial_coefficients(nn, m);
+                let (mut re, mut im) = (0.0, 0.0);
+                for &(rho, theta) in polar.iter() {
+                    let r: f64 = coefficients.iter().map(|&(c, power)| c * rho.powi(power)).sum();
+                    let angle = m as f64 * theta;
+                    re += r * cos(angle);
+                    im -= r * sin(angle);
+                }
+                // each pixel covers 1 / radius² of the unit disc
+                let scale = (nn + 1) as f64 / std::f64::consts::PI / (radius * radius);
+                magnitudes.push(scale * (re * re + im * im).sqrt());
+            }
+        }
+        Self { order, magnitudes }
+    }
+
+    /// Euclidean distance between the magnitudes; both must be of the same order
+    pub fn distance(&self, other: &Self) -> f64 {
+        assert_eq!(self.order, other.order);
+        self.magnitudes.iter().zip(other.magnitudes.iter())
+            .map(|(a, b)| (a - b) * (a - b))
+            .sum::<f64>()
+            .sqrt()
+    }
+}
+
+/// Terms (coefficient, power of rho) of the radial polynomial `R(n, m)`
+fn radial_coefficients(n: usize, m: usize) -> Vec<(f64, i32)> {
+    let factorial = |k: usize| (1..=k).fold(1.0, |f, i| f * i as f64);
+    (0..=(n - m) / 2)
+        .map(|s| {
+            let sign = if s % 2 == 0 { 1.0 } else { -1.0 };
+            let c = sign * factorial(n - s) /
+                (factorial(s) * factorial((n + m) / 2 - s) * factorial((n - m) / 2 - s));
+            (c, (n - 2 * s) as i32)
+        })
+        .collect()
+}
+
+impl BinaryImage {
+    /// See `ZernikeMoments`
+    pub fn zernike_moments(&self, order: usize) -> ZernikeMoments {
+        ZernikeMoments::from_image(self, order)
+    }
+}
+
+impl Cluster {
+    /// See `ZernikeMoments`; holes of the cluster are excluded
+    pub fn zernike_moments(&self, parent: &ClustersView, order: usize) -> ZernikeMoments {
+        ZernikeMoments::from_image(&self.to_image(parent), order)
+    }
+}
+
+impl BinaryCluster {
+    /// See `ZernikeMoments`
+    pub fn zernike_moments(&self, order: usize) -> ZernikeMoments {
+        ZernikeMoments::from_image(&self.to_binary_image(), order)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn invariant_to_rotation_and_scale() {
+        let l = BinaryImage::from_string(&[
+            "**----",
+            "**----",
+            "**----",
+            "**----",
+            "******",
+            "******",
+        ].join("\n"));
+        let t = BinaryImage::from_string(&[
+            "******",
+            "******",
+            "--**--",
+            "--**--",
+            "--**--",
+            "--**--",
+        ].join("\n"));
+        // the L turned a quarter, and doubled in size
+        let mut turned = BinaryImage::new_w_h(6, 6);
+        let mut doubled = BinaryImage::new_w_h(12, 12);
+        for y in 0..6 {
+            for x in 0..6 {
+                turned.set_pixel(5 - y, x, l.get_pixel(x, y));
+            }
+        }
+        for y in 0..12 {
+            for x in 0..12 {
+                doubled.set_pixel(x, y, l.get_pixel(x / 2, y / 2));
+            }
+        }
+        let order = 8;
+        let moments = l.zernike_moments(order);
+        assert_eq!(moments.magnitudes.len(), 25);
+        assert!(moments.distance(&turned.zernike_moments(order)) < 1e-6);
+        let scaled = moments.distance(&doubled.zernike_moments(order));
+        let other = moments.distance(&t.zernike_moments(order));
+        assert!(scaled * 3.0 < other, "{} {}", scaled, other);
+        assert!(BinaryImage::new_w_h(2, 2).zernike_moments(order).magnitudes.iter().all(|&m| m == 0.0));
+    }
+}
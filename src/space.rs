@@ -0,0 +1,123 @@
+use std::marker::PhantomData;
+use std::ops::{Add, Sub};
+use crate::{BoundingRect, PointF64, PointI32};
+
+/// Marker of the coordinate space of a `Point`
+pub trait CoordinateSpace: Copy + Default + std::fmt::Debug + PartialEq {}
+
+/// Pixel coordinates of the full image
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ImageSpace;
+
+/// Pixel coordinates relative to the top left of a cluster's (or any crop's) rect
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct ClusterLocalSpace;
+
+/// Fractions of the image width and height, in [0, 1] inside the image
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct NormalizedSpace;
+
+impl CoordinateSpace for ImageSpace {}
+impl CoordinateSpace for ClusterLocalSpace {}
+impl CoordinateSpace for NormalizedSpace {}
+
+/// A point tagged with its coordinate space, so that e.g. cluster local coordinates cannot be mixed up
+/// with image coordinates; converting between spaces is explicit. Opt-in: the plain `PointF64` remains
+/// what the rest of the crate takes.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct Point<S: CoordinateSpace> {
+    pub x: f64,
+    pub y: f64,
+    space: PhantomData<S>,
+}
+
+impl<S: CoordinateSpace> Point<S> {
+    pub fn new(x: f64, y: f64) -> Self {
+        Self { x, y, space: PhantomData }
+    }
+
+    /// Tags `p` as being in space `S`
+    pub fn from_point_f64(p: PointF64) -> Self {
+        Self::new(p.x, p.y)
+    }
+
+    pub fn from_point_i32(p: PointI32) -> Self {
+        Self::new(p.x as f64, p.y as f64)
+    }
+
+    /// The untagged point
+    pub fn to_point_f64(&self) -> PointF64 {
+        PointF64::new(self.x, self.y)
+    }
+
+    pub fn distance_to(&self, other: &Self) -> f64 {
+        (*self - *other).norm()
+    }
+}
+
+/// The offset between two points of the same space
+impl<S: CoordinateSpace> Sub for Point<S> {
+    type Output = PointF64;
+
+    fn sub(self, other: Self) -> PointF64 {
+        PointF64::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+/// Moves a point by an offset, within its space
+impl<S: CoordinateSpace> Add<PointF64> for Point<S> {
+    type Output = Self;
+
+    fn add(self, offset: PointF64) -> Self {
+        Self::new(self.x + offset.x, self.y + offset.y)
+    }
+}
+
+impl Point<ImageSpace> {
+    /// Relative to the top left of `rect`, e.g. a cluster's rect
+    pub fn to_local(&self, rect: &BoundingRect) -> Point<ClusterLocalSpace> {
+        Point::new(self.x - rect.left as f64, self.y - rect.top as f64)
+    }
+
+    /// As fractions of an image of `width` x `height`
+    pub fn to_normalized(&self, width: usize, height: usize) -> Point<NormalizedSpace> {
+        assert!(width > 0 && height > 0);
+        Point::new(self.x / width as f64, self.y / height as f64)
+    }
+}
+
+impl Point<ClusterLocalSpace> {
+    /// Back to image coordinates, `rect` being the one `to_local` was relative to
+    pub fn to_image(&self, rect: &BoundingRect) -> Point<ImageSpace> {
+        Point::new(self.x + rect.left as f64, self.y + rect.top as f64)
+    }
+}
+
+impl Point<NormalizedSpace> {
+    /// In pixels of an image of `width` x `height`
+    pub fn to_image(&self, width: usize, height: usize) -> Point<ImageSpace> {
+        Point::new(self.x * width as f64, self.y * height as f64)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conversions_round_trip() {
+        let rect = BoundingRect::new_x_y_w_h(10, 20, 5, 5);
+        let p = Point::<ImageSpace>::from_point_i32(PointI32::new(12, 23));
+        let local = p.to_local(&rect);
+        assert_eq!(local.to_point_f64(), PointF64::new(2.0, 3.0));
+        assert_eq!(local.to_image(&rect), p);
+
+        let normalized = p.to_normalized(24, 46);
+        assert_eq!(normalized.to_point_f64(), PointF64::new(0.5, 0.5));
+        assert_eq!(normalized.to_image(24, 46), p);
+
+        assert_eq!(p - Point::new(2.0, 3.0), PointF64::new(10.0, 20.0));
+        assert_eq!((local + PointF64::new(1.0, 1.0)).to_point_f64(), PointF64::new(3.0, 4.0));
+        assert_eq!(local.distance_to(&Point::new(5.0, 7.0)), 5.0);
+    }
+}
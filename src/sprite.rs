@@ -0,0 +1,209 @@
+use crate::{BinaryImage, BoundingRect, Color, ColorImage, Components, Image};
+use crate::bound::merge_expand;
+
+/// Parameters of `split_sprite_sheet`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SpriteConfig {
+    /// on a sheet with transparency, pixels of an alpha at or below this are background
+    pub alpha_threshold: u8,
+    /// on an opaque sheet, pixels within this (sum of absolute channel differences) of the top left pixel are background
+    pub background_tolerance: i32,
+    /// without a grid, components this near (in pixels, along both axes) are one sprite, e.g. a head apart from its body
+    pub group_distance: i32,
+    /// foreground components of fewer pixels than this are specks, and ignored
+    pub min_area: usize,
+}
+
+impl Default for SpriteConfig {
+    fn default() -> Self {
+        Self {
+            alpha_threshold: 0,
+            background_tolerance: 24,
+            group_distance: 2,
+            min_area: 4,
+        }
+    }
+}
+
+/// Uniformly spaced cells dividing a sprite sheet, starting at the top left
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct SpriteGrid {
+    pub columns: usize,
+    pub rows: usize,
+    pub cell_width: usize,
+    pub cell_height: usize,
+}
+
+impl SpriteGrid {
+    pub fn cell_rect(&self, column: usize, row: usize) -> BoundingRect {
+        BoundingRect::new_x_y_w_h(
+            (column * self.cell_width) as i32, (row * self.cell_height) as i32,
+            self.cell_width as i32, self.cell_height as i32,
+        )
+    }
+}
+
+/// A sprite found by `split_sprite_sheet`
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Sprite {
+    /// the bounds of the foreground of the sprite
+    pub rect: BoundingRect,
+    /// (column, row) of its cell, if the sheet is a grid
+    pub cell: Option<(usize, usize)>,
+}
+
+impl Sprite {
+    /// The sprite cut out of `sheet`, e.g. to be clustered by itself
+    pub fn to_image(&self, sheet: &ColorImage) -> ColorImage {
+        sheet.view(self.rect).to_image()
+    }
+}
+
+pub struct SpriteSheet {
+    /// None if the sprites are not uniformly spaced, and were found as groups of components instead
+    pub grid: Option<SpriteGrid>,
+    /// in reading order
+    pub sprites: Vec<Sprite>,
+}
+
+/// Finds the sprites of a sheet. The background is the transparent pixels, or on an opaque sheet
+/// those of about the color of the top left pixel. If the foreground falls into uniform cells
+/// (each cell holding some and none straddling two), there is a sprite per occupied cell;
+/// otherwise the foreground components are grouped by proximity, a sprite per group.
+pub fn split_sprite_sheet(image: &ColorImage, config: &SpriteConfig) -> SpriteSheet {
+    let foreground = sprite_foreground(image, config);
+    let components = Components::new(&foreground, true);
+    let mask = components.filter().min_area(config.min_area).to_binary_image();
+
+    let occupied_columns: Vec<bool> = (0..mask.width).map(|x| (0..mask.height).any(|y| mask.get_pixel(x, y))).collect();
+    let occupied_rows: Vec<bool> = (0..mask.height).map(|y| (0..mask.width).any(|x| mask.get_pixel(x, y))).collect();
+    let columns = grid_divisions(&occupied_columns);
+    let rows = grid_divisions(&occupied_rows);
+
+    if columns * rows > 1 {
+        let grid = SpriteGrid {
+            columns, rows,
+            cell_width: mask.width / columns,
+            cell_height: mask.height / rows,
+        };
+        let mut sprites = Vec::new();
+        for row in 0..rows {
+            for column in 0..columns {
+                let mut rect = BoundingRect::default();
+                for (y, left, right) in grid.cell_rect(column, row).scanlines() {
+                    for x in left..right {
+                        if mask.get_pixel(x as usize, y as usize) {
+                            rect.add_x_y(x, y);
+                        }
+                    }
+                }
+                if !rect.is_empty() {
+                    sprites.push(Sprite { rect, cell: Some((column, row)) });
+                }
+            }
+        }
+        return SpriteSheet { grid: Some(grid), sprites };
+    }
+
+    let rects: Vec<BoundingRect> = components.clusters.clusters.iter()
+        .filter(|c| c.points.len() >= config.min_area)
+        .map(|c| c.rect)
+        .collect();
+    let d = config.group_distance;
+    let mut sprites: Vec<Sprite> = merge_expand(rects, (d + 1) / 2, (d + 1) / 2)
+        .into_iter()
+        .map(|group| {
+            let mut rect = BoundingRect::default();
+            group.into_iter().for_each(|r| rect.merge(r));
+            Sprite { rect, cell: None }
+        })
+        .collect();
+    sprites.sort_by_key(|s| (s.rect.top, s.rect.left));
+    SpriteSheet { grid: None, sprites }
+}
+
+fn sprite_foreground(image: &ColorImage, config: &SpriteConfig) -> BinaryImage {
+    let mut foreground = BinaryImage::new_w_h(image.width, image.height);
+    if image.width == 0 || image.height == 0 {
+        return foreground;
+    }
+    let transparent = (0..image.height).any(|y| (0..image.width).any(|x| image.get_pixel(x, y).a < 255));
+    let corner = image.get_pixel(0, 0);
+    let differs = |c: Color| {
+        (c.r as i32 - corner.r as i32).abs() + (c.g as i32 - corner.g as i32).abs() + (c.b as i32 - corner.b as i32).abs() >
+            config.background_tolerance
+    };
+    for y in 0..image.height {
+        for x in 0..image.width {
+            let c = image.get_pixel(x, y);
+            foreground.set_pixel(x, y, if transparent { c.a > config.alpha_threshold } else { differs(c) });
+        }
+    }
+    foreground
+}
+
+/// The most cells, dividing `occupied` evenly, such that every cell is occupied and no run of
+/// occupied entries straddles two; 1 if there are none
+#[allow(clippy::manual_is_multiple_of)] // `is_multiple_of` needs Rust 1.87
+fn grid_divisions(occupied: &[bool]) -> usize {
+    let len = occupied.len();
+    let mut runs = Vec::new();
+    let mut start = None;
+    for (i, &o) in occupied.iter().chain(std::iter::once(&false)).enumerate() {
+        match (o, start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                runs.push((s, i));
+                start = None;
+            },
+            _ => {},
+        }
+    }
+    (2..=runs.len().max(1))
+        .rev()
+        .filter(|&cells| len % cells == 0)
+        .find(|&cells| {
+            let pitch = len / cells;
+            let mut filled = vec![false; cells];
+            runs.iter().all(|&(s, e)| {
+                filled[s / pitch] = true;
+                s / pitch == (e - 1) / pitch
+            }) && filled.iter().all(|&f| f)
+        })
+        .unwrap_or(1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_grid_and_loose_sprites() {
+        // a 3 x 2 grid of 16 pixel cells, the last one empty, with sprites of various sizes
+        let mut sheet = ColorImage::new_w_h(48, 32);
+        let red = Color::new(200, 0, 0);
+        for (i, size) in [10, 6, 12, 8, 4].into_iter().enumerate() {
+            let (cx, cy) = ((i % 3) * 16 + 2, (i / 3) * 16 + 3);
+            sheet.fill_rect(&BoundingRect::new_x_y_w_h(cx as i32, cy as i32, size, size), &red);
+        }
+        let split = split_sprite_sheet(&sheet, &SpriteConfig::default());
+        assert_eq!(split.grid, Some(SpriteGrid { columns: 3, rows: 2, cell_width: 16, cell_height: 16 }));
+        assert_eq!(split.sprites.len(), 5);
+        assert_eq!(split.sprites[4], Sprite { rect: BoundingRect::new_x_y_w_h(18, 19, 4, 4), cell: Some((1, 1)) });
+        assert_eq!(split.sprites[2].to_image(&sheet).width, 12);
+
+        // loose sprites on an opaque background, one of two parts
+        let mut sheet = ColorImage::new_w_h(40, 20);
+        sheet.fill_rect(&BoundingRect::new_x_y_w_h(0, 0, 40, 20), &Color::new(255, 0, 255));
+        sheet.fill_rect(&BoundingRect::new_x_y_w_h(2, 2, 20, 4), &red);
+        sheet.fill_rect(&BoundingRect::new_x_y_w_h(2, 7, 20, 10), &red);
+        sheet.fill_rect(&BoundingRect::new_x_y_w_h(30, 12, 6, 6), &red);
+        sheet.set_pixel(26, 2, &red);
+        let split = split_sprite_sheet(&sheet, &SpriteConfig::default());
+        assert_eq!(split.grid, None);
+        assert_eq!(split.sprites.iter().map(|s| s.rect).collect::<Vec<_>>(), [
+            BoundingRect::new_x_y_w_h(2, 2, 20, 15),
+            BoundingRect::new_x_y_w_h(30, 12, 6, 6),
+        ]);
+    }
+}
@@ -0,0 +1,155 @@
+use crate::{BinaryImage, Color, ColorImage, PerspectiveTransform, PlanarImage, PointF64, collapse_pyramid,
+    distance_to_mask, gaussian_pyramid, laplacian_pyramid};
+
+impl ColorImage {
+    /// The image warped by `transform` onto a `width` x `height` canvas, each pixel sampled bilinearly at
+    /// `transform_inverse` of its own position; pixels mapping outside the image are transparent
+    pub fn warp_perspective(&self, transform: &PerspectiveTransform, width: usize, height: usize) -> ColorImage {
+        let mut warped = ColorImage::new_w_h(width, height);
+        let (w, h) = (self.width as i64, self.height as i64);
+        let premultiplied = |x: i64, y: i64| {
+            if x < 0 || y < 0 || x >= w || y >= h {
+                return [0.0; 4];
+            }
+            let c = self.get_pixel(x as usize, y as usize);
+            let a = c.a as f64 / 255.0;
+            [c.r as f64 * a, c.g as f64 * a, c.b as f64 * a, c.a as f64]
+        };
+        for y in 0..height {
+            for x in 0..width {
+                let p = transform.transform_inverse(PointF64::new(x as f64, y as f64));
+                if !(p.x > -1.0 && p.y > -1.0 && p.x < w as f64 && p.y < h as f64) {
+                    continue;
+                }
+                let (x0, y0) = (p.x.floor(), p.y.floor());
+                let (tx, ty) = (p.x - x0, p.y - y0);
+                let (x0, y0) = (x0 as i64, y0 as i64);
+                let mut sum = [0.0; 4];
+                for (dx, dy, weight) in [(0, 0, (1.0 - tx) * (1.0 - ty)), (1, 0, tx * (1.0 - ty)), (0, 1, (1.0 - tx) * ty), (1, 1, tx * ty)] {
+                    let v = premultiplied(x0 + dx, y0 + dy);
+                    sum.iter_mut().zip(v).for_each(|(s, v)| *s += v * weight);
+                }
+                warped.set_pixel(x, y, &unpremultiply([sum[0], sum[1], sum[2], sum[3]].map(|v| v as f32)));
+            }
+        }
+        warped
+    }
+}
+
+/// A seam between two overlapping images of the same size (e.g. each warped onto the canvas of a panorama),
+/// set where a pixel should come from `a`: where only `a` is opaque, or both are and it is deeper inside `a`
+pub fn stitch_seam_mask(a: &ColorImage, b: &ColorImage) -> BinaryImage {
+    assert!(a.width == b.width && a.height == b.height);
+    let transparent = |image: &ColorImage| {
+        let mut mask = BinaryImage::new_w_h(image.width, image.height);
+        for (i, c) in image.iter().enumerate() {
+            mask.pixels.set(i, c.a == 0);
+        }
+        mask
+    };
+    let (outside_a, outside_b) = (transparent(a), transparent(b));
+    let spread = (a.width + a.height) as f64;
+    let (depth_a, depth_b) = (distance_to_mask(&outside_a, spread), distance_to_mask(&outside_b, spread));
+    let mut mask = BinaryImage::new_w_h(a.width, a.height);
+    for i in 0..a.width * a.height {
+        let from_a = !outside_a.pixels[i] && (outside_b.pixels[i] || depth_a.channel(0)[i] >= depth_b.channel(0)[i]);
+        mask.pixels.set(i, from_a);
+    }
+    mask
+}
+
+/// Multi-band blending (Burt & Adelson) of two images of the same size, `a` where `mask` is set and `b` elsewhere:
+/// each band of detail of their Laplacian pyramids is blended across a smoothing of the mask as wide as its scale,
+/// so that nothing but the finest detail changes abruptly at the seam, and no ghosting spreads from it.
+/// Colors are blended premultiplied by alpha, so transparent pixels (outside a warp) do not bleed in;
+/// alpha is that of the image each pixel is taken from.
+/// `levels` (at least 1) should be about the log2 of the width of the overlap.
+pub fn multi_band_blend(a: &ColorImage, b: &ColorImage, mask: &BinaryImage, levels: usize) -> ColorImage {
+    assert!(a.width == b.width && a.height == b.height && mask.width == a.width && mask.height == a.height);
+    let premultiply = |image: &ColorImage| {
+        PlanarImage::from_color_image(image).map(|[r, g, b, a]| {
+            let alpha = a as f32 / 255.0;
+            [r as f32 * alpha, g as f32 * alpha, b as f32 * alpha, a as f32]
+        })
+    };
+    let (bands_a, bands_b) = (laplacian_pyramid(&premultiply(a), levels), laplacian_pyramid(&premultiply(b), levels));
+    let weight = PlanarImage::from_planes(mask.width, mask.height, [mask.pixels.iter().map(|v| v as u8 as f32).collect()]).unwrap();
+    let weights = gaussian_pyramid(&weight, levels);
+    let blended: Vec<PlanarImage<f32, 4>> = bands_a.iter().zip(bands_b.iter()).zip(weights.iter())
+        .map(|((band_a, band_b), weight)| {
+            let mut band = band_a.clone();
+            for c in 0..4 {
+                let (from_b, w) = (band_b.channel(c), weight.channel(0));
+                for (i, v) in band.channel_mut(c).iter_mut().enumerate() {
+                    *v = *v * w[i] + from_b[i] * (1.0 - w[i]);
+                }
+            }
+            band
+        })
+        .collect();
+    let blended = collapse_pyramid(&blended);
+    let mut image = ColorImage::new_w_h(a.width, a.height);
+    for y in 0..a.height {
+        for x in 0..a.width {
+            let c = unpremultiply(blended.get_pixel(x, y));
+            // the coverage is that of the image taken, not blended, so the seam leaves no translucent trail
+            let alpha = if mask.get_pixel(x, y) { a.get_pixel(x, y).a } else { b.get_pixel(x, y).a };
+            image.set_pixel(x, y, &Color::new_rgba(c.r, c.g, c.b, alpha));
+        }
+    }
+    image
+}
+
+/// A color from channels premultiplied by alpha, alpha in [0, 255]
+fn unpremultiply([r, g, b, a]: [f32; 4]) -> Color {
+    let alpha = a.clamp(0.0, 255.0);
+    if alpha < 0.5 {
+        return Color::new_rgba(0, 0, 0, 0);
+    }
+    let channel = |v: f32| (v * 255.0 / alpha).round().clamp(0.0, 255.0) as u8;
+    Color::new_rgba(channel(r), channel(g), channel(b), alpha.round() as u8)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stitch_two_warps() {
+        // two overlapping views of a ramp, the second darker, moved onto one canvas
+        let view = |left: usize, gain: f64| {
+            let mut image = ColorImage::new_w_h(40, 20);
+            for y in 0..20 {
+                for x in 0..40 {
+                    let v = ((100.0 + (left + x) as f64) * gain) as u8;
+                    image.set_pixel(x, y, &Color::new(v, v, v));
+                }
+            }
+            image
+        };
+        let identity = PerspectiveTransform::from_point_f64(
+            &[PointF64::new(0.0, 0.0), PointF64::new(1.0, 0.0), PointF64::new(1.0, 1.0), PointF64::new(0.0, 1.0)],
+            &[PointF64::new(0.0, 0.0), PointF64::new(1.0, 0.0), PointF64::new(1.0, 1.0), PointF64::new(0.0, 1.0)],
+        );
+        let shift = PerspectiveTransform::from_point_f64(
+            &[PointF64::new(0.0, 0.0), PointF64::new(1.0, 0.0), PointF64::new(1.0, 1.0), PointF64::new(0.0, 1.0)],
+            &[PointF64::new(24.0, 0.0), PointF64::new(25.0, 0.0), PointF64::new(25.0, 1.0), PointF64::new(24.0, 1.0)],
+        );
+        let a = view(0, 1.0).warp_perspective(&identity, 64, 20);
+        let b = view(24, 0.8).warp_perspective(&shift, 64, 20);
+        assert_eq!(a.get_pixel(50, 5).a, 0);
+        assert_eq!(b.get_pixel(30, 5), view(24, 0.8).get_pixel(6, 5));
+
+        let mask = stitch_seam_mask(&a, &b);
+        assert!(mask.get_pixel(10, 5) && !mask.get_pixel(50, 5));
+        let hard = multi_band_blend(&a, &b, &mask, 1);
+        let smooth = multi_band_blend(&a, &b, &mask, 4);
+        // the whole canvas is covered, and away from the seam each view is kept
+        assert!(smooth.iter().all(|c| c.a == 255));
+        assert_eq!(smooth.get_pixel(2, 10), a.get_pixel(2, 10));
+        assert_eq!(smooth.get_pixel(62, 10), b.get_pixel(62, 10));
+        // the step in brightness at the seam is spread out
+        let step = |image: &ColorImage| (28..36).map(|x| (image.get_pixel(x + 1, 10).r as i32 - image.get_pixel(x, 10).r as i32).abs()).max().unwrap();
+        assert!(step(&smooth) < step(&hard), "{} {}", step(&smooth), step(&hard));
+    }
+}
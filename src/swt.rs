@@ -0,0 +1,285 @@
+use crate::{BoundingRect, ColorImage, PlanarImage, PointF64};
+use crate::disjoint_sets::group_by_cached_key;
+
+/// Parameters of `stroke_width_transform` and `detect_text_regions`
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct SwtConfig {
+    /// true for dark text on light background, false for light text on dark background
+    pub dark_on_light: bool,
+    /// minimum Sobel gradient magnitude of an edge pixel
+    pub edge_threshold: f32,
+    /// strokes wider than this (in pixels) are ignored
+    pub max_stroke_width: f32,
+    /// range of letter heights, in pixels
+    pub min_height: i32,
+    pub max_height: i32,
+    /// maximum standard deviation of stroke width within a letter, relative to the mean
+    pub max_stroke_deviation: f32,
+    /// minimum number of letters in a text region
+    pub min_letters: usize,
+}
+
+impl Default for SwtConfig {
+    fn default() -> Self {
+        Self {
+            dark_on_light: true,
+            edge_threshold: 128.0,
+            max_stroke_width: 16.0,
+            min_height: 6,
+            max_height: 300,
+            max_stroke_deviation: 0.5,
+            min_letters: 2,
+        }
+    }
+}
+
+/// A group of letter candidates on a line
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct TextRegion {
+    pub rect: BoundingRect,
+    /// the bounding rect of each letter, from left to right
+    pub letters: Vec<BoundingRect>,
+    /// median stroke width of the letters
+    pub stroke_width: f32,
+}
+
+/// Stroke width transform (Epshtein et al.): the width of the stroke each pixel lies on, 0 if none.
+///
+/// From each edge pixel, a ray is cast across the stroke along the gradient, until it meets
+/// an edge pixel with an opposite gradient. Pixels on the ray take the smaller of the ray length and
+/// the median of the ray, so corners do not inflate the widths.
+pub fn stroke_width_transform(image: &ColorImage, config: &SwtConfig) -> PlanarImage<f32, 1> {
+    let (width, height) = (image.width, image.height);
+    let gradient = image.sobel();
+    let edges = edge_pixels(&gradient, config.edge_threshold);
+    let sign = if config.dark_on_light { -1.0 } else { 1.0 };
+    let mut swt = PlanarImage::new_w_h_with_value(width, height, f32::INFINITY);
+    let normal_at = |x: usize, y: usize| {
+        let [gx, gy] = gradient.get_pixel(x, y);
+        PointF64::new(gx as f64, gy as f64).get_normalized()
+    };
+
+    let mut rays = Vec::new();
+    for y in 0..height {
+        for x in 0..width {
+            if !edges[y * width + x] {
+                continue;
+            }
+            let normal = normal_at(x, y);
+            let direction = normal * sign;
+            let start = PointF64::new(x as f64 + 0.5, y as f64 + 0.5);
+            let mut ray = vec![(x, y)];
+            let mut t = 0.0;
+            loop {
+                t += 0.5;
+                if t > config.max_stroke_width as f64 {
+                    break;
+                }
+                let p = start + direction * t;
+                if p.x < 0.0 || p.y < 0.0 || p.x >= width as f64 || p.y >= height as f64 {
+                    break;
+                }
+                let (px, py) = (p.x as usize, p.y as usize);
+                if ray.last() != Some(&(px, py)) {
+                    ray.push((px, py));
+                }
+                if (px, py) == (x, y) || !edges[py * width + px] {
+                    continue;
+                }
+                let other = normal_at(px, py);
+                // the opposite side of a stroke has a roughly opposite gradient
+                if normal.x * other.x + normal.y * other.y < -(std::f64::consts::PI / 6.0).cos() {
+                    let (dx, dy) = (px as f64 - x as f64, py as f64 - y as f64);
+                    let stroke = (dx * dx + dy * dy).sqrt() as f32 + 1.0;
+                    for &(rx, ry) in ray.iter() {
+                        let v = swt.get(rx, ry, 0);
+                        swt.set(rx, ry, 0, v.min(stroke));
+                    }
+                    rays.push(ray);
+                }
+                break;
+            }
+        }
+    }
+
+    for ray in rays.iter() {
+        let mut values: Vec<f32> = ray.iter().map(|&(x, y)| swt.get(x, y, 0)).collect();
+        values.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        let median = values[values.len() / 2];
+        for &(x, y) in ray.iter() {
+            let v = swt.get(x, y, 0);
+            swt.set(x, y, 0, v.min(median));
+        }
+    }
+
+    for v in swt.channel_mut(0).iter_mut() {
+        if *v == f32::INFINITY {
+            *v = 0.0;
+        }
+    }
+    swt
+}
+
+/// Candidate text regions: connected components of consistent stroke width that look like letters,
+/// grouped into lines of letters of similar height and stroke width. Regions are sorted top to bottom.
+pub fn detect_text_regions(image: &ColorImage, config: &SwtConfig) -> Vec<TextRegion> {
+    let swt = stroke_width_transform(image, config);
+    let letters: Vec<(BoundingRect, f32)> = stroke_components(&swt)
+        .into_iter()
+        .filter_map(|(rect, mut widths)| {
+            let (w, h) = (rect.width(), rect.height());
+            if h < config.min_height || h > config.max_height {
+                return None;
+            }
+            let aspect = w as f64 / h as f64;
+            if !(0.1..=10.0).contains(&aspect) {
+                return None;
+            }
+            let mean = widths.iter().sum::<f32>() / widths.len() as f32;
+            let variance = widths.iter().map(|v| (v - mean) * (v - mean)).sum::<f32>() / widths.len() as f32;
+            if variance.sqrt() > config.max_stroke_deviation * mean {
+                return None;
+            }
+            widths.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            Some((rect, widths[widths.len() / 2]))
+        })
+        .collect();
+
+    let lines = group_by_cached_key(letters, |&letter| letter, |&(a, sa), &(b, sb)| {
+        let (ha, hb) = (a.height() as f64, b.height() as f64);
+        let gap = std::cmp::max(a.left, b.left) - std::cmp::min(a.right, b.right);
+        ha.max(hb) / ha.min(hb) < 2.0 &&
+            sa.max(sb) / sa.min(sb) < 2.0 &&
+            ((a.top + a.bottom) - (b.top + b.bottom)).abs() as f64 <= ha.min(hb) &&
+            (gap as f64) < 3.0 * ha.max(hb)
+    });
+
+    let mut regions: Vec<TextRegion> = lines
+        .into_iter()
+        .filter(|line| line.len() >= config.min_letters)
+        .map(|mut line| {
+            line.sort_by_key(|(rect, _)| rect.left);
+            let mut rect = line[0].0;
+            for (r, _) in line.iter() {
+                rect.merge(*r);
+            }
+            let mut widths: Vec<f32> = line.iter().map(|&(_, w)| w).collect();
+            widths.sort_by(|a, b| a.partial_cmp(b).unwrap());
+            TextRegion {
+                rect,
+                stroke_width: widths[widths.len() / 2],
+                letters: line.into_iter().map(|(r, _)| r).collect(),
+            }
+        })
+        .collect();
+    regions.sort_by_key(|r| (r.rect.top, r.rect.left));
+    regions
+}
+
+/// Sobel magnitude above `threshold` and a local maximum across the edge
+fn edge_pixels(gradient: &PlanarImage<f32, 2>, threshold: f32) -> Vec<bool> {
+    let (width, height) = (gradient.width(), gradient.height());
+    let magnitude = |x: i32, y: i32| {
+        if x < 0 || y < 0 || x >= width as i32 || y >= height as i32 {
+            return 0.0;
+        }
+        let [gx, gy] = gradient.get_pixel(x as usize, y as usize);
+        (gx * gx + gy * gy).sqrt()
+    };
+    let mut edges = vec![false; width * height];
+    for y in 0..height as i32 {
+        for x in 0..width as i32 {
+            let m = magnitude(x, y);
+            if m < threshold {
+                continue;
+            }
+            let [gx, gy] = gradient.get_pixel(x as usize, y as usize);
+            // quantize the gradient direction to one of the 8 neighbours
+            let (dx, dy) = (
+                if gx.abs() * 2.4142 < gy.abs() { 0 } else { gx.signum() as i32 },
+                if gy.abs() * 2.4142 < gx.abs() { 0 } else { gy.signum() as i32 },
+            );
+            // ties are broken towards the positive side, so a symmetric ramp still yields an edge
+            if m >= magnitude(x - dx, y - dy) && m > magnitude(x + dx, y + dy) {
+                edges[y as usize * width + x as usize] = true;
+            }
+        }
+    }
+    edges
+}
+
+/// 8-connected components of stroke pixels, where neighbours differ in width by less than a factor of 3.
+/// Returns the bounding rect and stroke widths of each component.
+fn stroke_components(swt: &PlanarImage<f32, 1>) -> Vec<(BoundingRect, Vec<f32>)> {
+    let (width, height) = (swt.width(), swt.height());
+    let mut visited = vec![false; width * height];
+    let mut components = Vec::new();
+    let mut stack = Vec::new();
+    for start in 0..width * height {
+        if visited[start] || swt.channel(0)[start] == 0.0 {
+            continue;
+        }
+        visited[start] = true;
+        stack.push(start);
+        let mut rect = BoundingRect::default();
+        let mut widths = Vec::new();
+        while let Some(i) = stack.pop() {
+            let (x, y) = ((i % width) as i32, (i / width) as i32);
+            let v = swt.channel(0)[i];
+            rect.add_x_y(x, y);
+            widths.push(v);
+            for (dx, dy) in [(-1, -1), (0, -1), (1, -1), (-1, 0), (1, 0), (-1, 1), (0, 1), (1, 1)] {
+                let (nx, ny) = (x + dx, y + dy);
+                if nx < 0 || ny < 0 || nx >= width as i32 || ny >= height as i32 {
+                    continue;
+                }
+                let n = ny as usize * width + nx as usize;
+                let w = swt.channel(0)[n];
+                if !visited[n] && w > 0.0 && w.max(v) / w.min(v) < 3.0 {
+                    visited[n] = true;
+                    stack.push(n);
+                }
+            }
+        }
+        components.push((rect, widths));
+    }
+    components
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Color;
+
+    fn fill(image: &mut ColorImage, rect: BoundingRect, color: Color) {
+        for y in rect.top..rect.bottom {
+            for x in rect.left..rect.right {
+                image.set_pixel(x as usize, y as usize, &color);
+            }
+        }
+    }
+
+    #[test]
+    fn text_regions_of_bars() {
+        let mut image = ColorImage::new_w_h(80, 40);
+        fill(&mut image, BoundingRect::new_x_y_w_h(0, 0, 80, 40), Color::new(255, 255, 255));
+        // three letter-like bars in a row
+        for i in 0..3 {
+            fill(&mut image, BoundingRect::new_x_y_w_h(6 + i * 8, 6, 3, 12), Color::new(0, 0, 0));
+        }
+        // a blob too thick to be a stroke
+        fill(&mut image, BoundingRect::new_x_y_w_h(44, 4, 30, 30), Color::new(0, 0, 0));
+
+        let config = SwtConfig::default();
+        let swt = stroke_width_transform(&image, &config);
+        let bar = swt.get(7, 12, 0);
+        assert!((2.0..=5.0).contains(&bar), "{}", bar);
+        assert_eq!(swt.get(59, 19, 0), 0.0);
+
+        let regions = detect_text_regions(&image, &config);
+        assert_eq!(regions.len(), 1);
+        assert_eq!(regions[0].letters.len(), 3);
+        assert!(regions[0].rect.left <= 6 && regions[0].rect.right >= 25);
+        assert!(regions[0].rect.right < 44);
+    }
+}
@@ -0,0 +1,222 @@
+//! Generators of synthetic images with known ground truth, for property-based tests of clustering and tracing.
+//! All randomness comes from a `seed`, so the same parameters always give the same image.
+//!
+//! ```
+//! use visioncortex::synth::random_polygon;
+//! let polygon = random_polygon(64, 64, 7, 42);
+//! assert_eq!(polygon.outline.len(), 7);
+//! assert!(polygon.mask.width == 64 && polygon.mask.height == 64);
+//! ```
+
+use crate::{BinaryImage, Color, ColorImage, FillRule, PathF64, PlanarImage, PointF64, distance_to_segment};
+
+/// A small deterministic pseudo random generator (xorshift64*)
+#[derive(Clone, Debug)]
+pub struct SynthRng {
+    state: u64,
+}
+
+impl SynthRng {
+    pub fn new(seed: u64) -> Self {
+        // the state must not be zero
+        Self { state: seed.wrapping_mul(0x9E37_79B9_7F4A_7C15) | 1 }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_F491_4F6C_DD1D)
+    }
+
+    /// Uniform in [0, 1)
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniform in [low, high)
+    pub fn range(&mut self, low: f64, high: f64) -> f64 {
+        low + (high - low) * self.next_f64()
+    }
+}
+
+/// Squares of `cell` pixels alternating between `a` (at the top left) and `b`
+pub fn checkerboard(width: usize, height: usize, cell: usize, a: Color, b: Color) -> ColorImage {
+    assert!(cell > 0);
+    let mut image = ColorImage::new_w_h(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            image.set_pixel(x, y, if (x / cell + y / cell) & 1 == 0 { &a } else { &b });
+        }
+    }
+    image
+}
+
+/// Linear gradient from `from` (left or top) to `to` (right or bottom)
+pub fn gradient(width: usize, height: usize, from: Color, to: Color, horizontal: bool) -> ColorImage {
+    let mut image = ColorImage::new_w_h(width, height);
+    let steps = if horizontal { width } else { height }.saturating_sub(1).max(1) as f64;
+    let mix = |a: u8, b: u8, t: f64| (a as f64 + (b as f64 - a as f64) * t).round() as u8;
+    for y in 0..height {
+        for x in 0..width {
+            let t = if horizontal { x } else { y } as f64 / steps;
+            image.set_pixel(x, y, &Color::new_rgba(
+                mix(from.r, to.r, t), mix(from.g, to.g, t), mix(from.b, to.b, t), mix(from.a, to.a, t),
+            ));
+        }
+    }
+    image
+}
+
+/// A polygon, and its rasterization
+pub struct SynthPolygon {
+    /// the vertices, implicitly closed, clockwise (in image coordinates)
+    pub outline: PathF64,
+    /// pixels whose centers are inside the outline
+    pub mask: BinaryImage,
+}
+
+impl SynthPolygon {
+    /// The mask as `foreground` on `background`
+    pub fn to_color_image(&self, foreground: Color, background: Color) -> ColorImage {
+        let mut image = ColorImage::new_w_h(self.mask.width, self.mask.height);
+        for y in 0..self.mask.height {
+            for x in 0..self.mask.width {
+                image.set_pixel(x, y, if self.mask.get_pixel(x, y) { &foreground } else { &background });
+            }
+        }
+        image
+    }
+}
+
+/// A random simple (star shaped) polygon of `vertices` within the image, about its center
+pub fn random_polygon(width: usize, height: usize, vertices: usize, seed: u64) -> SynthPolygon {
+    assert!(vertices >= 3);
+    let mut rng = SynthRng::new(seed);
+    let center = PointF64::new(width as f64 / 2.0, height as f64 / 2.0);
+    let max_radius = width.min(height) as f64 / 2.0 - 1.0;
+    // angles increasing in even sectors, so that the polygon does not self intersect
+    let sector = std::f64::consts::TAU / vertices as f64;
+    let outline = PathF64::from_points((0..vertices)
+        .map(|i| {
+            let angle = sector * (i as f64 + rng.range(0.1, 0.9));
+            let radius = max_radius * rng.range(0.3, 1.0);
+            center + PointF64::new(angle.cos(), angle.sin()) * radius
+        })
+        .collect());
+    let mut mask = BinaryImage::new_w_h(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            let p = PointF64::new(x as f64 + 0.5, y as f64 + 0.5);
+            mask.set_pixel(x, y, outline.contains_point(p, FillRule::NonZero));
+        }
+    }
+    SynthPolygon { outline, mask }
+}
+
+/// Text-like strokes: rows of glyphs, each a few random horizontal, vertical or diagonal strokes
+/// `stroke_width` wide within a cell of `glyph_size` pixels
+pub fn strokes(width: usize, height: usize, glyph_size: usize, stroke_width: f64, seed: u64) -> BinaryImage {
+    assert!(glyph_size >= 4);
+    let mut rng = SynthRng::new(seed);
+    let mut image = BinaryImage::new_w_h(width, height);
+    let pitch = glyph_size + glyph_size / 2;
+    let inner = (glyph_size - 1) as f64;
+    for top in (glyph_size / 4..height.saturating_sub(glyph_size)).step_by(pitch) {
+        for left in (glyph_size / 4..width.saturating_sub(glyph_size)).step_by(pitch) {
+            let origin = PointF64::new(left as f64, top as f64);
+            for _ in 0..2 + rng.next_u64() % 3 {
+                // ends snapped to the corners, edge midpoints and center of the cell
+                let mut end = || {
+                    let (i, j) = (rng.next_u64() % 3, rng.next_u64() % 3);
+                    origin + PointF64::new(i as f64 * inner / 2.0, j as f64 * inner / 2.0)
+                };
+                let (a, b) = (end(), end());
+                stamp_segment(&mut image, a, b, stroke_width);
+            }
+        }
+    }
+    image
+}
+
+/// Sets the pixels whose centers are within `stroke_width / 2` of the segment between pixel centers `a` and `b`
+fn stamp_segment(image: &mut BinaryImage, a: PointF64, b: PointF64, stroke_width: f64) {
+    let half = (stroke_width / 2.0).max(0.5);
+    let left = (a.x.min(b.x) - half).floor().max(0.0) as usize;
+    let top = (a.y.min(b.y) - half).floor().max(0.0) as usize;
+    let right = ((a.x.max(b.x) + half).ceil().max(0.0) as usize + 1).min(image.width);
+    let bottom = ((a.y.max(b.y) + half).ceil().max(0.0) as usize + 1).min(image.height);
+    for y in top..bottom {
+        for x in left..right {
+            if distance_to_segment(PointF64::new(x as f64, y as f64), a, b) <= half {
+                image.set_pixel(x, y, true);
+            }
+        }
+    }
+}
+
+/// Smooth value noise in [0, 1]: random values on a grid `scale` pixels apart, bilinearly interpolated
+pub fn noise_field(width: usize, height: usize, scale: usize, seed: u64) -> PlanarImage<f32, 1> {
+    assert!(scale > 0);
+    let mut rng = SynthRng::new(seed);
+    let (gw, gh) = (width / scale + 2, height / scale + 2);
+    let grid: Vec<f64> = (0..gw * gh).map(|_| rng.next_f64()).collect();
+    let mut field = PlanarImage::new_w_h(width, height);
+    for y in 0..height {
+        for x in 0..width {
+            let (gx, gy) = (x / scale, y / scale);
+            let (tx, ty) = ((x % scale) as f64 / scale as f64, (y % scale) as f64 / scale as f64);
+            let at = |i: usize, j: usize| grid[j * gw + i];
+            let top = at(gx, gy) * (1.0 - tx) + at(gx + 1, gy) * tx;
+            let bottom = at(gx, gy + 1) * (1.0 - tx) + at(gx + 1, gy + 1) * tx;
+            field.set(x, y, 0, (top * (1.0 - ty) + bottom * ty) as f32);
+        }
+    }
+    field
+}
+
+/// Adds independent uniform noise of up to `amplitude` to each color channel of each pixel
+pub fn add_noise(image: &mut ColorImage, amplitude: u8, seed: u64) {
+    let mut rng = SynthRng::new(seed);
+    let amplitude = amplitude as f64;
+    for y in 0..image.height {
+        for x in 0..image.width {
+            let c = image.get_pixel(x, y);
+            let mut jitter = |v: u8| (v as f64 + rng.range(-amplitude, amplitude + 1.0).floor()).clamp(0.0, 255.0) as u8;
+            let (r, g, b) = (jitter(c.r), jitter(c.g), jitter(c.b));
+            image.set_pixel(x, y, &Color::new_rgba(r, g, b, c.a));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generators_are_deterministic() {
+        let board = checkerboard(8, 4, 2, Color::new(0, 0, 0), Color::new(255, 255, 255));
+        assert_eq!(board.get_pixel(1, 1), Color::new(0, 0, 0));
+        assert_eq!(board.get_pixel(2, 1), Color::new(255, 255, 255));
+        let ramp = gradient(5, 1, Color::new(0, 0, 0), Color::new(200, 100, 0), true);
+        assert_eq!(ramp.get_pixel(2, 0), Color::new(100, 50, 0));
+
+        let polygon = random_polygon(40, 30, 6, 7);
+        assert_eq!(polygon.mask.pixels, random_polygon(40, 30, 6, 7).mask.pixels);
+        assert_ne!(polygon.mask.pixels, random_polygon(40, 30, 6, 8).mask.pixels);
+        // the star shaped polygon contains its center
+        assert!(polygon.mask.get_pixel(20, 15));
+        assert!(polygon.outline.iter().all(|p| p.x >= 0.0 && p.x <= 40.0 && p.y >= 0.0 && p.y <= 30.0));
+
+        let text = strokes(60, 20, 8, 2.0, 1);
+        let set = text.pixels.iter().filter(|&b| b).count();
+        assert!(set > 0 && set < 60 * 20 / 2);
+
+        let noise = noise_field(16, 16, 4, 3);
+        assert!(noise.channel(0).iter().all(|&v| (0.0..=1.0).contains(&v)));
+        let mut noisy = board.clone();
+        add_noise(&mut noisy, 10, 5);
+        assert_ne!(noisy.pixels, board.pixels);
+        assert!((0..8).all(|x| (noisy.get_pixel(x, 0).r as i32 - board.get_pixel(x, 0).r as i32).abs() <= 10));
+    }
+}
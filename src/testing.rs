@@ -0,0 +1,143 @@
+//! Comparisons for regression tests against visioncortex output, e.g. golden images:
+//!
+//! ```
+//! use visioncortex::{BinaryImage, assert_mask_close};
+//! let golden = BinaryImage::from_string("-**-\n-**-\n");
+//! let output = BinaryImage::from_string("-**-\n-*--\n");
+//! assert_mask_close!(output, golden, 0.7);
+//! ```
+
+use crate::{BinaryImage, ColorImage, CompoundPath, PointF64};
+use crate::distance_to_segment;
+use crate::sdf::flatten;
+
+/// Intersection over union of the set pixels of two masks of the same size; 1 if both are empty
+pub fn mask_iou(a: &BinaryImage, b: &BinaryImage) -> f64 {
+    assert!(a.width == b.width && a.height == b.height, "masks differ in size");
+    let (mut intersection, mut union) = (0usize, 0usize);
+    for (p, q) in a.pixels.iter().zip(b.pixels.iter()) {
+        intersection += (p && q) as usize;
+        union += (p || q) as usize;
+    }
+    if union == 0 {
+        return 1.0;
+    }
+    intersection as f64 / union as f64
+}
+
+/// Largest distance from any point on the outlines of either path to the outlines of the other
+/// (symmetric Hausdorff distance), in pixels; splines are flattened first.
+/// Infinite if exactly one of them is empty.
+pub fn path_deviation(a: &CompoundPath, b: &CompoundPath) -> f64 {
+    let (a, b) = (flatten(a), flatten(b));
+    one_sided_deviation(&a, &b).max(one_sided_deviation(&b, &a))
+}
+
+fn one_sided_deviation(from: &[Vec<PointF64>], to: &[Vec<PointF64>]) -> f64 {
+    let segments: Vec<(PointF64, PointF64)> = to.iter()
+        .flat_map(|polygon| polygon.windows(2).map(|w| (w[0], w[1])))
+        .collect();
+    let mut deviation: f64 = 0.0;
+    for polygon in from {
+        for w in polygon.windows(2) {
+            // half a pixel apart along the outline
+            let steps = (w[0].distance_to(w[1]) * 2.0).ceil().max(1.0) as usize;
+            for i in 0..=steps {
+                let p = w[0] + (w[1] - w[0]) * (i as f64 / steps as f64);
+                let nearest = segments.iter()
+                    .map(|&(s, t)| distance_to_segment(p, s, t))
+                    .fold(f64::INFINITY, f64::min);
+                deviation = deviation.max(nearest);
+            }
+        }
+    }
+    deviation
+}
+
+/// Peak signal to noise ratio over the RGB channels of two images of the same size,
+/// in dB; infinite if they are identical
+pub fn psnr(a: &ColorImage, b: &ColorImage) -> f64 {
+    assert!(a.width == b.width && a.height == b.height, "images differ in size");
+    let mut sum = 0.0;
+    for (p, q) in a.iter().zip(b.iter()) {
+        for (u, v) in [(p.r, q.r), (p.g, q.g), (p.b, q.b)] {
+            let d = u as f64 - v as f64;
+            sum += d * d;
+        }
+    }
+    let mse = sum / (3 * std::cmp::max(a.width * a.height, 1)) as f64;
+    if mse == 0.0 {
+        return f64::INFINITY;
+    }
+    10.0 * (255.0 * 255.0 / mse).log10()
+}
+
+/// Asserts that two `BinaryImage`s have a `mask_iou` of at least the given value
+#[macro_export]
+macro_rules! assert_mask_close {
+    ($actual:expr, $expected:expr, $min_iou:expr $(,)?) => {{
+        let iou = $crate::testing::mask_iou(&$actual, &$expected);
+        assert!(iou >= $min_iou, "mask IoU {} is below {}", iou, $min_iou);
+    }};
+}
+
+/// Asserts that two `CompoundPath`s have a `path_deviation` of at most the given value
+#[macro_export]
+macro_rules! assert_path_close {
+    ($actual:expr, $expected:expr, $max_deviation:expr $(,)?) => {{
+        let deviation = $crate::testing::path_deviation(&$actual, &$expected);
+        assert!(deviation <= $max_deviation, "path deviation {} is above {}", deviation, $max_deviation);
+    }};
+}
+
+/// Asserts that two `ColorImage`s have a `psnr` of at least the given value (in dB)
+#[macro_export]
+macro_rules! assert_image_close {
+    ($actual:expr, $expected:expr, $min_psnr:expr $(,)?) => {{
+        let psnr = $crate::testing::psnr(&$actual, &$expected);
+        assert!(psnr >= $min_psnr, "PSNR {} dB is below {} dB", psnr, $min_psnr);
+    }};
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Color, PathI32, PointI32};
+
+    fn square(left: i32, size: i32) -> CompoundPath {
+        let mut path = PathI32::new();
+        for &(x, y) in [(0, 0), (size, 0), (size, size), (0, size), (0, 0)].iter() {
+            path.add(PointI32::new(left + x, y));
+        }
+        let mut compound = CompoundPath::new();
+        compound.add_path_i32(path);
+        compound
+    }
+
+    #[test]
+    fn comparisons() {
+        let a = BinaryImage::from_string("**--\n**--\n");
+        let b = BinaryImage::from_string("***-\n**--\n");
+        assert_eq!(mask_iou(&a, &b), 0.8);
+        assert_mask_close!(a, b, 0.8);
+
+        assert_eq!(path_deviation(&square(0, 4), &square(1, 4)), 1.0);
+        assert_path_close!(square(0, 4), square(0, 4), 0.0);
+
+        let mut image = ColorImage::new_w_h(2, 2);
+        let other = image.clone();
+        assert_eq!(psnr(&image, &other), f64::INFINITY);
+        image.set_pixel(0, 0, &Color::new(255, 255, 255));
+        // 3 of 12 channels are off by 255
+        assert!((psnr(&image, &other) - 10.0 * 4.0_f64.log10()).abs() < 1e-9);
+        assert_image_close!(image, other, 6.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "mask IoU")]
+    fn assert_mask_close_fails() {
+        let a = BinaryImage::from_string("*-\n");
+        let b = BinaryImage::from_string("-*\n");
+        assert_mask_close!(a, b, 0.5);
+    }
+}